@@ -2,23 +2,31 @@ use std::task::Poll;
 
 use futures::stream::FusedStream;
 use futures::{Sink, SinkExt, Stream, StreamExt, TryStreamExt};
-use reqwest::header::USER_AGENT;
-use reqwest::Client;
 use reqwest_websocket::Message as WsMessage;
 use reqwest_websocket::{RequestBuilderExt, WebSocket};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use url::Url;
 
-use crate::model::{channel::ChannelId, message::Message, user::User};
+use crate::client::scheme::{self, UnsupportedScheme};
+use crate::client::ConnectOptions;
+use crate::model::{
+    channel::ChannelId,
+    message::{Message, MessageId},
+    user::User,
+};
 
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum Error {
+    #[error("initialization error")]
+    InitializationError(#[source] reqwest::Error),
     #[error("websocket error")]
     Websocket(#[from] reqwest_websocket::Error),
     #[error("invalid Quaddle URL: {0}")]
     InvalidUrl(Url),
+    #[error("invalid Quaddle URL")]
+    UnsupportedScheme(#[from] UnsupportedScheme),
     #[error("serialization error")]
     Serialization(#[from] serde_json::Error),
     #[error("unexpected binary message")]
@@ -36,8 +44,16 @@ pub enum Error {
 #[serde(tag = "op", rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum ClientGatewayMessage {
-    Identify { token: String },
-    Subscribe { channel_id: ChannelId },
+    Identify {
+        token: String,
+    },
+    Subscribe {
+        channel_id: ChannelId,
+    },
+    /// Stops the events of a channel. Not acknowledged.
+    Unsubscribe {
+        channel_id: ChannelId,
+    },
 }
 
 /// Gateway messages that the server makes.
@@ -45,10 +61,40 @@ pub enum ClientGatewayMessage {
 #[serde(tag = "event", rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum GatewayEvent {
-    Ready { session_id: String, user: User },
-    Error { reason: String },
-    MessageCreate { message: Message },
-    MessageEdit { message: Message },
+    Ready {
+        session_id: String,
+        user: User,
+    },
+    Error {
+        reason: String,
+    },
+    MessageCreate {
+        message: Message,
+    },
+    #[serde(alias = "message_update")]
+    MessageEdit {
+        message: Message,
+    },
+    MessageDelete {
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    /// Acknowledges a [`ClientGatewayMessage::Subscribe`].
+    Subscribed {
+        channel_id: ChannelId,
+    },
+    /// The server refused a [`ClientGatewayMessage::Subscribe`],
+    /// so no events will arrive for the channel.
+    SubscribeFailed {
+        channel_id: ChannelId,
+        reason: String,
+    },
+    /// The user's read marker in a channel moved, possibly on another device.
+    /// Sent regardless of subscriptions.
+    ReadMarkerUpdate {
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
 }
 
 pub struct Gateway {
@@ -58,7 +104,18 @@ pub struct Gateway {
 
 impl Gateway {
     /// Connects to the gateway of the Quaddle instance at `quaddle_url`.
-    pub async fn connect(mut quaddle_url: Url, user_agent: String) -> Result<Gateway, Error> {
+    pub async fn connect(quaddle_url: Url, user_agent: String) -> Result<Gateway, Error> {
+        Self::connect_with_options(quaddle_url, &ConnectOptions::new(user_agent)).await
+    }
+
+    /// Connects to the gateway of the Quaddle instance at `quaddle_url`
+    /// with custom connection options. An `http` or `https` URL is
+    /// taken to mean `ws` or `wss`.
+    pub async fn connect_with_options(
+        quaddle_url: Url,
+        options: &ConnectOptions,
+    ) -> Result<Gateway, Error> {
+        let mut quaddle_url = scheme::gateway_url(quaddle_url)?;
         let Ok(mut segments) = quaddle_url.path_segments_mut() else {
             return Err(Error::InvalidUrl(quaddle_url));
         };
@@ -67,9 +124,10 @@ impl Gateway {
 
         drop(segments);
 
-        let ws = Client::default()
+        let ws = options
+            .build_client()
+            .map_err(Error::InitializationError)?
             .get(quaddle_url)
-            .header(USER_AGENT, user_agent)
             .upgrade()
             .send()
             .await?
@@ -96,6 +154,12 @@ impl Gateway {
         self.send(ClientGatewayMessage::Subscribe { channel_id })
             .await
     }
+
+    /// Unsubscribes from the channel with ID `channel_id`
+    pub async fn unsubscribe(&mut self, channel_id: ChannelId) -> Result<(), Error> {
+        self.send(ClientGatewayMessage::Unsubscribe { channel_id })
+            .await
+    }
 }
 
 /// A lower-level way of sending gateway messages.
@@ -186,6 +250,40 @@ mod tests {
             .expect("failed to connect to local Quaddle server")
     }
 
+    #[test]
+    fn test_message_update_alias() {
+        let event: GatewayEvent = serde_json::from_value(serde_json::json!({
+            "event": "message_update",
+            "message": {
+                "id": 1,
+                "author": { "id": 2, "name": "meow" },
+                "channel": 3,
+                "content": "meow",
+            },
+        }))
+        .expect("failed to parse event");
+
+        assert!(matches!(event, GatewayEvent::MessageEdit { .. }));
+    }
+
+    #[test]
+    fn test_subscribe_failed() {
+        let event: GatewayEvent = serde_json::from_value(serde_json::json!({
+            "event": "subscribe_failed",
+            "channel_id": 1,
+            "reason": "missing permissions",
+        }))
+        .expect("failed to parse event");
+
+        assert!(matches!(
+            event,
+            GatewayEvent::SubscribeFailed {
+                channel_id: ChannelId(1),
+                ..
+            }
+        ));
+    }
+
     #[tokio::test]
     async fn test_connect() {
         let url = Url::parse("http://localhost:8080").expect("failed to parse URL");
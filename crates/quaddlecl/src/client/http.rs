@@ -1,21 +1,39 @@
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use crate::client::scheme::{self, UnsupportedScheme};
+use crate::client::ConnectOptions;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::client::{Certificate, RedirectPolicy};
 use crate::model::{
-    channel::ChannelId,
+    attachment::Attachment,
+    channel::{Channel, ChannelId, ReadMarker},
     message::{Message, MessageId},
-    user::User,
+    user::{User, UserId},
 };
-use reqwest::{header, Client, Method};
+use middleware::{Chain, Middleware};
+use reqwest::{header, Client, Method, RequestBuilder, Response, StatusCode};
+use retry::{Retrier, RetryPolicy, Sleep};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
 use url::Url;
 
+pub mod middleware;
+pub mod retry;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("initialization error")]
     InitializationError(#[source] reqwest::Error),
     #[error("invalid Quaddle URL: {0}")]
     InvalidUrl(Url),
+    #[error("invalid Quaddle URL")]
+    UnsupportedScheme(#[from] UnsupportedScheme),
     #[error("reqwest error")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("invalid response body")]
+    DeserializationError(#[from] serde_json::Error),
     #[error("API error: {reason} (HTTP status: {status})")]
     ApiError {
         reason: String,
@@ -23,6 +41,67 @@ pub enum Error {
     },
     #[error("authorization needed")]
     AuthorizationNeeded,
+    /// The server no longer accepts the token, because it expired or was revoked.
+    /// [`Http::refresh_token`] may get a new one, otherwise the user has to log in again.
+    #[error("session token expired: {reason}")]
+    TokenExpired { reason: String },
+    #[error("invalid history query: `after` must be older than `before`")]
+    InvalidHistoryQuery,
+    #[error("file is larger than {max_len} bytes")]
+    FileTooLarge { max_len: usize },
+}
+
+/// Broad categories of API errors, derived from their HTTP status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiErrorKind {
+    /// The token is missing or invalid.
+    Unauthorized,
+    /// The user isn't allowed to do this.
+    Forbidden,
+    NotFound,
+    RateLimited,
+    ServerError,
+    Other,
+}
+
+impl Error {
+    /// Returns what kind of API error this is, if it is one.
+    pub fn api_error_kind(&self) -> Option<ApiErrorKind> {
+        let status = match self {
+            Error::ApiError { status, .. } => status,
+            Error::TokenExpired { .. } => return Some(ApiErrorKind::Unauthorized),
+            _ => return None,
+        };
+
+        Some(match *status {
+            StatusCode::UNAUTHORIZED => ApiErrorKind::Unauthorized,
+            StatusCode::FORBIDDEN => ApiErrorKind::Forbidden,
+            StatusCode::NOT_FOUND => ApiErrorKind::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => ApiErrorKind::RateLimited,
+            s if s.is_server_error() => ApiErrorKind::ServerError,
+            _ => ApiErrorKind::Other,
+        })
+    }
+
+    /// Tells an authenticated request being turned away for its token apart
+    /// from other API errors.
+    fn classify(self, needs_login: bool) -> Self {
+        match self {
+            Error::ApiError { reason, status }
+                if needs_login && status == StatusCode::UNAUTHORIZED =>
+            {
+                Error::TokenExpired { reason }
+            }
+            e => e,
+        }
+    }
+
+    /// Whether the request failed before the server could answer it,
+    /// e.g. because it's unreachable. Trying again later may work.
+    pub fn is_network_error(&self) -> bool {
+        matches!(self, Error::ReqwestError(e) if e.is_request() || e.is_timeout())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -49,12 +128,27 @@ where
     pub async fn invoke<Retval>(
         self,
         client: &Client,
-        mut quaddle_url: Url,
+        quaddle_url: Url,
         token: Option<String>,
     ) -> Result<Retval, Error>
     where
         Retval: DeserializeOwned,
     {
+        let needs_login = self.needs_login;
+        let req = self.build(client, quaddle_url, token)?;
+        let resp = check_status(req.send().await?)
+            .await
+            .map_err(|e| e.classify(needs_login))?;
+        read_body(resp).await
+    }
+
+    /// Prepares the request without sending it.
+    fn build(
+        self,
+        client: &Client,
+        mut quaddle_url: Url,
+        token: Option<String>,
+    ) -> Result<RequestBuilder, Error> {
         let mut path_segments = quaddle_url.path_segments_mut().unwrap();
         path_segments.extend(self.path);
         drop(path_segments);
@@ -72,19 +166,65 @@ where
             }
         }
 
-        let resp = req.send().await?;
+        Ok(req)
+    }
+}
+
+/// Turns responses with an error status into [`Error::ApiError`]s.
+async fn check_status(resp: Response) -> Result<Response, Error> {
+    let status = resp.status();
+    if !status.is_success() {
+        let errresp: ApiErrorResponse = resp.json().await?;
+        return Err(Error::ApiError {
+            reason: errresp.reason,
+            status,
+        });
+    }
+
+    Ok(resp)
+}
+
+/// Reads the body of `resp`, giving up once it's larger than `max_len` bytes.
+async fn read_capped(resp: Response, max_len: usize) -> Result<Vec<u8>, Error> {
+    if resp
+        .content_length()
+        .is_some_and(|len| len > max_len as u64)
+    {
+        return Err(Error::FileTooLarge { max_len });
+    }
 
-        let status = resp.status();
-        if !status.is_success() {
-            let errresp: ApiErrorResponse = resp.json().await?;
-            return Err(Error::ApiError {
-                reason: errresp.reason,
-                status,
-            });
+    // the length may be missing or wrong, so the body is counted as it comes in
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut resp = resp;
+        let mut body = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            if body.len() + chunk.len() > max_len {
+                return Err(Error::FileTooLarge { max_len });
+            }
+            body.extend_from_slice(&chunk);
         }
+        Ok(body)
+    }
+    // browsers hand the body over in one piece
+    #[cfg(target_arch = "wasm32")]
+    {
+        let body = resp.bytes().await?;
+        if body.len() > max_len {
+            return Err(Error::FileTooLarge { max_len });
+        }
+        Ok(body.to_vec())
+    }
+}
 
-        Ok(resp.json().await?)
+async fn read_body<Retval: DeserializeOwned>(resp: Response) -> Result<Retval, Error> {
+    // some endpoints respond with an empty body, which we treat as `null`
+    let body = resp.bytes().await?;
+    if body.is_empty() {
+        return Ok(serde_json::from_slice(b"null")?);
     }
+
+    Ok(serde_json::from_slice(&body)?)
 }
 
 #[derive(Debug)]
@@ -92,31 +232,162 @@ pub struct Http {
     client: reqwest::Client,
     quaddle_url: Url,
     token: Option<String>,
+    retrier: Option<Retrier>,
+    middleware: Chain,
 }
 
-impl Http {
-    /// Constructs a new REST client.
-    pub fn new(quaddle_url: Url, user_agent: String) -> Result<Self, Error> {
+/// Sets up an [`Http`] with more than the default options.
+#[derive(Debug)]
+pub struct HttpBuilder {
+    quaddle_url: Url,
+    options: ConnectOptions,
+    retrier: Option<Retrier>,
+    middleware: Chain,
+    #[cfg(not(target_arch = "wasm32"))]
+    connect_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    read_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    root_certificates: Vec<Certificate>,
+    #[cfg(not(target_arch = "wasm32"))]
+    redirect: Option<RedirectPolicy>,
+}
+
+impl HttpBuilder {
+    /// Gives up on connecting to the server after `timeout`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Gives up on a response once the server has been silent for `timeout`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Routes all requests through an HTTP(S) or SOCKS proxy.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: Url) -> Self {
+        self.options.proxy = Some(proxy);
+        self
+    }
+
+    /// Trusts servers whose certificates are signed by `cert`, e.g. a self-hosted
+    /// instance with its own certificate authority, on top of the usual ones.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_root_certificate(mut self, cert: Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    /// Decides which redirects are followed. By default, up to 10 in a row are.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn redirect(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect = Some(policy);
+        self
+    }
+
+    /// Sends failed requests again as `policy` says, waiting with `sleep` in between.
+    pub fn retry(mut self, policy: impl RetryPolicy + 'static, sleep: Sleep) -> Self {
+        self.retrier = Some(Retrier {
+            policy: Arc::new(policy),
+            sleep,
+        });
+        self
+    }
+
+    /// Runs every request through `middleware`, inside of the middleware added before.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.0.push(Arc::new(middleware));
+        self
+    }
+
+    /// A `ws` or `wss` URL is taken to mean `http` or `https`.
+    pub fn build(self) -> Result<Http, Error> {
+        let quaddle_url = scheme::rest_url(self.quaddle_url)?;
         if quaddle_url.cannot_be_a_base() {
             return Err(Error::InvalidUrl(quaddle_url));
         }
 
-        Ok(Self {
-            client: Client::builder()
-                .user_agent(user_agent)
-                .build()
-                .map_err(Error::InitializationError)?,
+        let builder = self
+            .options
+            .client_builder()
+            .map_err(Error::InitializationError)?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = {
+            let mut builder = builder;
+            if let Some(timeout) = self.connect_timeout {
+                builder = builder.connect_timeout(timeout);
+            }
+            if let Some(timeout) = self.read_timeout {
+                builder = builder.read_timeout(timeout);
+            }
+            for cert in self.root_certificates {
+                builder = builder.add_root_certificate(cert);
+            }
+            if let Some(policy) = self.redirect {
+                builder = builder.redirect(policy);
+            }
+            builder
+        };
+
+        Ok(Http {
+            client: builder.build().map_err(Error::InitializationError)?,
             quaddle_url,
             token: None,
+            retrier: self.retrier,
+            middleware: self.middleware,
         })
     }
+}
+
+impl Http {
+    /// Constructs a new REST client, with the defaults for everything [`Http::builder`] sets up.
+    pub fn new(quaddle_url: Url, user_agent: String) -> Result<Self, Error> {
+        Self::with_options(quaddle_url, &ConnectOptions::new(user_agent))
+    }
+
+    /// Constructs a new REST client with custom connection options.
+    /// A `ws` or `wss` URL is taken to mean `http` or `https`.
+    pub fn with_options(quaddle_url: Url, options: &ConnectOptions) -> Result<Self, Error> {
+        Self::builder(quaddle_url, options.clone()).build()
+    }
+
+    /// Starts setting up a REST client, e.g. one with timeouts
+    /// or one that retries failed requests.
+    pub fn builder(quaddle_url: Url, options: ConnectOptions) -> HttpBuilder {
+        HttpBuilder {
+            quaddle_url,
+            options,
+            retrier: None,
+            middleware: Chain::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            connect_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            read_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            root_certificates: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            redirect: None,
+        }
+    }
+
+    /// Returns the address of the REST API.
+    pub fn url(&self) -> &Url {
+        &self.quaddle_url
+    }
 
     /// Returns the token, if logged in.
     pub fn token(&self) -> Option<&str> {
         self.token.as_deref()
     }
 
-    /// Fires a request using the REST.
+    /// Fires a request using the REST, through the client's middleware,
+    /// retrying it if the client was set up to.
     pub async fn fire<PathSegment, Path, Json, Query, Retval>(
         &self,
         req: Request<Path, Json, Query>,
@@ -128,8 +399,22 @@ impl Http {
         Query: Serialize,
         Retval: DeserializeOwned,
     {
-        req.invoke(&self.client, self.quaddle_url.clone(), self.token.clone())
-            .await
+        let needs_login = req.needs_login;
+        let req = req.build(&self.client, self.quaddle_url.clone(), self.token.clone())?;
+        self.send(req, needs_login).await
+    }
+
+    /// Sends a built request through the middleware and reads the response.
+    async fn send<Retval: DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+        needs_login: bool,
+    ) -> Result<Retval, Error> {
+        let resp = self
+            .middleware
+            .send(&self.client, self.retrier.as_ref(), req.build()?)
+            .await;
+        read_body(resp.map_err(|e| e.classify(needs_login))?).await
     }
 
     /// Creates an account and returns the resulting user.
@@ -186,6 +471,87 @@ impl Http {
         Ok(())
     }
 
+    /// Trades the current token for a new one, which keeps the session going
+    /// without asking for the password again. The old token stops working.
+    /// Servers accept expired tokens here for a while, but not revoked ones.
+    pub async fn refresh_token(&mut self) -> Result<(), Error> {
+        #[derive(Deserialize)]
+        struct RefreshResponse {
+            token: String,
+        }
+
+        let r: RefreshResponse = self
+            .fire(Request {
+                method: Method::POST,
+                needs_login: true,
+                path: ["auth", "refresh"],
+                json: None::<()>,
+                query: (),
+            })
+            .await?;
+
+        self.set_token(r.token);
+
+        Ok(())
+    }
+
+    /// Changes the password of the logged in account. Responds with
+    /// [`ApiErrorKind::Forbidden`] if `old` is wrong.
+    pub async fn change_password(&self, old: &str, new: &str) -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct ChangePasswordRequest<'a> {
+            old_password: &'a str,
+            new_password: &'a str,
+        }
+
+        self.fire(Request {
+            method: Method::PATCH,
+            needs_login: true,
+            path: ["users", "@me", "password"],
+            json: Some(ChangePasswordRequest {
+                old_password: old,
+                new_password: new,
+            }),
+            query: (),
+        })
+        .await
+    }
+
+    /// Renames the logged in account and returns it under its new name.
+    pub async fn change_username(&self, new_name: &str) -> Result<User, Error> {
+        #[derive(Serialize)]
+        struct ChangeUsernameRequest<'a> {
+            name: &'a str,
+        }
+
+        self.fire(Request {
+            method: Method::PATCH,
+            needs_login: true,
+            path: ["users", "@me"],
+            json: Some(ChangeUsernameRequest { name: new_name }),
+            query: (),
+        })
+        .await
+    }
+
+    /// Deletes the logged in account for good, along with its sessions.
+    /// The password is asked for again so that a stolen token can't do it.
+    pub async fn delete_account(&self, password: &str) -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct DeleteAccountRequest<'a> {
+            password: &'a str,
+        }
+
+        self.fire(Request {
+            method: Method::DELETE,
+            needs_login: true,
+            path: ["users", "@me"],
+            json: Some(DeleteAccountRequest { password }),
+            query: (),
+        })
+        .await
+    }
+
     /// Logs out.
     pub fn logout(&mut self) {
         self.token = None;
@@ -196,6 +562,69 @@ impl Http {
         self.token = Some(tok);
     }
 
+    /// Downloads a file the server links to, such as an avatar, retrying it if the
+    /// client was set up to. Files larger than `max_len` bytes are given up on.
+    pub async fn fetch_file(&self, url: &Url, max_len: usize) -> Result<Vec<u8>, Error> {
+        let fetch = move || async move {
+            let resp = self
+                .client
+                .get(url.clone())
+                .send()
+                .await?
+                .error_for_status()?;
+            read_capped(resp, max_len).await
+        };
+        match &self.retrier {
+            Some(retrier) => retrier.run(&Method::GET, fetch).await,
+            None => fetch().await,
+        }
+    }
+
+    /// Uploads a file called `name` to a channel, for messages in it to link to.
+    pub async fn upload_attachment(
+        &self,
+        channel_id: ChannelId,
+        name: &str,
+        content_type: &str,
+        bytes: Vec<u8>,
+    ) -> Result<Attachment, Error> {
+        let req = Request {
+            method: Method::POST,
+            needs_login: true,
+            path: ["channels", &channel_id.to_string(), "attachments"],
+            json: None::<()>,
+            query: [("name", name)],
+        }
+        .build(&self.client, self.quaddle_url.clone(), self.token.clone())?
+        .header(header::CONTENT_TYPE, content_type)
+        .body(bytes);
+        self.send(req, true).await
+    }
+
+    /// Lists the channels on the server.
+    pub async fn list_channels(&self) -> Result<Vec<Channel>, Error> {
+        self.fire(Request {
+            method: Method::GET,
+            needs_login: true,
+            path: ["channels"],
+            json: None::<()>,
+            query: (),
+        })
+        .await
+    }
+
+    /// Fetches a user.
+    pub async fn fetch_user(&self, user_id: UserId) -> Result<User, Error> {
+        self.fire(Request {
+            method: Method::GET,
+            needs_login: true,
+            path: ["users", &user_id.to_string()],
+            json: None::<()>,
+            query: (),
+        })
+        .await
+    }
+
     /// Fetches a message.
     pub async fn fetch_message(
         &self,
@@ -222,17 +651,38 @@ impl Http {
         &self,
         channel_id: ChannelId,
         content: &str,
+    ) -> Result<Message, Error> {
+        self.post_message(channel_id, content, None).await
+    }
+
+    /// Creates a message replying to `reply_to`, which has to be in the same channel.
+    pub async fn create_reply(
+        &self,
+        channel_id: ChannelId,
+        reply_to: MessageId,
+        content: &str,
+    ) -> Result<Message, Error> {
+        self.post_message(channel_id, content, Some(reply_to)).await
+    }
+
+    async fn post_message(
+        &self,
+        channel_id: ChannelId,
+        content: &str,
+        reply_to: Option<MessageId>,
     ) -> Result<Message, Error> {
         #[derive(Serialize)]
         struct CreateMessageRequest<'a> {
             content: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            reply_to: Option<MessageId>,
         }
 
         self.fire(Request {
             method: Method::POST,
             needs_login: true,
             path: ["channels", &channel_id.to_string(), "messages"],
-            json: Some(CreateMessageRequest { content }),
+            json: Some(CreateMessageRequest { content, reply_to }),
             query: (),
         })
         .await
@@ -265,28 +715,148 @@ impl Http {
         .await
     }
 
-    /// Gets message history.
-    pub async fn message_history(
+    /// Deletes a message.
+    pub async fn delete_message(
         &self,
         channel_id: ChannelId,
-        before: Option<MessageId>,
-    ) -> Result<Vec<Message>, Error> {
+        message_id: MessageId,
+    ) -> Result<(), Error> {
+        self.fire(Request {
+            method: Method::DELETE,
+            needs_login: true,
+            path: [
+                "channels",
+                &channel_id.to_string(),
+                "messages",
+                &message_id.to_string(),
+            ],
+            json: None::<()>,
+            query: (),
+        })
+        .await
+    }
+
+    /// Lists how far the user has read in each channel. Servers that don't
+    /// keep read markers respond with [`ApiErrorKind::NotFound`].
+    pub async fn read_markers(&self) -> Result<Vec<ReadMarker>, Error> {
+        self.fire(Request {
+            method: Method::GET,
+            needs_login: true,
+            path: ["read_markers"],
+            json: None::<()>,
+            query: (),
+        })
+        .await
+    }
+
+    /// Moves the user's read marker in a channel. The gateway announces
+    /// it to their sessions with [`GatewayEvent::ReadMarkerUpdate`].
+    ///
+    /// [`GatewayEvent::ReadMarkerUpdate`]: crate::client::gateway::GatewayEvent::ReadMarkerUpdate
+    pub async fn set_read_marker(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<(), Error> {
         #[derive(Serialize)]
-        struct MessageHistoryQuery {
-            before: Option<MessageId>,
+        struct SetReadMarkerRequest {
+            message_id: MessageId,
         }
 
+        self.fire(Request {
+            method: Method::PUT,
+            needs_login: true,
+            path: ["channels", &channel_id.to_string(), "read_marker"],
+            json: Some(SetReadMarkerRequest { message_id }),
+            query: (),
+        })
+        .await
+    }
+
+    /// Acknowledges every message of a channel up to and including `up_to`
+    /// as delivered, in one request. Acknowledgements never move backwards,
+    /// so an older `up_to` than before is ignored.
+    pub async fn ack_messages(&self, channel_id: ChannelId, up_to: MessageId) -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct AckRequest {
+            up_to: MessageId,
+        }
+
+        self.fire(Request {
+            method: Method::POST,
+            needs_login: true,
+            path: ["channels", &channel_id.to_string(), "messages", "ack"],
+            json: Some(AckRequest { up_to }),
+            query: (),
+        })
+        .await
+    }
+
+    /// Gets message history, newest first.
+    pub async fn message_history(
+        &self,
+        channel_id: ChannelId,
+        query: History,
+    ) -> Result<Vec<Message>, Error> {
         self.fire(Request {
             method: Method::GET,
             needs_login: true,
             path: ["channels", &channel_id.to_string(), "messages"],
             json: None::<()>,
-            query: &MessageHistoryQuery { before },
+            query: &query,
         })
         .await
     }
 }
 
+/// Which part of a channel's history to retrieve,
+/// e.g. `History::before(id).after(other_id)?.limit(20)`.
+/// Ranges are checked as they're built, so a query that exists is a valid one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct History {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<MessageId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<MessageId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+}
+
+impl History {
+    /// The newest messages in the channel.
+    pub fn latest() -> Self {
+        Self::default()
+    }
+
+    /// Messages older than `id`.
+    pub fn before(id: MessageId) -> Self {
+        Self {
+            before: Some(id),
+            ..Self::default()
+        }
+    }
+
+    /// Only messages newer than `id`, which has to be older than the `before`
+    /// bound if there is one, or else the range would be empty.
+    pub fn after(self, id: MessageId) -> Result<Self, Error> {
+        if self.before.is_some_and(|before| id >= before) {
+            return Err(Error::InvalidHistoryQuery);
+        }
+        Ok(Self {
+            after: Some(id),
+            ..self
+        })
+    }
+
+    /// Retrieves at most `n` messages. The server caps this at its page size.
+    pub fn limit(self, n: u32) -> Self {
+        Self {
+            limit: Some(n),
+            ..self
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use rand::{
@@ -357,6 +927,15 @@ pub mod tests {
         assert_ne!(http.token(), None);
     }
 
+    #[tokio::test]
+    async fn test_list_channels() {
+        let http = make_signed_in().await;
+
+        let channels = http.list_channels().await.expect("failed to list channels");
+
+        assert!(channels.iter().any(|c| c.id == ChannelId(1)));
+    }
+
     #[tokio::test]
     #[serial(message_create)]
     async fn test_fetch_message() {
@@ -415,6 +994,25 @@ pub mod tests {
         assert_eq!("start doing this", fetched_message.content);
     }
 
+    #[tokio::test]
+    #[serial(message_create)]
+    async fn test_delete_message() {
+        let http = make_signed_in().await;
+
+        let msg = http
+            .create_message(ChannelId(1), "meow")
+            .await
+            .expect("failed to create message");
+
+        http.delete_message(ChannelId(1), msg.id)
+            .await
+            .expect("failed to delete message");
+
+        http.fetch_message(ChannelId(1), msg.id)
+            .await
+            .expect_err("fetched a deleted message");
+    }
+
     #[tokio::test]
     #[serial(message_create)]
     async fn test_message_history_latest() {
@@ -427,7 +1025,7 @@ pub mod tests {
         }
 
         let hist = http
-            .message_history(ChannelId(1), None)
+            .message_history(ChannelId(1), History::latest())
             .await
             .expect("failed to retrieve message history");
 
@@ -454,10 +1052,23 @@ pub mod tests {
             .expect("failed to create message");
 
         let hist = http
-            .message_history(ChannelId(1), Some(msg.id))
+            .message_history(ChannelId(1), History::before(msg.id))
             .await
             .expect("failed to retrieve message history");
 
         assert_eq!(hist[0].content, "meow1");
     }
+
+    #[test]
+    fn test_history_invalid_range() {
+        History::before(MessageId(1))
+            .after(MessageId(2))
+            .expect_err("built a query for an empty range");
+        History::before(MessageId(2))
+            .after(MessageId(2))
+            .expect_err("built a query for an empty range");
+        History::before(MessageId(2))
+            .after(MessageId(1))
+            .expect("rejected a query for a valid range");
+    }
 }
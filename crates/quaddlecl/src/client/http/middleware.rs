@@ -0,0 +1,110 @@
+//! Hooks that every request of an [`Http`] passes through, for things that
+//! concern all endpoints alike, such as tracing requests, holding them back
+//! while rate limited or trading in an expired token and trying again.
+//!
+//! Hooks run in the order they were added before a request is sent, and in
+//! the reverse order once it's answered, so that the first one added wraps
+//! all the others. Retried requests pass through them again, starting from
+//! the request as it was before any of them changed it.
+//!
+//! [`Http`]: super::Http
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use reqwest::Client;
+pub use reqwest::{Method, Request, Response};
+use url::Url;
+
+use super::retry::Retrier;
+use super::{check_status, Error};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type HookFuture<'a, T> = futures::future::BoxFuture<'a, T>;
+#[cfg(target_arch = "wasm32")]
+pub type HookFuture<'a, T> = futures::future::LocalBoxFuture<'a, T>;
+
+/// What happens to a request once a [`Middleware`] has seen its response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Next {
+    /// Hands the response on to the next hook, or to the endpoint.
+    Continue,
+    /// Sends the request again, through every hook once more. It's up to the
+    /// hook not to ask for this forever. Requests with a streamed body can't
+    /// be sent again and are handed on instead.
+    Resend,
+}
+
+/// Pre-request and post-response hooks. Both do nothing unless implemented.
+pub trait Middleware: Debug + Send + Sync {
+    /// Looks at or changes `req` right before it's sent, e.g. to add a header
+    /// or to wait for a slot. Failing keeps it from being sent.
+    fn before_request<'a>(&'a self, req: &'a mut Request) -> HookFuture<'a, Result<(), Error>> {
+        let _ = req;
+        Box::pin(futures::future::ready(Ok(())))
+    }
+
+    /// Looks at the response to a `method` request to `url`, which error
+    /// statuses have already been turned into [`Error::ApiError`]s for,
+    /// and may replace it.
+    fn after_response<'a>(
+        &'a self,
+        method: &'a Method,
+        url: &'a Url,
+        res: &'a mut Result<Response, Error>,
+    ) -> HookFuture<'a, Next> {
+        let _ = (method, url, res);
+        Box::pin(futures::future::ready(Next::Continue))
+    }
+}
+
+/// The hooks of an [`Http`](super::Http), in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Chain(pub(crate) Vec<Arc<dyn Middleware>>);
+
+impl Chain {
+    /// Sends `req` through every hook, and again whenever one of them asks to
+    /// or `retrier` wants a failed attempt retried.
+    pub(crate) async fn send(
+        &self,
+        client: &Client,
+        retrier: Option<&Retrier>,
+        mut req: Request,
+    ) -> Result<Response, Error> {
+        let mut retries = 0;
+        'send: loop {
+            // taken before the hooks change it, so that a resend isn't changed twice
+            let mut copy = req.try_clone();
+            for hook in &self.0 {
+                hook.before_request(&mut req).await?;
+            }
+
+            let method = req.method().clone();
+            let url = req.url().clone();
+            let mut res = match client.execute(req).await {
+                Ok(resp) => check_status(resp).await,
+                Err(e) => Err(e.into()),
+            };
+
+            // the hooks only get to see the last attempt
+            if let (Some(retrier), Err(e)) = (retrier, &res) {
+                if copy.is_some() && retrier.wait(&method, e, retries).await {
+                    retries += 1;
+                    req = copy.take().expect("the request could be cloned");
+                    continue;
+                }
+            }
+
+            for hook in self.0.iter().rev() {
+                if hook.after_response(&method, &url, &mut res).await == Next::Resend {
+                    // otherwise the response is handed on as if it was Continue
+                    if let Some(copy) = copy.take() {
+                        req = copy;
+                        continue 'send;
+                    }
+                }
+            }
+            return res;
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Sending requests again when the link to the server is flaky.
+//!
+//! Whether a failed request is worth another try is up to a [`RetryPolicy`].
+//! The default one only retries idempotent requests that never reached the
+//! server, since sending anything else twice could e.g. post a message twice.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Method;
+
+use super::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type SleepFuture = futures::future::BoxFuture<'static, ()>;
+#[cfg(target_arch = "wasm32")]
+pub type SleepFuture = futures::future::LocalBoxFuture<'static, ()>;
+
+/// Waits for the given time. quaddlecl doesn't pick an async runtime,
+/// so what to wait with is up to the user.
+pub type Sleep = fn(Duration) -> SleepFuture;
+
+/// Decides whether and when a failed request is sent again.
+pub trait RetryPolicy: Debug + Send + Sync {
+    /// How long to wait before sending the request again, or `None` to give up.
+    /// `attempt` counts the retries made so far, starting at 0.
+    fn retry_after(&self, method: &Method, error: &Error, attempt: u32) -> Option<Duration>;
+}
+
+/// Whether sending a request with `method` twice has the same effect as sending it once.
+pub fn is_idempotent(method: &Method) -> bool {
+    [
+        Method::GET,
+        Method::HEAD,
+        Method::OPTIONS,
+        Method::TRACE,
+        Method::PUT,
+        Method::DELETE,
+    ]
+    .contains(method)
+}
+
+/// Retries idempotent requests that failed because of the network,
+/// waiting twice as long before each retry as before the last one.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    pub max_retries: u32,
+    /// How long to wait before the first retry.
+    pub initial: Duration,
+    /// The longest wait between two attempts.
+    pub max: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn retry_after(&self, method: &Method, error: &Error, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries || !is_idempotent(method) || !error.is_network_error() {
+            return None;
+        }
+
+        let factor = 2u32.saturating_pow(attempt);
+        Some(self.initial.saturating_mul(factor).min(self.max))
+    }
+}
+
+/// A retry policy, along with what to wait with in between.
+#[derive(Debug, Clone)]
+pub(crate) struct Retrier {
+    pub(crate) policy: Arc<dyn RetryPolicy>,
+    pub(crate) sleep: Sleep,
+}
+
+impl Retrier {
+    /// Waits until a `method` request that failed with `error` may be sent again,
+    /// or returns `false` right away if the policy gives up on it. `attempt` counts
+    /// the retries made so far.
+    pub(crate) async fn wait(&self, method: &Method, error: &Error, attempt: u32) -> bool {
+        match self.policy.retry_after(method, error, attempt) {
+            Some(delay) => {
+                (self.sleep)(delay).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs `attempt`, which makes a `method` request, until it succeeds
+    /// or the policy gives up on it.
+    pub(crate) async fn run<T, F>(
+        &self,
+        method: &Method,
+        mut attempt: impl FnMut() -> F,
+    ) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        let mut retries = 0;
+        loop {
+            let res = attempt().await;
+            let Err(e) = res else {
+                return res;
+            };
+            if !self.wait(method, &e, retries).await {
+                return Err(e);
+            }
+            retries += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use futures::FutureExt;
+    use url::Url;
+
+    use super::*;
+    use crate::client::http::Http;
+    use crate::client::ConnectOptions;
+    use crate::model::{channel::ChannelId, message::MessageId};
+
+    #[test]
+    fn test_idempotent_methods() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::DELETE));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+
+    #[test]
+    fn test_only_network_errors_are_retried() {
+        let policy = ExponentialBackoff::default();
+        let error = Error::InvalidHistoryQuery;
+        assert_eq!(policy.retry_after(&Method::GET, &error, 0), None);
+    }
+
+    static SLEEPS: AtomicU32 = AtomicU32::new(0);
+
+    fn counting_sleep(_: Duration) -> SleepFuture {
+        SLEEPS.fetch_add(1, Ordering::SeqCst);
+        futures::future::ready(()).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server() {
+        // nothing listens on a port that was just freed up
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("could not bind");
+        let addr = listener.local_addr().expect("no local address");
+        drop(listener);
+
+        let url = Url::parse(&format!("http://{addr}")).expect("could not parse URL");
+        let policy = ExponentialBackoff {
+            max_retries: 2,
+            ..Default::default()
+        };
+        let http = Http::builder(url, ConnectOptions::new("quaddlecl tester"))
+            .retry(policy, counting_sleep)
+            .build()
+            .expect("could not create a REST client instance");
+
+        let err = http
+            .fetch_message(ChannelId(1), MessageId(1))
+            .await
+            .expect_err("fetched a message from nowhere");
+        assert!(err.is_network_error());
+        assert_eq!(SLEEPS.load(Ordering::SeqCst), 2);
+    }
+}
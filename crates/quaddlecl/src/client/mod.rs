@@ -5,6 +5,53 @@ use url::Url;
 use crate::model::user::User;
 use crate::Error;
 
+pub use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+#[cfg(not(target_arch = "wasm32"))]
+pub use reqwest::{redirect::Policy as RedirectPolicy, Certificate};
+
+/// Settings applied to every connection made to a Quaddle instance,
+/// both over HTTP and to the gateway.
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    pub user_agent: String,
+    /// Headers sent along with every request.
+    pub headers: HeaderMap,
+    /// Proxy to route all traffic through.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub proxy: Option<Url>,
+}
+
+impl ConnectOptions {
+    pub fn new(user_agent: impl Into<String>) -> Self {
+        Self {
+            user_agent: user_agent.into(),
+            headers: HeaderMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+        }
+    }
+
+    /// Builds a reqwest client that applies these options.
+    pub(crate) fn build_client(&self) -> Result<reqwest::Client, reqwest::Error> {
+        self.client_builder()?.build()
+    }
+
+    /// Sets up a reqwest client that applies these options, to be customized further.
+    pub(crate) fn client_builder(&self) -> Result<reqwest::ClientBuilder, reqwest::Error> {
+        let builder = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .default_headers(self.headers.clone());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = match &self.proxy {
+            Some(proxy) => builder.proxy(reqwest::Proxy::all(proxy.clone())?),
+            None => builder,
+        };
+
+        Ok(builder)
+    }
+}
+
 /// Holds the HTTP and gateway clients.
 pub struct Client {
     http: Http,
@@ -12,11 +59,26 @@ pub struct Client {
 }
 
 impl Client {
-    /// Creates a new Client.
-    pub async fn new(quaddle_url: Url, user_agent: &str) -> Result<Self, Error> {
+    /// Creates a new Client. The gateway is assumed to live at `quaddle_url`
+    /// as well, unless `gateway_url` says otherwise.
+    pub async fn new(
+        quaddle_url: Url,
+        gateway_url: Option<Url>,
+        user_agent: &str,
+    ) -> Result<Self, Error> {
+        Self::with_options(quaddle_url, gateway_url, &ConnectOptions::new(user_agent)).await
+    }
+
+    /// Creates a new Client with custom connection options.
+    pub async fn with_options(
+        quaddle_url: Url,
+        gateway_url: Option<Url>,
+        options: &ConnectOptions,
+    ) -> Result<Self, Error> {
+        let gateway_url = gateway_url.unwrap_or_else(|| quaddle_url.clone());
         Ok(Self {
-            http: Http::new(quaddle_url.clone(), user_agent.to_string())?,
-            gateway: Gateway::connect(quaddle_url, user_agent.to_string()).await?,
+            http: Http::with_options(quaddle_url, options)?,
+            gateway: Gateway::connect_with_options(gateway_url, options).await?,
         })
     }
 
@@ -70,6 +132,7 @@ impl AsMut<Gateway> for Client {
 
 pub mod gateway;
 pub mod http;
+pub mod scheme;
 
 #[cfg(test)]
 mod tests {
@@ -79,7 +142,7 @@ mod tests {
     async fn test_login() {
         let uname = http::tests::make_username();
         let url = Url::parse("http://localhost:8080").expect("failed to parse URL");
-        let mut client = Client::new(url, "quaddlecl tester")
+        let mut client = Client::new(url, None, "quaddlecl tester")
             .await
             .expect("failed to create client");
 
@@ -0,0 +1,87 @@
+//! The URL schemes a Quaddle instance can be reached with. Users type
+//! `https://` and `wss://` interchangeably, so either is accepted for both
+//! the REST API and the gateway and switched to the one each of them speaks.
+
+use thiserror::Error;
+use url::Url;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unsupported URL scheme `{0}`, expected http, https, ws or wss")]
+pub struct UnsupportedScheme(pub String);
+
+/// Whether the connection is encrypted, going by the scheme of `url`.
+fn is_secure(url: &Url) -> Result<bool, UnsupportedScheme> {
+    match url.scheme() {
+        "http" | "ws" => Ok(false),
+        "https" | "wss" => Ok(true),
+        other => Err(UnsupportedScheme(other.to_string())),
+    }
+}
+
+fn with_scheme(mut url: Url, scheme: &str) -> Url {
+    url.set_scheme(scheme)
+        .expect("switching between special schemes can't fail");
+    url
+}
+
+/// The address of the REST API: `http` or `https`.
+pub fn rest_url(url: Url) -> Result<Url, UnsupportedScheme> {
+    let scheme = if is_secure(&url)? { "https" } else { "http" };
+    Ok(with_scheme(url, scheme))
+}
+
+/// The address of the gateway: `ws` or `wss`.
+pub fn gateway_url(url: Url) -> Result<Url, UnsupportedScheme> {
+    let scheme = if is_secure(&url)? { "wss" } else { "ws" };
+    Ok(with_scheme(url, scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).expect("failed to parse URL")
+    }
+
+    #[test]
+    fn test_rest_url() {
+        assert_eq!(
+            rest_url(url("wss://example.com/quaddle")),
+            Ok(url("https://example.com/quaddle"))
+        );
+        assert_eq!(
+            rest_url(url("ws://localhost:8080")),
+            Ok(url("http://localhost:8080"))
+        );
+        assert_eq!(
+            rest_url(url("https://example.com")),
+            Ok(url("https://example.com"))
+        );
+    }
+
+    #[test]
+    fn test_gateway_url() {
+        assert_eq!(
+            gateway_url(url("https://example.com/quaddle")),
+            Ok(url("wss://example.com/quaddle"))
+        );
+        assert_eq!(
+            gateway_url(url("http://localhost:8080")),
+            Ok(url("ws://localhost:8080"))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_scheme() {
+        assert_eq!(
+            rest_url(url("ftp://example.com")),
+            Err(UnsupportedScheme("ftp".to_string()))
+        );
+        // a host without a scheme parses as one
+        assert_eq!(
+            gateway_url(url("localhost:8080")),
+            Err(UnsupportedScheme("localhost".to_string()))
+        );
+    }
+}
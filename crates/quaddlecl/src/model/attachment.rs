@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// A file uploaded to a channel, which messages share by linking to it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub name: String,
+    /// Where the file can be downloaded from.
+    pub url: Url,
+    /// How many bytes the file takes up.
+    pub size: u64,
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use super::message::MessageId;
 use super::snowflake::{extra_sf_impls, newtype_sf_impl};
 
 /// A Quaddle channel ID.
@@ -11,3 +12,30 @@ pub struct ChannelId(pub u64);
 
 newtype_sf_impl!(ChannelId);
 extra_sf_impls!(ChannelId);
+
+/// Represents a Quaddle channel.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[non_exhaustive]
+pub struct Channel {
+    pub id: ChannelId,
+    pub name: String,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Channel {
+    /// A channel called `name` that was just created.
+    pub fn fake(name: &str) -> Self {
+        Channel {
+            id: ChannelId::from_timestamp(chrono::Utc::now()),
+            name: name.to_string(),
+        }
+    }
+}
+
+/// How far a user has read in a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadMarker {
+    pub channel_id: ChannelId,
+    /// The newest message the user has seen.
+    pub message_id: MessageId,
+}
@@ -22,4 +22,71 @@ pub struct Message {
     pub author: User,
     pub channel: ChannelId,
     pub content: String,
+    /// The message this one replies to, in the same channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<MessageId>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Message {
+    /// Starts making up a message, sent just now by a made up user.
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder {
+            sent: chrono::Utc::now(),
+            author: User::fake("meow"),
+            channel: ChannelId::default(),
+            content: String::new(),
+            reply_to: None,
+        }
+    }
+}
+
+/// Made up messages, see [`Message::builder`].
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    sent: chrono::DateTime<chrono::Utc>,
+    author: User,
+    channel: ChannelId,
+    content: String,
+    reply_to: Option<MessageId>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MessageBuilder {
+    /// When the message was sent, which its id is made up from.
+    pub fn sent(mut self, sent: chrono::DateTime<chrono::Utc>) -> Self {
+        self.sent = sent;
+        self
+    }
+
+    pub fn author(mut self, author: User) -> Self {
+        self.author = author;
+        self
+    }
+
+    pub fn channel(mut self, channel: ChannelId) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    pub fn reply_to(mut self, reply_to: MessageId) -> Self {
+        self.reply_to = Some(reply_to);
+        self
+    }
+
+    pub fn build(self) -> Message {
+        Message {
+            id: MessageId::from_timestamp(self.sent),
+            author: self.author,
+            channel: self.channel,
+            content: self.content,
+            reply_to: self.reply_to,
+        }
+    }
 }
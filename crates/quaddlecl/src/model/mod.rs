@@ -1,3 +1,4 @@
+pub mod attachment;
 pub mod channel;
 pub mod message;
 pub mod snowflake;
@@ -18,6 +18,22 @@ pub const EPOCH: DateTime<Utc> = {
 
 const TS_OFFSET: u64 = 22;
 
+/// Makes up a snowflake created at `ts`. The lower bits are taken from a counter,
+/// so that snowflakes made up in the same process never collide.
+///
+/// # Panics
+/// If `ts` is before the [`EPOCH`].
+#[cfg(any(test, feature = "test-util"))]
+pub(crate) fn fake(ts: DateTime<Utc>) -> u64 {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let millis = u64::try_from((ts - EPOCH).num_milliseconds())
+        .expect("snowflakes can't be older than the epoch");
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed) & ((1 << TS_OFFSET) - 1);
+    (millis << TS_OFFSET) | sequence
+}
+
 /// Marker trait for newtypes over snowflakes
 pub trait Snowflake: Into<u64> + Clone + Sealed
 where
@@ -64,6 +80,14 @@ macro_rules! extra_sf_impls {
             }
         }
 
+        #[cfg(any(test, feature = "test-util"))]
+        impl $ty {
+            /// Makes up an id created at `ts`, unique within the process.
+            pub fn from_timestamp(ts: ::chrono::DateTime<::chrono::Utc>) -> Self {
+                Self(crate::model::snowflake::fake(ts))
+            }
+        }
+
         impl ::std::str::FromStr for $ty {
             type Err = <u64 as ::std::str::FromStr>::Err;
 
@@ -100,4 +124,14 @@ mod tests {
         assert_eq!(dt.minute(), 18);
         assert_eq!(dt.second(), 25);
     }
+
+    #[test]
+    fn test_from_timestamp() {
+        let ts = EPOCH + TimeDelta::days(500) + TimeDelta::milliseconds(123);
+        let a = MeowId::from_timestamp(ts);
+        let b = MeowId::from_timestamp(ts);
+        assert_ne!(a.0, b.0);
+        assert_eq!(a.timestamp(), ts);
+        assert_eq!(b.timestamp(), ts);
+    }
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use super::snowflake::{extra_sf_impls, newtype_sf_impl};
 
@@ -16,4 +17,19 @@ extra_sf_impls!(UserId);
 pub struct User {
     pub id: UserId,
     pub name: String,
+    /// Where the user's avatar can be downloaded from, if they have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<Url>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl User {
+    /// A user called `name` who just signed up, without an avatar.
+    pub fn fake(name: &str) -> Self {
+        User {
+            id: UserId::from_timestamp(chrono::Utc::now()),
+            name: name.to_string(),
+            avatar_url: None,
+        }
+    }
 }
@@ -0,0 +1,754 @@
+//! An in-process fake Quaddle server, implementing just enough of the HTTP API
+//! and the gateway for quaddlecl's integration tests.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use axum::body::Bytes;
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::routing::{delete, get, patch, post, put};
+use axum::{Json, Router};
+use chrono::Utc;
+use quaddlecl::client::gateway::{ClientGatewayMessage, GatewayEvent};
+use quaddlecl::model::attachment::Attachment;
+use quaddlecl::model::channel::{Channel, ChannelId, ReadMarker};
+use quaddlecl::model::message::{Message, MessageId};
+use quaddlecl::model::snowflake::EPOCH;
+use quaddlecl::model::user::{User, UserId};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// How many messages a history request returns at most.
+pub const HISTORY_PAGE_SIZE: usize = 50;
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, Json<Value>)>;
+
+fn api_error(status: StatusCode, reason: &str) -> (StatusCode, Json<Value>) {
+    (status, Json(json!({ "reason": reason })))
+}
+
+#[derive(Default)]
+struct Db {
+    /// name -> (user, password)
+    users: HashMap<String, (User, String)>,
+    tokens: HashMap<String, User>,
+    /// Tokens that can only be refreshed anymore.
+    expired_tokens: HashMap<String, User>,
+    messages: BTreeMap<MessageId, Message>,
+    channels: BTreeMap<ChannelId, Channel>,
+    read_markers: HashMap<UserId, BTreeMap<ChannelId, MessageId>>,
+    /// id -> uploaded bytes
+    attachments: HashMap<u64, Vec<u8>>,
+    acks: HashMap<UserId, BTreeMap<ChannelId, MessageId>>,
+    sequence: u64,
+}
+
+impl Db {
+    fn next_snowflake(&mut self) -> u64 {
+        let millis = (Utc::now() - EPOCH).num_milliseconds() as u64;
+        self.sequence += 1;
+        (millis << 22) | (self.sequence & 0x3f_ffff)
+    }
+}
+
+struct AppState {
+    db: Mutex<Db>,
+    events: broadcast::Sender<GatewayEvent>,
+    /// Events for the sessions of one user only.
+    user_events: broadcast::Sender<(UserId, GatewayEvent)>,
+    kick: broadcast::Sender<()>,
+}
+
+impl AppState {
+    fn authorize(&self, headers: &HeaderMap) -> Result<User, (StatusCode, Json<Value>)> {
+        let db = self.db.lock().unwrap();
+        let token = token(headers);
+        if let Some(user) = token.and_then(|tok| db.tokens.get(tok)) {
+            return Ok(user.clone());
+        }
+        match token.is_some_and(|tok| db.expired_tokens.contains_key(tok)) {
+            true => Err(api_error(StatusCode::UNAUTHORIZED, "token expired")),
+            false => Err(api_error(StatusCode::UNAUTHORIZED, "invalid token")),
+        }
+    }
+}
+
+fn token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|tok| tok.to_str().ok())
+}
+
+/// A fake Quaddle server listening on a random local port.
+/// It shuts down when dropped.
+pub struct FakeServer {
+    url: Url,
+    state: Arc<AppState>,
+    handle: JoinHandle<()>,
+}
+
+impl FakeServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(AppState {
+            db: Mutex::default(),
+            events: broadcast::channel(64).0,
+            user_events: broadcast::channel(64).0,
+            kick: broadcast::channel(1).0,
+        });
+
+        let router = Router::new()
+            .route("/auth/signup", post(signup))
+            .route("/auth/login", post(login))
+            .route("/auth/refresh", post(refresh))
+            .route("/channels", get(list_channels))
+            .route(
+                "/channels/:channel_id/messages",
+                get(message_history).post(create_message),
+            )
+            .route(
+                "/channels/:channel_id/messages/:message_id",
+                get(fetch_message)
+                    .patch(edit_message)
+                    .delete(delete_message),
+            )
+            .route("/channels/:channel_id/messages/ack", post(ack_messages))
+            .route("/channels/:channel_id/read_marker", put(set_read_marker))
+            .route("/channels/:channel_id/attachments", post(upload_attachment))
+            .route("/attachments/:id/:name", get(attachment))
+            .route("/read_markers", get(read_markers))
+            .route("/users/@me", delete(delete_account).patch(change_username))
+            .route("/users/@me/password", patch(change_password))
+            .route("/avatars/:user_id", get(avatar))
+            .route("/users/:user_id", get(fetch_user))
+            .route("/app", get(gateway))
+            .with_state(Arc::clone(&state));
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind the fake server");
+        let addr = listener.local_addr().expect("no local address");
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, router)
+                .await
+                .expect("fake server crashed")
+        });
+
+        Self {
+            url: Url::parse(&format!("http://{addr}")).expect("failed to parse URL"),
+            state,
+            handle,
+        }
+    }
+
+    /// The base URL of the server.
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Creates a channel that shows up in the channel list.
+    pub fn add_channel(&self, id: ChannelId, name: &str) {
+        let mut channel = Channel::default();
+        channel.id = id;
+        channel.name = name.to_string();
+        self.state.db.lock().unwrap().channels.insert(id, channel);
+    }
+
+    /// Closes all open gateway connections.
+    pub fn disconnect_all(&self) {
+        let _ = self.state.kick.send(());
+    }
+
+    /// Lets every token expire, so that they can only be refreshed.
+    pub fn expire_tokens(&self) {
+        let mut db = self.state.db.lock().unwrap();
+        let tokens = std::mem::take(&mut db.tokens);
+        db.expired_tokens.extend(tokens);
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    name: String,
+    password: String,
+}
+
+async fn signup(
+    State(state): State<Arc<AppState>>,
+    Json(creds): Json<Credentials>,
+) -> ApiResult<Value> {
+    let mut db = state.db.lock().unwrap();
+    if db.users.contains_key(&creds.name) {
+        return Err(api_error(StatusCode::CONFLICT, "username taken"));
+    }
+
+    let user = User {
+        id: UserId(db.next_snowflake()),
+        name: creds.name.clone(),
+        avatar_url: None,
+    };
+    db.users.insert(creds.name, (user.clone(), creds.password));
+
+    Ok(Json(json!({ "new_user": user })))
+}
+
+async fn login(
+    State(state): State<Arc<AppState>>,
+    Json(creds): Json<Credentials>,
+) -> ApiResult<Value> {
+    let mut db = state.db.lock().unwrap();
+    let user = match db.users.get(&creds.name) {
+        Some((user, password)) if *password == creds.password => user.clone(),
+        _ => return Err(api_error(StatusCode::UNAUTHORIZED, "invalid credentials")),
+    };
+
+    let token = format!("token-{id}", id = db.next_snowflake());
+    db.tokens.insert(token.clone(), user);
+
+    Ok(Json(json!({ "token": token })))
+}
+
+/// The bytes served as everyone's avatar.
+pub const AVATAR: &[u8] = b"\x89PNG not really";
+
+async fn avatar(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<UserId>,
+) -> Result<&'static [u8], StatusCode> {
+    let db = state.db.lock().unwrap();
+    if db.users.values().any(|(user, _)| user.id == user_id) {
+        Ok(AVATAR)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Trades a live or expired token for a new one, revoking the old one.
+async fn refresh(State(state): State<Arc<AppState>>, headers: HeaderMap) -> ApiResult<Value> {
+    let mut db = state.db.lock().unwrap();
+    let user = token(&headers)
+        .and_then(|tok| {
+            let live = db.tokens.remove(tok);
+            let expired = db.expired_tokens.remove(tok);
+            live.or(expired)
+        })
+        .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "invalid token"))?;
+
+    let token = format!("token-{id}", id = db.next_snowflake());
+    db.tokens.insert(token.clone(), user);
+
+    Ok(Json(json!({ "token": token })))
+}
+
+#[derive(Deserialize)]
+struct DeleteAccountBody {
+    password: String,
+}
+
+async fn delete_account(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<DeleteAccountBody>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let user = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    if db
+        .users
+        .get(&user.name)
+        .is_some_and(|(_, password)| *password != body.password)
+    {
+        return Err(api_error(StatusCode::FORBIDDEN, "wrong password"));
+    }
+    db.users.remove(&user.name);
+    db.tokens.retain(|_, token_user| token_user.id != user.id);
+    db.expired_tokens
+        .retain(|_, token_user| token_user.id != user.id);
+    db.read_markers.remove(&user.id);
+    db.acks.remove(&user.id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct ChangePasswordBody {
+    old_password: String,
+    new_password: String,
+}
+
+async fn change_password(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<ChangePasswordBody>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let user = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    match db.users.get_mut(&user.name) {
+        Some((_, password)) if *password == body.old_password => *password = body.new_password,
+        _ => return Err(api_error(StatusCode::FORBIDDEN, "wrong password")),
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct ChangeUsernameBody {
+    name: String,
+}
+
+async fn change_username(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<ChangeUsernameBody>,
+) -> ApiResult<User> {
+    let user = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    if db.users.contains_key(&body.name) {
+        return Err(api_error(StatusCode::CONFLICT, "name taken"));
+    }
+    let Some((mut renamed, password)) = db.users.remove(&user.name) else {
+        return Err(api_error(StatusCode::NOT_FOUND, "no such user"));
+    };
+    renamed.name = body.name.clone();
+    db.users.insert(body.name, (renamed.clone(), password));
+    for token_user in db.tokens.values_mut() {
+        if token_user.id == renamed.id {
+            *token_user = renamed.clone();
+        }
+    }
+
+    Ok(Json(renamed))
+}
+
+async fn fetch_user(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(user_id): Path<UserId>,
+) -> ApiResult<User> {
+    state.authorize(&headers)?;
+    let db = state.db.lock().unwrap();
+
+    db.users
+        .values()
+        .map(|(user, _)| user)
+        .find(|user| user.id == user_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "no such user"))
+}
+
+#[derive(Deserialize)]
+struct AttachmentQuery {
+    name: String,
+}
+
+async fn upload_attachment(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(_channel_id): Path<ChannelId>,
+    Query(query): Query<AttachmentQuery>,
+    body: Bytes,
+) -> ApiResult<Attachment> {
+    state.authorize(&headers)?;
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, "no host"))?;
+    let mut db = state.db.lock().unwrap();
+    let id = db.next_snowflake();
+    let url = Url::parse(&format!("http://{host}/attachments/{id}/{}", query.name))
+        .map_err(|_| api_error(StatusCode::BAD_REQUEST, "invalid name"))?;
+    let size = body.len() as u64;
+    db.attachments.insert(id, body.to_vec());
+
+    Ok(Json(Attachment {
+        name: query.name,
+        url,
+        size,
+    }))
+}
+
+async fn attachment(
+    State(state): State<Arc<AppState>>,
+    Path((id, _name)): Path<(u64, String)>,
+) -> Result<Vec<u8>, StatusCode> {
+    let db = state.db.lock().unwrap();
+    db.attachments
+        .get(&id)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn list_channels(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<Channel>> {
+    state.authorize(&headers)?;
+    let db = state.db.lock().unwrap();
+
+    Ok(Json(db.channels.values().cloned().collect()))
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    before: Option<MessageId>,
+    after: Option<MessageId>,
+    limit: Option<usize>,
+}
+
+async fn message_history(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<ChannelId>,
+    Query(query): Query<HistoryQuery>,
+) -> ApiResult<Vec<Message>> {
+    state.authorize(&headers)?;
+    let db = state.db.lock().unwrap();
+    let upper = query.before.unwrap_or(MessageId(u64::MAX));
+    let lower = query.after.unwrap_or(MessageId(0));
+    let limit = query
+        .limit
+        .unwrap_or(HISTORY_PAGE_SIZE)
+        .min(HISTORY_PAGE_SIZE);
+
+    Ok(Json(
+        db.messages
+            .range(..upper)
+            .rev()
+            .map(|(_, msg)| msg)
+            .take_while(|msg| msg.id > lower)
+            .filter(|msg| msg.channel == channel_id)
+            .take(limit)
+            .cloned()
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct MessageContent {
+    content: String,
+    #[serde(default)]
+    reply_to: Option<MessageId>,
+}
+
+async fn create_message(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<ChannelId>,
+    Json(body): Json<MessageContent>,
+) -> ApiResult<Message> {
+    let author = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    let replied_here = |id| {
+        db.messages
+            .get(&id)
+            .is_some_and(|m| m.channel == channel_id)
+    };
+    if body.reply_to.is_some_and(|id| !replied_here(id)) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "replied to message not in this channel",
+        ));
+    }
+
+    let mut message = Message::default();
+    message.id = MessageId(db.next_snowflake());
+    message.author = author;
+    message.channel = channel_id;
+    message.content = body.content;
+    message.reply_to = body.reply_to;
+    db.messages.insert(message.id, message.clone());
+
+    let _ = state.events.send(GatewayEvent::MessageCreate {
+        message: message.clone(),
+    });
+
+    Ok(Json(message))
+}
+
+async fn fetch_message(
+    State(state): State<Arc<AppState>>,
+    Path((channel_id, message_id)): Path<(ChannelId, MessageId)>,
+) -> ApiResult<Message> {
+    state
+        .db
+        .lock()
+        .unwrap()
+        .messages
+        .get(&message_id)
+        .filter(|msg| msg.channel == channel_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "no such message"))
+}
+
+async fn edit_message(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((channel_id, message_id)): Path<(ChannelId, MessageId)>,
+    Json(body): Json<MessageContent>,
+) -> ApiResult<Message> {
+    let user = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    let Some(message) = db
+        .messages
+        .get_mut(&message_id)
+        .filter(|msg| msg.channel == channel_id)
+    else {
+        return Err(api_error(StatusCode::NOT_FOUND, "no such message"));
+    };
+
+    if message.author.id != user.id {
+        return Err(api_error(StatusCode::FORBIDDEN, "not your message"));
+    }
+
+    message.content = body.content;
+    let message = message.clone();
+
+    let _ = state.events.send(GatewayEvent::MessageEdit {
+        message: message.clone(),
+    });
+
+    Ok(Json(message))
+}
+
+async fn delete_message(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((channel_id, message_id)): Path<(ChannelId, MessageId)>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let user = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    let Some(message) = db
+        .messages
+        .get(&message_id)
+        .filter(|msg| msg.channel == channel_id)
+    else {
+        return Err(api_error(StatusCode::NOT_FOUND, "no such message"));
+    };
+
+    if message.author.id != user.id {
+        return Err(api_error(StatusCode::FORBIDDEN, "not your message"));
+    }
+
+    db.messages.remove(&message_id);
+
+    let _ = state.events.send(GatewayEvent::MessageDelete {
+        channel_id,
+        message_id,
+    });
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn read_markers(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<ReadMarker>> {
+    let user = state.authorize(&headers)?;
+    let db = state.db.lock().unwrap();
+
+    let markers = db.read_markers.get(&user.id).into_iter().flatten();
+    Ok(Json(
+        markers
+            .map(|(&channel_id, &message_id)| ReadMarker {
+                channel_id,
+                message_id,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct ReadMarkerBody {
+    message_id: MessageId,
+}
+
+async fn set_read_marker(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<ChannelId>,
+    Json(body): Json<ReadMarkerBody>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let user = state.authorize(&headers)?;
+    state
+        .db
+        .lock()
+        .unwrap()
+        .read_markers
+        .entry(user.id)
+        .or_default()
+        .insert(channel_id, body.message_id);
+
+    let _ = state.user_events.send((
+        user.id,
+        GatewayEvent::ReadMarkerUpdate {
+            channel_id,
+            message_id: body.message_id,
+        },
+    ));
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct AckBody {
+    up_to: MessageId,
+}
+
+async fn ack_messages(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(channel_id): Path<ChannelId>,
+    Json(body): Json<AckBody>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let user = state.authorize(&headers)?;
+    let mut db = state.db.lock().unwrap();
+
+    if db
+        .messages
+        .get(&body.up_to)
+        .is_none_or(|msg| msg.channel != channel_id)
+    {
+        return Err(api_error(StatusCode::NOT_FOUND, "no such message"));
+    }
+    let acked = db
+        .acks
+        .entry(user.id)
+        .or_default()
+        .entry(channel_id)
+        .or_default();
+    *acked = (*acked).max(body.up_to);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn gateway(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    ws.on_upgrade(move |socket| gateway_session(socket, state))
+}
+
+fn event_channel(event: &GatewayEvent) -> Option<ChannelId> {
+    match event {
+        GatewayEvent::MessageCreate { message } | GatewayEvent::MessageEdit { message } => {
+            Some(message.channel)
+        }
+        GatewayEvent::MessageDelete { channel_id, .. } => Some(*channel_id),
+        _ => None,
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &GatewayEvent) -> bool {
+    let txt = serde_json::to_string(event).expect("failed to serialize event");
+    socket.send(WsMessage::Text(txt)).await.is_ok()
+}
+
+async fn gateway_session(mut socket: WebSocket, state: Arc<AppState>) {
+    let mut events = state.events.subscribe();
+    let mut user_events = state.user_events.subscribe();
+    let mut kick = state.kick.subscribe();
+    let mut identified = false;
+    let mut user_id = None;
+    let mut subscriptions = HashSet::new();
+
+    loop {
+        tokio::select! {
+            msg = socket.recv() => {
+                let txt = match msg {
+                    Some(Ok(WsMessage::Text(txt))) => txt,
+                    Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => continue,
+                };
+
+                let reply = match serde_json::from_str(&txt) {
+                    Ok(ClientGatewayMessage::Identify { token }) => {
+                        let user = state.db.lock().unwrap().tokens.get(&token).cloned();
+                        match user {
+                            Some(user) => {
+                                identified = true;
+                                user_id = Some(user.id);
+                                Some(GatewayEvent::Ready {
+                                    session_id: format!("session-{token}"),
+                                    user,
+                                })
+                            }
+                            None => Some(GatewayEvent::Error {
+                                reason: "invalid token".to_string(),
+                            }),
+                        }
+                    }
+                    Ok(ClientGatewayMessage::Subscribe { channel_id }) if identified => {
+                        subscriptions.insert(channel_id);
+                        Some(GatewayEvent::Subscribed { channel_id })
+                    }
+                    Ok(ClientGatewayMessage::Subscribe { channel_id }) => {
+                        Some(GatewayEvent::SubscribeFailed {
+                            channel_id,
+                            reason: "not identified".to_string(),
+                        })
+                    }
+                    Ok(ClientGatewayMessage::Unsubscribe { channel_id }) => {
+                        subscriptions.remove(&channel_id);
+                        None
+                    }
+                    Ok(_) => Some(GatewayEvent::Error {
+                        reason: "unexpected op".to_string(),
+                    }),
+                    Err(e) => Some(GatewayEvent::Error {
+                        reason: e.to_string(),
+                    }),
+                };
+
+                if let Some(reply) = reply {
+                    if !send_event(&mut socket, &reply).await {
+                        return;
+                    }
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+
+                let relevant = event_channel(&event)
+                    .is_some_and(|channel| subscriptions.contains(&channel));
+                if identified && relevant && !send_event(&mut socket, &event).await {
+                    return;
+                }
+            }
+            event = user_events.recv() => {
+                let (target, event) = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+
+                if user_id == Some(target) && !send_event(&mut socket, &event).await {
+                    return;
+                }
+            }
+            _ = kick.recv() => {
+                let _ = socket.close().await;
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,921 @@
+//! Runs the client against an in-process fake Quaddle server, so that these
+//! tests don't need a real instance listening on localhost.
+
+mod common;
+
+use common::FakeServer;
+use futures::{future, StreamExt, TryStreamExt};
+use quaddlecl::client::gateway::{Gateway, GatewayEvent};
+use quaddlecl::client::http::middleware::{
+    HookFuture, Method, Middleware, Next, Request, Response,
+};
+use quaddlecl::client::http::{ApiErrorKind, Error, History, Http};
+use quaddlecl::client::{Client, ConnectOptions, RedirectPolicy};
+use quaddlecl::model::channel::{ChannelId, ReadMarker};
+use quaddlecl::model::user::UserId;
+use reqwest::header;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+async fn signed_in(server: &FakeServer, name: &str) -> Http {
+    let mut http = Http::new(server.url(), "quaddlecl tester".to_string())
+        .expect("could not create a REST client instance");
+
+    http.signup(name, "the_meower")
+        .await
+        .expect("failed to sign up");
+    http.login(name, "the_meower")
+        .await
+        .expect("failed to log in");
+
+    http
+}
+
+async fn subscribed_gateway(server: &FakeServer, http: &Http, channel_id: ChannelId) -> Gateway {
+    let mut gateway = Gateway::connect(server.url(), "quaddlecl tester".to_string())
+        .await
+        .expect("failed to connect to the fake server");
+
+    gateway
+        .identify(http.token().expect("not logged in").to_string())
+        .await
+        .expect("failed to identify");
+
+    gateway
+        .subscribe(channel_id)
+        .await
+        .expect("failed to subscribe");
+
+    let GatewayEvent::Subscribed { channel_id: acked } = next_event(&mut gateway).await else {
+        panic!("subscription wasn't acknowledged")
+    };
+    assert_eq!(acked, channel_id);
+
+    gateway
+}
+
+async fn next_event(gateway: &mut Gateway) -> GatewayEvent {
+    gateway
+        .try_next()
+        .await
+        .expect("error receiving event")
+        .expect("gateway socket closed")
+}
+
+#[tokio::test]
+async fn test_login() {
+    let server = FakeServer::start().await;
+    let mut client = Client::new(server.url(), None, "quaddlecl tester")
+        .await
+        .expect("failed to create client");
+
+    client
+        .http()
+        .signup("meow", "the_meower")
+        .await
+        .expect("failed to sign up");
+
+    let (_, user) = client
+        .login("meow", "the_meower")
+        .await
+        .expect("failed to log in");
+
+    assert_eq!(user.name, "meow");
+}
+
+#[tokio::test]
+async fn test_login_wrong_password() {
+    let server = FakeServer::start().await;
+    let mut http = signed_in(&server, "meow").await;
+    http.logout();
+
+    http.login("meow", "the_barker")
+        .await
+        .expect_err("logged in with the wrong password");
+
+    assert_eq!(http.token(), None);
+}
+
+#[tokio::test]
+async fn test_unreachable_server() {
+    // grab a free port, then stop listening on it
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to find a free port");
+    let url = url::Url::parse(&format!("http://{addr}")).expect("failed to parse URL");
+
+    let http = Http::new(url, "quaddlecl tester".to_string())
+        .expect("could not create a REST client instance");
+    let err = http
+        .signup("meow", "the_meower")
+        .await
+        .expect_err("signed up on a server that's gone");
+
+    assert!(err.is_network_error(), "unexpected error: {err:?}");
+}
+
+#[tokio::test]
+async fn test_send_and_receive() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    let sent = http
+        .create_message(ChannelId(1), "sussy balls")
+        .await
+        .expect("failed to send a message");
+
+    let GatewayEvent::MessageCreate { message } = next_event(&mut gateway).await else {
+        panic!("received an unexpected event")
+    };
+
+    assert_eq!(message.id, sent.id);
+    assert_eq!(message.content, "sussy balls");
+}
+
+#[tokio::test]
+async fn test_unsubscribed_channel_is_silent() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    http.create_message(ChannelId(2), "nobody hears this")
+        .await
+        .expect("failed to send a message");
+    http.create_message(ChannelId(1), "meow")
+        .await
+        .expect("failed to send a message");
+
+    let GatewayEvent::MessageCreate { message } = next_event(&mut gateway).await else {
+        panic!("received an unexpected event")
+    };
+
+    assert_eq!(message.content, "meow");
+}
+
+#[tokio::test]
+async fn test_unsubscribe() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    gateway
+        .unsubscribe(ChannelId(1))
+        .await
+        .expect("failed to unsubscribe");
+    gateway
+        .subscribe(ChannelId(2))
+        .await
+        .expect("failed to subscribe");
+    // the acknowledgement comes after the unsubscription was handled
+    let GatewayEvent::Subscribed { .. } = next_event(&mut gateway).await else {
+        panic!("subscription wasn't acknowledged")
+    };
+
+    http.create_message(ChannelId(1), "nobody hears this")
+        .await
+        .expect("failed to send a message");
+    http.create_message(ChannelId(2), "meow")
+        .await
+        .expect("failed to send a message");
+
+    let GatewayEvent::MessageCreate { message } = next_event(&mut gateway).await else {
+        panic!("received an unexpected event")
+    };
+    assert_eq!(message.content, "meow");
+}
+
+#[tokio::test]
+async fn test_subscribe_before_identify() {
+    let server = FakeServer::start().await;
+    let mut gateway = Gateway::connect(server.url(), "quaddlecl tester".to_string())
+        .await
+        .expect("failed to connect to the fake server");
+
+    gateway
+        .subscribe(ChannelId(1))
+        .await
+        .expect("failed to subscribe");
+
+    let GatewayEvent::SubscribeFailed { channel_id, .. } = next_event(&mut gateway).await else {
+        panic!("subscription wasn't refused")
+    };
+    assert_eq!(channel_id, ChannelId(1));
+}
+
+#[tokio::test]
+async fn test_edit() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    let msg = http
+        .create_message(ChannelId(1), "sussy balls")
+        .await
+        .expect("failed to send a message");
+
+    http.edit_message(ChannelId(1), msg.id, "sussy balls2")
+        .await
+        .expect("failed to edit a message");
+
+    let events = gateway
+        .take(2)
+        .try_collect::<Vec<_>>()
+        .await
+        .expect("error getting gateway messages");
+
+    let GatewayEvent::MessageCreate { message: created } = &events[0] else {
+        panic!("unexpected messages: {events:?}")
+    };
+    let GatewayEvent::MessageEdit { message: edited } = &events[1] else {
+        panic!("unexpected messages: {events:?}")
+    };
+
+    assert_eq!(created.id, edited.id);
+    assert_eq!(edited.content, "sussy balls2");
+
+    let fetched = http
+        .fetch_message(ChannelId(1), msg.id)
+        .await
+        .expect("failed to fetch message");
+
+    assert_eq!(fetched.content, "sussy balls2");
+}
+
+#[tokio::test]
+async fn test_edit_someone_elses_message() {
+    let server = FakeServer::start().await;
+    let author = signed_in(&server, "meow").await;
+    let intruder = signed_in(&server, "woof").await;
+
+    let msg = author
+        .create_message(ChannelId(1), "meow")
+        .await
+        .expect("failed to send a message");
+
+    intruder
+        .edit_message(ChannelId(1), msg.id, "woof")
+        .await
+        .expect_err("edited someone else's message");
+}
+
+#[tokio::test]
+async fn test_delete() {
+    let server = FakeServer::start().await;
+    let author = signed_in(&server, "meow").await;
+    let intruder = signed_in(&server, "woof").await;
+
+    let msg = author
+        .create_message(ChannelId(1), "meow")
+        .await
+        .expect("failed to send a message");
+
+    intruder
+        .delete_message(ChannelId(1), msg.id)
+        .await
+        .expect_err("deleted someone else's message");
+
+    author
+        .delete_message(ChannelId(1), msg.id)
+        .await
+        .expect("failed to delete a message");
+
+    author
+        .fetch_message(ChannelId(1), msg.id)
+        .await
+        .expect_err("fetched a deleted message");
+
+    let history = author
+        .message_history(ChannelId(1), History::latest())
+        .await
+        .expect("failed to retrieve message history");
+
+    assert!(history.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_event() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let msg = http
+        .create_message(ChannelId(1), "meow")
+        .await
+        .expect("failed to send a message");
+
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    http.delete_message(ChannelId(1), msg.id)
+        .await
+        .expect("failed to delete a message");
+
+    let GatewayEvent::MessageDelete {
+        channel_id,
+        message_id,
+    } = next_event(&mut gateway).await
+    else {
+        panic!("received an unexpected event")
+    };
+
+    assert_eq!(channel_id, ChannelId(1));
+    assert_eq!(message_id, msg.id);
+}
+
+#[tokio::test]
+async fn test_list_channels() {
+    let server = FakeServer::start().await;
+    server.add_channel(ChannelId(1), "general");
+    server.add_channel(ChannelId(2), "random");
+    let http = signed_in(&server, "meow").await;
+
+    let channels = http.list_channels().await.expect("failed to list channels");
+
+    let names: Vec<_> = channels.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["general", "random"]);
+}
+
+#[tokio::test]
+async fn test_fetch_file() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let user = signed_in(&server, "purr")
+        .await
+        .create_message(ChannelId(1), "hi")
+        .await
+        .expect("failed to create message")
+        .author;
+
+    let url = server
+        .url()
+        .join(&format!("avatars/{id}", id = user.id))
+        .expect("failed to build the avatar URL");
+    let avatar = http
+        .fetch_file(&url, 1024)
+        .await
+        .expect("failed to fetch file");
+    assert_eq!(avatar, common::AVATAR);
+
+    let err = http
+        .fetch_file(&url, common::AVATAR.len() - 1)
+        .await
+        .expect_err("fetched a file over the size limit");
+    assert!(matches!(err, Error::FileTooLarge { .. }));
+
+    let missing = server
+        .url()
+        .join("avatars/1")
+        .expect("failed to build the avatar URL");
+    http.fetch_file(&missing, 1024)
+        .await
+        .expect_err("fetched a file that doesn't exist");
+}
+
+#[tokio::test]
+async fn test_fetch_user() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let other = signed_in(&server, "purr").await;
+
+    let msg = other
+        .create_message(ChannelId(1), "hi")
+        .await
+        .expect("failed to create message");
+
+    let user = http
+        .fetch_user(msg.author.id)
+        .await
+        .expect("failed to fetch user");
+
+    assert_eq!(user.id, msg.author.id);
+    assert_eq!(user.name, "purr");
+}
+
+#[tokio::test]
+async fn test_delete_account() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let err = http
+        .delete_account("not_the_meower")
+        .await
+        .expect_err("deleted an account with the wrong password");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::Forbidden));
+
+    http.delete_account("the_meower")
+        .await
+        .expect("failed to delete the account");
+
+    // the token is gone along with the account
+    let err = http
+        .read_markers()
+        .await
+        .expect_err("the token outlived the account");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::Unauthorized));
+
+    let mut http = Http::new(server.url(), "quaddlecl tester".to_string())
+        .expect("could not create a REST client instance");
+    http.login("meow", "the_meower")
+        .await
+        .expect_err("logged into a deleted account");
+}
+
+#[tokio::test]
+async fn test_change_password() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let err = http
+        .change_password("not_the_meower", "the_new_meower")
+        .await
+        .expect_err("changed the password without knowing it");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::Forbidden));
+
+    http.change_password("the_meower", "the_new_meower")
+        .await
+        .expect("failed to change the password");
+
+    let mut http = Http::new(server.url(), "quaddlecl tester".to_string())
+        .expect("could not create a REST client instance");
+    http.login("meow", "the_meower")
+        .await
+        .expect_err("logged in with the old password");
+    http.login("meow", "the_new_meower")
+        .await
+        .expect("failed to log in with the new password");
+}
+
+#[tokio::test]
+async fn test_change_username() {
+    let server = FakeServer::start().await;
+    signed_in(&server, "purr").await;
+    let http = signed_in(&server, "meow").await;
+
+    let err = http
+        .change_username("purr")
+        .await
+        .expect_err("took the name of another user");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::Other));
+
+    let user = http
+        .change_username("mrrp")
+        .await
+        .expect("failed to change the name");
+    assert_eq!(user.name, "mrrp");
+    let fetched = http
+        .fetch_user(user.id)
+        .await
+        .expect("failed to fetch the renamed user");
+    assert_eq!(fetched.name, "mrrp");
+
+    let mut http = Http::new(server.url(), "quaddlecl tester".to_string())
+        .expect("could not create a REST client instance");
+    http.login("mrrp", "the_meower")
+        .await
+        .expect("failed to log in under the new name");
+}
+
+#[tokio::test]
+async fn test_reply() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let original = http
+        .create_message(ChannelId(1), "meow?")
+        .await
+        .expect("failed to send a message");
+    assert_eq!(original.reply_to, None);
+
+    let reply = http
+        .create_reply(ChannelId(1), original.id, "meow!")
+        .await
+        .expect("failed to reply");
+    assert_eq!(reply.reply_to, Some(original.id));
+
+    let fetched = http
+        .fetch_message(ChannelId(1), reply.id)
+        .await
+        .expect("failed to fetch the reply");
+    assert_eq!(fetched.reply_to, Some(original.id));
+
+    let err = http
+        .create_reply(ChannelId(2), original.id, "meow!")
+        .await
+        .expect_err("replied to a message in another channel");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::Other));
+}
+
+#[tokio::test]
+async fn test_refresh_token() {
+    let server = FakeServer::start().await;
+    let mut http = signed_in(&server, "meow").await;
+    let old_token = http.token().unwrap().to_string();
+
+    server.expire_tokens();
+    let err = http
+        .list_channels()
+        .await
+        .expect_err("an expired token was accepted");
+    assert!(matches!(err, Error::TokenExpired { .. }), "{err:?}");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::Unauthorized));
+
+    http.refresh_token()
+        .await
+        .expect("failed to refresh the token");
+    assert_ne!(http.token(), Some(old_token.as_str()));
+    http.list_channels()
+        .await
+        .expect("the refreshed token was rejected");
+
+    // the old token was revoked by the refresh
+    http.set_token(old_token);
+    let err = http
+        .refresh_token()
+        .await
+        .expect_err("refreshed a revoked token");
+    assert!(matches!(err, Error::TokenExpired { .. }), "{err:?}");
+}
+
+/// Swaps in a token of its own once the client's is turned away, and counts the requests sent.
+#[derive(Debug)]
+struct SwapToken {
+    token: String,
+    swapped: AtomicBool,
+    sends: Arc<AtomicU32>,
+}
+
+impl Middleware for SwapToken {
+    fn before_request<'a>(&'a self, req: &'a mut Request) -> HookFuture<'a, Result<(), Error>> {
+        self.sends.fetch_add(1, Ordering::SeqCst);
+        if self.swapped.load(Ordering::SeqCst) {
+            let token = self.token.parse().expect("invalid token");
+            req.headers_mut().insert(header::AUTHORIZATION, token);
+        }
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn after_response<'a>(
+        &'a self,
+        _: &'a Method,
+        _: &'a Url,
+        res: &'a mut Result<Response, Error>,
+    ) -> HookFuture<'a, Next> {
+        let rejected =
+            matches!(res, Err(e) if e.api_error_kind() == Some(ApiErrorKind::Unauthorized));
+        let next = match rejected && !self.swapped.swap(true, Ordering::SeqCst) {
+            true => Next::Resend,
+            false => Next::Continue,
+        };
+        Box::pin(future::ready(next))
+    }
+}
+
+#[tokio::test]
+async fn test_middleware() {
+    let server = FakeServer::start().await;
+    let token = signed_in(&server, "meow")
+        .await
+        .token()
+        .unwrap()
+        .to_string();
+
+    let sends = Arc::new(AtomicU32::new(0));
+    let mut http = Http::builder(server.url(), ConnectOptions::new("quaddlecl tester"))
+        .middleware(SwapToken {
+            token,
+            swapped: AtomicBool::new(false),
+            sends: Arc::clone(&sends),
+        })
+        .build()
+        .expect("could not create a REST client instance");
+    http.set_token("nonsense".to_string());
+
+    http.list_channels()
+        .await
+        .expect("the swapped in token was rejected");
+    assert_eq!(sends.load(Ordering::SeqCst), 2);
+}
+
+/// Adds a header on every send and asks for one resend, counting the sends
+/// and noting the most copies of the header a request went out with.
+#[derive(Debug)]
+struct AddHeader {
+    resent: AtomicBool,
+    sends: Arc<AtomicU32>,
+    most: Arc<AtomicU32>,
+}
+
+impl Middleware for AddHeader {
+    fn before_request<'a>(&'a self, req: &'a mut Request) -> HookFuture<'a, Result<(), Error>> {
+        self.sends.fetch_add(1, Ordering::SeqCst);
+        let headers = req.headers_mut();
+        headers.append("x-hooked", header::HeaderValue::from_static("1"));
+        let count = headers.get_all("x-hooked").iter().count() as u32;
+        self.most.fetch_max(count, Ordering::SeqCst);
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn after_response<'a>(
+        &'a self,
+        _: &'a Method,
+        _: &'a Url,
+        _: &'a mut Result<Response, Error>,
+    ) -> HookFuture<'a, Next> {
+        let next = match self.resent.swap(true, Ordering::SeqCst) {
+            false => Next::Resend,
+            true => Next::Continue,
+        };
+        Box::pin(future::ready(next))
+    }
+}
+
+#[tokio::test]
+async fn test_resend_starts_from_the_original_request() {
+    let server = FakeServer::start().await;
+    let token = signed_in(&server, "meow")
+        .await
+        .token()
+        .unwrap()
+        .to_string();
+
+    let sends = Arc::new(AtomicU32::new(0));
+    let most = Arc::new(AtomicU32::new(0));
+    let mut http = Http::builder(server.url(), ConnectOptions::new("quaddlecl tester"))
+        .middleware(AddHeader {
+            resent: AtomicBool::new(false),
+            sends: Arc::clone(&sends),
+            most: Arc::clone(&most),
+        })
+        .build()
+        .expect("could not create a REST client instance");
+    http.set_token(token);
+
+    http.list_channels().await.expect("could not list channels");
+    assert_eq!(sends.load(Ordering::SeqCst), 2);
+    assert_eq!(most.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_wrong_password_is_not_an_expired_token() {
+    let server = FakeServer::start().await;
+    signed_in(&server, "meow").await;
+
+    let mut http = Http::new(server.url(), "quaddlecl tester".to_string())
+        .expect("could not create a REST client instance");
+    let err = http
+        .login("meow", "not_the_meower")
+        .await
+        .expect_err("logged in with the wrong password");
+    assert!(matches!(err, Error::ApiError { .. }), "{err:?}");
+}
+
+#[tokio::test]
+async fn test_fetch_missing_user() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let err = http
+        .fetch_user(UserId(1))
+        .await
+        .expect_err("fetched a user that doesn't exist");
+
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::NotFound));
+}
+
+#[tokio::test]
+async fn test_upload_attachment() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let attachment = http
+        .upload_attachment(ChannelId(1), "paste.txt", "text/plain", b"mrrp".to_vec())
+        .await
+        .expect("failed to upload attachment");
+    assert_eq!(attachment.name, "paste.txt");
+    assert_eq!(attachment.size, 4);
+
+    let bytes = http
+        .fetch_file(&attachment.url, 1024)
+        .await
+        .expect("failed to fetch the attachment");
+    assert_eq!(bytes, b"mrrp");
+}
+
+#[tokio::test]
+async fn test_read_markers() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let markers = http
+        .read_markers()
+        .await
+        .expect("failed to list read markers");
+    assert!(markers.is_empty());
+
+    let msg = http
+        .create_message(ChannelId(1), "meow")
+        .await
+        .expect("failed to create message");
+    http.set_read_marker(ChannelId(1), msg.id)
+        .await
+        .expect("failed to set read marker");
+
+    let markers = http
+        .read_markers()
+        .await
+        .expect("failed to list read markers");
+    assert_eq!(
+        markers,
+        [ReadMarker {
+            channel_id: ChannelId(1),
+            message_id: msg.id,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_read_marker_event() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    // the marker event goes to every session, subscribed to the channel or not
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(2)).await;
+
+    let msg = http
+        .create_message(ChannelId(1), "meow")
+        .await
+        .expect("failed to create message");
+    http.set_read_marker(ChannelId(1), msg.id)
+        .await
+        .expect("failed to set read marker");
+
+    let GatewayEvent::ReadMarkerUpdate {
+        channel_id,
+        message_id,
+    } = next_event(&mut gateway).await
+    else {
+        panic!("expected a read marker update");
+    };
+    assert_eq!(channel_id, ChannelId(1));
+    assert_eq!(message_id, msg.id);
+}
+
+#[tokio::test]
+async fn test_ack_messages() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let mut newest = None;
+    for content in ["meow1", "meow2", "meow3"] {
+        newest = Some(
+            http.create_message(ChannelId(1), content)
+                .await
+                .expect("failed to create message"),
+        );
+    }
+    let newest = newest.unwrap();
+    http.ack_messages(ChannelId(1), newest.id)
+        .await
+        .expect("failed to acknowledge messages");
+
+    // the message has to be in the channel being acknowledged
+    let err = http
+        .ack_messages(ChannelId(2), newest.id)
+        .await
+        .expect_err("acknowledged a message of another channel");
+    assert_eq!(err.api_error_kind(), Some(ApiErrorKind::NotFound));
+}
+
+#[tokio::test]
+async fn test_history() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let mut sent = Vec::new();
+    for content in ["meow1", "meow2", "meow3"] {
+        sent.push(
+            http.create_message(ChannelId(1), content)
+                .await
+                .expect("failed to create message"),
+        );
+    }
+
+    let latest = http
+        .message_history(ChannelId(1), History::latest())
+        .await
+        .expect("failed to retrieve message history");
+
+    let contents: Vec<_> = latest.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["meow3", "meow2", "meow1"]);
+
+    let before = http
+        .message_history(ChannelId(1), History::before(sent[1].id))
+        .await
+        .expect("failed to retrieve message history");
+
+    assert_eq!(before.len(), 1);
+    assert_eq!(before[0].content, "meow1");
+}
+
+#[tokio::test]
+async fn test_history_range() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+
+    let mut sent = Vec::new();
+    for content in ["meow1", "meow2", "meow3", "meow4"] {
+        sent.push(
+            http.create_message(ChannelId(1), content)
+                .await
+                .expect("failed to create message"),
+        );
+    }
+
+    let range = History::before(sent[3].id)
+        .after(sent[0].id)
+        .expect("rejected a valid range");
+    let between = http
+        .message_history(ChannelId(1), range)
+        .await
+        .expect("failed to retrieve message history");
+
+    let contents: Vec<_> = between.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["meow3", "meow2"]);
+
+    let limited = http
+        .message_history(ChannelId(1), History::latest().limit(1))
+        .await
+        .expect("failed to retrieve message history");
+
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].content, "meow4");
+}
+
+#[tokio::test]
+async fn test_reconnect() {
+    let server = FakeServer::start().await;
+    let http = signed_in(&server, "meow").await;
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    server.disconnect_all();
+
+    assert!(
+        matches!(gateway.next().await, None | Some(Err(_))),
+        "gateway still open after being disconnected"
+    );
+
+    let mut gateway = subscribed_gateway(&server, &http, ChannelId(1)).await;
+
+    http.create_message(ChannelId(1), "still here")
+        .await
+        .expect("failed to send a message");
+
+    let GatewayEvent::MessageCreate { message } = next_event(&mut gateway).await else {
+        panic!("received an unexpected event")
+    };
+
+    assert_eq!(message.content, "still here");
+}
+
+#[tokio::test]
+async fn test_builder() {
+    let server = FakeServer::start().await;
+    let http = Http::builder(server.url(), ConnectOptions::new("quaddlecl tester"))
+        .connect_timeout(Duration::from_secs(5))
+        .read_timeout(Duration::from_secs(5))
+        .redirect(RedirectPolicy::none())
+        .build()
+        .expect("could not create a REST client instance");
+
+    let user = http
+        .signup("meow", "the_meower")
+        .await
+        .expect("failed to sign up");
+
+    assert_eq!(user.name, "meow");
+}
+
+#[tokio::test]
+async fn test_read_timeout() {
+    // accepts connections, but never answers on them
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("could not bind");
+    let addr = listener.local_addr().expect("no local address");
+    let (silent, _) = tokio::join!(listener.accept(), async {
+        let url = format!("http://{addr}")
+            .parse()
+            .expect("could not parse URL");
+        let http = Http::builder(url, ConnectOptions::new("quaddlecl tester"))
+            .read_timeout(Duration::from_millis(100))
+            .build()
+            .expect("could not create a REST client instance");
+
+        let err = http
+            .signup("meow", "the_meower")
+            .await
+            .expect_err("signed up with a server that never answered");
+        assert!(err.is_network_error());
+    });
+    drop(silent);
+}
@@ -89,6 +89,10 @@
               patchelf $out/bin/eyeqwst --add-needed ${pkgs.xorg.libX11}/lib/libX11.so
             '';
 
+            postInstall = ''
+              install -Dm644 assets/eyeqwst.desktop $out/share/applications/eyeqwst.desktop
+            '';
+
             src = ./.;
           };
           eyeqwst-wrapped = pkgs.writeShellScriptBin "eyeqwst" ''
@@ -0,0 +1,140 @@
+//! Deleting the logged in account, which has to be confirmed
+//! by typing its name and password again.
+
+use std::sync::Arc;
+
+use iced::widget::{button, column, row, text, text_input};
+use iced::{theme, Element, Length, Theme};
+use quaddlecl::client::http::{self, ApiErrorKind};
+use quaddlecl::model::user::User;
+
+use crate::utils::{ErrorSummary, TextInputExt};
+
+#[derive(Debug, Clone)]
+pub enum AccountDeletionMessage {
+    NameEdited(String),
+    PasswordEdited(String),
+    Submitted,
+    /// Handled by the main screen, which closes the form.
+    Cancelled,
+    Failed(Arc<http::Error>),
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Idle,
+    InProgress,
+    Failed(Arc<http::Error>),
+}
+
+#[derive(Debug, Default)]
+pub struct AccountDeletion {
+    name: String,
+    password: String,
+    state: State,
+}
+
+impl AccountDeletion {
+    fn is_confirmed(&self, user: &User) -> bool {
+        self.name == user.name && !self.password.is_empty()
+    }
+
+    /// Returns the password to delete the account with, once the deletion is confirmed.
+    pub fn update(&mut self, message: AccountDeletionMessage, user: &User) -> Option<String> {
+        match message {
+            AccountDeletionMessage::NameEdited(name) => self.name = name,
+            AccountDeletionMessage::PasswordEdited(password) => self.password = password,
+            AccountDeletionMessage::Submitted
+                if self.is_confirmed(user) && !matches!(self.state, State::InProgress) =>
+            {
+                self.state = State::InProgress;
+                return Some(self.password.clone());
+            }
+            AccountDeletionMessage::Failed(e) => self.fail(e),
+            AccountDeletionMessage::Submitted | AccountDeletionMessage::Cancelled => {}
+        }
+        None
+    }
+
+    /// Lets the deletion be submitted again, showing why it didn't go through.
+    pub fn fail(&mut self, error: Arc<http::Error>) {
+        self.state = State::Failed(error);
+    }
+
+    pub fn view(&self, user: &User, theme: &Theme) -> Element<'_, AccountDeletionMessage> {
+        let idle = !matches!(self.state, State::InProgress);
+        let error = match &self.state {
+            State::Failed(e) if e.api_error_kind() == Some(ApiErrorKind::Forbidden) => {
+                Some("Wrong password".to_string())
+            }
+            State::Failed(e) => Some(format!("Could not delete the account: {}", ErrorSummary(e))),
+            State::Idle | State::InProgress => None,
+        };
+
+        column![
+            text(format!(
+                "Deleting {name} can't be undone. Type the account's name and password to confirm.",
+                name = user.name
+            ))
+            .size(14),
+            text_input(&user.name, &self.name)
+                .on_input_if(idle, AccountDeletionMessage::NameEdited)
+                .on_submit(AccountDeletionMessage::Submitted),
+            text_input("Password", &self.password)
+                .secure(true)
+                .on_input_if(idle, AccountDeletionMessage::PasswordEdited)
+                .on_submit(AccountDeletionMessage::Submitted),
+        ]
+        .push_maybe(error.map(|error| text(error).size(14).style(theme.palette().danger)))
+        .push(
+            row![
+                button(text("Cancel").size(14))
+                    .style(theme::Button::Text)
+                    .on_press_maybe(idle.then_some(AccountDeletionMessage::Cancelled)),
+                iced::widget::horizontal_space(),
+                button(text(if idle { "Delete" } else { "Deleting…" }).size(14))
+                    .style(theme::Button::Destructive)
+                    .on_press_maybe(
+                        (idle && self.is_confirmed(user))
+                            .then_some(AccountDeletionMessage::Submitted),
+                    ),
+            ]
+            .width(Length::Fill),
+        )
+        .spacing(10)
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_allows_retrying() {
+        let user = User::fake("meow");
+        let mut form = AccountDeletion::default();
+        form.update(
+            AccountDeletionMessage::NameEdited("meow".to_string()),
+            &user,
+        );
+        form.update(
+            AccountDeletionMessage::PasswordEdited("mrrp".to_string()),
+            &user,
+        );
+
+        assert_eq!(
+            form.update(AccountDeletionMessage::Submitted, &user),
+            Some("mrrp".to_string())
+        );
+        assert_eq!(form.update(AccountDeletionMessage::Submitted, &user), None);
+
+        form.fail(Arc::new(http::Error::AuthorizationNeeded));
+        assert!(matches!(form.state, State::Failed(_)));
+        assert_eq!(
+            form.update(AccountDeletionMessage::Submitted, &user),
+            Some("mrrp".to_string())
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Renaming the logged in account and changing its password. Both ask
+//! the user to confirm before anything is sent to the server.
+
+use std::sync::Arc;
+
+use iced::widget::{button, column, row, text, text_input};
+use iced::{theme, Element, Length, Theme};
+use quaddlecl::client::http::{self, ApiErrorKind};
+use quaddlecl::model::user::User;
+
+use crate::utils::ErrorSummary;
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Editing,
+    Confirming,
+    InProgress,
+    Failed(Arc<http::Error>),
+    Done,
+}
+
+impl State {
+    fn is_editable(&self) -> bool {
+        matches!(self, State::Editing | State::Failed(_))
+    }
+}
+
+/// Cancel on the left, the action that moves the form along on the right.
+fn buttons<'a, Message: Clone + 'a>(
+    cancel: Option<Message>,
+    label: &'a str,
+    style: theme::Button,
+    on_press: Option<Message>,
+) -> Element<'a, Message> {
+    row![
+        button(text("Cancel").size(14))
+            .style(theme::Button::Text)
+            .on_press_maybe(cancel),
+        iced::widget::horizontal_space(),
+        button(text(label).size(14))
+            .style(style)
+            .on_press_maybe(on_press),
+    ]
+    .width(Length::Fill)
+    .into()
+}
+
+fn error_text<'a, Message: 'a>(
+    error: Option<String>,
+    theme: &Theme,
+) -> Option<Element<'a, Message>> {
+    error.map(|error| text(error).size(14).style(theme.palette().danger).into())
+}
+
+#[derive(Debug, Clone)]
+pub enum RenameMessage {
+    NameEdited(String),
+    Submitted,
+    Confirmed,
+    /// Goes back from the confirmation to editing the name.
+    Back,
+    /// Handled by the main screen, which closes the form.
+    Cancelled,
+    /// Handled by the main screen, which closes the form and shows the new name.
+    Succeeded(User),
+    Failed(Arc<http::Error>),
+}
+
+#[derive(Debug, Default)]
+pub struct Rename {
+    name: String,
+    state: State,
+}
+
+impl Rename {
+    fn is_valid(&self, user: &User) -> bool {
+        let name = self.name.trim();
+        (1..1024).contains(&name.len()) && name != user.name
+    }
+
+    /// Returns the new name once the rename is confirmed.
+    pub fn update(&mut self, message: RenameMessage, user: &User) -> Option<String> {
+        match message {
+            RenameMessage::NameEdited(name) if self.state.is_editable() => self.name = name,
+            RenameMessage::Submitted if self.state.is_editable() && self.is_valid(user) => {
+                self.state = State::Confirming
+            }
+            RenameMessage::Back if matches!(self.state, State::Confirming) => {
+                self.state = State::Editing
+            }
+            RenameMessage::Confirmed if matches!(self.state, State::Confirming) => {
+                self.state = State::InProgress;
+                return Some(self.name.trim().to_string());
+            }
+            RenameMessage::Failed(e) => self.state = State::Failed(e),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn view(&self, user: &User, theme: &Theme) -> Element<'_, RenameMessage> {
+        if let State::Confirming | State::InProgress = self.state {
+            let in_progress = matches!(self.state, State::InProgress);
+            return column![
+                text(format!(
+                    "Rename {old} to {new}? Others will see the new name on your messages.",
+                    old = user.name,
+                    new = self.name.trim()
+                ))
+                .size(14),
+                buttons(
+                    (!in_progress).then_some(RenameMessage::Back),
+                    if in_progress { "Renaming…" } else { "Rename" },
+                    theme::Button::Primary,
+                    (!in_progress).then_some(RenameMessage::Confirmed),
+                ),
+            ]
+            .spacing(10)
+            .into();
+        }
+
+        let error = match &self.state {
+            State::Failed(e) if e.api_error_kind() == Some(ApiErrorKind::Other) => {
+                Some("The name is taken or not allowed".to_string())
+            }
+            State::Failed(e) => Some(format!("Could not rename the account: {}", ErrorSummary(e))),
+            _ => None,
+        };
+
+        column![
+            text("New name").size(14),
+            text_input(&user.name, &self.name)
+                .on_input(RenameMessage::NameEdited)
+                .on_submit(RenameMessage::Submitted),
+        ]
+        .push_maybe(error_text(error, theme))
+        .push(buttons(
+            Some(RenameMessage::Cancelled),
+            "Continue",
+            theme::Button::Primary,
+            self.is_valid(user).then_some(RenameMessage::Submitted),
+        ))
+        .spacing(10)
+        .into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PasswordChangeMessage {
+    OldEdited(String),
+    NewEdited(String),
+    RepeatEdited(String),
+    Submitted,
+    Confirmed,
+    /// Goes back from the confirmation to editing the passwords.
+    Back,
+    /// Handled by the main screen, which closes the form.
+    Cancelled,
+    Succeeded,
+    Failed(Arc<http::Error>),
+}
+
+#[derive(Debug, Default)]
+pub struct PasswordChange {
+    old: String,
+    new: String,
+    repeat: String,
+    state: State,
+}
+
+impl PasswordChange {
+    fn is_valid(&self) -> bool {
+        !self.old.is_empty() && (1..1024).contains(&self.new.len()) && self.new == self.repeat
+    }
+
+    /// Returns the old and the new password once the change is confirmed.
+    pub fn update(&mut self, message: PasswordChangeMessage) -> Option<(String, String)> {
+        match message {
+            PasswordChangeMessage::OldEdited(old) if self.state.is_editable() => self.old = old,
+            PasswordChangeMessage::NewEdited(new) if self.state.is_editable() => self.new = new,
+            PasswordChangeMessage::RepeatEdited(repeat) if self.state.is_editable() => {
+                self.repeat = repeat
+            }
+            PasswordChangeMessage::Submitted if self.state.is_editable() && self.is_valid() => {
+                self.state = State::Confirming
+            }
+            PasswordChangeMessage::Back if matches!(self.state, State::Confirming) => {
+                self.state = State::Editing
+            }
+            PasswordChangeMessage::Confirmed if matches!(self.state, State::Confirming) => {
+                self.state = State::InProgress;
+                return Some((self.old.clone(), self.new.clone()));
+            }
+            PasswordChangeMessage::Succeeded => {
+                *self = Self {
+                    state: State::Done,
+                    ..Self::default()
+                }
+            }
+            PasswordChangeMessage::Failed(e) => self.state = State::Failed(e),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn view(&self, theme: &Theme) -> Element<'_, PasswordChangeMessage> {
+        match self.state {
+            State::Done => {
+                return column![
+                    text("The password has been changed.").size(14),
+                    button(text("Close").size(14))
+                        .style(theme::Button::Text)
+                        .on_press(PasswordChangeMessage::Cancelled),
+                ]
+                .spacing(10)
+                .into()
+            }
+            State::Confirming | State::InProgress => {
+                let in_progress = matches!(self.state, State::InProgress);
+                return column![
+                    text(
+                        "Change the password? You'll need the new one to log in on other devices."
+                    )
+                    .size(14),
+                    buttons(
+                        (!in_progress).then_some(PasswordChangeMessage::Back),
+                        if in_progress { "Changing…" } else { "Change" },
+                        theme::Button::Primary,
+                        (!in_progress).then_some(PasswordChangeMessage::Confirmed),
+                    ),
+                ]
+                .spacing(10)
+                .into();
+            }
+            State::Editing | State::Failed(_) => {}
+        }
+
+        let error = match &self.state {
+            State::Failed(e) if e.api_error_kind() == Some(ApiErrorKind::Forbidden) => {
+                Some("Wrong password".to_string())
+            }
+            State::Failed(e) => Some(format!(
+                "Could not change the password: {}",
+                ErrorSummary(e)
+            )),
+            _ if !self.repeat.is_empty() && self.new != self.repeat => {
+                Some("The new passwords don't match".to_string())
+            }
+            _ => None,
+        };
+
+        let input = |placeholder, value, on_input: fn(String) -> PasswordChangeMessage| {
+            text_input(placeholder, value)
+                .secure(true)
+                .on_input(on_input)
+                .on_submit(PasswordChangeMessage::Submitted)
+        };
+
+        column![
+            input(
+                "Current password",
+                &self.old,
+                PasswordChangeMessage::OldEdited
+            ),
+            input("New password", &self.new, PasswordChangeMessage::NewEdited),
+            input(
+                "Repeat the new password",
+                &self.repeat,
+                PasswordChangeMessage::RepeatEdited
+            ),
+        ]
+        .push_maybe(error_text(error, theme))
+        .push(buttons(
+            Some(PasswordChangeMessage::Cancelled),
+            "Continue",
+            theme::Button::Primary,
+            self.is_valid().then_some(PasswordChangeMessage::Submitted),
+        ))
+        .spacing(10)
+        .into()
+    }
+}
@@ -0,0 +1,168 @@
+//! The accounts that are logged in at the same time, and the rail for switching between them.
+
+use iced::widget::{button, column, container, text, tooltip, Column};
+use iced::{theme, Alignment, Element, Length};
+
+use crate::channel_select::unread_badge;
+use crate::config::Config;
+use crate::main_screen::MainScreen;
+use crate::toggle_button::pressed_button_style;
+use crate::utils::icon;
+
+const ADD_ICON: &str = "\u{f067}";
+
+/// Tells sessions apart, even after the ones before them are closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionId(u64);
+
+#[derive(Debug, Clone, Copy)]
+pub enum SwitcherMessage {
+    Selected(SessionId),
+    AddRequested,
+}
+
+/// Every logged in account. Sessions of the same account share a gateway connection.
+#[derive(Debug, Default)]
+pub struct Sessions {
+    screens: Vec<(SessionId, MainScreen)>,
+    active: usize,
+    next_id: u64,
+}
+
+impl Sessions {
+    /// Adds a session and brings it to the front.
+    pub fn add(&mut self, screen: MainScreen, config: &mut Config) -> SessionId {
+        let id = SessionId(self.next_id);
+        self.next_id += 1;
+        self.screens.push((id, screen));
+        self.select(id, config);
+        id
+    }
+
+    /// Closes a session, bringing the one before it to the front if it was there.
+    pub fn remove(&mut self, id: SessionId, config: &mut Config) -> Option<MainScreen> {
+        let idx = self.position(id)?;
+        let (_, screen) = self.screens.remove(idx);
+        let was_active = idx == self.active;
+        if idx < self.active || (was_active && idx > 0) {
+            self.active -= 1;
+        }
+        if was_active {
+            if let Some((_, screen)) = self.screens.get_mut(self.active) {
+                screen.set_active(true, config);
+            }
+        }
+        Some(screen)
+    }
+
+    pub fn select(&mut self, id: SessionId, config: &mut Config) {
+        let Some(idx) = self.position(id) else {
+            return;
+        };
+        for (i, (_, screen)) in self.screens.iter_mut().enumerate() {
+            screen.set_active(i == idx, config);
+        }
+        self.active = idx;
+    }
+
+    pub fn get_mut(&mut self, id: SessionId) -> Option<&mut MainScreen> {
+        self.screens
+            .iter_mut()
+            .find(|(sid, _)| *sid == id)
+            .map(|(_, screen)| screen)
+    }
+
+    pub fn active(&self) -> Option<(SessionId, &MainScreen)> {
+        self.screens
+            .get(self.active)
+            .map(|(id, screen)| (*id, screen))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SessionId, &MainScreen)> {
+        self.screens.iter().map(|(id, screen)| (*id, screen))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SessionId, &mut MainScreen)> {
+        self.screens.iter_mut().map(|(id, screen)| (*id, screen))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.screens.is_empty()
+    }
+
+    fn position(&self, id: SessionId) -> Option<usize> {
+        self.screens.iter().position(|(sid, _)| *sid == id)
+    }
+
+    /// A narrow column with one button per account, each showing its unread count.
+    pub fn view(&self) -> Element<'_, SwitcherMessage> {
+        let accounts = self.screens.iter().enumerate().map(|(i, (id, screen))| {
+            let host = screen
+                .server()
+                .host_str()
+                .unwrap_or(screen.server().as_str());
+            let (initial, label) = match screen.user() {
+                Some(user) => (
+                    user.name
+                        .chars()
+                        .next()
+                        .unwrap_or('?')
+                        .to_uppercase()
+                        .to_string(),
+                    format!("{name} on {host}", name = user.name),
+                ),
+                None => ("…".to_string(), format!("Connecting to {host}")),
+            };
+            let unread = screen.unread_total();
+
+            tooltip(
+                button(
+                    column![text(initial).font(crate::DEFAULT_FONT_MEDIUM).size(18)]
+                        .push_maybe((unread > 0).then(|| unread_badge(unread)))
+                        .align_items(Alignment::Center)
+                        .spacing(2),
+                )
+                .on_press(SwitcherMessage::Selected(*id))
+                .style(if i == self.active {
+                    pressed_button_style(theme::Button::Secondary)
+                } else {
+                    theme::Button::Secondary
+                })
+                .width(44)
+                .padding([8, 0]),
+                text(label).size(12),
+                tooltip::Position::Right,
+            )
+            .style(theme::Container::Box)
+            .padding(5)
+            .into()
+        });
+
+        let add = tooltip(
+            button(
+                container(icon(ADD_ICON).size(16))
+                    .width(Length::Fill)
+                    .center_x(),
+            )
+            .on_press(SwitcherMessage::AddRequested)
+            .style(theme::Button::Text)
+            .width(44)
+            .padding([8, 0]),
+            text("Add account").size(12),
+            tooltip::Position::Right,
+        )
+        .style(theme::Container::Box)
+        .padding(5);
+
+        container(
+            Column::with_children(accounts)
+                .push(add)
+                .spacing(10)
+                .align_items(Alignment::Center),
+        )
+        .height(Length::Fill)
+        .padding([10, 5])
+        .style(theme::Container::Box)
+        .into()
+    }
+}
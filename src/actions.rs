@@ -0,0 +1,101 @@
+//! Everything that can be done from the keyboard, shared by the
+//! keybindings and the command palette so that the two never disagree.
+
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    CommandPalette,
+    SwitchChannel,
+    FindInChannel,
+    SavedMessages,
+    PreviousChannel,
+    NextChannel,
+    ToggleTheme,
+    TogglePlayAnimations,
+    CyclePastedFileFormat,
+    ToggleEnterToSend,
+    ToggleStrictMode,
+    CycleOwnMessageStyle,
+    CycleNotificationPreview,
+    ExportHistory,
+    OpenSettings,
+    Reconnect,
+    WhatsNew,
+}
+
+impl Action {
+    /// In the order the command palette lists them.
+    pub const ALL: [Action; 17] = [
+        Action::SwitchChannel,
+        Action::FindInChannel,
+        Action::SavedMessages,
+        Action::PreviousChannel,
+        Action::NextChannel,
+        Action::ToggleTheme,
+        Action::TogglePlayAnimations,
+        Action::CyclePastedFileFormat,
+        Action::ToggleEnterToSend,
+        Action::ToggleStrictMode,
+        Action::CycleOwnMessageStyle,
+        Action::CycleNotificationPreview,
+        Action::ExportHistory,
+        Action::OpenSettings,
+        Action::Reconnect,
+        Action::WhatsNew,
+        Action::CommandPalette,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::CommandPalette => "Show all commands",
+            Action::SwitchChannel => "Switch channel",
+            Action::FindInChannel => "Find in channel",
+            Action::SavedMessages => "Show saved messages",
+            Action::PreviousChannel => "Go to previous channel",
+            Action::NextChannel => "Go to next channel",
+            Action::ToggleTheme => "Toggle dark theme",
+            Action::TogglePlayAnimations => "Toggle playing animations right away",
+            Action::CyclePastedFileFormat => "Change what long pastes are attached as",
+            Action::ToggleEnterToSend => "Toggle sending with Enter",
+            Action::ToggleStrictMode => "Toggle strict mode",
+            Action::CycleOwnMessageStyle => "Change how your own messages look",
+            Action::CycleNotificationPreview => "Change how much notifications show",
+            Action::ExportHistory => "Export channel history",
+            Action::OpenSettings => "Open settings file",
+            Action::Reconnect => "Reconnect to the server",
+            Action::WhatsNew => "Show what's new",
+        }
+    }
+
+    /// The key combination bound to the action, as shown to the user.
+    pub fn shortcut(self) -> Option<&'static str> {
+        match self {
+            Action::CommandPalette => Some("Ctrl+Shift+P"),
+            Action::SwitchChannel => Some("Ctrl+K"),
+            Action::FindInChannel => Some("Ctrl+F"),
+            Action::PreviousChannel => Some("Alt+Up"),
+            Action::NextChannel => Some("Alt+Down"),
+            _ => None,
+        }
+    }
+}
+
+/// The action bound to a key combination, if any.
+pub fn from_key(key: &Key, modifiers: Modifiers) -> Option<Action> {
+    match key.as_ref() {
+        // shift changes the character on some layouts, so compare case-insensitively
+        Key::Character(c)
+            if c.eq_ignore_ascii_case("p")
+                && modifiers == Modifiers::COMMAND | Modifiers::SHIFT =>
+        {
+            Some(Action::CommandPalette)
+        }
+        Key::Character("k") if modifiers == Modifiers::COMMAND => Some(Action::SwitchChannel),
+        Key::Character("f") if modifiers == Modifiers::COMMAND => Some(Action::FindInChannel),
+        Key::Named(Named::ArrowUp) if modifiers == Modifiers::ALT => Some(Action::PreviousChannel),
+        Key::Named(Named::ArrowDown) if modifiers == Modifiers::ALT => Some(Action::NextChannel),
+        _ => None,
+    }
+}
@@ -0,0 +1,133 @@
+//! How busy each channel was over the last day, going by the messages that were
+//! loaded or arrived over the gateway, and the sparkline that shows it in the sidebar.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use iced::widget::{container, row, text, tooltip, Space};
+use iced::{theme, Alignment, Border, Element, Theme};
+use quaddlecl::model::channel::ChannelId;
+use quaddlecl::model::message::MessageId;
+use quaddlecl::model::snowflake::Snowflake;
+
+/// How many hours the sparkline covers, one bar each.
+pub const HOURS: usize = 24;
+
+const BAR_WIDTH: f32 = 2.0;
+const HEIGHT: f32 = 14.0;
+
+#[derive(Debug, Default)]
+pub struct Activity {
+    /// Messages of the last day, by channel. Ids are kept rather than counts
+    /// so that messages seen both in history and over the gateway count once.
+    messages: HashMap<ChannelId, BTreeSet<MessageId>>,
+}
+
+impl Activity {
+    /// Takes note of messages in `channel_id`, forgetting those older than a day.
+    pub fn record(&mut self, channel_id: ChannelId, ids: impl IntoIterator<Item = MessageId>) {
+        let since = Utc::now() - TimeDelta::hours(HOURS as i64);
+        let messages = self.messages.entry(channel_id).or_default();
+        messages.extend(ids.into_iter().filter(|id| id.timestamp() >= since));
+        messages.retain(|id| id.timestamp() >= since);
+    }
+
+    /// Messages per hour in `channel_id`, the oldest hour first.
+    pub fn hourly(&self, channel_id: ChannelId, now: DateTime<Utc>) -> [usize; HOURS] {
+        let mut hours = [0; HOURS];
+        for id in self.messages.get(&channel_id).into_iter().flatten() {
+            let age = (now - id.timestamp()).num_hours();
+            match usize::try_from(age) {
+                Ok(age) if age < HOURS => hours[HOURS - 1 - age] += 1,
+                _ => {}
+            }
+        }
+        hours
+    }
+
+    /// A row of bars, one per hour, or nothing if the channel was quiet all day.
+    pub fn sparkline<'a, Message: 'a>(
+        &self,
+        channel_id: ChannelId,
+    ) -> Option<Element<'a, Message>> {
+        let hours = self.hourly(channel_id, Utc::now());
+        let busiest = hours.iter().copied().max().filter(|&n| n > 0)?;
+        let total: usize = hours.iter().sum();
+
+        let bars = row(hours.iter().map(|&count| {
+            let height = match count {
+                0 => 1.0,
+                n => (HEIGHT * n as f32 / busiest as f32).max(2.0),
+            };
+            container(Space::new(BAR_WIDTH, height))
+                .style(bar_style(count > 0))
+                .into()
+        }))
+        .spacing(1)
+        .height(HEIGHT)
+        .align_items(Alignment::End);
+
+        Some(
+            tooltip(
+                bars,
+                text(format!("{total} messages in the last day")).size(12),
+                tooltip::Position::FollowCursor,
+            )
+            .style(theme::Container::Box)
+            .padding(5)
+            .into(),
+        )
+    }
+}
+
+fn bar_style(active: bool) -> impl Fn(&Theme) -> container::Appearance {
+    move |t: &Theme| {
+        use iced::widget::container::StyleSheet;
+        let palette = t.extended_palette();
+        let color = if active {
+            palette.primary.weak.color
+        } else {
+            palette.background.strong.color
+        };
+        container::Appearance {
+            background: Some(iced::Background::Color(color)),
+            border: Border::with_radius(1),
+            ..t.appearance(&theme::Container::Transparent)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hourly() {
+        let now = Utc::now();
+        let ago = |minutes| MessageId::from_timestamp(now - TimeDelta::minutes(minutes));
+        let recent = ago(30);
+        let mut activity = Activity::default();
+        activity.record(ChannelId(1), [recent, ago(150), ago(310), ago(315)]);
+        // seen again over the gateway, and one that's too old to count
+        activity.record(ChannelId(1), [recent, ago(HOURS as i64 * 60 + 30)]);
+
+        let hours = activity.hourly(ChannelId(1), now);
+        let mut expected = [0; HOURS];
+        expected[HOURS - 1] = 1;
+        expected[HOURS - 3] = 1;
+        expected[HOURS - 6] = 2;
+        assert_eq!(hours, expected);
+
+        assert_eq!(activity.hourly(ChannelId(2), now), [0; HOURS]);
+    }
+
+    #[test]
+    fn test_hourly_ignores_the_future() {
+        let now = Utc::now();
+        let mut activity = Activity::default();
+        activity.record(ChannelId(1), [MessageId::from_timestamp(now)]);
+
+        let earlier = now - TimeDelta::hours(2);
+        assert_eq!(activity.hourly(ChannelId(1), earlier), [0; HOURS]);
+    }
+}
@@ -0,0 +1,283 @@
+//! Animated images. The renderer only ever draws the first frame of a GIF, so
+//! animated ones have all of their frames decoded here, together with how long
+//! each is shown. They stand still on their first frame until they're [played],
+//! unless the user asked for animations to play right away.
+//!
+//! [played]: Playback::play
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::time::Duration;
+
+use iced::time::Instant;
+use iced::widget::image::Handle;
+use iced::widget::{container, row, text};
+use iced::{Alignment, Color, Element, Theme};
+use url::Url;
+
+use crate::utils::icon;
+
+const PLAY: &str = "\u{f04b}";
+
+/// Animations whose frames take up more than this once decoded only show the first one.
+const MAX_ANIMATION_BYTES: usize = 64 * 1024 * 1024;
+
+/// Frames meant to be shown for less than this are shown for [`DEFAULT_FRAME_DELAY`]
+/// instead, as browsers do and as GIFs made for them count on.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// How often playing animations are moved on to the frame they're at.
+pub const FRAME_TICK: Duration = Duration::from_millis(30);
+
+/// A downloaded image, ready to be shown.
+#[derive(Debug, Clone)]
+pub enum Decoded {
+    Still(Handle),
+    Animated(Animation),
+}
+
+impl Decoded {
+    /// The image, or the first frame of the animation.
+    pub fn first(&self) -> &Handle {
+        match self {
+            Decoded::Still(handle) => handle,
+            Decoded::Animated(animation) => animation.first(),
+        }
+    }
+}
+
+/// The frames of an animated GIF, each with how long it's shown for.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<(Handle, Duration)>,
+    /// How long it takes to go through all of them once.
+    length: Duration,
+}
+
+impl Animation {
+    /// `None` unless `bytes` are a GIF with more than one frame that isn't too big.
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        use ::image::codecs::gif::GifDecoder;
+        use ::image::AnimationDecoder;
+
+        let decoder = GifDecoder::new(Cursor::new(bytes)).ok()?;
+        let mut frames = Vec::new();
+        let mut size = 0;
+        for frame in decoder.into_frames() {
+            let frame = frame.ok()?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_millis(u64::from(numer / denom.max(1)));
+            let delay = match delay < MIN_FRAME_DELAY {
+                true => DEFAULT_FRAME_DELAY,
+                false => delay,
+            };
+            let buffer = frame.into_buffer();
+            size += buffer.as_raw().len();
+            if size > MAX_ANIMATION_BYTES {
+                return None;
+            }
+            let (width, height) = buffer.dimensions();
+            frames.push((Handle::from_pixels(width, height, buffer.into_raw()), delay));
+        }
+        (frames.len() > 1).then(|| Self::new(frames))
+    }
+
+    fn new(frames: Vec<(Handle, Duration)>) -> Self {
+        let length = frames.iter().map(|(_, delay)| *delay).sum();
+        Self { frames, length }
+    }
+
+    pub fn first(&self) -> &Handle {
+        &self.frames[0].0
+    }
+
+    /// The frame shown `elapsed` after the animation started. It loops forever.
+    pub fn frame_at(&self, elapsed: Duration) -> &Handle {
+        let length = self.length.as_millis().max(1);
+        let mut left = Duration::from_millis((elapsed.as_millis() % length) as u64);
+        for (handle, delay) in &self.frames {
+            if left < *delay {
+                return handle;
+            }
+            left -= *delay;
+        }
+        self.first()
+    }
+}
+
+/// Decodes every frame of animated GIFs, and leaves everything else to the renderer.
+pub fn decode_now(bytes: Vec<u8>) -> Decoded {
+    let animation = bytes
+        .starts_with(b"GIF8")
+        .then(|| Animation::decode(&bytes))
+        .flatten();
+    match animation {
+        Some(animation) => Decoded::Animated(animation),
+        None => Decoded::Still(Handle::from_memory(bytes)),
+    }
+}
+
+/// Decoding all frames of a GIF can take a while, so it's done on tokio's blocking threads.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn decode(bytes: Vec<u8>) -> Decoded {
+    tokio::task::spawn_blocking(move || decode_now(bytes))
+        .await
+        .expect("decoding an image panicked")
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn decode(bytes: Vec<u8>) -> Decoded {
+    decode_now(bytes)
+}
+
+/// Which animations were played and how far along they are, going by a clock
+/// that is moved on every [`FRAME_TICK`] while any of them needs it.
+#[derive(Debug, Default)]
+pub struct Playback {
+    /// The animations that were clicked to play, with when that was.
+    playing: HashMap<Url, Instant>,
+    /// When the first and the latest tick came in. Animations
+    /// that play right away all started at the first.
+    clock: Option<(Instant, Instant)>,
+}
+
+impl Playback {
+    /// Starts playing the animation at `url` from its first frame.
+    pub fn play(&mut self, url: &Url) {
+        self.playing.insert(url.clone(), Instant::now());
+    }
+
+    /// Forgets that the animation at `url` was played, e.g. once it's no longer kept.
+    pub fn forget(&mut self, url: &Url) {
+        self.playing.remove(url);
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        let started = self.clock.map_or(now, |(started, _)| started);
+        self.clock = Some((started, now));
+    }
+
+    /// Whether an animation was played, and so needs ticks.
+    pub fn is_playing(&self) -> bool {
+        !self.playing.is_empty()
+    }
+
+    /// The frame the animation at `url` is at, and whether it waits to be played.
+    /// Animations play right away with `autoplay`.
+    pub fn frame<'a>(
+        &self,
+        url: &Url,
+        animation: &'a Animation,
+        autoplay: bool,
+    ) -> (&'a Handle, bool) {
+        let started = match self.playing.get(url) {
+            Some(started) => Some(*started),
+            None if autoplay => self.clock.map(|(started, _)| started),
+            None => return (animation.first(), true),
+        };
+        let elapsed = match (started, self.clock) {
+            (Some(started), Some((_, now))) => now.saturating_duration_since(started),
+            _ => Duration::ZERO,
+        };
+        (animation.frame_at(elapsed), false)
+    }
+}
+
+/// Marks an animated image that's waiting to be played.
+pub fn play_badge<'a, Message: 'a>() -> Element<'a, Message> {
+    container(
+        row![icon(PLAY).size(12), text("GIF").size(12)]
+            .spacing(4)
+            .align_items(Alignment::Center),
+    )
+    .padding([2, 6])
+    .style(|_: &Theme| container::Appearance {
+        text_color: Some(Color::WHITE),
+        background: Some(iced::Background::Color(Color {
+            a: 0.7,
+            ..Color::BLACK
+        })),
+        border: iced::Border::with_radius(3),
+        ..Default::default()
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gif(frames: u32) -> Vec<u8> {
+        use ::image::codecs::gif::GifEncoder;
+        use ::image::{Delay, Frame, Rgba, RgbaImage};
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for i in 0..frames {
+                let pixels = RgbaImage::from_pixel(2, 2, Rgba([i as u8 * 100, 0, 0, 255]));
+                let delay = Delay::from_numer_denom_ms(50, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(pixels, 0, 0, delay))
+                    .expect("failed to encode a frame");
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decode_animation() {
+        let Decoded::Animated(animation) = decode_now(gif(3)) else {
+            panic!("an animated GIF wasn't decoded as one");
+        };
+        assert_eq!(animation.frames.len(), 3);
+        assert_eq!(animation.length, Duration::from_millis(150));
+
+        assert!(matches!(decode_now(gif(1)), Decoded::Still(_)));
+        assert!(matches!(
+            decode_now(b"not a gif".to_vec()),
+            Decoded::Still(_)
+        ));
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let frame = |ms, shade| {
+            (
+                Handle::from_pixels(1, 1, vec![shade; 4]),
+                Duration::from_millis(ms),
+            )
+        };
+        let animation = Animation::new(vec![frame(100, 0), frame(50, 255)]);
+        let at = |ms| animation.frame_at(Duration::from_millis(ms)).id();
+        let [first, second] = [0, 1].map(|i| animation.frames[i].0.id());
+        assert_eq!(at(0), first);
+        assert_eq!(at(99), first);
+        assert_eq!(at(100), second);
+        assert_eq!(at(149), second);
+        // it loops
+        assert_eq!(at(150), first);
+        assert_eq!(at(1120), first);
+        assert_eq!(at(1170), second);
+    }
+
+    #[test]
+    fn test_paused_until_played() {
+        let Decoded::Animated(animation) = decode_now(gif(2)) else {
+            panic!("an animated GIF wasn't decoded as one");
+        };
+        let url = Url::parse("https://cdn.example/cat.gif").unwrap();
+        let mut playback = Playback::default();
+        assert!(playback.frame(&url, &animation, false).1);
+        assert!(!playback.frame(&url, &animation, true).1);
+        assert!(!playback.is_playing());
+
+        playback.play(&url);
+        assert!(playback.is_playing());
+        assert!(!playback.frame(&url, &animation, false).1);
+
+        playback.forget(&url);
+        assert!(playback.frame(&url, &animation, false).1);
+    }
+}
@@ -0,0 +1,224 @@
+//! Files attached to the message in the composer, such as long pastes and
+//! pasted images. Quaddle messages only carry text, so they're uploaded to the
+//! channel once the message is sent, and linked to at its end.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use iced::widget::image::Handle;
+use iced::widget::{button, container, image, row, text};
+use iced::{theme, Alignment, Element};
+use quaddlecl::client::http;
+use quaddlecl::model::attachment::Attachment;
+use quaddlecl::model::channel::ChannelId;
+
+use crate::config::PastedFileFormat;
+use crate::scheduler::{Priority, Scheduler};
+use crate::utils::icon;
+
+const CLOSE: &str = "\u{f00d}";
+const FILE: &str = "\u{f15b}";
+
+/// How large the preview of a pasted image is, at most, on its longer side.
+const THUMBNAIL_SIZE: u32 = 32;
+
+/// A file waiting for its message to be sent.
+#[derive(Debug, Clone)]
+pub struct PendingAttachment {
+    pub name: String,
+    pub content_type: &'static str,
+    pub bytes: Arc<Vec<u8>>,
+    /// A small preview, for images.
+    pub thumbnail: Option<Handle>,
+}
+
+impl PendingAttachment {
+    /// Pasted text, as a file in `format`.
+    pub fn from_paste(text: String, format: PastedFileFormat) -> Self {
+        Self {
+            name: format!("paste.{ext}", ext = format.extension()),
+            content_type: format.content_type(),
+            bytes: Arc::new(text.into_bytes()),
+            thumbnail: None,
+        }
+    }
+
+    /// An image of `width` by `height` RGBA pixels, as a PNG file.
+    pub fn from_image(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self> {
+        let image = ::image::RgbaImage::from_raw(width, height, rgba)?;
+        let mut png = Cursor::new(Vec::new());
+        if let Err(e) = image.write_to(&mut png, ::image::ImageOutputFormat::Png) {
+            log::warn!("could not encode the pasted image: {e}");
+            return None;
+        }
+        let thumbnail = ::image::DynamicImage::ImageRgba8(image)
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .into_rgba8();
+        Some(Self {
+            name: "image.png".to_string(),
+            content_type: "image/png",
+            bytes: Arc::new(png.into_inner()),
+            thumbnail: Some(Handle::from_pixels(
+                thumbnail.width(),
+                thumbnail.height(),
+                thumbnail.into_raw(),
+            )),
+        })
+    }
+
+    /// The attachment in the composer, with a button that sends `on_remove`.
+    pub fn view<'a, Message: Clone + 'a>(&self, on_remove: Message) -> Element<'a, Message> {
+        container(
+            row![
+                match &self.thumbnail {
+                    Some(thumbnail) => Element::from(image(thumbnail.clone())),
+                    None => icon(FILE).into(),
+                },
+                text(&self.name).size(14),
+                text(size_label(self.bytes.len())).size(12),
+                button(icon(CLOSE).size(12))
+                    .style(theme::Button::Text)
+                    .padding(0)
+                    .on_press(on_remove),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(5),
+        )
+        .padding([3, 8])
+        .style(theme::Container::Box)
+        .into()
+    }
+}
+
+/// The image on the clipboard, if there's one there.
+pub async fn paste_image() -> Option<PendingAttachment> {
+    clipboard_image::read().await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod clipboard_image {
+    use super::PendingAttachment;
+
+    /// Reading the clipboard waits on whichever application owns it, and
+    /// encoding the image takes a while too, so both are done on tokio's
+    /// blocking threads.
+    pub async fn read() -> Option<PendingAttachment> {
+        tokio::task::spawn_blocking(read_now).await.ok().flatten()
+    }
+
+    fn read_now() -> Option<PendingAttachment> {
+        let image = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_image())
+        {
+            Ok(image) => image,
+            Err(e) => {
+                log::debug!("no image to paste: {e}");
+                return None;
+            }
+        };
+        PendingAttachment::from_image(
+            image.width.try_into().ok()?,
+            image.height.try_into().ok()?,
+            image.bytes.into_owned(),
+        )
+    }
+}
+
+/// Browsers only hand clipboard images to paste events, which iced doesn't see.
+#[cfg(target_arch = "wasm32")]
+mod clipboard_image {
+    use super::PendingAttachment;
+
+    pub async fn read() -> Option<PendingAttachment> {
+        None
+    }
+}
+
+/// Uploads `attachments` to a channel one after the other, giving up at the first that fails.
+pub async fn upload(
+    http: Arc<Scheduler>,
+    channel_id: ChannelId,
+    attachments: Vec<PendingAttachment>,
+) -> Result<Vec<Attachment>, http::Error> {
+    let mut uploaded = Vec::new();
+    for attachment in attachments {
+        let bytes = attachment.bytes.to_vec();
+        uploaded.push(
+            http.run(Priority::Interactive, |http| {
+                http.upload_attachment(channel_id, &attachment.name, attachment.content_type, bytes)
+            })
+            .await?,
+        );
+    }
+    Ok(uploaded)
+}
+
+/// `content` with a link to each of `attachments` on a line of its own at the end.
+pub fn with_links(content: &str, attachments: &[Attachment]) -> String {
+    let links = attachments.iter().map(|attachment| attachment.url.as_str());
+    let content = content.trim_end();
+    let lines: Vec<_> = (!content.is_empty())
+        .then_some(content)
+        .into_iter()
+        .chain(links)
+        .collect();
+    lines.join("\n")
+}
+
+/// `bytes` in the largest unit it makes at least one of, e.g. `1.5 KB`.
+pub fn size_label(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1000.0 {
+            break;
+        }
+        size /= 1000.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn test_with_links() {
+        let attachment = |name: &str| Attachment {
+            name: name.to_string(),
+            url: Url::parse(&format!("https://cdn.example/{name}")).unwrap(),
+            size: 4,
+        };
+        let attachments = [attachment("a.txt"), attachment("b.md")];
+        assert_eq!(
+            with_links("meow\n", &attachments),
+            "meow\nhttps://cdn.example/a.txt\nhttps://cdn.example/b.md"
+        );
+        assert_eq!(
+            with_links("  ", &attachments[..1]),
+            "https://cdn.example/a.txt"
+        );
+    }
+
+    #[test]
+    fn test_from_image() {
+        let attachment = PendingAttachment::from_image(64, 16, vec![255; 64 * 16 * 4]).unwrap();
+        assert_eq!(attachment.name, "image.png");
+        assert!(attachment.bytes.starts_with(b"\x89PNG"));
+        assert!(attachment.thumbnail.is_some());
+        assert!(PendingAttachment::from_image(64, 16, vec![255; 4]).is_none());
+    }
+
+    #[test]
+    fn test_size_label() {
+        assert_eq!(size_label(999), "999 B");
+        assert_eq!(size_label(1500), "1.5 KB");
+        assert_eq!(size_label(2_000_000), "2.0 MB");
+        assert_eq!(size_label(3_500_000_000), "3.5 GB");
+    }
+}
@@ -2,12 +2,14 @@ use std::error::Error;
 use std::fmt::Debug;
 
 use iced::theme::Button;
-use iced::widget::{button, container, text, text_input, Column};
+use iced::widget::{button, container, pick_list, row, text, text_input, Column};
 use iced::{Command, Element, Length, Theme};
 use quaddlecl::client::http::{self, Http};
+use quaddlecl::client::scheme;
 use url::Url;
 
-use crate::USER_AGENT;
+use crate::config::{Config, Profile};
+use crate::scheduler;
 
 #[derive(Debug)]
 enum ActionState {
@@ -28,6 +30,8 @@ pub struct AuthScreen {
     server: String,
     username: String,
     password: String,
+    /// The name of a profile to be created.
+    new_profile: String,
 }
 
 #[derive(Debug, Clone)]
@@ -40,13 +44,16 @@ pub enum UiMessage {
     SignupSucceeded,
     SwitchToLogin,
     SwitchToSignup,
+    /// Handled by the application, which switches over to the profile's config.
+    ProfileSelected(Profile),
+    NewProfileUpdated(String),
 }
 
 #[derive(Debug)]
 pub enum IoMessage {
     SignupSucceeded,
     SignupFailed(Box<dyn Error + Send + Sync>),
-    LoginSucceeded(Http, Url),
+    LoginSucceeded(Box<Http>, Url),
     LoginFailed(Box<dyn Error + Send + Sync>),
 }
 
@@ -69,25 +76,58 @@ impl Default for AuthScreen {
             server: String::new(),
             username: String::new(),
             password: String::new(),
+            new_profile: String::new(),
         }
     }
 }
 
 impl AuthScreen {
-    pub fn update(&mut self, msg: Message) -> Command<Message> {
+    /// Starts out with the server filled in, e.g. after a session has expired.
+    pub fn with_server(server: &Url) -> Self {
+        Self {
+            server: server.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Starts out with the server and the user's name filled in,
+    /// so that only the password has to be typed again.
+    pub fn with_account(server: &Url, username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            ..Self::with_server(server)
+        }
+    }
+
+    /// The server's address as entered, with a websocket scheme switched to HTTP
+    /// so that the account is stored under the same address either way.
+    fn server_url(&self) -> Result<Url, Box<dyn Error + Send + Sync>> {
+        let server = Url::parse(&self.server)?;
+        Ok(scheme::rest_url(server)?)
+    }
+
+    pub fn update(&mut self, msg: Message, config: &Config) -> Command<Message> {
         use Message::{Io, Ui};
         match msg {
             Ui(UiMessage::ServerUpdated(srv)) => self.server = srv,
             Ui(UiMessage::UsernameUpdated(uname)) => self.username = uname,
             Ui(UiMessage::PasswordUpdated(pwd)) => self.password = pwd,
+            Ui(UiMessage::NewProfileUpdated(name)) => self.new_profile = name,
             Ui(UiMessage::SignupInitiated) => {
+                let server = match self.server_url() {
+                    Ok(server) => server,
+                    Err(e) => {
+                        self.state = AuthScreenState::Signup(ActionState::Error(e));
+                        return Command::none();
+                    }
+                };
                 self.state = AuthScreenState::Signup(ActionState::InProgress);
-                let server: String = self.server.to_string();
+                let options = config.connect_options(&server);
                 let username: String = self.username.to_string();
                 let password: String = self.password.to_string();
                 return Command::perform(
                     async move {
-                        Http::new(Url::parse(&server).unwrap(), USER_AGENT.to_string())?
+                        scheduler::new_http(server, &options)?
                             .signup(&username, &password)
                             .await
                     },
@@ -104,18 +144,25 @@ impl AuthScreen {
                 self.state = AuthScreenState::Signup(ActionState::Error(err))
             }
             Ui(UiMessage::LoginInitiated) => {
+                let server = match self.server_url() {
+                    Ok(server) => server,
+                    Err(e) => {
+                        self.state = AuthScreenState::Login(ActionState::Error(e));
+                        return Command::none();
+                    }
+                };
                 self.state = AuthScreenState::Login(ActionState::InProgress);
-                let server: Url = Url::parse(&self.server).unwrap();
+                let options = config.connect_options(&server);
                 let username: String = self.username.to_string();
                 let password: String = self.password.to_string();
                 return Command::perform(
                     async move {
-                        let mut http = Http::new(server.clone(), USER_AGENT.to_string())?;
+                        let mut http = scheduler::new_http(server.clone(), &options)?;
                         http.login(&username, &password).await?;
                         Ok((http, server))
                     },
                     |res: Result<_, http::Error>| match res {
-                        Ok((http, server)) => Io(IoMessage::LoginSucceeded(http, server)),
+                        Ok((http, server)) => Io(IoMessage::LoginSucceeded(Box::new(http), server)),
                         Err(e) => Io(IoMessage::LoginFailed(Box::new(e))),
                     },
                 );
@@ -133,14 +180,25 @@ impl AuthScreen {
         Command::none()
     }
 
-    pub fn view<'a>(&self, theme: &Theme) -> Element<'a, Message> {
+    /// `profiles` are offered to switch to, `current` being the one in use.
+    pub fn view<'a>(
+        &self,
+        theme: &Theme,
+        profiles: Vec<Profile>,
+        current: Profile,
+    ) -> Element<'a, Message> {
         let AuthScreen {
             server,
             username,
             password,
+            new_profile,
             state,
-            ..
         } = self;
+        let idle = !matches!(
+            state,
+            AuthScreenState::Login(ActionState::InProgress)
+                | AuthScreenState::Signup(ActionState::InProgress)
+        );
         let submit_msg = match state {
             AuthScreenState::Login(_) => UiMessage::LoginInitiated,
             AuthScreenState::Signup(_) => UiMessage::SignupInitiated,
@@ -213,6 +271,23 @@ impl AuthScreen {
                             .style(Button::Secondary)
                     }
                 })
+                .push(
+                    pick_list(profiles, Some(current), UiMessage::ProfileSelected)
+                        .width(Length::Fill),
+                )
+                .push(
+                    row![
+                        text_input("New profile", new_profile)
+                            .on_input(UiMessage::NewProfileUpdated),
+                        button("Create").on_press_maybe(
+                            Some(UiMessage::ProfileSelected(Profile::Named(
+                                new_profile.clone()
+                            )))
+                            .filter(|_| idle && Profile::is_valid_name(new_profile))
+                        ),
+                    ]
+                    .spacing(5),
+                )
                 .spacing(10)
                 .width(200),
         )
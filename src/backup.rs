@@ -0,0 +1,245 @@
+//! Automatic backups: new messages of the channels that opted in get
+//! appended to local archives, one JSON Lines file per channel.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local};
+#[cfg(not(target_arch = "wasm32"))]
+use directories::BaseDirs;
+use quaddlecl::client::http::{self, History};
+use quaddlecl::model::channel::ChannelId;
+use quaddlecl::model::message::{Message as QMessage, MessageId};
+use quaddlecl::model::user::UserId;
+use url::Url;
+
+use crate::config::BackupSettings;
+use crate::scheduler::{Priority, Scheduler};
+
+#[cfg(not(target_arch = "wasm32"))]
+const BACKUP_PATH: &str = "eyeqwst/backups";
+
+#[derive(Debug)]
+pub enum BackupError {
+    NoArchiveDir,
+    Http(http::Error),
+    Serialization(serde_json::Error),
+    #[cfg(not(target_arch = "wasm32"))]
+    Io(std::io::Error),
+    #[cfg(target_arch = "wasm32")]
+    Unsupported,
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::NoArchiveDir => write!(f, "could not find a directory for the archives"),
+            BackupError::Http(e) => write!(f, "could not retrieve messages: {e}"),
+            BackupError::Serialization(e) => write!(f, "could not serialize a message: {e}"),
+            #[cfg(not(target_arch = "wasm32"))]
+            BackupError::Io(e) => write!(f, "could not write the archive: {e}"),
+            #[cfg(target_arch = "wasm32")]
+            BackupError::Unsupported => write!(f, "backups aren't available on the web"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+/// What one channel's backup came to.
+#[derive(Debug, Clone)]
+pub struct ChannelBackup {
+    pub channel: ChannelId,
+    /// The newest message now in the archive and how many were added,
+    /// or `None` if there was nothing new.
+    pub result: Result<Option<(MessageId, usize)>, Arc<BackupError>>,
+}
+
+/// The outcome of the last backup run, as shown to the user.
+#[derive(Debug)]
+pub struct BackupReport {
+    pub finished: DateTime<Local>,
+    pub written: usize,
+    pub error: Option<String>,
+}
+
+impl BackupReport {
+    pub fn new(channels: &[ChannelBackup]) -> Self {
+        Self {
+            finished: Local::now(),
+            written: channels
+                .iter()
+                .filter_map(|c| c.result.as_ref().ok()?.as_ref())
+                .map(|(_, n)| n)
+                .sum(),
+            error: channels
+                .iter()
+                .find_map(|c| c.result.as_ref().err())
+                .map(ToString::to_string),
+        }
+    }
+}
+
+/// Where the archives of an account go.
+pub fn archive_dir(settings: &BackupSettings, server: &Url, user: UserId) -> Option<PathBuf> {
+    let base = match &settings.directory {
+        Some(dir) => dir.clone(),
+        None => default_dir()?,
+    };
+    Some(
+        base.join(server.host_str().unwrap_or("unknown"))
+            .join(user.to_string()),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_dir() -> Option<PathBuf> {
+    Some(BaseDirs::new()?.data_dir().join(BACKUP_PATH))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Appends the messages of `channels` newer than the given ones to their archives.
+/// Channels that were never backed up start with their latest page of messages.
+pub async fn run(
+    http: Arc<Scheduler>,
+    archive: Option<PathBuf>,
+    channels: Vec<(ChannelId, Option<MessageId>)>,
+) -> Vec<ChannelBackup> {
+    let mut results = Vec::new();
+    for (channel, after) in channels {
+        let result = match &archive {
+            Some(archive) => backup_channel(&http, archive, channel, after).await,
+            None => Err(BackupError::NoArchiveDir),
+        };
+        results.push(ChannelBackup {
+            channel,
+            result: result.map_err(Arc::new),
+        });
+    }
+    results
+}
+
+async fn backup_channel(
+    http: &Scheduler,
+    archive: &std::path::Path,
+    channel: ChannelId,
+    after: Option<MessageId>,
+) -> Result<Option<(MessageId, usize)>, BackupError> {
+    // pages come newest first, so walk backwards until reaching `after`
+    let mut new_msgs: Vec<QMessage> = Vec::new();
+    loop {
+        let query = match (new_msgs.last(), after) {
+            (None, None) => Ok(History::latest()),
+            (None, Some(after)) => History::latest().after(after),
+            (Some(oldest), None) => Ok(History::before(oldest.id)),
+            (Some(oldest), Some(after)) => History::before(oldest.id).after(after),
+        }
+        .map_err(BackupError::Http)?;
+        let page = http
+            .run(Priority::Background, |http| {
+                http.message_history(channel, query)
+            })
+            .await
+            .map_err(BackupError::Http)?;
+        if page.is_empty() {
+            break;
+        }
+        new_msgs.extend(page);
+        if after.is_none() {
+            break;
+        }
+    }
+
+    let Some(newest) = new_msgs.first().map(|msg| msg.id) else {
+        return Ok(None);
+    };
+    new_msgs.reverse();
+    append(&archive.join(format!("{channel}.jsonl")), &new_msgs)?;
+    Ok(Some((newest, new_msgs.len())))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn append(path: &std::path::Path, messages: &[QMessage]) -> Result<(), BackupError> {
+    use std::io::Write;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(BackupError::Io)?;
+    }
+    let mut lines = Vec::new();
+    for msg in messages {
+        serde_json::to_writer(&mut lines, msg).map_err(BackupError::Serialization)?;
+        lines.push(b'\n');
+    }
+    // written in one go, so that a failed run doesn't leave half a batch behind
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&lines))
+        .map_err(BackupError::Io)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn append(_path: &std::path::Path, _messages: &[QMessage]) -> Result<(), BackupError> {
+    Err(BackupError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_dir() {
+        let settings = BackupSettings {
+            directory: Some(PathBuf::from("/archives")),
+            ..BackupSettings::default()
+        };
+        let server = Url::parse("https://example.com:8080/quaddle").unwrap();
+        assert_eq!(
+            archive_dir(&settings, &server, UserId(7)),
+            Some(PathBuf::from("/archives/example.com/7"))
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let backup = |channel, result| ChannelBackup {
+            channel: ChannelId(channel),
+            result,
+        };
+        let report = BackupReport::new(&[
+            backup(1, Ok(Some((MessageId(10), 3)))),
+            backup(2, Ok(None)),
+            backup(3, Err(Arc::new(BackupError::NoArchiveDir))),
+            backup(4, Ok(Some((MessageId(20), 2)))),
+        ]);
+        assert_eq!(report.written, 5);
+        assert_eq!(
+            report.error.as_deref(),
+            Some("could not find a directory for the archives")
+        );
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = std::env::temp_dir().join(format!("eyeqwst-backup-{}", std::process::id()));
+        let path = dir.join("1.jsonl");
+        let msg = |content| QMessage::builder().content(content).build();
+
+        append(&path, &[msg("meow"), msg("purr")]).expect("failed to write the archive");
+        append(&path, &[msg("hiss")]).expect("failed to append to the archive");
+
+        let archive = std::fs::read_to_string(&path).expect("failed to read the archive");
+        let _ = std::fs::remove_dir_all(&dir);
+        let contents: Vec<_> = archive
+            .lines()
+            .map(|line| serde_json::from_str::<QMessage>(line).unwrap().content)
+            .collect();
+        assert_eq!(contents, ["meow", "purr", "hiss"]);
+    }
+}
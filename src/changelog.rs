@@ -0,0 +1,88 @@
+//! Release notes, shown once after an upgrade.
+
+use iced::widget::{button, column, container, scrollable, text, Column};
+use iced::{Alignment, Element, Length};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub struct Release {
+    pub version: &'static str,
+    pub changes: &'static [&'static str],
+}
+
+/// Newest release first.
+pub const CHANGELOG: &[Release] = &[Release {
+    version: "0.0.2",
+    changes: &[
+        "Messages are rendered as markdown, and links open in the browser",
+        "Drafts and unfinished edits are kept per channel",
+        "Unread message counts in the channel list",
+        "Older history loads as you scroll up",
+        "Messages can be deleted and copied as plain text or markdown",
+        "Per-channel message templates with tab-through placeholders",
+        "Channels on the server are offered when adding one",
+        "Per-server connection settings: gateway URL, headers and proxy",
+    ],
+}];
+
+/// Whether the release notes should be shown to someone who last ran `last_seen`.
+/// Configs from before versions were recorded have none, and haven't seen them either.
+pub fn is_unseen(last_seen: Option<&str>) -> bool {
+    last_seen != Some(VERSION)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum WhatsNewMessage {
+    Closed,
+}
+
+pub fn view<'a>() -> Element<'a, WhatsNewMessage> {
+    let releases = Column::with_children(CHANGELOG.iter().map(|release| {
+        Column::with_children(
+            std::iter::once(
+                text(format!("eyeqwst {version}", version = release.version))
+                    .font(crate::DEFAULT_FONT_MEDIUM)
+                    .size(20)
+                    .into(),
+            )
+            .chain(
+                release
+                    .changes
+                    .iter()
+                    .map(|change| text(format!("• {change}")).into()),
+            ),
+        )
+        .spacing(5)
+        .into()
+    }))
+    .spacing(20);
+
+    container(
+        column![
+            text("What's new").size(30),
+            scrollable(releases).height(Length::Shrink),
+            button("Continue").on_press(WhatsNewMessage::Closed),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(20)
+        .max_width(500),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x()
+    .center_y()
+    .padding(20)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unseen() {
+        assert!(is_unseen(None));
+        assert!(is_unseen(Some("0.0.0")));
+        assert!(!is_unseen(Some(VERSION)));
+    }
+}
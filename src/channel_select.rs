@@ -1,35 +1,131 @@
-use std::{mem, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::Arc,
+    time::Duration,
+};
 
+use iced::theme::palette;
+use iced::time::Instant;
 use iced::{
     font::Weight,
     theme,
-    widget::{button, container, row, rule, scrollable, text, text_input, tooltip, Column, Rule},
+    widget::{
+        button, container, horizontal_space, mouse_area, row, rule, scrollable, text, text_input,
+        tooltip, Column, Rule, Space,
+    },
     Command, Element, Font, Length,
 };
 use iced::{Alignment, Border, Theme};
 use iced_aw::native::DropDown;
-use quaddlecl::client::{
-    gateway::ClientGatewayMessage,
-    http::{self, Http},
-};
-use quaddlecl::model::channel::ChannelId;
+use quaddlecl::client::gateway::ClientGatewayMessage;
+use quaddlecl::client::http::{self, History};
+use quaddlecl::model::channel::{Channel as QChannel, ChannelId};
 use quaddlecl::model::message::Message as QMessage;
 
 use crate::{
+    activity::Activity,
     config::Channel,
     messageview::HistoryQMessage,
+    scheduler::{Priority, Scheduler},
     toggle_button::pressed_button_style,
     utils::{icon, ErrorWithCauses},
 };
 use crate::{gateway::Connection, utils::TextInputExt};
 
+#[derive(Debug, Clone)]
 pub enum ChannelListMessage {
     SelectChannel(usize),
+    RetrySubscription(ChannelId),
+    Menu(ChannelMenuMessage),
+}
+
+#[derive(Debug, Clone)]
+pub enum ChannelMenuMessage {
+    Opened(usize),
+    Dismissed,
+    RenameStarted(usize),
+    RenameEdited(String),
+    /// Renames the channel, or leaves it alone if the new name is blank.
+    RenameSubmitted,
+    RemoveRequested(usize),
+}
+
+/// Where a channel's gateway subscription stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionState {
+    /// Asked for at the given time, but neither acknowledged nor refused yet.
+    Pending(Instant),
+    Subscribed,
+    /// Refused by the gateway or never sent, with the reason.
+    Failed(String),
+}
+
+/// Changes to the account's channels asked for from a channel's menu.
+#[derive(Debug, Clone)]
+pub enum ChannelAction {
+    Rename(usize, String),
+    Remove(usize),
+}
+
+/// The menu of a channel in the sidebar, opened with its kebab button or a right click,
+/// and the channel being renamed in place, if any.
+#[derive(Debug, Default)]
+pub struct ChannelMenu {
+    open: Option<usize>,
+    renaming: Option<(usize, String)>,
+}
+
+impl ChannelMenu {
+    /// Returns what to do to the channels, once the user has decided.
+    pub fn update(
+        &mut self,
+        msg: ChannelMenuMessage,
+        channels: &[Channel],
+    ) -> Option<ChannelAction> {
+        match msg {
+            ChannelMenuMessage::Opened(idx) => self.open = Some(idx),
+            ChannelMenuMessage::Dismissed => self.open = None,
+            ChannelMenuMessage::RenameStarted(idx) => {
+                self.open = None;
+                self.renaming = channels.get(idx).map(|c| (idx, c.name.clone()));
+            }
+            ChannelMenuMessage::RenameEdited(name) => {
+                if let Some((_, editing)) = &mut self.renaming {
+                    *editing = name;
+                }
+            }
+            ChannelMenuMessage::RenameSubmitted => {
+                let (idx, name) = self.renaming.take()?;
+                let name = name.trim();
+                return (!name.is_empty()).then(|| ChannelAction::Rename(idx, name.to_string()));
+            }
+            ChannelMenuMessage::RemoveRequested(idx) => {
+                self.open = None;
+                self.renaming = None;
+                return Some(ChannelAction::Remove(idx));
+            }
+        }
+        None
+    }
+
+    /// Forgets about the channels, e.g. because one was removed and the rest moved up.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
 pub struct ChannelList<'a, Message, It> {
     selected_channel: usize,
     on_selection: Option<Box<dyn Fn(usize) -> Message + 'a>>,
+    unread: Option<&'a HashMap<ChannelId, usize>>,
+    /// Channels with unread messages mentioning the user.
+    mentioned: Option<&'a HashSet<ChannelId>>,
+    activity: Option<&'a Activity>,
+    subscriptions: Option<&'a HashMap<ChannelId, SubscriptionState>>,
+    on_retry: Option<Box<dyn Fn(ChannelId) -> Message + 'a>>,
+    menu: Option<&'a ChannelMenu>,
+    on_menu: Option<Box<dyn Fn(ChannelMenuMessage) -> Message + 'a>>,
     channels: It,
     width: Length,
     height: Length,
@@ -43,6 +139,13 @@ where
         ChannelList {
             selected_channel,
             on_selection: None,
+            unread: None,
+            mentioned: None,
+            activity: None,
+            subscriptions: None,
+            on_retry: None,
+            menu: None,
+            on_menu: None,
             channels,
             width: Length::Shrink,
             height: Length::Shrink,
@@ -57,12 +160,63 @@ where
         Self { height, ..self }
     }
 
+    /// Shows unread message counts next to the channels.
+    pub fn unread(self, unread: &'a HashMap<ChannelId, usize>) -> Self {
+        Self {
+            unread: Some(unread),
+            ..self
+        }
+    }
+
+    /// Gives the unread counts of channels in `mentioned` a badge of their own.
+    pub fn mentioned(self, mentioned: &'a HashSet<ChannelId>) -> Self {
+        Self {
+            mentioned: Some(mentioned),
+            ..self
+        }
+    }
+
+    /// Shows how busy the channels were over the last day next to them.
+    pub fn activity(self, activity: &'a Activity) -> Self {
+        Self {
+            activity: Some(activity),
+            ..self
+        }
+    }
+
     pub fn on_selection(self, on_selection: impl Fn(usize) -> Message + 'a) -> Self {
         Self {
             on_selection: Some(Box::new(on_selection)),
             ..self
         }
     }
+
+    /// Marks channels still waiting on their subscription with a spinner, and those
+    /// that aren't receiving events with a warning, which retries the subscription when pressed.
+    pub fn subscriptions(
+        self,
+        subscriptions: &'a HashMap<ChannelId, SubscriptionState>,
+        on_retry: impl Fn(ChannelId) -> Message + 'a,
+    ) -> Self {
+        Self {
+            subscriptions: Some(subscriptions),
+            on_retry: Some(Box::new(on_retry)),
+            ..self
+        }
+    }
+
+    /// Gives every channel a menu for renaming and removing it.
+    pub fn menu(
+        self,
+        menu: &'a ChannelMenu,
+        on_menu: impl Fn(ChannelMenuMessage) -> Message + 'a,
+    ) -> Self {
+        Self {
+            menu: Some(menu),
+            on_menu: Some(Box::new(on_menu)),
+            ..self
+        }
+    }
 }
 
 impl<'a, 'b, Message: 'a, It> From<ChannelList<'a, Message, It>> for Element<'a, Message>
@@ -70,10 +224,46 @@ where
     It: IntoIterator<Item = &'b Channel>,
 {
     fn from(clist: ChannelList<'a, Message, It>) -> Self {
-        let el: Element<'a, usize> = scrollable({
+        let unread = clist.unread;
+        let mentioned = clist.mentioned;
+        let activity = clist.activity;
+        let subscriptions = clist.subscriptions;
+        let menu = clist.menu;
+        let el: Element<'a, ChannelListMessage> = scrollable({
             Column::with_children({
                 clist.channels.into_iter().enumerate().map(|(i, channel)| {
-                    button({
+                    let unread_count = unread
+                        .and_then(|u| u.get(&channel.id).copied())
+                        .filter(|&n| n > 0);
+                    let is_mentioned = mentioned.is_some_and(|m| m.contains(&channel.id));
+                    let subscription = subscriptions.and_then(|s| s.get(&channel.id));
+                    let renaming = menu
+                        .and_then(|m| m.renaming.as_ref())
+                        .filter(|(idx, _)| *idx == i)
+                        .map(|(_, name)| name);
+                    let name: Element<'a, ChannelListMessage> = match renaming {
+                        Some(name) => text_input(&channel.name, name)
+                            .on_input(|s| {
+                                ChannelListMessage::Menu(ChannelMenuMessage::RenameEdited(s))
+                            })
+                            .on_submit(ChannelListMessage::Menu(
+                                ChannelMenuMessage::RenameSubmitted,
+                            ))
+                            .padding(2)
+                            .into(),
+                        None => text(&channel.name)
+                            .font(Font {
+                                weight: if unread_count.is_some() {
+                                    Weight::Bold
+                                } else {
+                                    Weight::Medium
+                                },
+                                ..crate::DEFAULT_FONT
+                            })
+                            .width(Length::Fill)
+                            .into(),
+                    };
+                    let entry = button({
                         row![
                             Rule::vertical(3.0).style(move |t: &Theme| {
                                 use iced::widget::rule::StyleSheet;
@@ -88,25 +278,43 @@ where
                                     ..t.appearance(&theme::Rule::Default)
                                 }
                             }),
-                            row![
-                                icon("\u{f292}").size(20),
-                                text(&channel.name).font(Font {
-                                    weight: Weight::Medium,
-                                    ..crate::DEFAULT_FONT
+                            row![icon("\u{f292}").size(20), name]
+                                .push_maybe(activity.and_then(|a| a.sparkline(channel.id)))
+                                .push_maybe(match (unread_count, is_mentioned) {
+                                    (count, true) => Some(mention_badge(count.unwrap_or(1))),
+                                    (count, false) => count.map(unread_badge),
                                 })
-                            ]
-                            .spacing(5)
-                            .padding(5)
-                            .align_items(Alignment::Center)
+                                .push_maybe(match subscription {
+                                    Some(SubscriptionState::Pending(_)) =>
+                                        Some(subscription_pending()),
+                                    Some(SubscriptionState::Failed(reason)) => {
+                                        Some(subscription_warning(channel.id, reason))
+                                    }
+                                    Some(SubscriptionState::Subscribed) | None => None,
+                                })
+                                .push_maybe(menu.map(|m| channel_menu(i, m.open == Some(i))))
+                                .spacing(5)
+                                .padding(5)
+                                .width(Length::Fill)
+                                .align_items(Alignment::Center)
                         ]
+                        .width(Length::Fill)
                         .height(40)
                         .align_items(Alignment::Center)
                     })
-                    .on_press_maybe(Some(i).filter(|_| clist.on_selection.is_some()))
+                    .on_press_maybe(
+                        Some(ChannelListMessage::SelectChannel(i))
+                            .filter(|_| clist.on_selection.is_some()),
+                    )
                     .style(theme::Button::Secondary)
                     .padding(0)
-                    .width(Length::Fill)
-                    .into()
+                    .width(Length::Fill);
+                    match menu {
+                        Some(_) => mouse_area(entry)
+                            .on_right_press(ChannelListMessage::Menu(ChannelMenuMessage::Opened(i)))
+                            .into(),
+                        None => entry.into(),
+                    }
                 })
             })
             .spacing(10)
@@ -117,13 +325,185 @@ where
         .height(clist.height)
         .into();
 
-        el.map(move |i| match &clist.on_selection {
-            Some(select) => select(i),
-            None => panic!("disabled clist produced a message"),
-        })
+        el.map(
+            move |msg| match (msg, &clist.on_selection, &clist.on_retry, &clist.on_menu) {
+                (ChannelListMessage::SelectChannel(i), Some(select), _, _) => select(i),
+                (ChannelListMessage::RetrySubscription(id), _, Some(retry), _) => retry(id),
+                (ChannelListMessage::Menu(msg), _, _, Some(on_menu)) => on_menu(msg),
+                _ => panic!("disabled clist produced a message"),
+            },
+        )
     }
 }
 
+const WARNING_ICON: &str = "\u{f071}";
+const PENDING_ICON: &str = "\u{f110}";
+const MENU_ICON: &str = "\u{f142}";
+
+/// The kebab button of a channel, with its menu below it while `open`.
+fn channel_menu<'a>(idx: usize, open: bool) -> Element<'a, ChannelListMessage> {
+    let kebab = button(icon(MENU_ICON).size(14))
+        .on_press(ChannelListMessage::Menu(if open {
+            ChannelMenuMessage::Dismissed
+        } else {
+            ChannelMenuMessage::Opened(idx)
+        }))
+        .style(theme::Button::Text)
+        .padding([2, 6]);
+    let item = |label, msg| {
+        button(text(label).size(14))
+            .on_press(ChannelListMessage::Menu(msg))
+            .style(theme::Button::Text)
+            .width(Length::Fill)
+    };
+    let items = container(
+        Column::new()
+            .push(item("Rename", ChannelMenuMessage::RenameStarted(idx)))
+            .push(
+                item("Remove", ChannelMenuMessage::RemoveRequested(idx))
+                    .style(theme::Button::Destructive),
+            )
+            .spacing(2),
+    )
+    .style(theme::Container::Box)
+    .padding(5);
+
+    DropDown::new(kebab, items, open)
+        .alignment(iced_aw::drop_down::Alignment::Bottom)
+        .on_dismiss(ChannelListMessage::Menu(ChannelMenuMessage::Dismissed))
+        .width(120)
+        .into()
+}
+
+fn subscription_pending<'a>() -> Element<'a, ChannelListMessage> {
+    tooltip(
+        container(icon(PENDING_ICON).size(12)).style(|t: &Theme| {
+            use iced::widget::container::StyleSheet;
+            container::Appearance {
+                text_color: Some(t.extended_palette().background.strong.color),
+                ..t.appearance(&theme::Container::Transparent)
+            }
+        }),
+        text("Subscribing…").size(12),
+        tooltip::Position::FollowCursor,
+    )
+    .style(theme::Container::Box)
+    .padding(5)
+    .into()
+}
+
+fn subscription_warning<'a>(
+    channel_id: ChannelId,
+    reason: &str,
+) -> Element<'a, ChannelListMessage> {
+    tooltip(
+        button(icon(WARNING_ICON).size(14))
+            .on_press(ChannelListMessage::RetrySubscription(channel_id))
+            .style(theme::Button::Destructive)
+            .padding([2, 6]),
+        text(format!(
+            "Not receiving new messages: {reason}\nPress to retry"
+        ))
+        .size(12),
+        tooltip::Position::FollowCursor,
+    )
+    .style(theme::Container::Box)
+    .padding(5)
+    .into()
+}
+
+pub fn unread_badge<'a, Message: 'a>(count: usize) -> Element<'a, Message> {
+    badge(count, |t| t.extended_palette().primary.base)
+}
+
+/// An unread count that stands out, for channels where the user was mentioned.
+pub fn mention_badge<'a, Message: 'a>(count: usize) -> Element<'a, Message> {
+    badge(count, |t| t.extended_palette().danger.base)
+}
+
+fn badge<'a, Message: 'a>(count: usize, pair: fn(&Theme) -> palette::Pair) -> Element<'a, Message> {
+    let label = if count > 99 {
+        "99+".to_string()
+    } else {
+        count.to_string()
+    };
+
+    container(text(label).size(12))
+        .padding([2, 6])
+        .style(move |t: &Theme| {
+            use iced::widget::container::StyleSheet;
+            let pair = pair(t);
+            container::Appearance {
+                text_color: Some(pair.text),
+                background: Some(iced::Background::Color(pair.color)),
+                border: Border::with_radius(8),
+                ..t.appearance(&theme::Container::Transparent)
+            }
+        })
+        .into()
+}
+
+const EXPANDED_ICON: &str = "\u{f078}";
+const COLLAPSED_ICON: &str = "\u{f054}";
+
+/// How a server's gateway connection is doing, as shown in its sidebar header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connected,
+    Connecting,
+    Disconnected,
+}
+
+/// The header of a server's section in the sidebar, with the round trip
+/// time to its gateway next to the status dot while connected.
+/// Pressing it collapses or expands the channels listed under it.
+pub fn server_header<'a, Message: Clone + 'a>(
+    name: &str,
+    status: ConnectionStatus,
+    latency: Option<Duration>,
+    collapsed: bool,
+    on_toggle: Message,
+) -> Element<'a, Message> {
+    let dot = container(Space::new(8, 8)).style(move |t: &Theme| {
+        use iced::widget::container::StyleSheet;
+        let palette = t.extended_palette();
+        let color = match status {
+            ConnectionStatus::Connected => palette.success.base.color,
+            ConnectionStatus::Connecting => palette.background.strong.color,
+            ConnectionStatus::Disconnected => palette.danger.base.color,
+        };
+        container::Appearance {
+            background: Some(iced::Background::Color(color)),
+            border: Border::with_radius(4),
+            ..t.appearance(&theme::Container::Transparent)
+        }
+    });
+
+    button({
+        row![
+            icon(if collapsed {
+                COLLAPSED_ICON
+            } else {
+                EXPANDED_ICON
+            })
+            .size(12),
+            text(name).font(crate::DEFAULT_FONT_MEDIUM),
+            horizontal_space(),
+        ]
+        .push_maybe(
+            latency.map(|latency| text(format!("{ms} ms", ms = latency.as_millis())).size(10)),
+        )
+        .push(dot)
+        .spacing(5)
+        .align_items(Alignment::Center)
+    })
+    .style(theme::Button::Text)
+    .width(Length::Fill)
+    .padding(5)
+    .on_press(on_toggle)
+    .into()
+}
+
 const ADD_ICON: &str = "\u{f067}";
 
 #[derive(Debug, Clone)]
@@ -133,6 +513,9 @@ pub enum ChannelEditMessage {
     NewChannelNameEdited(String),
     NewChannelIdEdited(String),
     ChannelAddRequested,
+    ChannelsListed(Vec<QChannel>),
+    ChannelListError(Arc<http::Error>),
+    ChannelPicked(ChannelId, String),
     ChannelExists(Vec<QMessage>),
     ChannelError(Arc<http::Error>),
 }
@@ -163,6 +546,8 @@ pub struct ChannelEditStrip {
     expanded: bool,
     new_channel_name: String,
     new_channel_id: Option<ChannelId>,
+    /// Channels on the server that haven't been added yet.
+    discovered: Vec<QChannel>,
 }
 
 impl ChannelEditStrip {
@@ -207,6 +592,26 @@ impl ChannelEditStrip {
                         _ => None,
                     }
                 })
+                .push_maybe({
+                    (!self.discovered.is_empty()).then(|| {
+                        scrollable({
+                            Column::with_children(self.discovered.iter().map(|channel| {
+                                button(text(&channel.name))
+                                    .style(theme::Button::Text)
+                                    .width(Length::Fill)
+                                    .on_press_maybe({
+                                        Some(ChannelEditMessage::ChannelPicked(
+                                            channel.id,
+                                            channel.name.clone(),
+                                        ))
+                                        .filter(|_| self.state.is_idle())
+                                    })
+                                    .into()
+                            }))
+                        })
+                        .height(Length::Shrink)
+                    })
+                })
                 .push({
                     text_input("Name", &self.new_channel_name).on_input_if(
                         self.state.is_idle(),
@@ -260,11 +665,43 @@ impl ChannelEditStrip {
         selected_channel: &mut usize,
         messages: &mut Vec<HistoryQMessage>,
         gateway_conn: &mut Connection,
-        http: Arc<Http>,
+        http: Arc<Scheduler>,
     ) -> Command<ChannelEditMessage> {
         use ChannelEditStripState::{Confirming, Idle};
         match (&mut self.state, msg) {
-            (_, ChannelEditMessage::Expanded) => self.expanded = true,
+            (_, ChannelEditMessage::Expanded) => {
+                self.expanded = true;
+                return Command::perform(
+                    async move {
+                        http.run(Priority::Interactive, |http| http.list_channels())
+                            .await
+                    },
+                    |res| match res {
+                        Ok(channels) => ChannelEditMessage::ChannelsListed(channels),
+                        Err(e) => ChannelEditMessage::ChannelListError(Arc::new(e)),
+                    },
+                );
+            }
+            (_, ChannelEditMessage::ChannelsListed(mut listed)) => {
+                listed.retain(|l| !channels.iter().any(|c| c.id == l.id));
+                self.discovered = listed;
+            }
+            (_, ChannelEditMessage::ChannelListError(err)) => {
+                // not every server lists its channels, and IDs can still be entered by hand
+                log::warn!("failed to list channels: {err}", err = ErrorWithCauses(err));
+            }
+            (Idle { .. }, ChannelEditMessage::ChannelPicked(id, name)) => {
+                self.new_channel_id = Some(id);
+                self.new_channel_name = name;
+                return self.update(
+                    ChannelEditMessage::ChannelAddRequested,
+                    channels,
+                    selected_channel,
+                    messages,
+                    gateway_conn,
+                    http,
+                );
+            }
             (_, ChannelEditMessage::Dismissed) => self.expanded = false,
             (Idle { .. }, ChannelEditMessage::NewChannelNameEdited(s)) => self.new_channel_name = s,
             (Idle { .. }, ChannelEditMessage::NewChannelIdEdited(id)) => {
@@ -282,10 +719,19 @@ impl ChannelEditStrip {
                 self.state = ChannelEditStripState::Confirming(Channel {
                     id: channel_id,
                     name: mem::take(&mut self.new_channel_name),
+                    confirm_send: false,
+                    backup: false,
+                    spellcheck_language: None,
+                    dense: false,
                 });
 
                 return Command::perform(
-                    async move { http.message_history(channel_id, None).await },
+                    async move {
+                        http.run(Priority::Interactive, |http| {
+                            http.message_history(channel_id, History::latest())
+                        })
+                        .await
+                    },
                     |res| {
                         log::debug!("{res:?}");
                         match res {
@@ -304,6 +750,7 @@ impl ChannelEditStrip {
                 gateway_conn.send(ClientGatewayMessage::Subscribe {
                     channel_id: chan.id,
                 });
+                self.discovered.retain(|d| d.id != chan.id);
                 channels.push(chan);
                 self.expanded = false;
                 *selected_channel = channels.len() - 1;
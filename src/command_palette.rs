@@ -0,0 +1,121 @@
+//! The Ctrl+Shift+P overlay listing every action, for running one by typing part of its name.
+
+use iced::keyboard::key::Named;
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Column};
+use iced::{keyboard, theme, Alignment, Command, Element, Event, Length, Subscription};
+
+use crate::actions::Action;
+use crate::toggle_button::pressed_button_style;
+use crate::utils::fuzzy_score;
+
+const COMMAND_PALETTE_INPUT_ID: &str = "command_palette_input";
+
+#[derive(Debug, Clone)]
+pub enum CommandPaletteMessage {
+    QueryChanged(String),
+    /// Moves the highlight by the given number of matches, wrapping around.
+    Moved(isize),
+    Submitted,
+    Picked(Action),
+    Closed,
+}
+
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    query: String,
+    /// Position of the highlighted match.
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn focus<Message: 'static>() -> Command<Message> {
+        text_input::focus(text_input::Id::new(COMMAND_PALETTE_INPUT_ID))
+    }
+
+    /// The actions matching the query, best first.
+    fn matches(&self) -> Vec<Action> {
+        let mut scored: Vec<_> = Action::ALL
+            .into_iter()
+            // running the palette from itself would do nothing
+            .filter(|&action| action != Action::CommandPalette)
+            .filter_map(|action| Some((fuzzy_score(&self.query, action.label())?, action)))
+            .collect();
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        scored.into_iter().map(|(_, action)| action).collect()
+    }
+
+    /// Returns the action to run, if one was picked.
+    pub fn update(&mut self, message: CommandPaletteMessage) -> Option<Action> {
+        let matches = self.matches();
+        match message {
+            CommandPaletteMessage::QueryChanged(query) => {
+                self.query = query;
+                self.selected = 0;
+                None
+            }
+            CommandPaletteMessage::Moved(step) if !matches.is_empty() => {
+                let len = matches.len() as isize;
+                self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+                None
+            }
+            CommandPaletteMessage::Submitted => matches.get(self.selected).copied(),
+            CommandPaletteMessage::Picked(action) => Some(action),
+            CommandPaletteMessage::Moved(_) | CommandPaletteMessage::Closed => None,
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, CommandPaletteMessage> {
+        let matches = self.matches();
+        let rows = matches.iter().enumerate().map(|(pos, &action)| {
+            button(
+                row![text(action.label()).width(Length::Fill)]
+                    .push_maybe(action.shortcut().map(|shortcut| text(shortcut).size(12)))
+                    .align_items(Alignment::Center)
+                    .spacing(10),
+            )
+            .on_press(CommandPaletteMessage::Picked(action))
+            .style(if pos == self.selected {
+                pressed_button_style(theme::Button::Text)
+            } else {
+                theme::Button::Text
+            })
+            .width(Length::Fill)
+            .into()
+        });
+
+        let list: Element<_> = if matches.is_empty() {
+            text("No matching commands").size(14).into()
+        } else {
+            scrollable(Column::with_children(rows).spacing(2)).into()
+        };
+
+        container(
+            column![
+                text_input("Type a command", &self.query)
+                    .id(text_input::Id::new(COMMAND_PALETTE_INPUT_ID))
+                    .on_input(CommandPaletteMessage::QueryChanged)
+                    .on_submit(CommandPaletteMessage::Submitted),
+                list,
+            ]
+            .spacing(10),
+        )
+        .width(400)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+    }
+}
+
+/// Arrow keys move the highlight and Escape closes the palette.
+pub fn subscription() -> Subscription<CommandPaletteMessage> {
+    // the input captures Escape, so look at key presses whether they were handled or not
+    iced::event::listen_with(|event, _| match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) => match key.as_ref() {
+            keyboard::Key::Named(Named::ArrowUp) => Some(CommandPaletteMessage::Moved(-1)),
+            keyboard::Key::Named(Named::ArrowDown) => Some(CommandPaletteMessage::Moved(1)),
+            keyboard::Key::Named(Named::Escape) => Some(CommandPaletteMessage::Closed),
+            _ => None,
+        },
+        _ => None,
+    })
+}
@@ -1,8 +1,22 @@
+use std::any::TypeId;
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use chrono_tz::Tz;
 #[cfg(not(target_arch = "wasm32"))]
 use directories::BaseDirs;
-use quaddlecl::model::{channel::ChannelId, user::UserId};
+use futures::channel::mpsc;
+use futures::{select, FutureExt, SinkExt, StreamExt};
+use iced::{subscription, Subscription};
+use quaddlecl::client::{ConnectOptions, HeaderName, HeaderValue};
+use quaddlecl::model::{
+    channel::ChannelId,
+    message::{Message, MessageId},
+    user::{User, UserId},
+};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use serde_with::DisplayFromStr;
@@ -10,63 +24,487 @@ use serde_with::DisplayFromStr;
 use std::fs;
 use url::Url;
 
+use crate::utils::sleep;
+use crate::USER_AGENT;
+
+mod migrate;
+
 #[cfg(not(target_arch = "wasm32"))]
 const CONFIG_PATH: &str = "eyeqwst/config.json";
+/// Read if there's no JSON config, for those who wrote theirs by hand.
+#[cfg(not(target_arch = "wasm32"))]
+const TOML_CONFIG_PATH: &str = "eyeqwst/config.toml";
+#[cfg(not(target_arch = "wasm32"))]
+const PROFILES_PATH: &str = "eyeqwst/profiles";
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "config";
+
+/// How long the config has to stay unchanged before it gets written out.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct Config {
     #[serde_as(as = "HashMap<_, HashMap<DisplayFromStr, _>>")]
     pub accounts: HashMap<Url, HashMap<UserId, Account>>,
+    #[serde(default)]
+    pub servers: HashMap<Url, ServerSettings>,
+    #[serde(default)]
+    pub settings: Settings,
+    /// The version of eyeqwst that last ran with this config.
+    #[serde(default)]
+    pub last_seen_version: Option<String>,
+    /// The account that was in front when eyeqwst last ran.
+    #[serde(default)]
+    pub last_session: Option<Session>,
+    /// Every account to log back into on startup, in the order they were added.
+    #[serde(default)]
+    pub sessions: Vec<Session>,
+    #[serde(skip)]
+    writer: Option<ConfigWriter>,
+    #[serde(skip)]
+    profile: Profile,
+    /// How the config was written out when it stood in for one that couldn't be
+    /// read. It isn't saved over that one for as long as it's unchanged.
+    #[serde(skip)]
+    stand_in: Option<String>,
 }
 
-impl Config {
+#[derive(Debug)]
+pub enum SaveError {
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn load() -> Config {
+    NoConfigDir,
+    Serialization(serde_json::Error),
+    #[cfg(not(target_arch = "wasm32"))]
+    Io(std::io::Error),
+    #[cfg(target_arch = "wasm32")]
+    Storage,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            SaveError::NoConfigDir => write!(f, "could not find the config directory"),
+            SaveError::Serialization(e) => write!(f, "could not serialize config: {e}"),
+            #[cfg(not(target_arch = "wasm32"))]
+            SaveError::Io(e) => write!(f, "could not write config file: {e}"),
+            #[cfg(target_arch = "wasm32")]
+            SaveError::Storage => write!(f, "could not write to local storage"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// A config that was written in an older format and upgraded when it was loaded,
+/// or that couldn't be read at all and was started over.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// What the config was upgraded from, e.g. `TOML` or `version 0`,
+    /// or `None` if it was started over.
+    pub from: Option<String>,
+    /// Where a copy of the original was kept, if making one worked.
+    pub backup: Option<String>,
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.from {
+            Some(from) => write!(f, "Settings were upgraded from {from}")?,
+            None => write!(
+                f,
+                "Settings could not be read and were reset, but aren't written over until changed"
+            )?,
+        }
+        match &self.backup {
+            Some(backup) => write!(f, ", the original was kept at {backup}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What's written out: the config, stamped with the version of its schema.
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u64,
+    #[serde(flatten)]
+    config: &'a Config,
+}
+
+/// A handle to the background task that writes the config out.
+#[derive(Debug, Clone)]
+pub struct ConfigWriter(mpsc::UnboundedSender<Config>);
+
+#[derive(Debug)]
+pub enum PersistenceEvent {
+    Ready(ConfigWriter),
+    SaveFailed(SaveError),
+}
+
+/// Which config eyeqwst runs with. Each keeps its own accounts, channels and drafts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Profile {
+    #[default]
+    Default,
+    /// One the user named, such as `work`, kept apart from the default one.
+    Named(String),
+    /// A config file given on the command line.
+    File(PathBuf),
+}
+
+impl Profile {
+    /// Names can't reach outside of the directory profiles are kept in.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+    }
+
+    /// Where the profile's config is kept, on platforms that keep it in a file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn path(&self) -> Option<PathBuf> {
+        let dirs = BaseDirs::new();
+        match self {
+            Profile::Default => dirs.map(|dirs| dirs.config_dir().join(CONFIG_PATH)),
+            Profile::Named(name) => dirs.map(|dirs| {
+                dirs.config_dir()
+                    .join(PROFILES_PATH)
+                    .join(format!("{name}.json"))
+            }),
+            Profile::File(path) => Some(path.clone()),
+        }
+    }
+
+    /// The local storage key the profile's config is kept under.
+    #[cfg(target_arch = "wasm32")]
+    fn storage_key(&self) -> String {
+        match self {
+            Profile::Named(name) => format!("{STORAGE_KEY}.{name}"),
+            Profile::Default | Profile::File(_) => STORAGE_KEY.to_string(),
+        }
+    }
+
+    /// The profiles there are configs for, besides the default one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn saved() -> Vec<Profile> {
         let Some(dirs) = BaseDirs::new() else {
-            log::warn!("could not get basedirs");
-            return Default::default();
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(dirs.config_dir().join(PROFILES_PATH)) else {
+            return Vec::new();
+        };
+        let mut names: Vec<_> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let name = path.file_stem()?.to_str()?;
+                let is_json = path.extension().is_some_and(|ext| ext == "json");
+                is_json.then(|| name.to_string())
+            })
+            .collect();
+        names.sort();
+        names.into_iter().map(Profile::Named).collect()
+    }
+
+    /// The profiles there are configs for, besides the default one.
+    #[cfg(target_arch = "wasm32")]
+    pub fn saved() -> Vec<Profile> {
+        let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+        let prefix = format!("{STORAGE_KEY}.");
+        let mut names: Vec<_> = (0..storage.length().unwrap_or(0))
+            .filter_map(|idx| storage.key(idx).ok().flatten())
+            .filter_map(|key| Some(key.strip_prefix(&prefix)?.to_string()))
+            .filter(|name| name != "bak" && !name.ends_with(".bak"))
+            .collect();
+        names.sort();
+        names.into_iter().map(Profile::Named).collect()
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Profile::Default => write!(f, "Default"),
+            Profile::Named(name) => write!(f, "{name}"),
+            Profile::File(path) => write!(f, "{path}", path = path.display()),
+        }
+    }
+}
+
+/// Parses a config in any format it was ever written in.
+fn parse(contents: &str) -> Result<(Config, Option<String>), Box<dyn std::error::Error>> {
+    let (value, migrated_from) = migrate::parse(contents)?;
+    Ok((serde_json::from_value(value)?, migrated_from))
+}
+
+impl Config {
+    /// Loads the config of `profile`, upgrading it if it was written in an older format.
+    /// Profiles that weren't used before start out empty.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_profile(profile: Profile) -> (Config, Option<Migration>) {
+        let empty = || Config {
+            profile: profile.clone(),
+            ..Default::default()
+        };
+        let candidates: Vec<PathBuf> = match &profile {
+            Profile::Default => match BaseDirs::new() {
+                Some(dirs) => [CONFIG_PATH, TOML_CONFIG_PATH]
+                    .into_iter()
+                    .map(|path| dirs.config_dir().join(path))
+                    .collect(),
+                None => {
+                    log::warn!("could not get basedirs");
+                    return (empty(), None);
+                }
+            },
+            profile => profile.path().into_iter().collect(),
         };
-        let path = dirs.config_dir().join(CONFIG_PATH);
-        let Ok(contents) = fs::read_to_string(path) else {
+        let Some((file, contents)) = candidates.into_iter().find_map(|file| {
+            let contents = fs::read_to_string(&file).ok()?;
+            Some((file, contents))
+        }) else {
             log::warn!("could not read file");
-            return Default::default();
+            return (empty(), None);
         };
 
-        let config = match serde_json::from_str(&contents) {
-            Ok(x) => x,
+        let back_up = || {
+            let mut backup = file.clone().into_os_string();
+            backup.push(".bak");
+            match fs::write(&backup, &contents) {
+                Ok(()) => Some(backup.to_string_lossy().into_owned()),
+                Err(e) => {
+                    log::warn!("could not back up the config: {e}");
+                    None
+                }
+            }
+        };
+        let path = file.display();
+        let (mut config, migration) = match parse(&contents) {
+            Ok((config, None)) => (config, None),
+            Ok((config, Some(from))) => {
+                log::info!("upgraded the config at {path} from {from}");
+                let backup = back_up();
+                let from = Some(from);
+                (config, Some(Migration { from, backup }))
+            }
+            // starting over would overwrite the file the next time the config is saved
             Err(e) => {
-                log::warn!("error deserializing config: {e}");
-                return Default::default();
+                log::warn!("could not read the config at {path}: {e}");
+                let backup = back_up();
+                (
+                    Config::stand_in(empty()),
+                    Some(Migration { from: None, backup }),
+                )
             }
         };
-
+        config.profile = profile;
         log::debug!("config: {config:?}");
 
+        (config, migration)
+    }
+
+    /// Loads the config of `profile`, upgrading it if it was written in an older format.
+    /// Profiles that weren't used before start out empty.
+    #[cfg(target_arch = "wasm32")]
+    pub fn load_profile(profile: Profile) -> (Config, Option<Migration>) {
+        let empty = || Config {
+            profile: profile.clone(),
+            ..Default::default()
+        };
+        let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+        let key = profile.storage_key();
+        let Some(json) = storage.get_item(&key).unwrap() else {
+            return (empty(), None);
+        };
+        let back_up = || {
+            let backup = format!("{key}.bak");
+            match storage.set_item(&backup, &json) {
+                Ok(()) => Some(format!("{backup} in local storage")),
+                Err(_) => {
+                    log::warn!("could not back up the config");
+                    None
+                }
+            }
+        };
+        let (mut config, migration) = match parse(&json) {
+            Ok((config, None)) => (config, None),
+            Ok((config, Some(from))) => {
+                log::info!("upgraded the config from {from}");
+                let backup = back_up();
+                let from = Some(from);
+                (config, Some(Migration { from, backup }))
+            }
+            // starting over would overwrite the config the next time it's saved
+            Err(e) => {
+                log::warn!("could not read the config: {e}");
+                let backup = back_up();
+                (
+                    Config::stand_in(empty()),
+                    Some(Migration { from: None, backup }),
+                )
+            }
+        };
+        config.profile = profile;
+
+        (config, migration)
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Marks `config` as standing in for one that couldn't be read, e.g. because a
+    /// newer eyeqwst wrote it, so that it's left alone until something is changed.
+    fn stand_in(mut config: Config) -> Config {
+        config.stand_in = config.to_json().ok();
         config
     }
 
+    /// Whether this still stands in for a config that couldn't be read, unchanged.
+    pub fn is_stand_in(&self) -> bool {
+        self.stand_in.is_some() && self.to_json().ok() == self.stand_in
+    }
+
+    /// Where the config is written to, on platforms that keep it in a file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.profile.path()
+    }
+
     #[cfg(target_arch = "wasm32")]
-    pub fn load() -> Config {
-        web_sys::window()
-            .unwrap()
-            .local_storage()
-            .unwrap()
-            .unwrap()
-            .get_item("config")
-            .unwrap()
-            .and_then(|json| {
-                serde_json::from_str(&json)
-                    .inspect_err(|err| log::error!("deserialization error: {err}"))
-                    .ok()
-            })
-            .unwrap_or_default()
+    pub fn file_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn to_json(&self) -> Result<String, SaveError> {
+        serde_json::to_string_pretty(&Versioned {
+            version: migrate::CURRENT_VERSION,
+            config: self,
+        })
+        .map_err(SaveError::Serialization)
     }
 
     pub fn get_account_config(&self, quaddle_url: &Url, user: UserId) -> Option<&Account> {
         self.accounts.get(quaddle_url)?.get(&user)
     }
 
+    /// Returns the base URL of the gateway belonging to the server at `quaddle_url`.
+    pub fn gateway_url(&self, quaddle_url: &Url) -> Url {
+        self.servers
+            .get(quaddle_url)
+            .and_then(|settings| settings.gateway_url.clone())
+            .unwrap_or_else(|| quaddle_url.clone())
+    }
+
+    /// Returns the options to use when connecting to the server at `quaddle_url`.
+    pub fn connect_options(&self, quaddle_url: &Url) -> ConnectOptions {
+        self.servers
+            .get(quaddle_url)
+            .map(ServerSettings::connect_options)
+            .unwrap_or_else(|| ConnectOptions::new(USER_AGENT))
+    }
+
+    /// Returns the sessions that can be resumed, along with their tokens.
+    pub fn saved_sessions(&self) -> Vec<(Session, String)> {
+        self.sessions
+            .iter()
+            .filter_map(|session| {
+                let token = self
+                    .get_account_config(&session.server, session.user)?
+                    .token
+                    .clone()?;
+                Some((session.clone(), token))
+            })
+            .collect()
+    }
+
+    /// Remembers the token of a logged in account for the next launch.
+    pub fn remember_session(&mut self, server: &Url, user: UserId, token: &str) {
+        let session = Session {
+            server: server.clone(),
+            user,
+        };
+        let known = self
+            .get_account_config(server, user)
+            .is_some_and(|account| account.token.as_deref() == Some(token));
+        if known && self.sessions.contains(&session) {
+            return;
+        }
+
+        self.get_account_config_mut(server, user).token = Some(token.to_string());
+        if !self.sessions.contains(&session) {
+            self.sessions.push(session);
+        }
+        self.save();
+    }
+
+    /// Remembers which account was in front, so that it is again on the next launch.
+    pub fn set_active_session(&mut self, server: &Url, user: UserId) {
+        let session = Session {
+            server: server.clone(),
+            user,
+        };
+        if self.last_session.as_ref() != Some(&session) {
+            self.last_session = Some(session);
+            self.save();
+        }
+    }
+
+    /// Swaps the token of the account on `server` that uses `old` for `new`,
+    /// once the server handed out a new one.
+    pub fn replace_token(&mut self, server: &Url, old: &str, new: &str) {
+        let Some(account) = self.accounts.get_mut(server).and_then(|accounts| {
+            accounts
+                .values_mut()
+                .find(|account| account.token.as_deref() == Some(old))
+        }) else {
+            return;
+        };
+
+        account.token = Some(new.to_string());
+        self.save();
+    }
+
+    /// Forgets the session on `server` that uses `token`,
+    /// e.g. once the server stops accepting it.
+    pub fn forget_session(&mut self, server: &Url, token: &str) {
+        let Some(user) = self.accounts.get(server).and_then(|accounts| {
+            accounts
+                .iter()
+                .find(|(_, account)| account.token.as_deref() == Some(token))
+                .map(|(&user, _)| user)
+        }) else {
+            return;
+        };
+
+        self.get_account_config_mut(server, user).token = None;
+        self.sessions
+            .retain(|session| session.server != *server || session.user != user);
+        if self
+            .last_session
+            .as_ref()
+            .is_some_and(|session| session.server == *server && session.user == user)
+        {
+            self.last_session = None;
+        }
+        self.save();
+    }
+
+    /// Forgets everything stored about an account, e.g. once it was deleted.
+    pub fn forget_account(&mut self, server: &Url, user: UserId) {
+        if let Some(accounts) = self.accounts.get_mut(server) {
+            accounts.remove(&user);
+        }
+        self.sessions
+            .retain(|session| session.server != *server || session.user != user);
+        if self
+            .last_session
+            .as_ref()
+            .is_some_and(|session| session.server == *server && session.user == user)
+        {
+            self.last_session = None;
+        }
+        self.save();
+    }
+
     pub fn get_account_config_mut(&mut self, quaddle_url: &Url, user: UserId) -> &mut Account {
         self.accounts
             .entry(quaddle_url.clone())
@@ -75,70 +513,432 @@ impl Config {
             .or_default()
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    /// Hands the config over to the background writer. Falls back to
+    /// writing it out immediately if the writer isn't running yet.
     pub fn save(&mut self) {
-        let Some(dirs) = BaseDirs::new() else {
-            log::warn!("could not find basedirs");
-            return;
-        };
-
-        let path = dirs.config_dir().join(CONFIG_PATH);
-
-        let toml_str = match serde_json::to_string_pretty(&self) {
-            Ok(x) => x,
-            Err(e) => {
-                log::warn!("could not serialize config: {e}");
+        if let Some(ConfigWriter(sender)) = &self.writer {
+            if sender.unbounded_send(self.clone()).is_ok() {
                 return;
             }
-        };
+        }
 
-        if let Some(ancestor) = path.parent() {
-            if let Err(e) = fs::create_dir_all(ancestor) {
-                log::warn!(
-                    "could not create {path}: {e}",
-                    path = path.as_os_str().to_string_lossy()
-                );
-                return;
-            }
+        if let Err(e) = self.save_now() {
+            log::warn!("{e}");
         }
+    }
+
+    /// Swaps in a freshly loaded config, keeping the background writer.
+    pub fn replace_with(&mut self, loaded: Config) {
+        let writer = self.writer.take();
+        *self = loaded;
+        self.writer = writer;
+    }
 
-        if let Err(e) = fs::write(path, toml_str) {
-            log::warn!("could not write config file: {e}");
+    pub fn set_writer(&mut self, writer: ConfigWriter) {
+        self.writer = Some(writer);
+    }
+
+    /// Writes the config out on the current thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_now(&self) -> Result<(), SaveError> {
+        if self.is_stand_in() {
+            return Ok(());
         }
+        let path = self.file_path().ok_or(SaveError::NoConfigDir)?;
+
+        let json_str = self.to_json()?;
+
+        if let Some(ancestor) = path.parent() {
+            fs::create_dir_all(ancestor).map_err(SaveError::Io)?;
+        }
+
+        fs::write(path, json_str).map_err(SaveError::Io)
     }
 
+    /// Writes the config out on the current thread.
     #[cfg(target_arch = "wasm32")]
-    pub fn save(&mut self) {
-        let json_str = match serde_json::to_string_pretty(&self) {
-            Ok(x) => x,
-            Err(e) => {
-                log::warn!("could not serialize config: {e}");
-                return;
-            }
-        };
+    pub fn save_now(&self) -> Result<(), SaveError> {
+        if self.is_stand_in() {
+            return Ok(());
+        }
+        let json_str = self.to_json()?;
         web_sys::window()
             .unwrap()
             .local_storage()
             .unwrap()
             .unwrap()
-            .set_item("config", &json_str)
-            .unwrap()
+            .set_item(&self.profile.storage_key(), &json_str)
+            .map_err(|_| SaveError::Storage)
+    }
+}
+
+async fn persistence_service(mut output: mpsc::Sender<PersistenceEvent>) -> Infallible {
+    let (sender, mut receiver) = mpsc::unbounded();
+    let _ = output
+        .send(PersistenceEvent::Ready(ConfigWriter(sender)))
+        .await;
+
+    loop {
+        let Some(mut config) = receiver.next().await else {
+            return futures::future::pending().await;
+        };
+
+        // only write the latest snapshot once the saves calm down
+        loop {
+            select! {
+                newer = receiver.next() => match newer {
+                    Some(newer) => config = newer,
+                    None => break,
+                },
+                _ = sleep(SAVE_DEBOUNCE).fuse() => break,
+            }
+        }
+
+        if let Err(e) = write_out(config).await {
+            log::warn!("{e}");
+            let _ = output.send(PersistenceEvent::SaveFailed(e)).await;
+        }
+    }
+}
+
+/// Writes a snapshot of the config out on tokio's blocking threads, so that
+/// a slow disk doesn't hold up the executor the GUI's tasks run on.
+#[cfg(not(target_arch = "wasm32"))]
+async fn write_out(config: Config) -> Result<(), SaveError> {
+    tokio::task::spawn_blocking(move || config.save_now())
+        .await
+        .expect("writing the config out panicked")
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn write_out(config: Config) -> Result<(), SaveError> {
+    config.save_now()
+}
+
+/// Runs the background config writer.
+pub fn persistence() -> Subscription<PersistenceEvent> {
+    struct Persistence;
+
+    subscription::channel(TypeId::of::<Persistence>(), 10, persistence_service)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub server: Url,
+    pub user: UserId,
+}
+
+/// Application-wide preferences.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Ask before leaving a channel with an unsent message in the composer.
+    pub confirm_unsent_draft: bool,
+    /// Play animated images right away, instead of once they're clicked.
+    pub play_animations: bool,
+    /// Show a toast for messages arriving in channels other than the open one.
+    pub notify_messages: bool,
+    /// The language the composer is spellchecked in, e.g. `en`, unless the channel
+    /// has its own. Unless set, nothing is spellchecked.
+    pub spellcheck_language: Option<String>,
+    /// Pastes longer than this many characters are offered to be attached as a file instead.
+    pub paste_to_file_over: Option<usize>,
+    /// What long pastes are attached as.
+    pub pasted_file_format: PastedFileFormat,
+    /// The IANA timezone times and days are shown in, e.g. `Europe/Berlin`,
+    /// instead of the system's.
+    pub timezone: Option<Tz>,
+    /// Use the dark theme instead of the light one.
+    pub dark_theme: bool,
+    /// Send messages with Enter, instead of starting a new line with it.
+    /// Unless set, Enter sends everywhere but on touchscreens, which get a send button instead.
+    pub enter_to_send: Option<bool>,
+    /// Don't load images from other hosts than the server, and don't open links from messages.
+    pub strict_mode: bool,
+    /// Messages longer than this many bytes are cut short until expanded,
+    /// as laying them out in full can take a while.
+    pub collapse_messages_over: Option<usize>,
+    /// How many lines of text the composer has room for before it starts to grow.
+    pub composer_min_rows: Option<usize>,
+    pub own_messages: OwnMessageStyle,
+    pub notification_preview: NotificationPreview,
+    pub backup: BackupSettings,
+}
+
+impl Settings {
+    pub fn paste_to_file_over(&self) -> usize {
+        self.paste_to_file_over.unwrap_or(2000)
+    }
+
+    pub fn enter_sends(&self) -> bool {
+        self.enter_to_send
+            .unwrap_or_else(|| !crate::utils::is_touch_device())
+    }
+
+    pub fn collapse_messages_over(&self) -> usize {
+        self.collapse_messages_over.unwrap_or(4000)
+    }
+
+    pub fn composer_min_rows(&self) -> usize {
+        self.composer_min_rows.unwrap_or(1)
+    }
+}
+
+/// The kind of file long pastes are attached as.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PastedFileFormat {
+    /// Plain text.
+    #[default]
+    Txt,
+    /// Markdown, for pastes that are formatted.
+    Md,
+}
+
+impl PastedFileFormat {
+    /// The setting after this one, going round.
+    pub fn next(self) -> Self {
+        match self {
+            PastedFileFormat::Txt => PastedFileFormat::Md,
+            PastedFileFormat::Md => PastedFileFormat::Txt,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PastedFileFormat::Txt => "txt",
+            PastedFileFormat::Md => "md",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            PastedFileFormat::Txt => "text/plain; charset=utf-8",
+            PastedFileFormat::Md => "text/markdown; charset=utf-8",
+        }
+    }
+}
+
+/// How the user's own messages stand out from everyone else's.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnMessageStyle {
+    /// They look like everyone else's.
+    #[default]
+    Plain,
+    /// They get a faint background.
+    Tint,
+    /// They get a faint background and keep to the right, like in most messengers.
+    Right,
+}
+
+impl OwnMessageStyle {
+    /// The style after this one, going round.
+    pub fn next(self) -> Self {
+        match self {
+            OwnMessageStyle::Plain => OwnMessageStyle::Tint,
+            OwnMessageStyle::Tint => OwnMessageStyle::Right,
+            OwnMessageStyle::Right => OwnMessageStyle::Plain,
+        }
+    }
+}
+
+/// How much of a message notifications about it give away,
+/// for when others can see the screen.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPreview {
+    /// Who sent it, where, and the start of it.
+    Full,
+    /// Who sent it and where.
+    #[default]
+    Sender,
+    /// Only that there is a new message.
+    Hidden,
+}
+
+impl NotificationPreview {
+    /// The setting after this one, going round.
+    pub fn next(self) -> Self {
+        match self {
+            NotificationPreview::Full => NotificationPreview::Sender,
+            NotificationPreview::Sender => NotificationPreview::Hidden,
+            NotificationPreview::Hidden => NotificationPreview::Full,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NotificationPreview::Full => "the sender and the message",
+            NotificationPreview::Sender => "only the sender",
+            NotificationPreview::Hidden => "nothing about the message",
+        }
+    }
+}
+
+/// Periodic exports of new messages to local archives.
+/// Which channels get backed up is chosen per channel.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Where the archives go, instead of the data directory.
+    pub directory: Option<PathBuf>,
+    /// How often to look for new messages.
+    pub interval_minutes: u64,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            interval_minutes: 60,
+        }
     }
 }
 
-impl Drop for Config {
-    fn drop(&mut self) {
-        self.save()
+/// Connection overrides for a server, e.g. one sitting behind a gateway
+/// that requires extra headers.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// Where the gateway lives, if not on the same host as the HTTP API.
+    pub gateway_url: Option<Url>,
+    /// Appended to eyeqwst's user agent.
+    pub user_agent_suffix: Option<String>,
+    pub extra_headers: HashMap<String, String>,
+    /// Ignored on the web, where the browser decides.
+    pub proxy: Option<Url>,
+}
+
+impl ServerSettings {
+    pub fn connect_options(&self) -> ConnectOptions {
+        let mut options = ConnectOptions::new(match &self.user_agent_suffix {
+            Some(suffix) => format!("{USER_AGENT} {suffix}"),
+            None => USER_AGENT.to_string(),
+        });
+
+        for (name, value) in &self.extra_headers {
+            match (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+                (Ok(name), Ok(value)) => {
+                    options.headers.insert(name, value);
+                }
+                _ => log::warn!("ignoring invalid header {name:?}: {value:?}"),
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            options.proxy = self.proxy.clone();
+        }
+
+        options
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Account {
     pub channels: Vec<Channel>,
+    /// Unsent composer contents, by channel.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    pub drafts: HashMap<ChannelId, String>,
+    /// Unsubmitted in-place edits, by message.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    pub edit_drafts: HashMap<MessageId, String>,
+    /// The newest message seen in each channel.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, DisplayFromStr>")]
+    pub last_read: HashMap<ChannelId, MessageId>,
+    /// Message templates offered in the composer, by channel.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    pub templates: HashMap<ChannelId, Vec<Template>>,
+    /// The session token, so that the next launch can skip logging in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Messages that couldn't be sent while offline, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outbox: Vec<Outgoing>,
+    /// The newest message written to each channel's backup archive.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<DisplayFromStr, DisplayFromStr>")]
+    pub backed_up: HashMap<ChannelId, MessageId>,
+    /// The account as the server last described it, so that the next launch
+    /// can show its channels before the gateway connects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<User>,
+    /// Messages the user saved for later, in the order they were saved.
+    /// They're kept whole, so that they can be listed without the server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<Message>,
+}
+
+/// A message waiting for the connection to come back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Outgoing {
+    pub channel: ChannelId,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<MessageId>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A canned message, e.g. a standup format.
+///
+/// Parts of the body written as `{{field}}` are placeholders,
+/// which can be tabbed through after the template is inserted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Channel {
     pub id: ChannelId,
     pub name: String,
+    /// Ask before sending, e.g. in announcement channels.
+    #[serde(default)]
+    pub confirm_send: bool,
+    /// Include the channel in automatic backups.
+    #[serde(default)]
+    pub backup: bool,
+    /// The language the composer is spellchecked in while the channel is selected,
+    /// instead of the one in the settings.
+    #[serde(default)]
+    pub spellcheck_language: Option<String>,
+    /// Show the channel's messages as single lines, IRC style.
+    #[serde(default)]
+    pub dense: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_unreadable_config_is_kept() {
+        let dir = std::env::temp_dir().join(format!("eyeqwst-config-{}", std::process::id()));
+        let path = dir.join("config.json");
+        fs::create_dir_all(&dir).expect("failed to create the directory");
+        fs::write(&path, "{ not json").expect("failed to write the config");
+
+        let (mut config, migration) = Config::load_profile(Profile::File(path.clone()));
+        config.save_now().expect("failed to save the config");
+        let kept = fs::read_to_string(&path).expect("failed to read the config");
+
+        config.settings.dark_theme = true;
+        config.save_now().expect("failed to save the config");
+        let replaced = fs::read_to_string(&path).expect("failed to read the config");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(migration.is_some_and(|migration| migration.from.is_none()));
+        assert_eq!(kept, "{ not json");
+        let (replaced, _) = parse(&replaced).expect("failed to parse the saved config");
+        assert!(replaced.settings.dark_theme);
+    }
 }
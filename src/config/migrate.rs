@@ -0,0 +1,181 @@
+//! Reading configs written by older versions of eyeqwst, or by hand in TOML,
+//! and upgrading them to the current schema before they're deserialized.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+/// Bumped whenever a config written by an older version can't be read as is.
+pub const CURRENT_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Json(serde_json::Error),
+    NotAnObject,
+    /// Written by a newer eyeqwst, which may have moved things this one doesn't know about.
+    TooNew(u64),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Json(e) => write!(f, "{e}"),
+            ParseError::NotAnObject => write!(f, "the config is not a table of settings"),
+            ParseError::TooNew(version) => write!(
+                f,
+                "the config is of version {version}, which is newer than this eyeqwst"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a config in any format eyeqwst understands and upgrades it.
+/// Also returns what it was upgraded from, if anything had to change.
+pub fn parse(contents: &str) -> Result<(Value, Option<String>), ParseError> {
+    let (value, format) = match serde_json::from_str(contents) {
+        Ok(value) => (value, None),
+        Err(e) => (
+            from_toml(contents).ok_or(ParseError::Json(e))?,
+            Some("TOML"),
+        ),
+    };
+    let Value::Object(mut config) = value else {
+        return Err(ParseError::NotAnObject);
+    };
+
+    let version = config.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > CURRENT_VERSION {
+        return Err(ParseError::TooNew(version));
+    }
+    let upgraded = upgrade(&mut config, version);
+    config.insert("version".to_string(), CURRENT_VERSION.into());
+
+    let from = match (format, upgraded) {
+        (Some(format), _) => Some(format.to_string()),
+        (None, true) => Some(format!("version {version}")),
+        (None, false) => None,
+    };
+    Ok((Value::Object(config), from))
+}
+
+/// Hand-written configs, for the TOML-minded.
+#[cfg(not(target_arch = "wasm32"))]
+fn from_toml(contents: &str) -> Option<Value> {
+    let table: toml::Table = toml::from_str(contents).ok()?;
+    serde_json::to_value(table).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn from_toml(_contents: &str) -> Option<Value> {
+    None
+}
+
+/// Runs every migration newer than `version`. Returns whether any of them changed something.
+fn upgrade(config: &mut Map<String, Value>, version: u64) -> bool {
+    let mut changed = false;
+    if version < 1 {
+        changed |= add_accounts(config);
+        changed |= add_sessions(config);
+    }
+    changed
+}
+
+/// `accounts` used to be the only thing in the config, so it's required,
+/// but hand-written configs may leave it out.
+fn add_accounts(config: &mut Map<String, Value>) -> bool {
+    if config.contains_key("accounts") {
+        return false;
+    }
+    config.insert("accounts".to_string(), Value::Object(Map::new()));
+    true
+}
+
+/// Configs written before there were several sessions only have `last_session`,
+/// and the first ones written after that have it missing from `sessions`.
+fn add_sessions(config: &mut Map<String, Value>) -> bool {
+    let Some(last) = config
+        .get("last_session")
+        .filter(|last| !last.is_null())
+        .cloned()
+    else {
+        return false;
+    };
+    let sessions = config
+        .entry("sessions")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    match sessions {
+        Value::Array(sessions) if !sessions.contains(&last) => {
+            sessions.push(last);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_current_config_is_untouched() {
+        let contents = json!({ "version": 1, "accounts": {}, "sessions": [] }).to_string();
+        let (value, from) = parse(&contents).unwrap();
+        assert_eq!(from, None);
+        assert_eq!(
+            value,
+            json!({ "version": 1, "accounts": {}, "sessions": [] })
+        );
+    }
+
+    #[test]
+    fn test_last_session_becomes_a_session() {
+        let session = json!({ "server": "https://example.com/", "user": 1 });
+        let contents = json!({ "accounts": {}, "last_session": session }).to_string();
+        let (value, from) = parse(&contents).unwrap();
+        assert_eq!(from.as_deref(), Some("version 0"));
+        assert_eq!(value["sessions"], json!([session]));
+        assert_eq!(value["version"], json!(CURRENT_VERSION));
+    }
+
+    #[test]
+    fn test_last_session_joins_the_sessions() {
+        let first = json!({ "server": "https://example.com/", "user": 1 });
+        let last = json!({ "server": "https://example.com/", "user": 2 });
+        let contents = json!({ "accounts": {}, "sessions": [first], "last_session": last });
+        let (value, _) = parse(&contents.to_string()).unwrap();
+        assert_eq!(value["sessions"], json!([first, last]));
+    }
+
+    #[test]
+    fn test_unversioned_config_without_changes() {
+        let contents = json!({ "accounts": {}, "sessions": [] }).to_string();
+        let (_, from) = parse(&contents).unwrap();
+        assert_eq!(from, None);
+    }
+
+    #[test]
+    fn test_toml() {
+        let contents = "[settings]\ndark_theme = true\n";
+        let (value, from) = parse(contents).unwrap();
+        assert_eq!(from.as_deref(), Some("TOML"));
+        assert_eq!(value["settings"]["dark_theme"], json!(true));
+        assert_eq!(value["accounts"], json!({}));
+    }
+
+    #[test]
+    fn test_garbage() {
+        assert!(matches!(parse("[1, 2"), Err(ParseError::Json(_))));
+        assert!(matches!(parse("[1, 2]"), Err(ParseError::NotAnObject)));
+    }
+
+    #[test]
+    fn test_newer_config() {
+        let version = CURRENT_VERSION + 1;
+        let contents = json!({ "version": version, "accounts": {} }).to_string();
+        assert!(matches!(parse(&contents), Err(ParseError::TooNew(v)) if v == version));
+    }
+}
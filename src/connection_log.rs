@@ -0,0 +1,87 @@
+//! What happened to the gateway connection lately, to make sense of flaky links.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use iced::widget::{button, column, row, text, tooltip, Column};
+use iced::{theme, Element};
+
+use crate::timezone;
+
+/// How many entries are kept before the oldest ones are dropped.
+const MAX_ENTRIES: usize = 50;
+/// How many of the newest entries are shown.
+const SHOWN_ENTRIES: usize = 5;
+
+#[derive(Debug)]
+struct Entry {
+    at: DateTime<Utc>,
+    what: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ConnectionLog {
+    /// Oldest first.
+    entries: VecDeque<Entry>,
+}
+
+impl ConnectionLog {
+    pub fn push(&mut self, what: impl Into<String>) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            at: Utc::now(),
+            what: what.into(),
+        });
+    }
+
+    /// The whole log with ISO 8601 timestamps, for pasting into bug reports.
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{at} {what}",
+                    at = entry.at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    what = entry.what
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The newest entries, newest first, with times relative to now
+    /// that show in full when hovered.
+    pub fn view<'a, Message: Clone + 'a>(
+        &'a self,
+        timezone: Option<Tz>,
+        on_copy: Message,
+    ) -> Element<'a, Message> {
+        let now = Utc::now();
+        let rows = self.entries.iter().rev().take(SHOWN_ENTRIES).map(|entry| {
+            row![
+                tooltip(
+                    text(timezone::relative(entry.at, now, timezone)).size(12),
+                    text(timezone::absolute(entry.at, timezone)).size(12),
+                    tooltip::Position::FollowCursor,
+                )
+                .style(theme::Container::Box)
+                .padding(5),
+                text(&entry.what).size(12),
+            ]
+            .spacing(10)
+            .into()
+        });
+
+        column![
+            Column::with_children(rows).spacing(2),
+            button(text("copy log").size(12))
+                .style(theme::Button::Text)
+                .on_press(on_copy),
+        ]
+        .spacing(5)
+        .into()
+    }
+}
@@ -0,0 +1,254 @@
+//! `quaddle://` links, which point at a message on some server so that
+//! they can be shared outside of the channel it was sent in, and opened
+//! by passing them to eyeqwst on the command line.
+//!
+//! The desktop opens a link by starting eyeqwst with it, even if eyeqwst is
+//! already running. That second instance then [hands it off](hand_off) to the
+//! first one, which picks it up through [`received`], and quits.
+
+use std::fmt;
+
+use iced::Subscription;
+use quaddlecl::model::channel::ChannelId;
+use quaddlecl::model::message::MessageId;
+use url::Url;
+
+pub const SCHEME: &str = "quaddle";
+
+/// A link to a message, as `quaddle://<server>/channels/<channel>/<message>`.
+/// The server is only its host and port, so servers are assumed to be
+/// served from the root of their domain.
+pub fn message_link(server: &Url, channel_id: ChannelId, message_id: MessageId) -> String {
+    let host = server.host_str().unwrap_or_default();
+    match server.port() {
+        Some(port) => format!("{SCHEME}://{host}:{port}/channels/{channel_id}/{message_id}"),
+        None => format!("{SCHEME}://{host}/channels/{channel_id}/{message_id}"),
+    }
+}
+
+/// A parsed [`message_link`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageLink {
+    host: String,
+    port: Option<u16>,
+    pub channel: ChannelId,
+    pub message: MessageId,
+}
+
+impl MessageLink {
+    pub fn parse(link: &str) -> Option<Self> {
+        let url = Url::parse(link).ok().filter(|url| url.scheme() == SCHEME)?;
+        let mut segments = url.path_segments()?;
+        let (Some("channels"), Some(channel), Some(message), None) = (
+            segments.next(),
+            segments.next(),
+            segments.next(),
+            segments.next(),
+        ) else {
+            return None;
+        };
+
+        Some(Self {
+            host: url.host_str()?.to_string(),
+            port: url.port(),
+            channel: channel.parse().ok()?,
+            message: message.parse().ok()?,
+        })
+    }
+
+    /// Whether the link points at a message on `server`.
+    pub fn is_on(&self, server: &Url) -> bool {
+        server.host_str() == Some(&self.host) && server.port() == self.port
+    }
+
+    /// The server as it's written in the link, to tell the user which one it was.
+    pub fn host(&self) -> String {
+        match self.port {
+            Some(port) => format!("{host}:{port}", host = self.host),
+            None => self.host.clone(),
+        }
+    }
+}
+
+impl fmt::Display for MessageLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{SCHEME}://{host}/channels/{channel}/{message}",
+            host = self.host(),
+            channel = self.channel,
+            message = self.message,
+        )
+    }
+}
+
+/// Passes `link` on to an instance of eyeqwst that's already running.
+/// `false` if there's none to take it.
+pub fn hand_off(link: &MessageLink) -> bool {
+    #[cfg(unix)]
+    {
+        handoff::send(link)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = link;
+        false
+    }
+}
+
+/// The links handed off by instances started after this one.
+pub fn received() -> Subscription<MessageLink> {
+    #[cfg(unix)]
+    {
+        handoff::listen()
+    }
+    #[cfg(not(unix))]
+    {
+        Subscription::none()
+    }
+}
+
+#[cfg(unix)]
+mod handoff {
+    use std::any::TypeId;
+    use std::convert::Infallible;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use directories::BaseDirs;
+    use futures::channel::mpsc;
+    use futures::{SinkExt, StreamExt};
+    use iced::{subscription, Subscription};
+
+    use super::MessageLink;
+
+    const SOCKET_NAME: &str = "eyeqwst-links.sock";
+
+    /// How long a sender may take to write its link before it's hung up on.
+    const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn path() -> Option<PathBuf> {
+        let dirs = BaseDirs::new()?;
+        let dir = dirs.runtime_dir().unwrap_or_else(|| dirs.cache_dir());
+        Some(dir.join(SOCKET_NAME))
+    }
+
+    pub fn send(link: &MessageLink) -> bool {
+        let Some(path) = path() else {
+            return false;
+        };
+        match UnixStream::connect(path).and_then(|mut stream| writeln!(stream, "{link}")) {
+            Ok(()) => true,
+            // nothing's listening, or only a socket a crashed instance left behind
+            Err(_) => false,
+        }
+    }
+
+    /// Listens on the socket, replacing one a crashed instance left behind.
+    fn bind(path: &Path) -> io::Result<UnixListener> {
+        match UnixListener::bind(path) {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(path).is_ok() {
+                    // another instance, e.g. of another profile, got there first
+                    return Err(e);
+                }
+                std::fs::remove_file(path)?;
+                UnixListener::bind(path)
+            }
+            res => res,
+        }
+    }
+
+    fn read_link(stream: UnixStream) -> io::Result<String> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(line.trim().to_string())
+    }
+
+    async fn handoff_service(mut output: mpsc::Sender<MessageLink>) -> Infallible {
+        let Some(path) = path() else {
+            return futures::future::pending().await;
+        };
+        let listener = match bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::warn!("not taking links from other instances: {e}");
+                return futures::future::pending().await;
+            }
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded();
+        // accepting blocks, so it gets a thread of its own
+        tokio::task::spawn_blocking(move || {
+            for stream in listener.incoming() {
+                let link = match stream.and_then(read_link) {
+                    Ok(link) => link,
+                    Err(e) => {
+                        log::warn!("could not take a link from another instance: {e}");
+                        continue;
+                    }
+                };
+                match MessageLink::parse(&link) {
+                    Some(link) => {
+                        if sender.unbounded_send(link).is_err() {
+                            return;
+                        }
+                    }
+                    None => log::warn!("another instance passed a bad link: {link:?}"),
+                }
+            }
+        });
+
+        while let Some(link) = receiver.next().await {
+            let _ = output.send(link).await;
+        }
+        futures::future::pending().await
+    }
+
+    pub fn listen() -> Subscription<MessageLink> {
+        struct Handoff;
+
+        subscription::channel(TypeId::of::<Handoff>(), 10, handoff_service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_link() {
+        let server = Url::parse("https://quaddle.example:8443/").unwrap();
+        let link = message_link(&server, ChannelId(12), MessageId(34));
+        assert_eq!(link, "quaddle://quaddle.example:8443/channels/12/34");
+
+        let parsed = MessageLink::parse(&link).unwrap();
+        assert!(parsed.is_on(&server));
+        assert_eq!(
+            (parsed.channel, parsed.message),
+            (ChannelId(12), MessageId(34))
+        );
+
+        assert_eq!(
+            MessageLink::parse("https://quaddle.example/channels/12/34"),
+            None
+        );
+        assert_eq!(
+            MessageLink::parse("quaddle://quaddle.example/channels/12"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for link in [
+            "quaddle://quaddle.example:8443/channels/12/34",
+            "quaddle://quaddle.example/channels/12/34",
+        ] {
+            assert_eq!(MessageLink::parse(link).unwrap().to_string(), link);
+        }
+    }
+}
@@ -2,18 +2,17 @@ use std::sync::Arc;
 
 use iced::advanced::text::highlighter::PlainText;
 use iced::advanced::widget::{tree, Tree};
-use iced::advanced::{text, Widget};
+use iced::advanced::{clipboard, text, Widget};
 use iced::event::Status;
 use iced::keyboard::key::Named;
 use iced::keyboard::Key;
 use iced::mouse::Button;
+use iced::time::Instant;
+use iced::window::{self, RedrawRequest};
 
-use iced::widget::text_editor::{Action, Content, Motion};
+use iced::widget::text_editor::{Action, Content, Edit, Motion};
 use iced::widget::TextEditor;
-use iced::{keyboard, widget, Command, Element, Event, Length, Padding, Renderer};
-use quaddlecl::client::http::{self, Http};
-use quaddlecl::model::channel::ChannelId;
-use quaddlecl::model::message::Message as QMessage;
+use iced::{alignment, keyboard, widget, Element, Event, Length, Padding, Point, Size};
 
 pub struct MessageEditor<'a, Highlighter, Message, Theme = iced::Theme, Renderer = iced::Renderer>
 where
@@ -23,12 +22,59 @@ where
 {
     text_editor: TextEditor<'a, Highlighter, Message, Theme, Renderer>,
     on_enter: Option<Message>,
+    on_tab: Option<Message>,
+    on_up_empty: Option<Message>,
     on_action: Option<fn(Action) -> Message>,
+    /// Key presses left to the application's shortcuts.
+    passthrough: Option<fn(&Key, keyboard::Modifiers) -> bool>,
     is_disabled: bool,
+    /// Shown while the editor is empty.
+    placeholder: Option<String>,
+    is_empty: bool,
+    padding: Padding,
+    /// Pastes longer than this many characters, and what to do with them instead.
+    on_long_paste: Option<(usize, OnLongPaste<Message>)>,
+    /// Sent for pastes that aren't text, such as images, which the editor can't paste itself.
+    on_other_paste: Option<Message>,
+    /// The editor is never shorter than this many lines, however little is in it.
+    min_rows: usize,
 }
 
+type OnLongPaste<Message> = fn(String) -> Message;
+
+/// How quickly the height follows the text, as the time it takes to get
+/// about two thirds of the way there.
+const GROW_TIME_CONSTANT: f32 = 0.04;
+
 struct State {
     is_focused: bool, // goofy ahh hack
+    /// The height the editor is drawn at, which follows `target` over a few frames
+    /// as lines are added or removed. `None` until the first layout, which isn't animated.
+    height: Option<f32>,
+    target: f32,
+    last_frame: Option<Instant>,
+}
+
+impl State {
+    /// Moves the height towards the target by however much time passed since the
+    /// last frame. Returns whether it's still on its way.
+    fn animate(&mut self, now: Instant) -> bool {
+        let Some(height) = self.height else {
+            return false;
+        };
+        if (self.target - height).abs() < 0.5 {
+            self.height = Some(self.target);
+            self.last_frame = None;
+            return false;
+        }
+        let elapsed = self.last_frame.map_or(0.0, |last| {
+            now.saturating_duration_since(last).as_secs_f32()
+        });
+        let step = 1.0 - (-elapsed / GROW_TIME_CONSTANT).exp();
+        self.height = Some(height + (self.target - height) * step);
+        self.last_frame = Some(now);
+        true
+    }
 }
 
 impl<'a, Message, Theme, Renderer> MessageEditor<'a, PlainText, Message, Theme, Renderer>
@@ -41,8 +87,18 @@ where
         Self {
             text_editor: TextEditor::new(content),
             on_enter: None,
+            on_tab: None,
+            on_up_empty: None,
             on_action: None,
+            passthrough: None,
             is_disabled: true,
+            placeholder: None,
+            is_empty: content.line_count() == 1
+                && content.line(0).is_some_and(|line| line.is_empty()),
+            padding: Padding::new(5.0),
+            on_long_paste: None,
+            on_other_paste: None,
+            min_rows: 1,
         }
     }
 }
@@ -64,16 +120,112 @@ where
         }
     }
 
+    /// Like [`on_action`](Self::on_action), but leaves the editor disabled if `enabled` is false.
+    pub fn on_action_if(self, enabled: bool, f: fn(Action) -> Message) -> Self {
+        if enabled {
+            self.on_action(f)
+        } else {
+            self
+        }
+    }
+
+    pub fn placeholder(self, placeholder: impl Into<String>) -> Self {
+        Self {
+            placeholder: Some(placeholder.into()),
+            ..self
+        }
+    }
+
     pub fn on_enter(self, msg: Message) -> Self {
+        self.on_enter_maybe(Some(msg))
+    }
+
+    /// Captures Enter presses if there's a message, instead of starting a new line.
+    pub fn on_enter_maybe(self, msg: Option<Message>) -> Self {
+        Self {
+            on_enter: msg,
+            ..self
+        }
+    }
+
+    /// Captures Tab presses instead of inserting a tab character.
+    pub fn on_tab_maybe(self, msg: Option<Message>) -> Self {
+        Self {
+            on_tab: msg,
+            ..self
+        }
+    }
+
+    /// Captures Up presses while the editor is empty, which would do nothing otherwise.
+    pub fn on_up_empty(self, msg: Message) -> Self {
+        Self {
+            on_up_empty: Some(msg),
+            ..self
+        }
+    }
+
+    /// Lets key presses that `f` matches through to the application while focused,
+    /// instead of moving the cursor or typing them.
+    pub fn passthrough(self, f: fn(&Key, keyboard::Modifiers) -> bool) -> Self {
+        Self {
+            passthrough: Some(f),
+            ..self
+        }
+    }
+
+    /// Hands pastes longer than `threshold` characters to `f`, instead of pasting them.
+    pub fn on_long_paste(self, threshold: usize, f: fn(String) -> Message) -> Self {
+        Self {
+            on_long_paste: Some((threshold, f)),
+            ..self
+        }
+    }
+
+    /// Keeps room for at least `rows` lines of text, so the editor only starts
+    /// growing once there's more than that.
+    pub fn min_rows(self, rows: usize) -> Self {
+        Self {
+            min_rows: rows.max(1),
+            ..self
+        }
+    }
+
+    /// Sends `message` when what's pasted isn't text, e.g. an image, instead of pasting nothing.
+    pub fn on_other_paste(self, message: Message) -> Self {
         Self {
-            on_enter: Some(msg),
+            on_other_paste: Some(message),
             ..self
         }
     }
 
+    /// Has `H`, made from `settings`, mark up the text, which `to_format` turns into how it looks.
+    pub fn highlight<H: text::Highlighter>(
+        self,
+        settings: H::Settings,
+        to_format: fn(&H::Highlight, &Theme) -> text::highlighter::Format<Renderer::Font>,
+    ) -> MessageEditor<'a, H, Message, Theme, Renderer> {
+        MessageEditor {
+            text_editor: self.text_editor.highlight(settings, to_format),
+            on_enter: self.on_enter,
+            on_tab: self.on_tab,
+            on_up_empty: self.on_up_empty,
+            on_action: self.on_action,
+            passthrough: self.passthrough,
+            is_disabled: self.is_disabled,
+            placeholder: self.placeholder,
+            is_empty: self.is_empty,
+            padding: self.padding,
+            on_long_paste: self.on_long_paste,
+            on_other_paste: self.on_other_paste,
+            min_rows: self.min_rows,
+        }
+    }
+
     pub fn padding(self, p: impl Into<Padding>) -> Self {
+        let padding = p.into();
         Self {
-            text_editor: self.text_editor.padding(p),
+            text_editor: self.text_editor.padding(padding),
+            padding,
             ..self
         }
     }
@@ -97,8 +249,17 @@ where
         renderer: &Renderer,
         limits: &iced::advanced::layout::Limits,
     ) -> iced::advanced::layout::Node {
-        self.text_editor
-            .layout(&mut tree.children[0], renderer, limits)
+        let node = self
+            .text_editor
+            .layout(&mut tree.children[0], renderer, limits);
+        let line_height = text::LineHeight::default().to_absolute(renderer.default_size());
+        let min_height = line_height.0 * self.min_rows as f32 + self.padding.vertical();
+        let target = node.size().height.max(min_height).min(limits.max().height);
+
+        let state = tree.state.downcast_mut::<State>();
+        state.target = target;
+        let height = *state.height.get_or_insert(target);
+        iced::advanced::layout::Node::new(Size::new(node.size().width, height))
     }
 
     fn draw(
@@ -119,7 +280,31 @@ where
             layout,
             cursor,
             viewport,
-        )
+        );
+
+        let Some(placeholder) = self.placeholder.as_deref().filter(|_| self.is_empty) else {
+            return;
+        };
+        let bounds = layout.bounds();
+        let size = Size::new(
+            bounds.width - self.padding.horizontal(),
+            bounds.height - self.padding.vertical(),
+        );
+        renderer.fill_text(
+            text::Text {
+                content: placeholder,
+                bounds: size,
+                size: renderer.default_size(),
+                line_height: text::LineHeight::default(),
+                font: renderer.default_font(),
+                horizontal_alignment: alignment::Horizontal::Left,
+                vertical_alignment: alignment::Vertical::Top,
+                shaping: text::Shaping::Advanced,
+            },
+            Point::new(bounds.x + self.padding.left, bounds.y + self.padding.top),
+            theme.placeholder_color(&Default::default()),
+            *viewport,
+        );
     }
 
     fn size_hint(&self) -> iced::Size<Length> {
@@ -131,7 +316,12 @@ where
     }
 
     fn state(&self) -> iced::advanced::widget::tree::State {
-        tree::State::new(State { is_focused: false })
+        tree::State::new(State {
+            is_focused: false,
+            height: None,
+            target: 0.0,
+            last_frame: None,
+        })
     }
 
     fn children(&self) -> Vec<iced::advanced::widget::Tree> {
@@ -166,6 +356,12 @@ where
         viewport: &iced::Rectangle,
     ) -> iced::advanced::graphics::core::event::Status {
         let state = tree.state.downcast_mut::<State>();
+        if let Event::Window(_, window::Event::RedrawRequested(now)) = event {
+            if state.animate(now) {
+                shell.invalidate_layout();
+                shell.request_redraw(RedrawRequest::NextFrame);
+            }
+        }
         if !self.is_disabled {
             match (&self, &event) {
                 (
@@ -191,6 +387,35 @@ where
                         return Status::Captured;
                     }
                 }
+                (
+                    Self {
+                        on_tab: Some(on_tab),
+                        ..
+                    },
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: Key::Named(Named::Tab),
+                        modifiers,
+                        ..
+                    }),
+                ) if modifiers.is_empty() && state.is_focused => {
+                    shell.publish(on_tab.clone());
+                    return Status::Captured;
+                }
+                (
+                    Self {
+                        on_up_empty: Some(on_up_empty),
+                        is_empty: true,
+                        ..
+                    },
+                    Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: Key::Named(Named::ArrowUp),
+                        modifiers,
+                        ..
+                    }),
+                ) if modifiers.is_empty() && state.is_focused => {
+                    shell.publish(on_up_empty.clone());
+                    return Status::Captured;
+                }
                 (
                     Self {
                         on_action: Some(on_action),
@@ -205,6 +430,43 @@ where
                     shell.publish(on_action(Action::Select(Motion::DocumentEnd)));
                     return Status::Captured;
                 }
+                (
+                    Self {
+                        on_long_paste,
+                        on_other_paste,
+                        ..
+                    },
+                    Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }),
+                ) if matches!(key.as_ref(), Key::Character("v"))
+                    && modifiers.command()
+                    && state.is_focused
+                    && (on_long_paste.is_some() || on_other_paste.is_some()) =>
+                {
+                    match (clipboard.read(clipboard::Kind::Standard), on_long_paste) {
+                        (Some(pasted), Some((threshold, on_long_paste)))
+                            if pasted.chars().count() > *threshold =>
+                        {
+                            shell.publish(on_long_paste(pasted));
+                            return Status::Captured;
+                        }
+                        (None, _) => {
+                            if let Some(on_other_paste) = on_other_paste.clone() {
+                                shell.publish(on_other_paste);
+                                return Status::Captured;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                (
+                    Self {
+                        passthrough: Some(passthrough),
+                        ..
+                    },
+                    Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }),
+                ) if passthrough(key, *modifiers) && state.is_focused => {
+                    return Status::Ignored;
+                }
                 _ => {}
             }
         }
@@ -257,3 +519,139 @@ where
         Self::new(editor)
     }
 }
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+
+/// Finds the first template placeholder at or after byte `from`,
+/// returning its byte range.
+fn find_placeholder(text: &str, from: usize) -> Option<std::ops::Range<usize>> {
+    let start = from + text[from..].find(PLACEHOLDER_START)?;
+    let len = text[start..].find(PLACEHOLDER_END)? + PLACEHOLDER_END.len();
+    Some(start..start + len)
+}
+
+pub fn has_placeholder(text: &str) -> bool {
+    find_placeholder(text, 0).is_some()
+}
+
+/// Selects the first placeholder after the cursor, wrapping around to the start.
+/// Returns `false` if there are none left.
+pub fn select_next_placeholder<R: text::Renderer>(content: &mut Content<R>) -> bool {
+    let text = content.text();
+    let cursor = cursor_offset(content, &text);
+    let Some(range) = find_placeholder(&text, cursor).or_else(|| find_placeholder(&text, 0)) else {
+        return false;
+    };
+
+    select_range(content, &text, range);
+    true
+}
+
+/// Inserts a template at the cursor, replacing the selection,
+/// and selects its first placeholder.
+pub fn insert_template<R: text::Renderer>(content: &mut Content<R>, body: &str) {
+    if content.selection().is_some() {
+        content.perform(Action::Edit(Edit::Delete));
+    }
+    let start = cursor_offset(content, &content.text());
+    content.perform(Action::Edit(Edit::Paste(Arc::new(body.to_string()))));
+
+    if has_placeholder(body) {
+        let text = content.text();
+        if let Some(range) = find_placeholder(&text, start) {
+            select_range(content, &text, range);
+        }
+    }
+}
+
+/// The byte offset of the cursor in `text`, the contents of `content`.
+fn cursor_offset<R: text::Renderer>(content: &Content<R>, text: &str) -> usize {
+    let (line, col) = content.cursor_position();
+    let offset = text
+        .split('\n')
+        .take(line)
+        .map(|l| l.len() + 1)
+        .sum::<usize>()
+        + col;
+    offset.min(text.len())
+}
+
+/// Selects the byte `range` of `text`, the contents of `content`.
+fn select_range<R: text::Renderer>(
+    content: &mut Content<R>,
+    text: &str,
+    range: std::ops::Range<usize>,
+) {
+    // a selection collapses onto its end with the first move
+    if content.selection().is_some() {
+        content.perform(Action::Move(Motion::Right));
+    }
+
+    // the editor only moves by characters, so walk there from the cursor,
+    // or from the start if that's closer
+    let mut cursor = cursor_offset(content, text);
+    if range.start < cursor && range.start < cursor - range.start {
+        content.perform(Action::Move(Motion::DocumentStart));
+        cursor = 0;
+    }
+    let (motion, between) = if range.start < cursor {
+        (Motion::Left, &text[range.start..cursor])
+    } else {
+        (Motion::Right, &text[cursor..range.start])
+    };
+    for _ in between.chars() {
+        content.perform(Action::Move(motion));
+    }
+    for _ in text[range].chars() {
+        content.perform(Action::Select(Motion::Right));
+    }
+}
+
+/// The word being typed right before the cursor, if it starts with `sigil`,
+/// e.g. `:smi` while typing a shortcode. Words are separated by whitespace.
+pub fn word_before_cursor<R: text::Renderer>(content: &Content<R>, sigil: char) -> Option<String> {
+    if content.selection().is_some() {
+        return None;
+    }
+    let text = content.text();
+    trailing_word(&text[..cursor_offset(content, &text)], sigil).map(str::to_string)
+}
+
+/// The last word of `text`, if it starts with `sigil`.
+fn trailing_word(text: &str, sigil: char) -> Option<&str> {
+    let start = text
+        .char_indices()
+        .rfind(|(_, c)| c.is_whitespace())
+        .map_or(0, |(idx, c)| idx + c.len_utf8());
+    let word = &text[start..];
+    word.starts_with(sigil).then_some(word)
+}
+
+/// Replaces the last `chars` characters before the cursor with `replacement`,
+/// e.g. to complete the word being typed.
+pub fn replace_before_cursor<R: text::Renderer>(
+    content: &mut Content<R>,
+    chars: usize,
+    replacement: &str,
+) {
+    for _ in 0..chars {
+        content.perform(Action::Select(Motion::Left));
+    }
+    content.perform(Action::Edit(Edit::Paste(Arc::new(replacement.to_string()))));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_word() {
+        assert_eq!(trailing_word("hi :smi", ':'), Some(":smi"));
+        assert_eq!(trailing_word("hi\n:", ':'), Some(":"));
+        assert_eq!(trailing_word(":joy: @me", '@'), Some("@me"));
+        assert_eq!(trailing_word("hi:smi", ':'), None);
+        assert_eq!(trailing_word("hi :smi ", ':'), None);
+        assert_eq!(trailing_word("", ':'), None);
+    }
+}
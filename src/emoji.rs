@@ -0,0 +1,159 @@
+//! The emoji the picker offers and `:shortcode:`s complete to. The index is
+//! embedded so that it works offline and on the web alike.
+
+/// Shortcodes and the emoji they stand for, in the order the picker shows them.
+pub const EMOJI: &[(&str, &str)] = &[
+    ("smile", "😄"),
+    ("grin", "😁"),
+    ("joy", "😂"),
+    ("rofl", "🤣"),
+    ("slightly_smiling_face", "🙂"),
+    ("upside_down_face", "🙃"),
+    ("wink", "😉"),
+    ("blush", "😊"),
+    ("innocent", "😇"),
+    ("heart_eyes", "😍"),
+    ("kissing_heart", "😘"),
+    ("yum", "😋"),
+    ("stuck_out_tongue", "😛"),
+    ("thinking", "🤔"),
+    ("zipper_mouth", "🤐"),
+    ("neutral_face", "😐"),
+    ("expressionless", "😑"),
+    ("smirk", "😏"),
+    ("unamused", "😒"),
+    ("roll_eyes", "🙄"),
+    ("grimacing", "😬"),
+    ("relieved", "😌"),
+    ("pensive", "😔"),
+    ("sleepy", "😪"),
+    ("sleeping", "😴"),
+    ("mask", "😷"),
+    ("nerd", "🤓"),
+    ("sunglasses", "😎"),
+    ("confused", "😕"),
+    ("worried", "😟"),
+    ("open_mouth", "😮"),
+    ("astonished", "😲"),
+    ("flushed", "😳"),
+    ("pleading", "🥺"),
+    ("cry", "😢"),
+    ("sob", "😭"),
+    ("scream", "😱"),
+    ("angry", "😠"),
+    ("rage", "😡"),
+    ("skull", "💀"),
+    ("clown", "🤡"),
+    ("ghost", "👻"),
+    ("alien", "👽"),
+    ("robot", "🤖"),
+    ("cat", "🐱"),
+    ("smiley_cat", "😺"),
+    ("dog", "🐶"),
+    ("fox", "🦊"),
+    ("frog", "🐸"),
+    ("penguin", "🐧"),
+    ("crab", "🦀"),
+    ("thumbsup", "👍"),
+    ("thumbsdown", "👎"),
+    ("ok_hand", "👌"),
+    ("wave", "👋"),
+    ("clap", "👏"),
+    ("raised_hands", "🙌"),
+    ("pray", "🙏"),
+    ("muscle", "💪"),
+    ("point_up", "☝️"),
+    ("eyes", "👀"),
+    ("brain", "🧠"),
+    ("heart", "❤️"),
+    ("orange_heart", "🧡"),
+    ("yellow_heart", "💛"),
+    ("green_heart", "💚"),
+    ("blue_heart", "💙"),
+    ("purple_heart", "💜"),
+    ("broken_heart", "💔"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("fire", "🔥"),
+    ("zap", "⚡"),
+    ("boom", "💥"),
+    ("tada", "🎉"),
+    ("confetti_ball", "🎊"),
+    ("gift", "🎁"),
+    ("trophy", "🏆"),
+    ("rocket", "🚀"),
+    ("bulb", "💡"),
+    ("bug", "🐛"),
+    ("wrench", "🔧"),
+    ("hammer", "🔨"),
+    ("lock", "🔒"),
+    ("key", "🔑"),
+    ("bell", "🔔"),
+    ("pushpin", "📌"),
+    ("memo", "📝"),
+    ("calendar", "📅"),
+    ("hourglass", "⌛"),
+    ("coffee", "☕"),
+    ("tea", "🍵"),
+    ("beer", "🍺"),
+    ("pizza", "🍕"),
+    ("cake", "🍰"),
+    ("apple", "🍎"),
+    ("sunny", "☀️"),
+    ("cloud", "☁️"),
+    ("rainbow", "🌈"),
+    ("snowflake", "❄️"),
+    ("earth", "🌍"),
+    ("moon", "🌙"),
+    ("white_check_mark", "✅"),
+    ("x", "❌"),
+    ("warning", "⚠️"),
+    ("question", "❓"),
+    ("exclamation", "❗"),
+    ("100", "💯"),
+    ("plus", "➕"),
+    ("arrow_up", "⬆️"),
+    ("arrow_down", "⬇️"),
+];
+
+/// The emoji `shortcode` stands for, without the colons around it.
+pub fn lookup(shortcode: &str) -> Option<&'static str> {
+    EMOJI
+        .iter()
+        .find(|(name, _)| *name == shortcode)
+        .map(|&(_, emoji)| emoji)
+}
+
+/// Emoji whose shortcode starts with `query`, followed by those that only contain it.
+pub fn search(query: &str) -> Vec<(&'static str, &'static str)> {
+    let (mut prefixed, contained): (Vec<_>, Vec<_>) = EMOJI
+        .iter()
+        .copied()
+        .filter(|(name, _)| name.contains(query))
+        .partition(|(name, _)| name.starts_with(query));
+    prefixed.extend(contained);
+    prefixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup("joy"), Some("😂"));
+        assert_eq!(lookup(":joy:"), None);
+        assert_eq!(lookup("jo"), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let names: Vec<_> = search("smi").into_iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            names,
+            ["smile", "smirk", "smiley_cat", "slightly_smiling_face"]
+        );
+        assert!(search("no_such_emoji").is_empty());
+        assert_eq!(search("").len(), EMOJI.len());
+    }
+}
@@ -0,0 +1,256 @@
+//! Searching through the history of the selected channel, loading
+//! older pages when the loaded messages run out of matches.
+
+use iced::widget::{button, container, row, text, text_input, tooltip};
+use iced::{theme, Alignment, Command, Element, Length};
+
+use crate::messageview::{Highlight, HistoryQMessage};
+use crate::utils::icon;
+
+const FIND_INPUT_ID: &str = "find_input";
+const OLDER: &str = "\u{f077}";
+const NEWER: &str = "\u{f078}";
+const CLOSE: &str = "\u{f00d}";
+const SEARCH_HISTORY: &str = "\u{f1da}";
+
+#[derive(Debug, Clone)]
+pub enum FindMessage {
+    QueryChanged(String),
+    Older,
+    Newer,
+    /// Look for more matches in history that isn't loaded yet.
+    SearchOlderHistory,
+    Closed,
+}
+
+#[derive(Debug, Default)]
+pub struct FindBar {
+    query: String,
+    /// Position of the current match, counting from the newest.
+    current: usize,
+    /// Whether older pages are being loaded until one has a match.
+    searching_history: bool,
+}
+
+impl FindBar {
+    pub fn focus<Message: 'static>() -> Command<Message> {
+        text_input::focus(text_input::Id::new(FIND_INPUT_ID))
+    }
+
+    /// Indices of the messages containing the query, newest first.
+    pub fn matches(&self, messages: &[HistoryQMessage]) -> Vec<usize> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+
+        let needle = self.query.to_lowercase();
+        messages
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, qmsg)| !qmsg.is_hidden())
+            .filter(|(_, qmsg)| qmsg.msg().content.to_lowercase().contains(&needle))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn is_searching_history(&self) -> bool {
+        self.searching_history
+    }
+
+    /// Gives up on searching older history, e.g. because a page failed to load.
+    pub fn stop_searching_history(&mut self) {
+        self.searching_history = false;
+    }
+
+    /// Looks for matches among the `count` messages that were just prepended to `messages`.
+    /// Returns the index of the newest of them, which becomes the current match.
+    /// The search goes on until a match turns up or the history is `exhausted`.
+    pub fn on_older_loaded(
+        &mut self,
+        count: usize,
+        messages: &[HistoryQMessage],
+        exhausted: bool,
+    ) -> Option<usize> {
+        if !self.searching_history {
+            return None;
+        }
+
+        let matches = self.matches(messages);
+        let found = matches.iter().position(|&idx| idx < count);
+        if found.is_some() || exhausted {
+            self.searching_history = false;
+        }
+
+        let pos = found?;
+        self.current = pos;
+        Some(matches[pos])
+    }
+
+    pub fn highlight(&self, idx: usize, matches: &[usize]) -> Highlight {
+        if matches.get(self.current) == Some(&idx) {
+            Highlight::Current
+        } else if matches.contains(&idx) {
+            Highlight::Match
+        } else {
+            Highlight::None
+        }
+    }
+
+    /// Returns the index of the message to bring into view, if the current match changed.
+    pub fn update(&mut self, message: FindMessage, messages: &[HistoryQMessage]) -> Option<usize> {
+        let matches = self.matches(messages);
+        match message {
+            FindMessage::QueryChanged(query) => {
+                self.query = query;
+                self.current = 0;
+                self.searching_history = false;
+                return self.matches(messages).first().copied();
+            }
+            FindMessage::Older if !matches.is_empty() => {
+                self.current = (self.current + 1) % matches.len();
+            }
+            FindMessage::Newer if !matches.is_empty() => {
+                self.current = (self.current + matches.len() - 1) % matches.len();
+            }
+            FindMessage::SearchOlderHistory if !self.query.is_empty() => {
+                self.searching_history = true;
+                return None;
+            }
+            _ => return None,
+        }
+
+        matches.get(self.current).copied()
+    }
+
+    /// `history_exhausted` tells whether there's any history left to search.
+    pub fn view(
+        &self,
+        messages: &[HistoryQMessage],
+        history_exhausted: bool,
+    ) -> Element<'_, FindMessage> {
+        let matches = self.matches(messages);
+        let counter = match (self.query.is_empty(), matches.len()) {
+            (true, _) => String::new(),
+            _ if self.searching_history => "Searching older messages…".to_string(),
+            (false, 0) => "No matches".to_string(),
+            (false, n) => format!("{current}/{n}", current = self.current.min(n - 1) + 1),
+        };
+
+        let action = |ic, label, msg: Option<FindMessage>| {
+            tooltip(
+                button(icon(ic).size(14))
+                    .style(theme::Button::Text)
+                    .on_press_maybe(msg),
+                label,
+                tooltip::Position::FollowCursor,
+            )
+        };
+        let has_matches = !matches.is_empty();
+        // offered once the loaded matches have been gone through
+        let at_oldest = self.current + 1 >= matches.len();
+        let can_search_history = !self.query.is_empty() && at_oldest && !history_exhausted;
+
+        container(
+            row![
+                text_input("Find in channel", &self.query)
+                    .id(text_input::Id::new(FIND_INPUT_ID))
+                    .on_input(FindMessage::QueryChanged)
+                    .on_submit(FindMessage::Older)
+                    .width(Length::Fill),
+                text(counter).size(14),
+                action(
+                    OLDER,
+                    "Older match",
+                    Some(FindMessage::Older).filter(|_| has_matches)
+                ),
+                action(
+                    NEWER,
+                    "Newer match",
+                    Some(FindMessage::Newer).filter(|_| has_matches)
+                ),
+                action(
+                    SEARCH_HISTORY,
+                    "Search older messages",
+                    Some(FindMessage::SearchOlderHistory)
+                        .filter(|_| can_search_history && !self.searching_history)
+                ),
+                action(CLOSE, "Close", Some(FindMessage::Closed)),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(5),
+        )
+        .padding([0, 20])
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quaddlecl::model::message::Message as QMessage;
+
+    use super::*;
+
+    fn history(contents: &[&str]) -> Vec<HistoryQMessage> {
+        contents
+            .iter()
+            .map(|content| HistoryQMessage::new(QMessage::builder().content(*content).build()))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches() {
+        let messages = history(&["Meow", "purr", "meow meow", "hiss"]);
+        let mut find = FindBar::default();
+
+        let first = find.update(FindMessage::QueryChanged("MEOW".to_string()), &messages);
+        assert_eq!(first, Some(2));
+        let matches = find.matches(&messages);
+        assert_eq!(matches, [2, 0]);
+
+        assert_eq!(find.update(FindMessage::Older, &messages), Some(0));
+        assert_eq!(find.update(FindMessage::Older, &messages), Some(2));
+        assert_eq!(find.update(FindMessage::Newer, &messages), Some(0));
+        assert_eq!(find.highlight(0, &matches), Highlight::Current);
+        assert_eq!(find.highlight(2, &matches), Highlight::Match);
+        assert_eq!(find.highlight(1, &matches), Highlight::None);
+    }
+
+    #[test]
+    fn test_empty_query() {
+        let messages = history(&["meow"]);
+        let mut find = FindBar::default();
+        assert_eq!(
+            find.update(FindMessage::QueryChanged(String::new()), &messages),
+            None
+        );
+        assert!(find.matches(&messages).is_empty());
+
+        find.update(FindMessage::SearchOlderHistory, &messages);
+        assert!(!find.is_searching_history());
+    }
+
+    #[test]
+    fn test_search_older_history() {
+        let mut messages = history(&["meow", "purr"]);
+        let mut find = FindBar::default();
+        find.update(FindMessage::QueryChanged("hiss".to_string()), &messages);
+        find.update(FindMessage::SearchOlderHistory, &messages);
+        assert!(find.is_searching_history());
+
+        // a page without a match keeps the search going
+        messages.splice(0..0, history(&["purr"]));
+        assert_eq!(find.on_older_loaded(1, &messages, false), None);
+        assert!(find.is_searching_history());
+
+        messages.splice(0..0, history(&["hiss", "meow"]));
+        assert_eq!(find.on_older_loaded(2, &messages, false), Some(0));
+        assert!(!find.is_searching_history());
+
+        // the beginning of the channel ends it too
+        find.update(FindMessage::QueryChanged("bark".to_string()), &messages);
+        find.update(FindMessage::SearchOlderHistory, &messages);
+        assert_eq!(find.on_older_loaded(0, &messages, true), None);
+        assert!(!find.is_searching_history());
+    }
+}
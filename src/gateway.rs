@@ -1,34 +1,129 @@
-use std::{any::TypeId, convert::Infallible, time::Duration};
+use std::{any::TypeId, convert::Infallible, fmt, sync::Arc, time::Duration};
 
-use futures::{channel::mpsc, select, SinkExt, StreamExt};
+use futures::{channel::mpsc, select, FutureExt, SinkExt, StreamExt};
+use iced::time::Instant;
 use iced::{subscription, Subscription};
 use quaddlecl::{
     client::gateway::{self, ClientGatewayMessage, Gateway, GatewayEvent},
+    client::ConnectOptions,
+    model::channel::ChannelId,
     model::user::User,
 };
 use url::Url;
 
-use crate::{utils::sleep, USER_AGENT};
+use crate::utils::sleep;
+
+mod trace;
+
+/// How long to wait for more events before handing a batch over to the UI.
+const BATCH_WINDOW: Duration = Duration::from_millis(50);
+/// The largest amount of events that get batched together.
+const MAX_BATCH_SIZE: usize = 100;
+/// How long to wait before dialing the gateway again.
+const DIAL_RETRY: Duration = Duration::from_secs(5);
+/// Bounds of the exponential backoff applied when identifying fails.
+const IDENTIFY_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const IDENTIFY_BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// How long the gateway may stay silent before the connection is taken to be dead.
+/// The protocol has no pings of its own, and a connection that dropped without
+/// a close frame would otherwise look alive forever.
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long sending a message to the gateway may take before it's given up on.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the latency is measured again while connected.
+const LATENCY_PROBE_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone)]
-pub struct Connection(mpsc::UnboundedSender<ClientGatewayMessage>);
+pub struct Connection(mpsc::UnboundedSender<Request>);
 
+/// What the UI asks of the connection.
 #[derive(Debug)]
+pub enum Request {
+    Send(ClientGatewayMessage),
+    /// Drops the connection and dials the gateway again.
+    Reconnect,
+}
+
+/// Cuts the wait before the gateway is dialed again short.
+#[derive(Debug, Clone)]
+pub struct Retry(mpsc::UnboundedSender<()>);
+
+impl Retry {
+    pub fn now(&self) -> bool {
+        self.0.unbounded_send(()).is_ok()
+    }
+}
+
+/// What tells gateway connections apart. Views of the same account share one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnectionKey {
+    pub url: Url,
+    pub token: String,
+}
+
+/// Why a message couldn't be sent to the gateway.
+#[derive(Debug, Clone)]
+pub enum SendError {
+    TimedOut,
+    Failed(Arc<gateway::Error>),
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::TimedOut => write!(f, "timed out"),
+            SendError::Failed(_) => write!(f, "could not send"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SendError::TimedOut => None,
+            SendError::Failed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// Handed to every view sharing the connection, hence the `Arc`s.
+#[derive(Debug, Clone)]
 pub enum GatewayMessage {
     Connected {
         conn: Connection,
         user: User,
         session_id: String,
     },
-    DialError(gateway::Error),
-    ReceiveError(gateway::Error),
+    DialError(Arc<gateway::Error>),
+    /// The gateway accepted the connection but failed to identify us
+    /// for reasons that are likely to go away by themselves.
+    ServerBusy(Arc<gateway::Error>),
+    ReceiveError(Arc<gateway::Error>),
+    /// `op` didn't make it to the gateway.
+    SendFailed {
+        op: ClientGatewayMessage,
+        error: SendError,
+    },
     Disconnected,
-    Event(GatewayEvent),
+    /// Events received in quick succession, in the order they arrived.
+    Events(Vec<GatewayEvent>),
+    /// How long the gateway took to answer, measured when identifying
+    /// and then every [`LATENCY_PROBE_INTERVAL`].
+    LatencyUpdated(Duration),
+    /// The gateway will be dialed again after `delay`, unless `retry` is used first.
+    RetryScheduled {
+        retry: Retry,
+        delay: Duration,
+    },
 }
 
 impl Connection {
     pub fn send(&mut self, msg: ClientGatewayMessage) -> bool {
-        self.0.unbounded_send(msg).is_ok()
+        self.0.unbounded_send(Request::Send(msg)).is_ok()
+    }
+
+    pub fn reconnect(&mut self) -> bool {
+        self.0.unbounded_send(Request::Reconnect).is_ok()
     }
 }
 
@@ -36,38 +131,158 @@ enum GatewayState {
     Disconnected,
     Connected {
         gateway: Gateway,
-        receiver: mpsc::UnboundedReceiver<ClientGatewayMessage>,
+        receiver: mpsc::UnboundedReceiver<Request>,
+        /// When the gateway was last heard from.
+        last_read: Instant,
+        probe: LatencyProbe,
     },
 }
 
+/// Measures the latency over and over. The protocol has no heartbeat to time,
+/// so a channel that's already subscribed to is subscribed to again, which
+/// changes nothing but gets acknowledged.
+struct LatencyProbe {
+    /// The channel subscribed to last, to probe with.
+    channel: Option<ChannelId>,
+    /// The probe waiting for its answer, with when it was sent.
+    sent: Option<(ChannelId, Instant)>,
+    /// When the next probe is sent.
+    due: Instant,
+}
+
+impl LatencyProbe {
+    fn new() -> Self {
+        Self {
+            channel: None,
+            sent: None,
+            due: Instant::now() + LATENCY_PROBE_INTERVAL,
+        }
+    }
+
+    /// Keeps track of the channels the UI subscribes to, to probe with one of them.
+    fn observe(&mut self, msg: &ClientGatewayMessage) {
+        match *msg {
+            ClientGatewayMessage::Subscribe { channel_id } => self.channel = Some(channel_id),
+            ClientGatewayMessage::Unsubscribe { channel_id }
+                if self.channel == Some(channel_id) =>
+            {
+                self.channel = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// The probe to send now, if there's a channel to probe with. One that
+    /// was never answered is given up on.
+    fn start(&mut self) -> Option<ClientGatewayMessage> {
+        let now = Instant::now();
+        self.due = now + LATENCY_PROBE_INTERVAL;
+        let channel_id = self.channel?;
+        self.sent = Some((channel_id, now));
+        Some(ClientGatewayMessage::Subscribe { channel_id })
+    }
+
+    /// How long the probe took, if `event` answers it.
+    fn answered(&mut self, event: &GatewayEvent) -> Option<Duration> {
+        let (GatewayEvent::Subscribed { channel_id }
+        | GatewayEvent::SubscribeFailed { channel_id, .. }) = event
+        else {
+            return None;
+        };
+        let (_, sent) = self.sent.filter(|(probed, _)| probed == channel_id)?;
+        self.sent = None;
+        Some(sent.elapsed())
+    }
+}
+
+/// Sends `msg`, giving up after [`SEND_TIMEOUT`].
+async fn send(gateway: &mut Gateway, msg: ClientGatewayMessage) -> Option<SendError> {
+    let mut timeout = Box::pin(sleep(SEND_TIMEOUT).fuse());
+    select! {
+        res = gateway.send(msg).fuse() => {
+            res.err().map(|e| SendError::Failed(Arc::new(e)))
+        },
+        _ = timeout => Some(SendError::TimedOut),
+    }
+}
+
+/// Whether an identify failure is worth retrying soon, as opposed to
+/// e.g. the server rejecting the token.
+fn is_transient(err: &gateway::Error) -> bool {
+    matches!(
+        err,
+        gateway::Error::UnexpectedSocketClose | gateway::Error::Websocket(_)
+    )
+}
+
+/// Waits `delay` before the next attempt to connect, or less if the UI asks to retry.
+async fn back_off(output: &mut mpsc::Sender<GatewayMessage>, delay: Duration) {
+    let (sender, mut receiver) = mpsc::unbounded();
+    let _ = output
+        .send(GatewayMessage::RetryScheduled {
+            retry: Retry(sender),
+            delay,
+        })
+        .await;
+
+    let mut timeout = Box::pin(sleep(delay).fuse());
+    select! {
+        _ = timeout => {},
+        () = receiver.select_next_some() => log::info!("retrying to connect right away"),
+    }
+}
+
 async fn gateway_service(
     mut output: mpsc::Sender<GatewayMessage>,
     url: Url,
+    options: ConnectOptions,
     token: String,
 ) -> Infallible {
+    if let Some(path) = trace::replay_path() {
+        return trace::replay(output, path).await;
+    }
+
+    let mut recorder = trace::recorder(&url);
     let mut state = GatewayState::Disconnected;
+    let mut identify_backoff = IDENTIFY_BACKOFF_MIN;
     loop {
         match state {
             GatewayState::Disconnected => {
-                let gateway_res = Gateway::connect(url.clone(), USER_AGENT.to_string()).await;
+                let gateway_res = Gateway::connect_with_options(url.clone(), &options).await;
 
                 let mut gateway = match gateway_res {
                     Ok(x) => x,
                     Err(e) => {
-                        let _ = output.send(GatewayMessage::DialError(e)).await;
-                        sleep(Duration::from_secs(5)).await;
+                        let _ = output.send(GatewayMessage::DialError(Arc::new(e))).await;
+                        back_off(&mut output, DIAL_RETRY).await;
                         continue;
                     }
                 };
 
+                let identify_start = Instant::now();
                 let (session_id, user) = match gateway.identify(token.to_string()).await {
                     Ok(x) => x,
                     Err(e) => {
-                        let _ = output.send(GatewayMessage::DialError(e)).await;
-                        sleep(Duration::from_secs(5)).await;
+                        let msg = if is_transient(&e) {
+                            GatewayMessage::ServerBusy(Arc::new(e))
+                        } else {
+                            GatewayMessage::DialError(Arc::new(e))
+                        };
+                        let _ = output.send(msg).await;
+                        back_off(&mut output, identify_backoff).await;
+                        identify_backoff = (identify_backoff * 2).min(IDENTIFY_BACKOFF_MAX);
                         continue;
                     }
                 };
+                identify_backoff = IDENTIFY_BACKOFF_MIN;
+                let latency = identify_start.elapsed();
+                trace::record(
+                    &mut recorder,
+                    trace::Frame::Identified {
+                        session_id: session_id.clone(),
+                        user: user.clone(),
+                    },
+                );
 
                 let (sender, receiver) = mpsc::unbounded();
 
@@ -78,34 +293,113 @@ async fn gateway_service(
                         session_id,
                     })
                     .await;
+                let _ = output.send(GatewayMessage::LatencyUpdated(latency)).await;
 
-                state = GatewayState::Connected { gateway, receiver };
+                state = GatewayState::Connected {
+                    gateway,
+                    receiver,
+                    last_read: Instant::now(),
+                    probe: LatencyProbe::new(),
+                };
             }
             GatewayState::Connected {
                 ref mut gateway,
                 ref mut receiver,
+                ref mut last_read,
+                ref mut probe,
             } => {
+                let mut read_timeout = Box::pin(
+                    sleep((*last_read + READ_TIMEOUT).saturating_duration_since(Instant::now()))
+                        .fuse(),
+                );
+                let mut probe_timer =
+                    Box::pin(sleep(probe.due.saturating_duration_since(Instant::now())).fuse());
                 select! {
                     gateway_res = gateway.next() => {
+                        *last_read = Instant::now();
                         match gateway_res {
                             Some(Ok(ev)) => {
+                                let mut batch = vec![ev];
+                                let mut closed = false;
+                                let mut window = Box::pin(sleep(BATCH_WINDOW).fuse());
+                                while batch.len() < MAX_BATCH_SIZE {
+                                    select! {
+                                        next = gateway.next() => match next {
+                                            Some(Ok(ev)) => batch.push(ev),
+                                            Some(Err(e)) => {
+                                                let _ = output
+                                                    .try_send(GatewayMessage::ReceiveError(Arc::new(e)));
+                                            },
+                                            None => {
+                                                closed = true;
+                                                break;
+                                            },
+                                        },
+                                        _ = window => break,
+                                    }
+                                }
+
+                                for event in &batch {
+                                    if let Some(latency) = probe.answered(event) {
+                                        let _ = output
+                                            .try_send(GatewayMessage::LatencyUpdated(latency));
+                                    }
+                                    trace::record(
+                                        &mut recorder,
+                                        trace::Frame::Event { event: event.clone() },
+                                    );
+                                }
                                 let _ = output
-                                    .try_send(GatewayMessage::Event(ev));
+                                    .try_send(GatewayMessage::Events(batch));
+
+                                if closed {
+                                    trace::record(&mut recorder, trace::Frame::Disconnected);
+                                    let _ = output.send(GatewayMessage::Disconnected)
+                                                  .await;
+                                    state = GatewayState::Disconnected;
+                                }
                             },
                             Some(Err(e)) => {
                                 let _ = output
-                                    .try_send(GatewayMessage::ReceiveError(e));
+                                    .try_send(GatewayMessage::ReceiveError(Arc::new(e)));
                             },
                             None => {
+                                trace::record(&mut recorder, trace::Frame::Disconnected);
                                 let _ = output.send(GatewayMessage::Disconnected)
                                               .await;
                                 state = GatewayState::Disconnected;
                             },
                         }
                     },
-                    new_message = receiver.select_next_some() => {
-                        let _ = gateway.send(new_message)
-                                       .await;
+                    request = receiver.select_next_some() => match request {
+                        Request::Send(msg) => {
+                            probe.observe(&msg);
+                            let op = msg.clone();
+                            if let Some(error) = send(gateway, msg).await {
+                                let _ = output.send(GatewayMessage::SendFailed { op, error }).await;
+                            }
+                        }
+                        Request::Reconnect => {
+                            log::info!("reconnecting to the gateway");
+                            let _ = gateway.close().await;
+                            trace::record(&mut recorder, trace::Frame::Disconnected);
+                            let _ = output.send(GatewayMessage::Disconnected).await;
+                            state = GatewayState::Disconnected;
+                        }
+                    },
+                    _ = probe_timer => {
+                        if let Some(msg) = probe.start() {
+                            if let Some(error) = send(gateway, msg).await {
+                                log::warn!("could not send a latency probe: {error}");
+                            }
+                        }
+                    },
+                    _ = read_timeout => {
+                        log::warn!("nothing heard from the gateway in {READ_TIMEOUT:?}, reconnecting");
+                        let _ = gateway.close().await;
+                        trace::record(&mut recorder, trace::Frame::Disconnected);
+                        let _ = output.send(GatewayMessage::Disconnected).await;
+                        state = GatewayState::Disconnected;
                     }
                 }
             }
@@ -113,10 +407,61 @@ async fn gateway_service(
     }
 }
 
-pub fn connect(url: Url, token: String) -> Subscription<GatewayMessage> {
+pub fn connect(key: ConnectionKey, options: ConnectOptions) -> Subscription<GatewayMessage> {
     struct Connect;
 
-    subscription::channel(TypeId::of::<Connect>(), 50, |output| {
-        gateway_service(output, url, token)
+    subscription::channel((TypeId::of::<Connect>(), key.clone()), 50, |output| {
+        gateway_service(output, key.url, options, key.token)
     })
 }
+
+/// One connection per distinct key, no matter how many views ask for it.
+/// Messages are tagged with the key so that they can be handed to all of them.
+pub fn connect_all(
+    wanted: impl IntoIterator<Item = (ConnectionKey, ConnectOptions)>,
+) -> Subscription<(ConnectionKey, GatewayMessage)> {
+    let mut keys = Vec::new();
+    let mut subs = Vec::new();
+    for (key, options) in wanted {
+        if keys.contains(&key) {
+            continue;
+        }
+        keys.push(key.clone());
+        subs.push(connect(key.clone(), options).with(key));
+    }
+    Subscription::batch(subs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_probe() {
+        let subscribed = |id| GatewayEvent::Subscribed {
+            channel_id: ChannelId(id),
+        };
+        let mut probe = LatencyProbe::new();
+        // nothing subscribed to yet, so nothing to probe with
+        assert!(probe.start().is_none());
+
+        probe.observe(&ClientGatewayMessage::Subscribe {
+            channel_id: ChannelId(1),
+        });
+        assert!(matches!(
+            probe.start(),
+            Some(ClientGatewayMessage::Subscribe {
+                channel_id: ChannelId(1)
+            })
+        ));
+        assert!(probe.answered(&subscribed(2)).is_none());
+        assert!(probe.answered(&subscribed(1)).is_some());
+        // answered only once
+        assert!(probe.answered(&subscribed(1)).is_none());
+
+        probe.observe(&ClientGatewayMessage::Unsubscribe {
+            channel_id: ChannelId(1),
+        });
+        assert!(probe.start().is_none());
+    }
+}
@@ -0,0 +1,222 @@
+//! Recording gateway sessions to a file and playing them back, so that bugs
+//! can be reproduced from a trace someone sent in.
+//!
+//! Set `EYEQWST_RECORD_GATEWAY` to a directory to record every connection
+//! into a file of its own, and `EYEQWST_REPLAY_GATEWAY` to one of those files
+//! to have all connections play it back instead of dialing the server.
+
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use quaddlecl::client::gateway::GatewayEvent;
+use quaddlecl::model::user::User;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use url::Url;
+
+use super::{Connection, GatewayMessage};
+use crate::utils::sleep;
+
+#[cfg(not(target_arch = "wasm32"))]
+const RECORD_VAR: &str = "EYEQWST_RECORD_GATEWAY";
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_VAR: &str = "EYEQWST_REPLAY_GATEWAY";
+
+/// Something that happened on the connection.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "frame", rename_all = "snake_case")]
+pub enum Frame {
+    Identified { session_id: String, user: User },
+    Event { event: GatewayEvent },
+    Disconnected,
+}
+
+/// One line of a trace.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// Milliseconds since the recording started.
+    at_ms: u64,
+    #[serde(flatten)]
+    frame: Frame,
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    file: File,
+    start: Instant,
+}
+
+/// Starts recording a connection to `url`, if asked to.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn recorder(url: &Url) -> Option<Recorder> {
+    let dir = PathBuf::from(std::env::var_os(RECORD_VAR)?);
+    let path = dir.join(format!(
+        "{host}-{time}.jsonl",
+        host = url.host_str().unwrap_or("gateway"),
+        time = chrono::Local::now().format("%Y%m%d-%H%M%S"),
+    ));
+    match std::fs::create_dir_all(&dir).and_then(|()| File::create(&path)) {
+        Ok(file) => {
+            log::info!("recording the gateway session to {}", path.display());
+            Some(Recorder {
+                file,
+                start: Instant::now(),
+            })
+        }
+        Err(e) => {
+            log::warn!("could not record the gateway session: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn recorder(_url: &url::Url) -> Option<Recorder> {
+    None
+}
+
+/// The trace to play back instead of connecting, if any.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn replay_path() -> Option<PathBuf> {
+    std::env::var_os(REPLAY_VAR).map(PathBuf::from)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn replay_path() -> Option<PathBuf> {
+    None
+}
+
+/// Appends `frame` to the trace. A recorder that fails to write is dropped.
+pub fn record(recorder: &mut Option<Recorder>, frame: Frame) {
+    let Some(rec) = recorder else {
+        return;
+    };
+
+    let entry = Entry {
+        at_ms: u64::try_from(rec.start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        frame,
+    };
+    let res = serde_json::to_writer(&mut rec.file, &entry)
+        .map_err(io::Error::from)
+        .and_then(|()| rec.file.write_all(b"\n"));
+    if let Err(e) = res {
+        log::warn!("stopped recording the gateway session: {e}");
+        *recorder = None;
+    }
+}
+
+fn load(path: &Path) -> io::Result<Vec<Entry>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|l| l.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// Plays the trace at `path` back with its original timing, as if it came from the gateway.
+/// Whatever the UI sends is dropped.
+pub async fn replay(mut output: mpsc::Sender<GatewayMessage>, path: PathBuf) -> Infallible {
+    let entries = load(&path).unwrap_or_else(|e| {
+        log::warn!("could not load the gateway trace {}: {e}", path.display());
+        Vec::new()
+    });
+    log::info!(
+        "replaying {n} gateway frames from {path}",
+        n = entries.len(),
+        path = path.display()
+    );
+
+    let (sender, mut receiver) = mpsc::unbounded();
+    let mut elapsed = 0;
+    for Entry { at_ms, frame } in entries {
+        sleep(Duration::from_millis(at_ms.saturating_sub(elapsed))).await;
+        elapsed = elapsed.max(at_ms);
+
+        let msg = match frame {
+            Frame::Identified { session_id, user } => GatewayMessage::Connected {
+                conn: Connection(sender.clone()),
+                user,
+                session_id,
+            },
+            Frame::Event { event } => GatewayMessage::Events(vec![event]),
+            Frame::Disconnected => GatewayMessage::Disconnected,
+        };
+        let _ = output.send(msg).await;
+    }
+    log::info!("gateway trace finished");
+
+    // `sender` is still around, so this never ends
+    while let Some(msg) = receiver.next().await {
+        log::debug!("not sending {msg:?} during a replay");
+    }
+    futures::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use quaddlecl::model::channel::ChannelId;
+
+    use super::*;
+
+    #[test]
+    fn test_record_and_load() {
+        let path = std::env::temp_dir().join(format!("eyeqwst-trace-{}.jsonl", std::process::id()));
+        let mut recorder = Some(Recorder {
+            file: File::create(&path).expect("failed to create the trace"),
+            start: Instant::now(),
+        });
+        record(
+            &mut recorder,
+            Frame::Identified {
+                session_id: "session".to_string(),
+                user: User::fake("meow"),
+            },
+        );
+        record(
+            &mut recorder,
+            Frame::Event {
+                event: GatewayEvent::Subscribed {
+                    channel_id: ChannelId(1),
+                },
+            },
+        );
+        record(&mut recorder, Frame::Disconnected);
+        assert!(recorder.is_some());
+        drop(recorder);
+
+        let entries = load(&path).expect("failed to load the trace");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(entries.len(), 3);
+        assert!(entries.windows(2).all(|w| w[0].at_ms <= w[1].at_ms));
+        assert!(matches!(
+            &entries[0].frame,
+            Frame::Identified { session_id, user } if session_id == "session" && user.name == "meow"
+        ));
+        assert!(matches!(
+            entries[1].frame,
+            Frame::Event {
+                event: GatewayEvent::Subscribed {
+                    channel_id: ChannelId(1)
+                }
+            }
+        ));
+        assert!(matches!(entries[2].frame, Frame::Disconnected));
+    }
+
+    #[test]
+    fn test_load_skips_blank_lines() {
+        let path =
+            std::env::temp_dir().join(format!("eyeqwst-trace-blank-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "\n{\"at_ms\":5,\"frame\":\"disconnected\"}\n\n")
+            .expect("failed to write the trace");
+        let entries = load(&path).expect("failed to load the trace");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].at_ms, 5);
+    }
+}
@@ -0,0 +1,718 @@
+//! Images downloaded from the server or linked in messages, such as avatars.
+//! The most recently used ones are kept in memory, and on native targets every
+//! download is kept on disk as well, so that it isn't fetched again after a restart.
+//!
+//! Every image is shown through [`ImageCache::view`], which stands in a placeholder
+//! while the image loads, offers to retry it if it couldn't be, and fades it in once it's in.
+//! Animated ones stand still with a badge until they're [played], unless the user
+//! asked for animations to play right away.
+//!
+//! Users without an avatar get a colored circle with their initials instead,
+//! through [`ImageCache::avatar`].
+//!
+//! [played]: ImageCache::play
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use iced::advanced::renderer::{self, Renderer as _};
+use iced::advanced::widget::Tree;
+use iced::advanced::{layout, mouse, Clipboard, Layout, Shell, Widget};
+use iced::time::Instant;
+use iced::widget::image::{Handle, Image};
+use iced::widget::{button, column, container, image, text};
+use iced::window::{self, RedrawRequest};
+use iced::{
+    event, theme, Alignment, Background, Border, Color, Command, Element, Event, Length, Rectangle,
+    Renderer, Size, Theme,
+};
+use iced_aw::floating_element::Anchor;
+use iced_aw::FloatingElement;
+use quaddlecl::client::http;
+use quaddlecl::model::user::{User, UserId};
+use url::Url;
+
+use crate::animation::{self, Decoded, Playback};
+use crate::scheduler::{Priority, Scheduler};
+use crate::strict_mode;
+use crate::utils::{icon, ErrorWithCauses};
+
+/// How long it takes an image to fade in once it has loaded.
+const FADE_IN: Duration = Duration::from_millis(200);
+
+const BROKEN_IMAGE: &str = "\u{f071}";
+
+/// How many images are kept in memory before the least recently used ones are dropped.
+const MAX_IMAGES: usize = 200;
+
+/// Larger images aren't downloaded, as anyone can link to anything in a message.
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Links to files with these extensions are shown as images in messages.
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+#[derive(Debug, Clone)]
+pub enum ImageMessage {
+    Fetched(Url, Result<Decoded, Arc<http::Error>>),
+    /// Playing animations show the frame they're at by now.
+    Tick(Instant),
+}
+
+#[derive(Debug)]
+enum ImageState {
+    Loading,
+    /// With when it was done loading, which it fades in from.
+    Loaded(Decoded, Instant),
+    /// Not retried until the user asks to or the cache is dropped, so that
+    /// a broken link doesn't get requested again for every message.
+    Failed,
+}
+
+/// How [`ImageCache::view`] lays an image out.
+#[derive(Debug, Clone, Copy)]
+pub enum ImageSize {
+    /// Exactly this many pixels on each side, like avatars.
+    Square(u16),
+    /// As big as the image is, up to this width and height, like thumbnails.
+    /// Placeholders take up all of it.
+    Within(f32, f32),
+    /// All the room there is, like a full size view.
+    Fill,
+}
+
+/// Where an image shown by [`ImageCache::view`] is at, for the view to pick what pressing it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageStatus {
+    Loading,
+    Failed,
+    /// An animation that waits to be played.
+    Paused,
+    Shown,
+}
+
+#[derive(Debug, Default)]
+pub struct ImageCache {
+    images: HashMap<Url, ImageState>,
+    playback: Playback,
+    /// The URLs in `images`, least recently requested first.
+    recent: VecDeque<Url>,
+}
+
+impl ImageCache {
+    /// Starts downloading the images at `urls` that haven't been asked for yet,
+    /// or loading them from disk. Those strict mode doesn't allow are left out,
+    /// and not remembered so that they load once it's turned off.
+    pub fn request(
+        &mut self,
+        urls: impl IntoIterator<Item = Url>,
+        http: &Arc<Scheduler>,
+    ) -> Command<ImageMessage> {
+        let mut cmds = Vec::new();
+        for url in urls {
+            if self.images.contains_key(&url) {
+                self.touch(&url);
+                continue;
+            }
+            if !strict_mode::may_load(&url, http.http().url()) {
+                continue;
+            }
+            self.images.insert(url.clone(), ImageState::Loading);
+            self.recent.push_back(url.clone());
+
+            let http = Arc::clone(http);
+            cmds.push(Command::perform(
+                async move {
+                    let res = match disk::load(url.clone()).await {
+                        Some(bytes) => Ok(bytes),
+                        None => {
+                            let res = http
+                                .run(Priority::Background, |http| {
+                                    http.fetch_file(&url, MAX_IMAGE_BYTES)
+                                })
+                                .await;
+                            match res {
+                                Ok(bytes) => Ok(disk::store(url.clone(), bytes).await),
+                                Err(e) => Err(e),
+                            }
+                        }
+                    };
+                    let res = match res {
+                        Ok(bytes) => Ok(animation::decode(bytes).await),
+                        Err(e) => Err(Arc::new(e)),
+                    };
+                    (url, res)
+                },
+                |(url, res)| ImageMessage::Fetched(url, res),
+            ));
+        }
+
+        self.evict();
+        Command::batch(cmds)
+    }
+
+    /// Downloads the image at `url` again, if it couldn't be before.
+    pub fn retry(&mut self, url: &Url, http: &Arc<Scheduler>) -> Command<ImageMessage> {
+        if !matches!(self.images.get(url), Some(ImageState::Failed)) {
+            return Command::none();
+        }
+        self.images.remove(url);
+        self.recent.retain(|u| u != url);
+        self.request([url.clone()], http)
+    }
+
+    /// Drops the least recently requested images while there are too many,
+    /// except those still loading, which would otherwise be requested again.
+    fn evict(&mut self) {
+        let mut excess = self.recent.len().saturating_sub(MAX_IMAGES);
+        self.recent.retain(|url| {
+            if excess == 0 || matches!(self.images.get(url), Some(ImageState::Loading)) {
+                return true;
+            }
+            self.images.remove(url);
+            self.playback.forget(url);
+            excess -= 1;
+            false
+        });
+    }
+
+    /// Moves `url` to the back of the queue of images to drop.
+    fn touch(&mut self, url: &Url) {
+        if let Some(url) = self
+            .recent
+            .iter()
+            .position(|u| u == url)
+            .and_then(|idx| self.recent.remove(idx))
+        {
+            self.recent.push_back(url);
+        }
+    }
+
+    pub fn update(&mut self, message: ImageMessage) {
+        let (url, res) = match message {
+            ImageMessage::Fetched(url, res) => (url, res),
+            ImageMessage::Tick(now) => return self.playback.tick(now),
+        };
+        // dropped while it was loading
+        let Some(state) = self.images.get_mut(&url) else {
+            return;
+        };
+        *state = match res {
+            Ok(decoded) => ImageState::Loaded(decoded, Instant::now()),
+            Err(e) => {
+                log::warn!(
+                    "could not fetch the image at {url}: {e}",
+                    e = ErrorWithCauses(e)
+                );
+                ImageState::Failed
+            }
+        };
+        // loading images may have kept the cache over its size
+        self.evict();
+    }
+
+    /// The image at `url`, if it has been downloaded. Animations stand still on their first frame.
+    pub fn get(&self, url: &Url) -> Option<&Handle> {
+        match self.images.get(url)? {
+            ImageState::Loaded(decoded, _) => Some(decoded.first()),
+            ImageState::Loading | ImageState::Failed => None,
+        }
+    }
+
+    /// What the image at `url` shows right now, if it has been downloaded, and whether
+    /// it's an animation that waits to be played. Those play right away with `autoplay`.
+    fn frame(&self, url: &Url, autoplay: bool) -> Option<(&Handle, bool)> {
+        match self.images.get(url)? {
+            ImageState::Loaded(Decoded::Animated(animation), _) => {
+                Some(self.playback.frame(url, animation, autoplay))
+            }
+            ImageState::Loaded(Decoded::Still(handle), _) => Some((handle, false)),
+            ImageState::Loading | ImageState::Failed => None,
+        }
+    }
+
+    /// Starts playing the image at `url` from its first frame, if it's an animation.
+    pub fn play(&mut self, url: &Url) {
+        if let Some(ImageState::Loaded(Decoded::Animated(_), _)) = self.images.get(url) {
+            self.playback.play(url);
+        }
+    }
+
+    /// Whether any animation is playing, and so needs [`ImageMessage::Tick`]s
+    /// every [`animation::FRAME_TICK`].
+    pub fn is_animating(&self, autoplay: bool) -> bool {
+        self.playback.is_playing()
+            || (autoplay
+                && self
+                    .images
+                    .values()
+                    .any(|state| matches!(state, ImageState::Loaded(Decoded::Animated(_), _))))
+    }
+
+    /// The image at `url` laid out as `size`, with where it's at, or `None` if it was never
+    /// requested. It's a placeholder while it loads, and if it failed, one that offers to
+    /// retry with `on_retry` if there's one. Animations play right away with `autoplay`,
+    /// otherwise they're paused until played.
+    pub fn view<'a, Message: Clone + 'a>(
+        &self,
+        url: &Url,
+        size: ImageSize,
+        autoplay: bool,
+        on_retry: Option<Message>,
+    ) -> Option<(Element<'a, Message>, ImageStatus)> {
+        let loaded_at = match self.images.get(url)? {
+            ImageState::Loading => return Some((placeholder(size, None), ImageStatus::Loading)),
+            ImageState::Failed => {
+                let failed = column![icon(BROKEN_IMAGE), text("Couldn't load the image").size(12)]
+                    .push_maybe(on_retry.map(|msg| {
+                        button(text("Retry").size(12))
+                            .style(theme::Button::Secondary)
+                            .on_press(msg)
+                    }))
+                    .align_items(Alignment::Center)
+                    .spacing(5);
+                return Some((placeholder(size, Some(failed.into())), ImageStatus::Failed));
+            }
+            ImageState::Loaded(_, loaded_at) => *loaded_at,
+        };
+        let (handle, paused) = self.frame(url, autoplay)?;
+        let image = match size {
+            ImageSize::Square(side) => image(handle.clone()).width(side).height(side),
+            ImageSize::Within(..) => image(handle.clone()),
+            ImageSize::Fill => image(handle.clone())
+                .width(Length::Fill)
+                .height(Length::Fill),
+        };
+        let image: Element<'a, Message> = FadeIn { image, loaded_at }.into();
+        let image = match size {
+            ImageSize::Within(width, height) => {
+                container(image).max_width(width).max_height(height).into()
+            }
+            ImageSize::Square(_) | ImageSize::Fill => image,
+        };
+        Some(match paused {
+            true => (
+                FloatingElement::new(image, animation::play_badge())
+                    .anchor(Anchor::SouthWest)
+                    .into(),
+                ImageStatus::Paused,
+            ),
+            false => (image, ImageStatus::Shown),
+        })
+    }
+
+    /// The user's avatar, or their initials while it loads or if they have none.
+    /// Animated avatars stand still.
+    pub fn avatar<'a, Message: 'a>(&self, user: &User, size: u16) -> Element<'a, Message> {
+        let loaded = user
+            .avatar_url
+            .as_ref()
+            .and_then(|url| self.images.get(url));
+        if let Some(ImageState::Loaded(decoded, loaded_at)) = loaded {
+            let image = image(decoded.first().clone()).width(size).height(size);
+            return FadeIn {
+                image,
+                loaded_at: *loaded_at,
+            }
+            .into();
+        }
+
+        let color = avatar_color(user.id);
+        container(
+            text(initials(&user.name))
+                .font(crate::DEFAULT_FONT_MEDIUM)
+                .size(f32::from(size) * 0.4),
+        )
+        .width(size)
+        .height(size)
+        .center_x()
+        .center_y()
+        .style(move |_: &Theme| container::Appearance {
+            text_color: Some(Color::WHITE),
+            background: Some(Background::Color(color)),
+            border: Border::with_radius(f32::from(size) / 2.0),
+            ..Default::default()
+        })
+        .into()
+    }
+}
+
+/// Whether `url` looks like it points at an image, going by its file extension.
+pub fn is_image_url(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+        && url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|name| name.rsplit_once('.'))
+            .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// Up to two letters: the first of the first and last words of `name`.
+fn initials(name: &str) -> String {
+    let mut words = name.split_whitespace();
+    let first = words.next().and_then(|w| w.chars().next());
+    let last = words.last().and_then(|w| w.chars().next());
+    let initials: String = first
+        .into_iter()
+        .chain(last)
+        .flat_map(char::to_uppercase)
+        .collect();
+    if initials.is_empty() {
+        "?".to_string()
+    } else {
+        initials
+    }
+}
+
+/// A color picked from the user's ID, so that it's the same on every device and every run.
+fn avatar_color(id: UserId) -> Color {
+    // splitmix64's finalizer, so that IDs made close together still get far apart colors
+    let mut x = id.0;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+
+    let hue = (x % 360) as f32;
+    hsl(hue, 0.55, 0.45)
+}
+
+/// Converts a hue in degrees and saturation and lightness between 0 and 1.
+fn hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Color::from_rgb(r + m, g + m, b + m)
+}
+
+/// A box as big as an image laid out as `size` may be, holding `content` in the middle.
+fn placeholder<'a, Message: 'a>(
+    size: ImageSize,
+    content: Option<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    let (width, height) = match size {
+        ImageSize::Square(side) => (Length::from(side), Length::from(side)),
+        ImageSize::Within(width, height) => (Length::from(width), Length::from(height)),
+        ImageSize::Fill => (Length::Fill, Length::Fill),
+    };
+    container(content.unwrap_or_else(|| text("").into()))
+        .width(width)
+        .height(height)
+        .center_x()
+        .center_y()
+        .style(|theme: &Theme| container::Appearance {
+            background: Some(Background::Color(
+                theme.extended_palette().background.weak.color,
+            )),
+            border: Border::with_radius(4.0),
+            ..Default::default()
+        })
+        .into()
+}
+
+/// An image that fades in from the background over [`FADE_IN`] after it loaded.
+struct FadeIn {
+    image: Image<Handle>,
+    loaded_at: Instant,
+}
+
+impl FadeIn {
+    /// How far it has faded in, from 0 to 1.
+    fn shown(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.loaded_at);
+        (elapsed.as_secs_f32() / FADE_IN.as_secs_f32()).min(1.0)
+    }
+}
+
+impl<Message> Widget<Message, Theme, Renderer> for FadeIn {
+    fn size(&self) -> Size<Length> {
+        Widget::<Message, Theme, Renderer>::size(&self.image)
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        Widget::<Message, Theme, Renderer>::layout(&self.image, tree, renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        Widget::<Message, Theme, Renderer>::draw(
+            &self.image,
+            tree,
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+        let shown = self.shown(Instant::now());
+        if shown < 1.0 {
+            // the image can't be drawn see-through, so the background is drawn over it instead
+            let background = theme.extended_palette().background.base.color;
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds: layout.bounds(),
+                    ..Default::default()
+                },
+                Color {
+                    a: 1.0 - shown,
+                    ..background
+                },
+            );
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        _tree: &mut Tree,
+        event: Event,
+        _layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        if let Event::Window(_, window::Event::RedrawRequested(now)) = event {
+            if self.shown(now) < 1.0 {
+                shell.request_redraw(RedrawRequest::NextFrame);
+            }
+        }
+        event::Status::Ignored
+    }
+}
+
+impl<'a, Message> From<FadeIn> for Element<'a, Message> {
+    fn from(fade_in: FadeIn) -> Self {
+        Element::new(fade_in)
+    }
+}
+
+/// Downloads kept in the user's cache directory, named after a hash of their URL.
+/// The least recently used ones are deleted once there are too many. The file
+/// system is only touched on tokio's blocking threads, not the executor's.
+#[cfg(not(target_arch = "wasm32"))]
+mod disk {
+    use std::path::{Path, PathBuf};
+    use std::time::SystemTime;
+
+    use directories::BaseDirs;
+    use url::Url;
+
+    const CACHE_PATH: &str = "eyeqwst/images";
+    /// How many downloads are kept before the least recently used ones are deleted.
+    const MAX_FILES: usize = 1000;
+
+    fn path(url: &Url) -> Option<PathBuf> {
+        let name = format!("{:016x}", super::fnv1a(url.as_str().as_bytes()));
+        Some(BaseDirs::new()?.cache_dir().join(CACHE_PATH).join(name))
+    }
+
+    /// The download of `url` kept earlier, if there is one.
+    pub async fn load(url: Url) -> Option<Vec<u8>> {
+        tokio::task::spawn_blocking(move || load_now(&url))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Keeps `bytes` as the download of `url`, handing them back once they're written.
+    pub async fn store(url: Url, bytes: Vec<u8>) -> Vec<u8> {
+        tokio::task::spawn_blocking(move || {
+            store_now(&url, &bytes);
+            bytes
+        })
+        .await
+        .expect("keeping an image on disk panicked")
+    }
+
+    fn load_now(url: &Url) -> Option<Vec<u8>> {
+        let path = path(url)?;
+        let bytes = std::fs::read(&path).ok()?;
+        // the modification time is what pruning goes by
+        if let Err(e) = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            log::debug!("could not touch {path}: {e}", path = path.display());
+        }
+        Some(bytes)
+    }
+
+    fn store_now(url: &Url, bytes: &[u8]) {
+        let Some(path) = path(url) else {
+            return;
+        };
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        if let Err(e) = std::fs::create_dir_all(dir).and_then(|()| std::fs::write(&path, bytes)) {
+            log::warn!("could not keep the image at {url} on disk: {e}");
+            return;
+        }
+        prune(dir);
+    }
+
+    fn prune(dir: &Path) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut files: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+        if files.len() <= MAX_FILES {
+            return;
+        }
+        files.sort();
+        for (_, path) in &files[..files.len() - MAX_FILES] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hashers is
+/// the same across Rust versions, so names on disk stay put.
+#[cfg(not(target_arch = "wasm32"))]
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+mod disk {
+    use url::Url;
+
+    pub async fn load(_url: Url) -> Option<Vec<u8>> {
+        None
+    }
+
+    pub async fn store(_url: Url, bytes: Vec<u8>) -> Vec<u8> {
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_status() {
+        let url = Url::parse("https://cdn.example/cat.png").unwrap();
+        let mut cache = ImageCache::default();
+        let status = |cache: &ImageCache| {
+            cache
+                .view::<()>(&url, ImageSize::Square(20), false, None)
+                .map(|(_, status)| status)
+        };
+        assert_eq!(status(&cache), None);
+
+        cache.images.insert(url.clone(), ImageState::Loading);
+        assert_eq!(status(&cache), Some(ImageStatus::Loading));
+
+        cache.images.insert(url.clone(), ImageState::Failed);
+        assert_eq!(status(&cache), Some(ImageStatus::Failed));
+
+        cache.update(ImageMessage::Fetched(
+            url.clone(),
+            Ok(Decoded::Still(Handle::from_memory(Vec::new()))),
+        ));
+        assert_eq!(status(&cache), Some(ImageStatus::Shown));
+        assert!(!cache.is_animating(true));
+    }
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(initials("meow"), "M");
+        assert_eq!(initials("meow the cat"), "MC");
+        assert_eq!(initials("  purr  "), "P");
+        assert_eq!(initials("ßtraße"), "SS");
+        assert_eq!(initials(""), "?");
+    }
+
+    #[test]
+    fn test_is_image_url() {
+        let is_image = |url| is_image_url(&Url::parse(url).unwrap());
+        assert!(is_image("https://cdn.example/cat.png"));
+        assert!(is_image("http://cdn.example/a/b/cat.JPEG?size=big"));
+        assert!(!is_image("https://cdn.example/cat.png/"));
+        assert!(!is_image("https://cdn.example/cat.pdf"));
+        assert!(!is_image("https://cdn.example/png"));
+        assert!(!is_image("file:///home/meow/cat.png"));
+    }
+
+    #[test]
+    fn test_evict_spares_loading() {
+        let url = |i| Url::parse(&format!("https://cdn.example/{i}.png")).unwrap();
+        let mut cache = ImageCache::default();
+        for i in 0..MAX_IMAGES + 2 {
+            let state = match i {
+                0 => ImageState::Loading,
+                _ => ImageState::Loaded(
+                    Decoded::Still(Handle::from_memory(Vec::new())),
+                    Instant::now(),
+                ),
+            };
+            cache.images.insert(url(i), state);
+            cache.recent.push_back(url(i));
+        }
+
+        cache.evict();
+        assert_eq!(cache.recent.len(), MAX_IMAGES);
+        assert!(cache.images.contains_key(&url(0)));
+        assert!(!cache.images.contains_key(&url(1)));
+        assert!(!cache.images.contains_key(&url(2)));
+        assert!(cache.images.contains_key(&url(3)));
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let loaded_at = Instant::now();
+        let fade_in = FadeIn {
+            image: image(Handle::from_memory(Vec::new())),
+            loaded_at,
+        };
+        assert_eq!(fade_in.shown(loaded_at), 0.0);
+        assert_eq!(fade_in.shown(loaded_at + FADE_IN / 2), 0.5);
+        assert_eq!(fade_in.shown(loaded_at + FADE_IN * 2), 1.0);
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_avatar_color() {
+        assert_eq!(avatar_color(UserId(1)), avatar_color(UserId(1)));
+        assert_ne!(avatar_color(UserId(1)), avatar_color(UserId(2)));
+        for id in 0..100 {
+            let Color { r, g, b, .. } = avatar_color(UserId(id));
+            assert!([r, g, b].iter().all(|c| (0.0..=1.0).contains(c)));
+        }
+    }
+}
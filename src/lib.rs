@@ -1,23 +1,66 @@
+use account_switcher::{SessionId, Sessions, SwitcherMessage};
+use actions::Action;
 use auth_screen::AuthScreen;
 use auth_screen::IoMessage as AuthIoMessage;
 use auth_screen::Message as AuthMessage;
-use config::Config;
+use auth_screen::UiMessage as AuthUiMessage;
+use command_palette::{CommandPalette, CommandPaletteMessage};
+use config::{Config, Migration, NotificationPreview, PersistenceEvent, Profile};
+use deep_link::MessageLink;
+use gateway::{ConnectionKey, GatewayMessage};
 use iced::keyboard::{key, on_key_press, Key};
+use iced::widget::{button, column, row};
 use iced::Font;
-use iced::{executor, widget, Application, Command, Element, Renderer, Subscription, Theme};
+use iced::{
+    executor, theme, widget, window, Application, Command, Element, Renderer, Subscription, Theme,
+};
+use iced_aw::floating_element::Anchor;
+use iced_aw::FloatingElement;
 use main_screen::MainScreen;
 use main_screen::MainScreenMessage;
+use messageview::HistoryQMsgMessage;
+use quick_switch::{Candidate, QuickSwitch, QuickSwitchMessage};
+use splash::{Splash, SplashMessage};
+use toast::{ToastAction, ToastKind, ToastMessage, Toasts};
+use url::Url;
 
 #[cfg(target_arch = "wasm32")]
 use iced::time::Duration;
 
+pub mod account_deletion;
+pub mod account_settings;
+pub mod account_switcher;
+pub mod actions;
+pub mod activity;
+pub mod animation;
+pub mod attachment;
 pub mod auth_screen;
+pub mod backup;
+pub mod changelog;
 pub mod channel_select;
+pub mod command_palette;
 pub mod config;
+pub mod connection_log;
+pub mod deep_link;
 pub mod editor;
+pub mod emoji;
+pub mod find;
 pub mod gateway;
+pub mod image_cache;
 pub mod main_screen;
+pub mod markdown;
+pub mod mention;
 pub mod messageview;
+pub mod plugin;
+pub mod profile;
+pub mod quick_switch;
+pub mod recent;
+pub mod scheduler;
+pub mod spellcheck;
+pub mod splash;
+pub mod strict_mode;
+pub mod timezone;
+pub mod toast;
 pub mod toggle_button;
 pub mod utils;
 
@@ -31,22 +74,66 @@ const DISCONNECTED: &str = "\u{f0783}";
 const CONNECTING: &str = "\u{f08bd}";
 const WARNING: &str = "\u{f071}";
 
+/// How eyeqwst was asked to start, on the command line.
+#[derive(Debug, Default)]
+pub struct Flags {
+    /// A link to open once logged in.
+    pub link: Option<MessageLink>,
+    /// The server to show first: the account on it if there's one, its login otherwise.
+    pub server: Option<Url>,
+    pub profile: Profile,
+    pub start_minimized: bool,
+}
+
 pub enum EyeqwstState {
+    Loading(Splash),
     Authenticating(AuthScreen),
-    LoggedIn(main_screen::MainScreen),
+    /// Showing the session in front.
+    LoggedIn,
 }
 
 pub struct Eyeqwst {
     state: EyeqwstState,
+    sessions: Sessions,
     config: Config,
+    /// Until the config is loaded, `config` holds defaults that mustn't be saved.
+    config_loaded: bool,
+    /// Whether the release notes are covering the current screen.
+    show_whats_new: bool,
+    quick_switch: Option<QuickSwitch>,
+    command_palette: Option<CommandPalette>,
+    toasts: Toasts,
+    /// The link eyeqwst was started with or was handed, until there's an account to open it with.
+    pending_link: Option<MessageLink>,
+    /// The server eyeqwst was started with, until the accounts are loaded.
+    start_server: Option<Url>,
+    /// The profiles there are configs for, to pick from when logging in.
+    profiles: Vec<Profile>,
 }
 
 #[derive(Debug)]
 pub enum Message {
+    Splash(SplashMessage),
     AuthScreen(AuthMessage),
-    MainScreen(MainScreenMessage),
+    MainScreen(SessionId, MainScreenMessage),
+    /// From a gateway connection, for every session using it.
+    Gateway(ConnectionKey, GatewayMessage),
+    AccountSwitcher(SwitcherMessage),
+    /// Goes back to the logged in accounts instead of adding another one.
+    AddAccountCancelled,
+    Persistence(PersistenceEvent),
+    Toast(ToastMessage),
     AutoSave,
     TabPressed,
+    WhatsNewClosed,
+    /// From a keybinding or the command palette.
+    Action(Action),
+    /// The config of the profile that was switched to.
+    ProfileLoaded(Box<(Config, Option<Migration>)>),
+    /// A link that was opened while eyeqwst was already running.
+    LinkReceived(MessageLink),
+    QuickSwitch(QuickSwitchMessage),
+    CommandPalette(CommandPaletteMessage),
 }
 
 impl Application for Eyeqwst {
@@ -56,38 +143,284 @@ impl Application for Eyeqwst {
 
     type Theme = Theme;
 
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new((): Self::Flags) -> (Self, Command<Self::Message>) {
+    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let (splash, cmd) = Splash::new(flags.profile);
+        let minimize = if flags.start_minimized {
+            window::minimize(window::Id::MAIN, true)
+        } else {
+            Command::none()
+        };
         (
             Self {
-                state: EyeqwstState::Authenticating(AuthScreen::default()),
-                config: Config::load(),
+                state: EyeqwstState::Loading(splash),
+                sessions: Sessions::default(),
+                config: Config::default(),
+                config_loaded: false,
+                show_whats_new: false,
+                quick_switch: None,
+                command_palette: None,
+                toasts: Toasts::default(),
+                pending_link: flags.link,
+                start_server: flags.server,
+                profiles: Vec::new(),
             },
-            Command::none(),
+            Command::batch([cmd.map(Message::Splash), minimize]),
         )
     }
 
     fn title(&self) -> String {
-        String::from("eyeqwst")
+        match self.config.profile() {
+            Profile::Default => String::from("eyeqwst"),
+            profile => format!("eyeqwst ({profile})"),
+        }
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match (&mut self.state, message) {
+            (EyeqwstState::Loading(splash), Message::Splash(msg)) => {
+                let config = splash.update(msg);
+                let done = splash.is_done();
+                if let Some((config, migration)) = config {
+                    self.use_config(config, migration);
+                }
+                if done {
+                    let cmd = self.resume_sessions();
+                    self.state = if self.sessions.is_empty() {
+                        EyeqwstState::Authenticating(AuthScreen::default())
+                    } else {
+                        EyeqwstState::LoggedIn
+                    };
+                    if let Some(server) = self.start_server.take() {
+                        self.show_server(&server);
+                    }
+                    return Command::batch([cmd, self.open_pending_link()]);
+                }
+            }
             (
-                s @ EyeqwstState::Authenticating(_),
+                EyeqwstState::Authenticating(_),
                 Message::AuthScreen(AuthMessage::Io(AuthIoMessage::LoginSucceeded(http, server))),
             ) => {
-                *s = EyeqwstState::LoggedIn(MainScreen::new(http, server));
+                let gateway_url = self.config.gateway_url(&server);
+                let options = self.config.connect_options(&server);
+                let scr = MainScreen::new(*http, server, gateway_url, options);
+                self.sessions.add(scr, &mut self.config);
+                self.state = EyeqwstState::LoggedIn;
+                return self.open_pending_link();
+            }
+            (EyeqwstState::Authenticating(_), Message::AddAccountCancelled)
+                if !self.sessions.is_empty() =>
+            {
+                self.state = EyeqwstState::LoggedIn
+            }
+            (
+                EyeqwstState::Authenticating(_),
+                Message::AuthScreen(AuthMessage::Ui(AuthUiMessage::ProfileSelected(profile))),
+            ) => return self.switch_profile(profile),
+            (_, Message::ProfileLoaded(loaded)) => {
+                let (config, migration) = *loaded;
+                self.use_config(config, migration);
+                let cmd = self.resume_sessions();
+                if !self.sessions.is_empty() {
+                    self.state = EyeqwstState::LoggedIn;
+                }
+                return cmd;
             }
             (EyeqwstState::Authenticating(scr), Message::AuthScreen(msg)) => {
-                return scr.update(msg).map(Message::AuthScreen)
+                return scr.update(msg, &self.config).map(Message::AuthScreen)
+            }
+            (
+                _,
+                Message::MainScreen(id, MainScreenMessage::MessageNotified { channel, message }),
+            ) => {
+                let text = format!(
+                    "{author} in #{channel}: {excerpt}",
+                    author = message.author.name,
+                    excerpt = messageview::excerpt(&message.content)
+                );
+                self.toasts.push_with_actions(
+                    ToastKind::Info,
+                    text,
+                    vec![
+                        ("Reply", ToastAction::Reply(id, message.clone())),
+                        ("Mark as read", ToastAction::MarkRead(id, message)),
+                    ],
+                );
+            }
+            (_, Message::MainScreen(_, MainScreenMessage::WhatsNewRequested)) => {
+                self.show_whats_new = true
+            }
+            (_, Message::MainScreen(id, MainScreenMessage::SessionExpired)) => {
+                let Some((server, name)) = self.sessions.get_mut(id).map(|scr| {
+                    (
+                        scr.server().clone(),
+                        scr.user().map(|user| user.name.clone()),
+                    )
+                }) else {
+                    return Command::none();
+                };
+                self.end_session(id);
+                self.state = EyeqwstState::Authenticating(match name {
+                    Some(name) => AuthScreen::with_account(&server, &name),
+                    None => AuthScreen::with_server(&server),
+                });
+                self.toasts.push(
+                    ToastKind::Info,
+                    "Your session has expired, please log in again",
+                );
+            }
+            (_, Message::MainScreen(id, MainScreenMessage::Mentioned { channel, message })) => {
+                let author = &message.author.name;
+                let text = match self.config.settings.notification_preview {
+                    NotificationPreview::Full => format!(
+                        "{author} in #{channel}: {excerpt}",
+                        excerpt = messageview::excerpt(&message.content)
+                    ),
+                    NotificationPreview::Sender => format!("{author} mentioned you in #{channel}"),
+                    NotificationPreview::Hidden => "New message".to_string(),
+                };
+                self.toasts.push_with_actions(
+                    ToastKind::Info,
+                    text,
+                    vec![
+                        ("Reply", ToastAction::Reply(id, message.clone())),
+                        ("Mark as read", ToastAction::MarkRead(id, message)),
+                    ],
+                );
+            }
+            (
+                _,
+                Message::MainScreen(
+                    _,
+                    MainScreenMessage::HistoryMessageEvent(_, HistoryQMsgMessage::LinkBlocked),
+                ),
+            ) => self.toasts.push(
+                ToastKind::Info,
+                "Strict mode is on, so the link was copied instead of opened",
+            ),
+            (_, Message::MainScreen(id, MainScreenMessage::LogoutRequested)) => {
+                self.end_session(id)
+            }
+            (_, Message::MainScreen(id, MainScreenMessage::AccountDeleted(user))) => {
+                // the gateway may be down by now, so the account is the one the deletion was for
+                let Some(server) = self.sessions.get_mut(id).map(|scr| scr.server().clone()) else {
+                    return Command::none();
+                };
+                self.end_session(id);
+                self.config.forget_account(&server, user);
+                recent::RecentMessages::forget(&server, user);
+                self.state = EyeqwstState::Authenticating(AuthScreen::with_server(&server));
+                self.toasts
+                    .push(ToastKind::Info, "The account has been deleted");
+            }
+            (
+                EyeqwstState::LoggedIn,
+                Message::MainScreen(_, MainScreenMessage::SessionChannelSelected(id, channel)),
+            ) => {
+                self.sessions.select(id, &mut self.config);
+                if let Some(scr) = self.sessions.get_mut(id) {
+                    return scr
+                        .update(
+                            MainScreenMessage::ChannelSelected(channel),
+                            &mut self.config,
+                        )
+                        .map(move |msg| Message::MainScreen(id, msg));
+                }
+            }
+            (_, Message::MainScreen(_, MainScreenMessage::SessionSectionToggled(id))) => {
+                if let Some(scr) = self.sessions.get_mut(id) {
+                    return scr
+                        .update(MainScreenMessage::ServerSectionToggled, &mut self.config)
+                        .map(move |msg| Message::MainScreen(id, msg));
+                }
+            }
+            // background sessions keep receiving events while another account is being added
+            (_, Message::MainScreen(id, msg)) => {
+                if let Some(scr) = self.sessions.get_mut(id) {
+                    return scr
+                        .update(msg, &mut self.config)
+                        .map(move |msg| Message::MainScreen(id, msg));
+                }
+            }
+            (_, Message::Gateway(key, msg)) => {
+                let cmds: Vec<_> = self
+                    .sessions
+                    .iter_mut()
+                    .filter(|(_, scr)| scr.connection().0 == key)
+                    .map(|(id, scr)| {
+                        scr.update(MainScreenMessage::Gateway(msg.clone()), &mut self.config)
+                            .map(move |msg| Message::MainScreen(id, msg))
+                    })
+                    .collect();
+                return Command::batch(cmds);
+            }
+            (EyeqwstState::LoggedIn, Message::AccountSwitcher(SwitcherMessage::Selected(id))) => {
+                self.sessions.select(id, &mut self.config)
+            }
+            (EyeqwstState::LoggedIn, Message::AccountSwitcher(SwitcherMessage::AddRequested)) => {
+                self.state = EyeqwstState::Authenticating(AuthScreen::default())
+            }
+            (EyeqwstState::LoggedIn, Message::Action(action)) => return self.perform(action),
+            (_, Message::QuickSwitch(QuickSwitchMessage::Closed))
+            | (_, Message::CommandPalette(CommandPaletteMessage::Closed)) => self.close_overlays(),
+            (_, Message::QuickSwitch(msg)) => {
+                let candidates = self.switch_candidates();
+                let Some(quick_switch) = &mut self.quick_switch else {
+                    return Command::none();
+                };
+                let Some(&Candidate {
+                    session, channel, ..
+                }) = quick_switch.update(msg, &candidates)
+                else {
+                    return Command::none();
+                };
+
+                self.close_overlays();
+                self.sessions.select(session, &mut self.config);
+                if let Some(scr) = self.sessions.get_mut(session) {
+                    return scr
+                        .update(
+                            MainScreenMessage::ChannelSelected(channel),
+                            &mut self.config,
+                        )
+                        .map(move |msg| Message::MainScreen(session, msg));
+                }
             }
-            (EyeqwstState::LoggedIn(mscr), Message::MainScreen(msg)) => {
-                return mscr.update(msg, &mut self.config).map(Message::MainScreen)
+            (_, Message::CommandPalette(msg)) => {
+                let Some(palette) = &mut self.command_palette else {
+                    return Command::none();
+                };
+                if let Some(action) = palette.update(msg) {
+                    self.close_overlays();
+                    return self.perform(action);
+                }
             }
-            (_, Message::AutoSave) => self.config.save(),
+            (_, Message::LinkReceived(link)) => {
+                // opened once there's an account for it, if there's none yet
+                self.pending_link = Some(link);
+                return Command::batch([
+                    window::gain_focus(window::Id::MAIN),
+                    self.open_pending_link(),
+                ]);
+            }
+            (_, Message::AutoSave) if self.config_loaded => self.config.save(),
+            (_, Message::Persistence(PersistenceEvent::Ready(writer))) => {
+                self.config.set_writer(writer)
+            }
+            (_, Message::Persistence(PersistenceEvent::SaveFailed(err))) => self
+                .toasts
+                .push(ToastKind::Error, format!("Settings not saved: {err}")),
+            (_, Message::Toast(ToastMessage::ActionPressed(toast, action))) => {
+                self.toasts.update(ToastMessage::Dismissed(toast));
+                return self.perform_toast_action(action);
+            }
+            (_, Message::Toast(msg)) => self.toasts.update(msg),
             (_, Message::TabPressed) => return widget::focus_next(),
+            (_, Message::WhatsNewClosed) => {
+                self.show_whats_new = false;
+                self.mark_changelog_seen();
+            }
             _ => {}
         }
 
@@ -95,30 +428,466 @@ impl Application for Eyeqwst {
     }
 
     fn view(&self) -> Element<'_, Self::Message, Self::Theme, Renderer> {
-        match &self.state {
-            EyeqwstState::Authenticating(scr) => scr.view(&self.theme()).map(Message::AuthScreen),
-            EyeqwstState::LoggedIn(scr) => scr
-                .view(&self.theme(), &self.config)
-                .map(Message::MainScreen),
-        }
+        let screen = match &self.state {
+            EyeqwstState::Loading(splash) => splash.view(),
+            _ if self.show_whats_new => changelog::view().map(|_| Message::WhatsNewClosed),
+            EyeqwstState::Authenticating(scr) if !self.sessions.is_empty() => column![
+                // `Message` isn't `Clone`, which buttons need
+                Element::from(button("Back").style(theme::Button::Text).on_press(()))
+                    .map(|()| Message::AddAccountCancelled),
+                scr.view(
+                    &self.theme(),
+                    self.profile_choices(),
+                    self.config.profile().clone()
+                )
+                .map(Message::AuthScreen),
+            ]
+            .padding(10)
+            .into(),
+            EyeqwstState::Authenticating(scr) => scr
+                .view(
+                    &self.theme(),
+                    self.profile_choices(),
+                    self.config.profile().clone(),
+                )
+                .map(Message::AuthScreen),
+            EyeqwstState::LoggedIn => match self.sessions.active() {
+                Some((id, scr)) => {
+                    let others = self
+                        .sessions
+                        .iter()
+                        .filter(|&(other, _)| other != id)
+                        .map(|(other, other_scr)| {
+                            let host = other_scr
+                                .server()
+                                .host_str()
+                                .unwrap_or(other_scr.server().as_str());
+                            // two accounts on the same server are told apart by name
+                            let name = match other_scr.user() {
+                                Some(user) if other_scr.server() == scr.server() => {
+                                    format!("{name} on {host}", name = user.name)
+                                }
+                                _ => host.to_string(),
+                            };
+                            other_scr.sidebar_section(other, &name, &self.config)
+                        })
+                        .collect();
+                    let overlay = self.overlay();
+                    let hidden = overlay.is_none();
+                    FloatingElement::new(
+                        row![
+                            self.sessions.view().map(Message::AccountSwitcher),
+                            scr.view(&self.theme(), &self.config, others)
+                                .map(move |msg| Message::MainScreen(id, msg)),
+                        ],
+                        overlay.unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                    )
+                    .anchor(Anchor::North)
+                    .hide(hidden)
+                    .into()
+                }
+                None => widget::Space::new(0, 0).into(),
+            },
+        };
+
+        FloatingElement::new(screen, self.toasts.view().map(Message::Toast))
+            .anchor(Anchor::SouthEast)
+            .hide(self.toasts.is_empty())
+            .into()
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
         Subscription::batch([
-            match &self.state {
-                EyeqwstState::LoggedIn(scr) => scr.subscription().map(Message::MainScreen),
-                _ => Subscription::none(),
-            },
-            on_key_press(|key, _| match key {
+            Subscription::batch(self.sessions.iter().map(|(id, scr)| {
+                scr.subscription(&self.config)
+                    .with(id)
+                    .map(|(id, msg)| Message::MainScreen(id, msg))
+            })),
+            gateway::connect_all(self.sessions.iter().map(|(_, scr)| scr.connection()))
+                .map(|(key, msg)| Message::Gateway(key, msg)),
+            config::persistence().map(Message::Persistence),
+            deep_link::received().map(Message::LinkReceived),
+            self.toasts.subscription().map(Message::Toast),
+            on_key_press(|key, modifiers| match key.as_ref() {
                 Key::Named(key::Named::Tab) => Some(Message::TabPressed),
-                _ => None,
+                _ => actions::from_key(&key, modifiers).map(Message::Action),
             }),
+            if self.quick_switch.is_some() {
+                quick_switch::subscription().map(Message::QuickSwitch)
+            } else {
+                Subscription::none()
+            },
+            if self.command_palette.is_some() {
+                command_palette::subscription().map(Message::CommandPalette)
+            } else {
+                Subscription::none()
+            },
             #[cfg(target_arch = "wasm32")]
             iced::time::every(Duration::from_secs(10)).map(|_| Message::AutoSave),
         ])
     }
 
     fn theme(&self) -> iced::Theme {
-        iced::Theme::Light
+        if self.config.settings.dark_theme {
+            iced::Theme::Dark
+        } else {
+            iced::Theme::Light
+        }
+    }
+}
+
+impl Eyeqwst {
+    /// Acts on a message a toast was about, bringing its session to the front to reply.
+    fn perform_toast_action(&mut self, action: ToastAction) -> Command<Message> {
+        match action {
+            ToastAction::Reply(id, message) => {
+                if self.sessions.get_mut(id).is_none() {
+                    return Command::none();
+                }
+                self.sessions.select(id, &mut self.config);
+                self.state = EyeqwstState::LoggedIn;
+                Command::batch([
+                    window::gain_focus(window::Id::MAIN),
+                    self.update(Message::MainScreen(
+                        id,
+                        MainScreenMessage::ReplyRequested(message),
+                    )),
+                ])
+            }
+            ToastAction::MarkRead(id, message) => self.update(Message::MainScreen(
+                id,
+                MainScreenMessage::MarkReadRequested(message.channel, message.id),
+            )),
+        }
+    }
+
+    /// Logs back into every account that left a token behind,
+    /// with the one that was in front last time in front again.
+    fn resume_sessions(&mut self) -> Command<Message> {
+        let mut front = None;
+        let cmds: Vec<_> = self
+            .config
+            .saved_sessions()
+            .into_iter()
+            .filter_map(|(session, token)| {
+                let server = session.server.clone();
+                let options = self.config.connect_options(&server);
+                let mut http = scheduler::new_http(server.clone(), &options)
+                    .inspect_err(|e| log::warn!("could not resume session: {e}"))
+                    .ok()?;
+                http.set_token(token);
+
+                let gateway_url = self.config.gateway_url(&server);
+                let mut scr = MainScreen::new(http, server, gateway_url, options);
+                scr.show_cached(session.user, &self.config);
+                let cmd = scr.check_session();
+                let id = self.sessions.add(scr, &mut self.config);
+                if self.config.last_session.as_ref() == Some(&session) {
+                    front = Some(id);
+                }
+                Some(cmd.map(move |msg| Message::MainScreen(id, msg)))
+            })
+            .collect();
+
+        if let Some(id) = front {
+            self.sessions.select(id, &mut self.config);
+        }
+        Command::batch(cmds)
+    }
+
+    /// Starts using a freshly loaded config, letting the user know if it had to be upgraded.
+    fn use_config(&mut self, config: Config, migration: Option<Migration>) {
+        self.config.replace_with(config);
+        self.config_loaded = true;
+        self.profiles = Profile::saved();
+        strict_mode::set_enabled(self.config.settings.strict_mode);
+        if let Some(migration) = migration {
+            let kind = match migration.from {
+                // write the upgraded config out, so that it's only upgraded once
+                Some(_) => {
+                    self.config.save();
+                    ToastKind::Info
+                }
+                None => ToastKind::Error,
+            };
+            self.toasts.push(kind, migration.to_string());
+        }
+        // the one that couldn't be read may well have seen them already
+        if self.config.is_stand_in() {
+            self.show_whats_new = false;
+            return;
+        }
+        self.show_whats_new = changelog::is_unseen(self.config.last_seen_version.as_deref());
+        if !self.show_whats_new {
+            self.mark_changelog_seen();
+        }
+    }
+
+    /// Closes every session and loads the config of `profile`, whose accounts then log
+    /// back in. The closed sessions stay saved, to be resumed when switching back.
+    fn switch_profile(&mut self, profile: Profile) -> Command<Message> {
+        if *self.config.profile() == profile {
+            return Command::none();
+        }
+        // closing the sessions brings others to the front, which shouldn't stick
+        let front = self.config.last_session.clone();
+        let ids: Vec<_> = self.sessions.iter().map(|(id, _)| id).collect();
+        for id in ids {
+            self.sessions.remove(id, &mut self.config);
+        }
+        self.config.last_session = front;
+        // written out right away, as the writer may only get to it once the
+        // other profile is in use. It's handed this snapshot too, so that an
+        // older one it still holds doesn't end up written over it.
+        self.config.save();
+        if let Err(e) = self.config.save_now() {
+            log::warn!("{e}");
+            self.toasts
+                .push(ToastKind::Error, format!("Settings not saved: {e}"));
+        }
+        self.config_loaded = false;
+        Command::perform(
+            async move { Box::new(Config::load_profile(profile)) },
+            Message::ProfileLoaded,
+        )
+    }
+
+    /// The profiles offered when logging in, the one in use among them.
+    fn profile_choices(&self) -> Vec<Profile> {
+        let current = self.config.profile();
+        let mut profiles = vec![Profile::Default];
+        profiles.extend(self.profiles.iter().cloned());
+        if !profiles.contains(current) {
+            profiles.push(current.clone());
+        }
+        profiles
+    }
+
+    /// Brings the account on `server` to the front, or asks to log in there if there's none.
+    fn show_server(&mut self, server: &Url) {
+        let on_server = self
+            .sessions
+            .iter()
+            .find(|(_, scr)| scr.server() == server)
+            .map(|(id, _)| id);
+        match on_server {
+            Some(id) => self.sessions.select(id, &mut self.config),
+            None => self.state = EyeqwstState::Authenticating(AuthScreen::with_server(server)),
+        }
+    }
+
+    /// Hands the link eyeqwst was opened with to an account on its server,
+    /// preferably one that has the linked channel.
+    fn open_pending_link(&mut self) -> Command<Message> {
+        if self.sessions.is_empty() {
+            return Command::none();
+        }
+        let Some(link) = self.pending_link.take() else {
+            return Command::none();
+        };
+        // the sessions can't be borrowed anymore once the one to open the link in is selected
+        let target = {
+            let mut on_server = self
+                .sessions
+                .iter()
+                .filter(|(_, scr)| link.is_on(scr.server()))
+                .peekable();
+            let first = on_server.peek().map(|(id, _)| *id);
+            let with_channel = on_server
+                .find(|(_, scr)| scr.channels(&self.config).any(|c| c.id == link.channel))
+                .map(|(id, _)| id);
+            with_channel.or(first)
+        };
+        let Some(id) = target else {
+            self.toasts.push(
+                ToastKind::Error,
+                format!(
+                    "Not logged in on {host} to open the link",
+                    host = link.host()
+                ),
+            );
+            return Command::none();
+        };
+
+        self.sessions.select(id, &mut self.config);
+        match self.sessions.get_mut(id) {
+            Some(scr) => scr
+                .update(
+                    MainScreenMessage::LinkOpened(link.channel, link.message),
+                    &mut self.config,
+                )
+                .map(move |msg| Message::MainScreen(id, msg)),
+            None => Command::none(),
+        }
+    }
+
+    /// Closes a session and logs it out, going back to
+    /// the login screen if it was the last one.
+    fn end_session(&mut self, id: SessionId) {
+        let Some(scr) = self.sessions.remove(id, &mut self.config) else {
+            return;
+        };
+        let server = scr.server().clone();
+        scr.logout(&mut self.config);
+        if self.sessions.is_empty() {
+            self.state = EyeqwstState::Authenticating(AuthScreen::with_server(&server));
+        }
+    }
+
+    /// The channels of every account, for the quick switcher.
+    fn switch_candidates(&self) -> Vec<Candidate> {
+        self.sessions
+            .iter()
+            .flat_map(|(id, scr)| {
+                let host = scr.server().host_str().unwrap_or(scr.server().as_str());
+                let account = match scr.user() {
+                    Some(user) => format!("{name} on {host}", name = user.name),
+                    None => host.to_string(),
+                };
+                scr.channels(&self.config)
+                    .enumerate()
+                    .map(move |(idx, channel)| Candidate {
+                        session: id,
+                        channel: idx,
+                        name: channel.name.clone(),
+                        account: account.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    fn perform(&mut self, action: Action) -> Command<Message> {
+        match action {
+            Action::CommandPalette => {
+                self.close_overlays();
+                self.command_palette = Some(CommandPalette::default());
+                self.set_covered(true);
+                return CommandPalette::focus();
+            }
+            Action::SwitchChannel => {
+                self.close_overlays();
+                self.quick_switch = Some(QuickSwitch::default());
+                self.set_covered(true);
+                return QuickSwitch::focus();
+            }
+            Action::FindInChannel => return self.update_active(MainScreenMessage::FindOpened),
+            Action::SavedMessages => return self.update_active(MainScreenMessage::SavedToggled),
+            Action::PreviousChannel => {
+                return self.update_active(MainScreenMessage::ChannelStepped(-1))
+            }
+            Action::NextChannel => return self.update_active(MainScreenMessage::ChannelStepped(1)),
+            Action::ExportHistory => return self.update_active(MainScreenMessage::ExportRequested),
+            Action::Reconnect => return self.update_active(MainScreenMessage::ReconnectRequested),
+            Action::ToggleTheme => {
+                self.config.settings.dark_theme = !self.config.settings.dark_theme;
+                self.config.save();
+            }
+            Action::TogglePlayAnimations => {
+                let settings = &mut self.config.settings;
+                settings.play_animations = !settings.play_animations;
+                self.config.save();
+            }
+            Action::CyclePastedFileFormat => {
+                let settings = &mut self.config.settings;
+                settings.pasted_file_format = settings.pasted_file_format.next();
+                let ext = settings.pasted_file_format.extension();
+                self.config.save();
+                self.toasts.push(
+                    ToastKind::Info,
+                    format!("Long pastes are now attached as .{ext} files"),
+                );
+            }
+            Action::ToggleStrictMode => {
+                let settings = &mut self.config.settings;
+                settings.strict_mode = !settings.strict_mode;
+                strict_mode::set_enabled(settings.strict_mode);
+                self.config.save();
+            }
+            Action::CycleOwnMessageStyle => {
+                let settings = &mut self.config.settings;
+                settings.own_messages = settings.own_messages.next();
+                self.config.save();
+            }
+            Action::CycleNotificationPreview => {
+                let settings = &mut self.config.settings;
+                settings.notification_preview = settings.notification_preview.next();
+                let label = settings.notification_preview.label();
+                self.config.save();
+                self.toasts
+                    .push(ToastKind::Info, format!("Notifications now show {label}"));
+            }
+            Action::ToggleEnterToSend => {
+                let settings = &mut self.config.settings;
+                settings.enter_to_send = Some(!settings.enter_sends());
+                self.config.save();
+            }
+            Action::OpenSettings => match self.config.file_path() {
+                Some(path) => {
+                    // so that the file exists and has the latest changes in it
+                    if let Err(e) = self.config.save_now() {
+                        log::warn!("{e}");
+                    }
+                    utils::open_url(&path.to_string_lossy());
+                }
+                None => self.toasts.push(
+                    ToastKind::Info,
+                    "Settings are kept in the browser's storage",
+                ),
+            },
+            Action::WhatsNew => self.show_whats_new = true,
+        }
+        Command::none()
+    }
+
+    /// Passes a message to the session in front.
+    fn update_active(&mut self, msg: MainScreenMessage) -> Command<Message> {
+        match self.sessions.active() {
+            Some((id, _)) => self.update(Message::MainScreen(id, msg)),
+            None => Command::none(),
+        }
+    }
+
+    /// The quick switcher or the command palette, whichever is open.
+    fn overlay(&self) -> Option<Element<'_, Message>> {
+        let overlay = match (&self.command_palette, &self.quick_switch) {
+            (Some(palette), _) => palette.view().map(Message::CommandPalette),
+            (None, Some(quick_switch)) => quick_switch
+                .view(&self.switch_candidates())
+                .map(Message::QuickSwitch),
+            (None, None) => return None,
+        };
+        Some(widget::container(overlay).padding(40).into())
+    }
+
+    fn close_overlays(&mut self) {
+        self.quick_switch = None;
+        self.command_palette = None;
+        self.set_covered(false);
+    }
+
+    /// Leaves the keyboard to an overlay in front of the sessions while `covered`.
+    fn set_covered(&mut self, covered: bool) {
+        for (_, scr) in self.sessions.iter_mut() {
+            scr.set_covered(covered);
+        }
+    }
+
+    fn mark_changelog_seen(&mut self) {
+        if self.config.last_seen_version.as_deref() != Some(changelog::VERSION) {
+            self.config.last_seen_version = Some(changelog::VERSION.to_string());
+            self.config.save();
+        }
+    }
+}
+
+impl Drop for Eyeqwst {
+    // the background writer may not get to run again, so flush synchronously
+    fn drop(&mut self) {
+        if !self.config_loaded {
+            return;
+        }
+        if let Err(e) = self.config.save_now() {
+            log::warn!("{e}");
+        }
     }
 }
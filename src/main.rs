@@ -1,35 +1,103 @@
-use eyeqwst::Eyeqwst;
+use std::path::PathBuf;
+
+use eyeqwst::config::Profile;
+use eyeqwst::deep_link::{self, MessageLink};
+use eyeqwst::{Eyeqwst, Flags};
 use iced::{Application, Settings};
+use log::LevelFilter;
+use url::Url;
+
+const USAGE: &str = "\
+usage: eyeqwst [options] [quaddle://link]
+
+options:
+  --server URL         show the account on URL first, or log in there
+  --config PATH        keep the config at PATH instead of the default place
+  --profile NAME       keep the config apart from the default one, under NAME
+  --log-level LEVEL    off, error, warn, info, debug or trace (default: info)
+  --start-minimized    start with the window minimized
+  -h, --help           show this message";
+
+struct Args {
+    flags: Flags,
+    log_level: LevelFilter,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut flags = Flags::default();
+    let mut log_level = LevelFilter::Info;
+    let mut config = None;
+    let mut profile = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--server" => {
+                let url = value()?;
+                let url = Url::parse(&url).map_err(|e| format!("invalid server {url:?}: {e}"))?;
+                flags.server = Some(url);
+            }
+            "--config" => config = Some(PathBuf::from(value()?)),
+            "--profile" => profile = Some(value()?),
+            "--log-level" => {
+                let level = value()?;
+                log_level = level
+                    .parse()
+                    .map_err(|_| format!("unknown log level {level:?}"))?;
+            }
+            "--start-minimized" => flags.start_minimized = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            // a `quaddle://` link, as passed by the desktop when one is clicked
+            _ => match MessageLink::parse(&arg) {
+                Some(link) => flags.link = Some(link),
+                None => return Err(format!("not a link to a message: {arg:?}")),
+            },
+        }
+    }
+
+    flags.profile = match (config, profile) {
+        (Some(_), Some(_)) => {
+            return Err("--config and --profile can't be used together".to_string())
+        }
+        (Some(path), None) => Profile::File(path),
+        (None, Some(name)) if Profile::is_valid_name(&name) => Profile::Named(name),
+        (None, Some(name)) => return Err(format!("invalid profile name {name:?}")),
+        (None, None) => Profile::Default,
+    };
+
+    Ok(Args { flags, log_level })
+}
 
 fn main() -> Result<(), iced::Error> {
+    // there are none on the web
+    let Args { flags, log_level } = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
     #[cfg(not(target_arch = "wasm32"))]
     {
-        env_logger::builder()
-            .filter(None, log::LevelFilter::Info)
-            .init();
+        env_logger::builder().filter(None, log_level).init();
     }
     #[cfg(target_arch = "wasm32")]
     {
-        console_log::init_with_level(log::Level::Info).unwrap();
+        if let Some(level) = log_level.to_level() {
+            console_log::init_with_level(level).unwrap();
+        }
+    }
+    if flags.link.as_ref().is_some_and(deep_link::hand_off) {
+        log::info!("passed the link on to the eyeqwst that's already running");
+        return Ok(());
     }
     Eyeqwst::run({
         Settings {
-            fonts: vec![
-                include_bytes!("../assets/SymbolsNerdFont-Regular.ttf").into(),
-                include_bytes!("../assets/Roboto-BlackItalic.ttf").into(),
-                include_bytes!("../assets/Roboto-Black.ttf").into(),
-                include_bytes!("../assets/Roboto-BoldItalic.ttf").into(),
-                include_bytes!("../assets/Roboto-Bold.ttf").into(),
-                include_bytes!("../assets/Roboto-Italic.ttf").into(),
-                include_bytes!("../assets/Roboto-LightItalic.ttf").into(),
-                include_bytes!("../assets/Roboto-Light.ttf").into(),
-                include_bytes!("../assets/Roboto-MediumItalic.ttf").into(),
-                include_bytes!("../assets/Roboto-Medium.ttf").into(),
-                include_bytes!("../assets/Roboto-Regular.ttf").into(),
-                include_bytes!("../assets/Roboto-ThinItalic.ttf").into(),
-                include_bytes!("../assets/Roboto-Thin.ttf").into(),
-            ],
             default_font: eyeqwst::DEFAULT_FONT,
+            flags,
             ..Settings::default()
         }
     })
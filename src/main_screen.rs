@@ -1,28 +1,114 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::sync::Arc;
 
 use iced::theme::palette;
-use iced::widget::scrollable::{self, snap_to, RelativeOffset};
-use iced::widget::{self, column, container, row, text, text_editor};
-use iced::{theme, Background, Color, Command, Element, Length, Renderer, Theme};
+use iced::time::{Duration, Instant};
+use iced::widget::scrollable::{
+    self, scroll_to, snap_to, AbsoluteOffset, RelativeOffset, Viewport,
+};
+use iced::widget::text_editor::{Action, Edit};
+use iced::widget::{self, button, column, container, pick_list, row, text, text_editor, tooltip};
+use iced::{keyboard, theme, window, Background, Color, Command, Element, Length, Renderer, Theme};
+use iced_aw::floating_element::Anchor;
+use iced_aw::native::DropDown;
+use iced_aw::{FloatingElement, Wrap};
 use quaddlecl::client::gateway::{ClientGatewayMessage, GatewayEvent};
-use quaddlecl::client::{self, http};
-use quaddlecl::model::message::Message as QMessage;
-use quaddlecl::model::user::User;
-use quaddlecl::{client::http::Http, model::channel::ChannelId};
+use quaddlecl::client::http::{ApiErrorKind, History};
+use quaddlecl::client::{self, http, ConnectOptions};
+use quaddlecl::model::attachment::Attachment;
+use quaddlecl::model::message::{Message as QMessage, MessageId};
+use quaddlecl::model::snowflake::Snowflake;
+use quaddlecl::model::user::{User, UserId};
+use quaddlecl::{
+    client::http::Http,
+    model::channel::{ChannelId, ReadMarker},
+};
 use url::Url;
 
+use crate::account_deletion::{AccountDeletion, AccountDeletionMessage};
+use crate::account_settings::{PasswordChange, PasswordChangeMessage, Rename, RenameMessage};
+use crate::account_switcher::SessionId;
+use crate::actions;
+use crate::activity::Activity;
+use crate::animation;
+use crate::attachment::{self, PendingAttachment};
+use crate::backup::{self, BackupReport, ChannelBackup};
 use crate::channel_select::ChannelEditStrip;
-use crate::channel_select::{ChannelEditMessage, ChannelList};
-use crate::config::{Channel, Config};
-use crate::editor::MessageEditor;
-use crate::gateway::{self, Connection, GatewayMessage};
+use crate::channel_select::{
+    server_header, ChannelAction, ChannelEditMessage, ChannelList, ChannelMenu, ChannelMenuMessage,
+    ConnectionStatus, SubscriptionState,
+};
+use crate::config::{Channel, Config, Outgoing, Template};
+use crate::connection_log::ConnectionLog;
+use crate::deep_link;
+use crate::editor::{self, MessageEditor};
+use crate::emoji;
+use crate::find::{FindBar, FindMessage};
+use crate::gateway::{Connection, ConnectionKey, GatewayMessage, Retry};
+use crate::image_cache::{ImageCache, ImageMessage, ImageSize};
+use crate::mention::{self, RecentAuthors};
 use crate::messageview::{
-    qmessage_list, retrieve_history, HistoryQMessage, HistoryQMessageId, HistoryQMsgMessage,
-    QMESSAGELIST_ID,
+    excerpt, is_at_bottom, qmessage_list, quote_messages, retrieve_history, scroll_to_message,
+    DisplayOptions, Highlight, HistoryLoader, HistoryQMessage, HistoryQMessageId,
+    HistoryQMsgMessage, QMESSAGELIST_ID,
 };
-use crate::utils::{icon, ErrorWithCauses};
-use crate::{CONNECTING, DEFAULT_FONT_MEDIUM, DISCONNECTED};
+use crate::plugin::{self, Plugin, PluginMessage};
+use crate::profile::ProfileCard;
+use crate::recent::RecentMessages;
+use crate::scheduler::{self, Priority, Scheduler};
+use crate::spellcheck::{self, Dictionary};
+use crate::timezone;
+use crate::toggle_button::pressed_button_style;
+use crate::utils::{details_toggle, icon, ErrorSummary, ErrorWithCauses};
+use crate::{CONNECTING, DEFAULT_FONT_MEDIUM, DISCONNECTED, WARNING};
+
+const CHANNEL_ICON: &str = "\u{f292}";
+const COPY: &str = "\u{f0c5}";
+const REFRESH: &str = "\u{f021}";
+const CLOSE: &str = "\u{f00d}";
+const TEMPLATE: &str = "\u{f15c}";
+const ANNOUNCEMENT: &str = "\u{f0a1}";
+const BACKUP: &str = "\u{f0c7}";
+const JUMP_DOWN: &str = "\u{f063}";
+const SEND: &str = "\u{f1d8}";
+const EMOJI: &str = "\u{f118}";
+const REPLY: &str = "\u{f112}";
+const SAVED: &str = "\u{f02e}";
+const DENSE: &str = "\u{f03a}";
+
+/// How many suggestions are shown for the word being typed at most.
+const MAX_COMPLETIONS: usize = 6;
+
+/// How many of the newest messages of a channel are kept to show on the next launch.
+const RECENT_MESSAGES: usize = 30;
+
+/// How long read markers are collected before they're sent to the server,
+/// so that reading a busy channel doesn't send a request for every message.
+const READ_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+/// How long reading has to pause before messages are acknowledged,
+/// so that scrolling through a channel sends one acknowledgement at the end.
+const ACK_DEBOUNCE: Duration = Duration::from_secs(2);
+/// How long typing has to pause before drafts are saved, so that the config
+/// isn't written out on every keystroke.
+const DRAFT_SAVE_DELAY: Duration = Duration::from_secs(2);
+/// How often queued messages are sent again while the gateway is connected,
+/// as it may only have been requests to the server that weren't getting through.
+const OUTBOX_RETRY: Duration = Duration::from_secs(15);
+/// How many messages leading up to one being jumped to are loaded in place of
+/// those shown, when it's older than all of them.
+const JUMP_CONTEXT: u32 = 50;
+/// How long a message that was jumped to stays highlighted.
+const FLASH_DURATION: Duration = Duration::from_millis(1500);
+/// How long a subscription may go unacknowledged before it's taken to have gone
+/// through. Servers from before acknowledgements never send one, and a refusal
+/// still shows if it comes later.
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the connection banner's timers are refreshed.
+const STATUS_TICK: Duration = Duration::from_secs(1);
+
+/// How many lines of a long paste are shown when asking whether to attach it.
+const PASTE_PREVIEW_LINES: usize = 8;
 
 const CONNECTING_SIZE: u16 = 16;
 const CONNECTING_ICON_SIZE: u16 = 17;
@@ -30,11 +116,15 @@ const CONNECTING_ICON_SIZE: u16 = 17;
 #[derive(Debug)]
 pub enum GatewayState {
     Disconnected {
-        error: Option<client::gateway::Error>,
+        error: Option<Arc<client::gateway::Error>>,
     },
+    /// Identifying keeps failing, but the server is expected to recover.
+    ServerBusy,
     Connected {
         user: User,
         conn: Connection,
+        /// How long the gateway took to answer identifying, once it was measured.
+        latency: Option<Duration>,
     },
 }
 
@@ -42,58 +132,392 @@ impl GatewayState {
     pub fn user(&self) -> Option<&User> {
         match self {
             GatewayState::Connected { user, .. } => Some(user),
-            GatewayState::Disconnected { .. } => None,
+            GatewayState::Disconnected { .. } | GatewayState::ServerBusy => None,
         }
     }
 }
 
+/// What's left to do after a batch of gateway events, done once for the whole
+/// batch so that a burst of messages doesn't save or fetch once per message.
+#[derive(Debug, Default)]
+struct Followup {
+    /// The newest message read in each channel.
+    read: HashMap<ChannelId, MessageId>,
+    /// The newest message mentioning the user in each channel.
+    mentions: HashMap<ChannelId, QMessage>,
+    /// The newest message to notify about in each channel, when not a mention.
+    notified: HashMap<ChannelId, QMessage>,
+    /// Channels to count the unread messages of again.
+    recount: HashSet<ChannelId>,
+    fetch_media: bool,
+}
+
+impl Followup {
+    fn read(&mut self, channel_id: ChannelId, message_id: MessageId) {
+        let newest = self.read.entry(channel_id).or_insert(message_id);
+        *newest = (*newest).max(message_id);
+    }
+}
+
 #[derive(Debug)]
 pub struct MainScreen {
     server: Url,
-    http: Arc<Http>,
+    gateway_url: Url,
+    http: Arc<Scheduler>,
+    connect_options: ConnectOptions,
     selected_channel: usize,
     gateway_state: GatewayState,
+    /// Who the account was last time, from the config, until the gateway says who it is now.
+    cached_user: Option<User>,
+    /// The newest messages of each channel as kept for the next launch, and whose they are.
+    recent: Option<(UserId, RecentMessages)>,
     channel_edit_strip: ChannelEditStrip,
+    channel_menu: ChannelMenu,
     // messages in the current channel
     messages: Vec<HistoryQMessage>,
+    history_loader: HistoryLoader,
+    /// Unread message counts of channels other than the selected one.
+    unread: HashMap<ChannelId, usize>,
+    /// Channels among the unread ones with a message mentioning the user.
+    mentioned: HashSet<ChannelId>,
+    activity: Activity,
+    recent_authors: RecentAuthors,
+    /// Whether the server keeps read markers, so that they can be shared with other devices.
+    read_sync: bool,
+    /// Read markers that moved here and haven't been sent to the server yet.
+    unsynced_reads: HashMap<ChannelId, MessageId>,
+    /// Newest read message of each channel whose delivery hasn't been acknowledged yet.
+    unacked: HashMap<ChannelId, MessageId>,
+    /// When a channel was last read, to hold acknowledgements back until reading pauses.
+    last_read_at: Instant,
+    /// When a draft last changed, as long as it hasn't been saved since.
+    draft_changed_at: Option<Instant>,
+    /// Whether the outbox is being sent, so that it isn't sent a second time meanwhile.
+    flushing_outbox: bool,
+    /// How the subscription to each channel is doing on the current connection.
+    subscriptions: HashMap<ChannelId, SubscriptionState>,
     editor: text_editor::Content,
+    /// Explains why the last message couldn't be sent or edited.
+    permission_notice: Option<&'static str>,
+    /// A channel switch waiting on the user to decide what to do with their unsent message.
+    pending_switch: Option<usize>,
+    templates_expanded: bool,
+    /// A paste too long to put in the composer without asking first.
+    long_paste: Option<String>,
+    /// The files the next message is sent with.
+    attachments: Vec<PendingAttachment>,
+    /// A message waiting on its attachments to upload.
+    uploading: Option<Upload>,
+    /// Why the attachments of the last message couldn't be uploaded.
+    upload_error: Option<Arc<http::Error>>,
+    emoji_picker_open: bool,
+    /// The message the composer replies to.
+    replying_to: Option<QMessage>,
+    /// Messages that loaded ones reply to, being fetched as they aren't loaded themselves.
+    quotes_fetching: HashSet<MessageId>,
+    /// Messages replied to that couldn't be fetched, e.g. as they were deleted.
+    /// They aren't asked for again, as each new page would otherwise do so.
+    quotes_failed: HashSet<MessageId>,
+    /// Whether the server's channels are hidden in the sidebar.
+    server_collapsed: bool,
+    window_focused: bool,
+    /// Whether this is the account in front, as opposed to one in the background.
+    active: bool,
+    /// Whether an overlay of the application has the keyboard, e.g. the quick switcher.
+    covered: bool,
+    /// Whether the connection banner shows every cause of the error, not just its summary,
+    /// along with the connection log.
+    error_details: bool,
+    connection_log: ConnectionLog,
+    /// When the gateway connection was lost, or first failed to come up.
+    disconnected_since: Option<Instant>,
+    /// Skips the wait before the gateway is dialed again, along with when that would happen.
+    retry: Option<(Retry, Instant)>,
+    /// Whether the newest messages of the selected channel are in view.
+    at_bottom: bool,
+    /// The message that was just jumped to, and when.
+    flash: Option<(HistoryQMessageId, Instant)>,
+    /// Where the user left off in channels they scrolled up in, counted from the bottom.
+    scroll_offsets: HashMap<ChannelId, AbsoluteOffset>,
+    /// Messages that arrived in the selected channel while scrolled up.
+    missed_below: usize,
+    /// A send waiting on the user to confirm it, in channels that ask for that.
+    confirming_send: bool,
+    find: Option<FindBar>,
+    backup_running: bool,
+    last_backup: Option<BackupReport>,
+    plugins: Vec<Box<dyn Plugin>>,
+    /// The languages there are dictionaries for, to spellcheck the composer in.
+    spellcheck_languages: Vec<String>,
+    /// The dictionaries asked for so far, by language. `None` while one
+    /// loads, or if it couldn't be.
+    dictionaries: HashMap<String, Option<Arc<Dictionary>>>,
+    profile: Option<ProfileCard>,
+    account_deletion: Option<AccountDeletion>,
+    rename: Option<Rename>,
+    password_change: Option<PasswordChange>,
+    /// Avatars and the images linked in messages.
+    images: ImageCache,
+    /// The image shown at full size in place of the messages.
+    lightbox: Option<Url>,
+    /// Whether the saved messages are listed in place of the messages.
+    saved_open: bool,
+    /// A message being jumped to, waiting for the messages around it to load.
+    pending_jump: Option<MessageId>,
+    /// Whether the messages shown end at one that was jumped to rather than at the
+    /// newest, so that new ones aren't added below them and reading doesn't count.
+    showing_context: bool,
+    /// A linked message to jump to once it's known who the account is.
+    pending_link: Option<(ChannelId, MessageId)>,
+    /// Whether an expired token is being traded for a new one.
+    refreshing_token: bool,
+}
+
+/// The language a channel is spellchecked in, as picked in its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SpellcheckChoice {
+    /// The one in the settings.
+    Usual,
+    Language(String),
+}
+
+impl Display for SpellcheckChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpellcheckChoice::Usual => write!(f, "Usual spellcheck"),
+            SpellcheckChoice::Language(language) => write!(f, "Spellcheck in {language}"),
+        }
+    }
+}
+
+/// A message held back while its attachments upload, to be put back in the
+/// composer if they can't be.
+#[derive(Debug)]
+struct Upload {
+    channel_id: ChannelId,
+    content: String,
+    reply_to: Option<QMessage>,
+    attachments: Vec<PendingAttachment>,
+}
+
+/// What to do with a long paste.
+#[derive(Debug, Clone, Copy)]
+pub enum PasteChoice {
+    Attach,
+    Paste,
+    Cancel,
+}
+
+/// What to do with an unsent message when leaving its channel.
+#[derive(Debug, Clone, Copy)]
+pub enum UnsentChoice {
+    Discard,
+    SaveDraft,
+    Cancel,
 }
 
 #[derive(Debug, Clone)]
 pub enum EditorMessage {
     Action(text_editor::Action),
     SendInitiated,
+    NextPlaceholder,
+    TemplatesToggled,
+    TemplatesDismissed,
+    TemplateInserted(usize),
+    EmojiPickerToggled,
+    EmojiPickerDismissed,
+    /// Inserts the emoji at the cursor.
+    EmojiPicked(&'static str),
+    /// Replaces the `:shortcode` being typed with the emoji.
+    EmojiCompleted(&'static str),
+    /// Replaces the `@name` being typed with a mention of the user of that name.
+    MentionCompleted(String),
+    /// Up was pressed in the empty editor.
+    EditLastRequested,
+    /// Something longer than the user wants pasted as text was pasted.
+    LongPasted(String),
+    /// Something that isn't text, such as an image, was pasted.
+    OtherPasted,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum MainScreenMessage {
     HistoryRetrieved(ChannelId, Vec<QMessage>),
-    HistoryRetrievalError(http::Error),
+    HistoryRetrievalError(Arc<http::Error>),
+    HistoryScrolled(Viewport),
+    JumpedToLatest,
+    FlashDue,
+    SubscriptionsDue,
+    WindowFocusChanged(bool),
+    ErrorDetailsToggled,
+    FindOpened,
+    Find(FindMessage),
+    UnreadCounted(ChannelId, Vec<QMessage>),
+    ReadMarkersFetched(Result<Vec<ReadMarker>, Arc<http::Error>>),
+    ReadMarkersDue,
+    /// The read markers that could not be sent.
+    ReadMarkersPushed(Vec<ReadMarker>),
+    AcksDue,
+    DraftsDue,
+    OutboxDue,
+    /// The acknowledgements that could not be sent.
+    AcksSent(Vec<(ChannelId, MessageId)>),
+    OlderHistoryRetrieved(ChannelId, Vec<QMessage>),
+    OlderHistoryRetrievalError(Arc<http::Error>),
+    /// The messages up to the one being jumped to.
+    JumpContextRetrieved(ChannelId, MessageId, Vec<QMessage>),
+    JumpContextRetrievalError(Arc<http::Error>),
     HistoryMessageAction(usize, HistoryQMsgMessage),
     HistoryMessageEvent(HistoryQMessageId, HistoryQMsgMessage),
+    ReplyCancelled,
+    QuoteFetched(MessageId, Result<QMessage, Arc<http::Error>>),
+    ProfileFetched(UserId, Result<User, Arc<http::Error>>),
+    ProfileClosed,
+    LightboxClosed,
+    /// Downloads an image that couldn't be again.
+    ImageRetried(Url),
+    SavedToggled,
+    BookmarkJumped(MessageId),
+    BookmarkRemoved(MessageId),
+    /// Jumps to the message a `quaddle://` link points at.
+    LinkOpened(ChannelId, MessageId),
+    Image(ImageMessage),
     ChannelSelected(usize),
+    /// Moves the selection by the given number of channels, wrapping around at the ends.
+    ChannelStepped(isize),
+    ServerSectionToggled,
+    /// Handled by the application, which brings the session of another account
+    /// to the front and selects the channel there.
+    SessionChannelSelected(SessionId, usize),
+    /// Handled by the application, which collapses or expands the section
+    /// of another account in the sidebar.
+    SessionSectionToggled(SessionId),
+    SubscriptionRetried(ChannelId),
+    /// Handled by the application, which owns the release notes.
+    WhatsNewRequested,
+    /// Handled by the application, which closes the session.
+    LogoutRequested,
+    RenameRequested,
+    Rename(RenameMessage),
+    PasswordChangeRequested,
+    PasswordChange(PasswordChangeMessage),
+    DeleteAccountRequested,
+    AccountDeletion(AccountDeletionMessage),
+    /// Handled by the application, which closes the session and forgets the account.
+    AccountDeleted(UserId),
+    /// The newest messages were written out for the next launch.
+    RecentSaved,
+    /// The result of checking a resumed session. An expired token gets refreshed.
+    SessionChecked(Result<(), Arc<http::Error>>),
+    /// The new token, or why the server wouldn't hand one out.
+    TokenRefreshed(Result<String, Arc<http::Error>>),
+    /// Handled by the application, which asks for the password again.
+    SessionExpired,
+    /// Handled by the application, which lets the user know that `message` in
+    /// `channel` mentions them, as much as the notification preview setting allows.
+    Mentioned {
+        channel: String,
+        message: QMessage,
+    },
+    UnsentPromptAnswered(UnsentChoice),
+    PastePromptAnswered(PasteChoice),
+    /// The image on the clipboard, read after a paste.
+    ImagePasted(Option<PendingAttachment>),
+    AttachmentRemoved(usize),
+    AttachmentsUploaded(Result<Vec<Attachment>, Arc<http::Error>>),
+    PermissionNoticeDismissed,
+    SendConfirmed(bool),
+    ConfirmSendToggled,
+    BackupToggled,
+    /// Spellchecks the selected channel in a language of its own, or the usual one with `None`.
+    SpellcheckLanguageSelected(Option<String>),
+    DictionaryLoaded(String, Option<Arc<Dictionary>>),
+    DenseToggled,
+    BackupDue,
+    /// Backs up the selected channel right away.
+    ExportRequested,
+    BackupFinished(Vec<ChannelBackup>),
+    /// A message for the plugin at the given index.
+    Plugin(usize, PluginMessage),
+    QuoteSelected,
+    SelectionCleared,
+    ChannelIdCopied,
+    RefreshRequested,
     Editor(EditorMessage),
     ChannelEditStrip(ChannelEditMessage),
+    ChannelMenu(ChannelMenuMessage),
     SentSuccessfully,
-    SendError(http::Error),
+    SendError(Arc<http::Error>),
+    /// Messages from the outbox that were sent or refused, oldest first.
+    OutboxFlushed(Vec<(Outgoing, Result<QMessage, Arc<http::Error>>)>),
+    /// Drops the gateway connection so that it's dialed again.
+    ReconnectRequested,
+    /// Dials the gateway right away instead of waiting for the next attempt.
+    RetryRequested,
+    StatusTick,
+    ConnectionLogCopied,
     Gateway(GatewayMessage),
+    /// Handled by the application, which lets the user know that `message`
+    /// arrived in `channel` while another one was open.
+    MessageNotified {
+        channel: String,
+        message: QMessage,
+    },
+    /// From a message's notification: opens the message and replies to it.
+    ReplyRequested(QMessage),
+    /// From a message's notification: marks its channel as read up to the message.
+    MarkReadRequested(ChannelId, MessageId),
+}
+
+/// Ctrl+1 to Ctrl+9 jump to the channel at that position.
+fn channel_shortcut(
+    key: &keyboard::Key,
+    modifiers: keyboard::Modifiers,
+) -> Option<MainScreenMessage> {
+    match key.as_ref() {
+        keyboard::Key::Character(c) if modifiers == keyboard::Modifiers::COMMAND => {
+            match c.parse::<usize>() {
+                Ok(n @ 1..=9) => Some(MainScreenMessage::ChannelSelected(n - 1)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_token_expired(e: &http::Error) -> bool {
+    matches!(e, http::Error::TokenExpired { .. })
+}
+
+/// A coarse duration like "1m 5s", for timers that tick every second.
+fn format_elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
 }
 
 fn connecting_indicator<'a, Message: 'a, T: Display, F>(
     ic: &'a str,
     message: T,
+    details: Option<Element<'a, Message>>,
     color: F,
 ) -> Element<'a, Message>
 where
     F: for<'b> Fn(&'b Theme) -> palette::Pair + 'static,
 {
     container(
-        row![
+        column![row![
             icon(ic).size(CONNECTING_ICON_SIZE),
             text(message)
                 .font(DEFAULT_FONT_MEDIUM)
                 .size(CONNECTING_SIZE)
         ]
+        .spacing(5)]
+        .push_maybe(details)
+        .align_items(iced::Alignment::Center)
         .spacing(5)
         .padding(10),
     )
@@ -112,16 +536,171 @@ where
 }
 
 impl MainScreen {
-    pub fn new(http: Http, server: Url) -> Self {
+    pub fn new(http: Http, server: Url, gateway_url: Url, connect_options: ConnectOptions) -> Self {
         Self {
             server,
-            http: Arc::new(http),
+            gateway_url,
+            http: Arc::new(Scheduler::new(http)),
+            connect_options,
             selected_channel: 0,
             gateway_state: GatewayState::Disconnected { error: None },
+            cached_user: None,
+            recent: None,
             channel_edit_strip: ChannelEditStrip::default(),
+            channel_menu: ChannelMenu::default(),
             messages: Vec::new(),
+            history_loader: HistoryLoader::default(),
+            unread: HashMap::new(),
+            mentioned: HashSet::new(),
+            activity: Activity::default(),
+            recent_authors: RecentAuthors::default(),
+            read_sync: false,
+            unsynced_reads: HashMap::new(),
+            unacked: HashMap::new(),
+            last_read_at: Instant::now(),
+            draft_changed_at: None,
+            flushing_outbox: false,
+            subscriptions: HashMap::new(),
             editor: text_editor::Content::new(),
+            permission_notice: None,
+            pending_switch: None,
+            templates_expanded: false,
+            long_paste: None,
+            attachments: Vec::new(),
+            uploading: None,
+            upload_error: None,
+            emoji_picker_open: false,
+            replying_to: None,
+            quotes_fetching: HashSet::new(),
+            quotes_failed: HashSet::new(),
+            server_collapsed: false,
+            window_focused: true,
+            active: true,
+            covered: false,
+            error_details: false,
+            connection_log: ConnectionLog::default(),
+            disconnected_since: Some(Instant::now()),
+            retry: None,
+            at_bottom: true,
+            flash: None,
+            scroll_offsets: HashMap::new(),
+            missed_below: 0,
+            confirming_send: false,
+            find: None,
+            backup_running: false,
+            last_backup: None,
+            plugins: plugin::builtin(),
+            spellcheck_languages: spellcheck::languages(),
+            dictionaries: HashMap::new(),
+            profile: None,
+            account_deletion: None,
+            rename: None,
+            password_change: None,
+            images: ImageCache::default(),
+            lightbox: None,
+            saved_open: false,
+            pending_jump: None,
+            showing_context: false,
+            pending_link: None,
+            refreshing_token: false,
+        }
+    }
+
+    pub fn server(&self) -> &Url {
+        &self.server
+    }
+
+    /// The logged in user, as known from the gateway or else from last time.
+    pub fn user(&self) -> Option<&User> {
+        self.gateway_state.user().or(self.cached_user.as_ref())
+    }
+
+    /// Shows the channels of `user` and the newest messages of the first one as they
+    /// were last time, so that there's something to read while the gateway connects.
+    /// They're replaced once it has.
+    pub fn show_cached(&mut self, user: UserId, config: &Config) {
+        let Some(account) = config.get_account_config(&self.server, user) else {
+            return;
+        };
+        let Some(user) = account.profile.clone() else {
+            return;
+        };
+        let first = account.channels.first().map(|channel| channel.id);
+        self.messages = first
+            .and_then(|channel_id| self.recent(user.id).get(channel_id))
+            .into_iter()
+            .flatten()
+            .cloned()
+            .map(HistoryQMessage::new)
+            .collect();
+        self.cached_user = Some(user);
+    }
+
+    /// The newest messages of each channel of `user` as kept for the next launch.
+    fn recent(&mut self, user: UserId) -> &mut RecentMessages {
+        if !matches!(&self.recent, Some((id, _)) if *id == user) {
+            self.recent = Some((user, RecentMessages::load(&self.server, user)));
+        }
+        &mut self
+            .recent
+            .as_mut()
+            .expect("the messages were just loaded")
+            .1
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.http.http().token()
+    }
+
+    /// Drops the session's token from the REST client and from the config.
+    /// The gateway connection closes once the screen's subscription goes away.
+    pub fn logout(mut self, config: &mut Config) {
+        if let Some(token) = self.token() {
+            config.forget_session(&self.server, token);
+        }
+        // requests still in flight keep their own handle until they finish
+        if let Some(scheduler) = Arc::get_mut(&mut self.http) {
+            scheduler.http_mut().logout();
+        }
+    }
+
+    /// Unread messages across all channels of this account.
+    pub fn unread_total(&self) -> usize {
+        self.unread.values().sum()
+    }
+
+    /// Brings this account to the front or sends it to the background.
+    /// Messages only get marked read in the account in front.
+    pub fn set_active(&mut self, active: bool, config: &mut Config) {
+        self.active = active;
+        if !active {
+            self.save_drafts(config);
+            return;
         }
+        if let Some(user) = self.gateway_state.user() {
+            config.set_active_session(&self.server, user.id);
+        }
+        self.catch_up(config);
+    }
+
+    /// Leaves the keyboard to an overlay while `covered`, so that typing in it
+    /// doesn't also go into the message editor.
+    pub fn set_covered(&mut self, covered: bool) {
+        self.covered = covered;
+    }
+
+    /// Makes an authenticated request to find out whether a saved token still works.
+    pub fn check_session(&self) -> Command<MainScreenMessage> {
+        let http = Arc::clone(&self.http);
+        Command::perform(
+            async move {
+                http.run(Priority::Interactive, |http| http.list_channels())
+                    .await
+                    .map(|_| ())
+                    .map_err(Arc::new)
+            },
+            MainScreenMessage::SessionChecked,
+        )
     }
 
     pub fn update(
@@ -132,183 +711,2345 @@ impl MainScreen {
         log::debug!("main screen message: {message:?}");
         match message {
             MainScreenMessage::ChannelSelected(new_selected)
-                if new_selected != self.selected_channel =>
+                if new_selected != self.selected_channel
+                    && self.channel_at(new_selected, config).is_some() =>
             {
-                if self.selected_channel(config).is_none() {
-                    return Command::none();
-                };
-
-                self.selected_channel = new_selected;
-                self.messages = Vec::new();
-                self.refresh_messages(config)
-            }
-            MainScreenMessage::HistoryMessageAction(idx, msg) => self
-                .messages
-                .get_mut(idx)
-                .map(|qmsg| qmsg.update(msg, &self.http))
-                .unwrap_or_else(|| Command::none())
-                .map(|(id, msg)| MainScreenMessage::HistoryMessageEvent(id, msg)),
-            MainScreenMessage::HistoryMessageEvent(id, msg) => self
-                .messages
-                .iter_mut()
-                .find(|qmsg| qmsg.id() == id)
-                .map(|qmsg| qmsg.update(msg, &self.http))
-                .unwrap_or_else(|| Command::none())
-                .map(|(id, msg)| MainScreenMessage::HistoryMessageEvent(id, msg)),
-            MainScreenMessage::Editor(EditorMessage::SendInitiated) => {
-                let Some(channel) = self.selected_channel(config) else {
+                if config.settings.confirm_unsent_draft && !self.editor.text().trim().is_empty() {
+                    self.pending_switch = Some(new_selected);
+                    return Command::none();
+                }
+
+                self.switch_channel(new_selected, config)
+            }
+            MainScreenMessage::ChannelStepped(step) => {
+                let count = self.channels(config).count();
+                if count == 0 {
+                    return Command::none();
+                }
+                let new_selected =
+                    (self.selected_channel as isize + step).rem_euclid(count as isize);
+                self.update(
+                    MainScreenMessage::ChannelSelected(new_selected as usize),
+                    config,
+                )
+            }
+            MainScreenMessage::PermissionNoticeDismissed => {
+                self.permission_notice = None;
+                Command::none()
+            }
+            MainScreenMessage::UnsentPromptAnswered(choice) => {
+                let Some(new_selected) = self.pending_switch.take() else {
+                    return Command::none();
+                };
+
+                match choice {
+                    UnsentChoice::Discard => {
+                        self.editor = text_editor::Content::new();
+                        self.sync_draft(config);
+                        self.switch_channel(new_selected, config)
+                    }
+                    // drafts are saved as they're typed
+                    UnsentChoice::SaveDraft => self.switch_channel(new_selected, config),
+                    UnsentChoice::Cancel => Command::none(),
+                }
+            }
+            MainScreenMessage::Editor(EditorMessage::LongPasted(pasted)) => {
+                self.long_paste = Some(pasted);
+                Command::none()
+            }
+            MainScreenMessage::PastePromptAnswered(choice) => {
+                let Some(pasted) = self.long_paste.take() else {
                     return Command::none();
                 };
 
+                match choice {
+                    PasteChoice::Attach => self.attachments.push(PendingAttachment::from_paste(
+                        pasted,
+                        config.settings.pasted_file_format,
+                    )),
+                    PasteChoice::Paste => {
+                        self.editor
+                            .perform(Action::Edit(Edit::Paste(Arc::new(pasted))));
+                        self.sync_draft(config);
+                    }
+                    PasteChoice::Cancel => {}
+                }
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::OtherPasted) => {
+                Command::perform(attachment::paste_image(), MainScreenMessage::ImagePasted)
+            }
+            MainScreenMessage::ImagePasted(attachment) => {
+                self.attachments.extend(attachment);
+                Command::none()
+            }
+            MainScreenMessage::AttachmentRemoved(idx) => {
+                if idx < self.attachments.len() {
+                    self.attachments.remove(idx);
+                }
+                Command::none()
+            }
+            MainScreenMessage::AttachmentsUploaded(res) => {
+                let Some(upload) = self.uploading.take() else {
+                    return Command::none();
+                };
                 let Some(user) = self.gateway_state.user().cloned() else {
                     return Command::none();
                 };
 
-                let msg = HistoryQMessage::sending(user, channel.id, self.editor.text());
-                let send_message_cmd = msg
-                    .send(Arc::clone(&self.http))
-                    .map(|(id, msg)| MainScreenMessage::HistoryMessageEvent(id, msg));
-                self.messages.push(msg);
-                self.editor = text_editor::Content::new();
+                match res {
+                    Ok(uploaded) => {
+                        let content = attachment::with_links(&upload.content, &uploaded);
+                        self.send_content(user, upload.channel_id, content, upload.reply_to, config)
+                    }
+                    Err(e) => {
+                        log::warn!("could not upload attachments: {e}", e = ErrorWithCauses(&e));
+                        self.upload_error = Some(e);
+                        let selected = self
+                            .selected_channel(config)
+                            .is_some_and(|c| c.id == upload.channel_id);
+                        if !selected {
+                            // the files are gone along with the channel's composer,
+                            // but the text is kept as its draft
+                            config
+                                .get_account_config_mut(&self.server, user.id)
+                                .drafts
+                                .entry(upload.channel_id)
+                                .or_insert(upload.content);
+                            config.save();
+                            return Command::none();
+                        }
 
-                Command::batch([
-                    send_message_cmd,
-                    snap_to(scrollable::Id::new(QMESSAGELIST_ID), RelativeOffset::START),
-                ])
+                        let mut attachments = upload.attachments;
+                        attachments.append(&mut self.attachments);
+                        self.attachments = attachments;
+                        if self.editor.text().trim().is_empty() {
+                            self.editor = text_editor::Content::with_text(&upload.content);
+                            self.sync_draft(config);
+                        }
+                        if self.replying_to.is_none() {
+                            self.replying_to = upload.reply_to;
+                        }
+                        Command::none()
+                    }
+                }
             }
-            MainScreenMessage::Editor(EditorMessage::Action(action)) => {
-                self.editor.perform(action);
+            MainScreenMessage::HistoryMessageAction(idx, HistoryQMsgMessage::AuthorClicked) => {
+                let Some(qmsg) = self.messages.get(idx) else {
+                    return Command::none();
+                };
+                let (card, cmd) = ProfileCard::open(
+                    qmsg.msg().author.clone(),
+                    Arc::clone(&self.http),
+                    MainScreenMessage::ProfileFetched,
+                );
+                self.profile = Some(card);
+                cmd
+            }
+            MainScreenMessage::HistoryMessageAction(idx, HistoryQMsgMessage::ReplyInitiated) => {
+                self.replying_to = self.messages.get(idx).map(|qmsg| qmsg.msg().clone());
                 Command::none()
             }
-            MainScreenMessage::ChannelEditStrip(msg) => {
-                let GatewayState::Connected { user, conn } = &mut self.gateway_state else {
+            MainScreenMessage::HistoryMessageAction(idx, HistoryQMsgMessage::QuoteClicked) => {
+                let quoted = self
+                    .messages
+                    .get(idx)
+                    .and_then(|qmsg| qmsg.msg().reply_to)
+                    .and_then(|id| self.messages.iter().position(|qmsg| qmsg.msg().id == id));
+                match quoted {
+                    Some(idx) => self.focus_message(idx),
+                    None => Command::none(),
+                }
+            }
+            MainScreenMessage::ReplyCancelled => {
+                self.replying_to = None;
+                Command::none()
+            }
+            MainScreenMessage::QuoteFetched(id, res) => {
+                self.quotes_fetching.remove(&id);
+                match res {
+                    Ok(quoted) => self.set_quoted(quoted),
+                    Err(e) => {
+                        log::warn!(
+                            "could not fetch the message {id} replied to: {e}",
+                            e = ErrorWithCauses(e)
+                        );
+                        self.quotes_failed.insert(id);
+                    }
+                }
+                Command::none()
+            }
+            MainScreenMessage::ProfileFetched(user_id, res) => {
+                let avatar_url = res.as_ref().ok().and_then(|user| user.avatar_url.clone());
+                if let Some(card) = &mut self.profile {
+                    card.on_fetched(user_id, res);
+                }
+                self.images
+                    .request(avatar_url, &self.http)
+                    .map(MainScreenMessage::Image)
+            }
+            MainScreenMessage::ProfileClosed => {
+                self.profile = None;
+                Command::none()
+            }
+            MainScreenMessage::HistoryMessageAction(_, HistoryQMsgMessage::ImageOpened(url)) => {
+                // animations play at full size, whether they played before or not
+                self.images.play(&url);
+                self.lightbox = Some(url);
+                Command::none()
+            }
+            MainScreenMessage::HistoryMessageAction(_, HistoryQMsgMessage::ImagePlayed(url)) => {
+                self.images.play(&url);
+                Command::none()
+            }
+            MainScreenMessage::ImageRetried(url)
+            | MainScreenMessage::HistoryMessageAction(_, HistoryQMsgMessage::ImageRetried(url)) => {
+                self.images
+                    .retry(&url, &self.http)
+                    .map(MainScreenMessage::Image)
+            }
+            MainScreenMessage::HistoryMessageAction(idx, HistoryQMsgMessage::LinkCopied) => {
+                match self.messages.get(idx) {
+                    Some(qmsg) => iced::clipboard::write(deep_link::message_link(
+                        &self.server,
+                        qmsg.msg().channel,
+                        qmsg.msg().id,
+                    )),
+                    None => Command::none(),
+                }
+            }
+            MainScreenMessage::LightboxClosed => {
+                self.lightbox = None;
+                Command::none()
+            }
+            MainScreenMessage::HistoryMessageAction(idx, HistoryQMsgMessage::BookmarkToggled) => {
+                let (Some(user), Some(qmsg)) = (self.user(), self.messages.get(idx)) else {
                     return Command::none();
                 };
-
-                let channels = &mut config
+                let bookmarks = &mut config
                     .get_account_config_mut(&self.server, user.id)
-                    .channels;
-
-                self.channel_edit_strip
-                    .update(
-                        msg,
-                        channels,
-                        &mut self.selected_channel,
-                        &mut self.messages,
-                        conn,
-                        Arc::clone(&self.http),
-                    )
-                    .map(MainScreenMessage::ChannelEditStrip)
+                    .bookmarks;
+                match bookmarks.iter().position(|b| b.id == qmsg.msg().id) {
+                    Some(pos) => {
+                        bookmarks.remove(pos);
+                    }
+                    None => bookmarks.push(qmsg.msg().clone()),
+                }
+                config.save();
+                Command::none()
             }
-            MainScreenMessage::HistoryRetrieved(channel_id, mut new_msgs) => {
-                if !self
+            MainScreenMessage::SavedToggled => {
+                self.saved_open = !self.saved_open;
+                Command::none()
+            }
+            MainScreenMessage::BookmarkRemoved(id) => {
+                let Some(user) = self.user() else {
+                    return Command::none();
+                };
+                config
+                    .get_account_config_mut(&self.server, user.id)
+                    .bookmarks
+                    .retain(|b| b.id != id);
+                config.save();
+                Command::none()
+            }
+            MainScreenMessage::BookmarkJumped(id) => {
+                let Some(channel_id) = self
+                    .bookmarks(config)
+                    .iter()
+                    .find(|b| b.id == id)
+                    .map(|b| b.channel)
+                else {
+                    return Command::none();
+                };
+                self.jump_to(channel_id, id, config)
+            }
+            MainScreenMessage::LinkOpened(channel_id, id) => {
+                self.pending_link = Some((channel_id, id));
+                self.open_pending_link(config)
+            }
+            MainScreenMessage::Image(msg) => {
+                self.images.update(msg);
+                Command::none()
+            }
+            MainScreenMessage::HistoryMessageAction(idx, msg) => {
+                let cmd = self
+                    .messages
+                    .get_mut(idx)
+                    .map(|qmsg| qmsg.update(msg, &self.http))
+                    .unwrap_or_else(Command::none)
+                    .map(|(id, msg)| MainScreenMessage::HistoryMessageEvent(id, msg));
+                self.sync_edit_draft(idx, config);
+                cmd
+            }
+            MainScreenMessage::HistoryMessageEvent(id, msg) => {
+                let Some(idx) = self.messages.iter().position(|qmsg| qmsg.id() == id) else {
+                    return Command::none();
+                };
+                let is_forbidden =
+                    |err: &http::Error| err.api_error_kind() == Some(ApiErrorKind::Forbidden);
+                match &msg {
+                    HistoryQMsgMessage::DeleteSucceeded => {
+                        self.messages.remove(idx);
+                        return Command::none();
+                    }
+                    // permission errors apply to the whole channel, so they're explained
+                    // by the composer. The message stays, marked failed, so that its text
+                    // can still be copied.
+                    HistoryQMsgMessage::SendingFailed(err) if is_forbidden(err) => {
+                        if self.editor.text().trim().is_empty() {
+                            let content = &self.messages[idx].msg().content;
+                            self.editor = text_editor::Content::with_text(content);
+                            self.sync_draft(config);
+                        }
+                        self.permission_notice = Some("You don't have permission to post here.");
+                    }
+                    HistoryQMsgMessage::SendingFailed(err) if err.is_network_error() => {
+                        let qmsg = &mut self.messages[idx];
+                        qmsg.queue();
+                        let QMessage {
+                            author,
+                            channel,
+                            content,
+                            reply_to,
+                            ..
+                        } = qmsg.msg();
+                        config
+                            .get_account_config_mut(&self.server, author.id)
+                            .outbox
+                            .push(Outgoing {
+                                channel: *channel,
+                                content: content.clone(),
+                                reply_to: *reply_to,
+                            });
+                        config.save();
+                        return Command::none();
+                    }
+                    HistoryQMsgMessage::EditFailed(err) if is_forbidden(err) => {
+                        self.messages[idx].abandon_edit();
+                        self.sync_edit_draft(idx, config);
+                        self.permission_notice =
+                            Some("You don't have permission to edit that message.");
+                        return Command::none();
+                    }
+                    _ => {}
+                }
+                let changed = matches!(
+                    msg,
+                    HistoryQMsgMessage::SendingSucceeded(_) | HistoryQMsgMessage::EditSucceeded(_)
+                );
+                let cmd = self.messages[idx]
+                    .update(msg, &self.http)
+                    .map(|(id, msg)| MainScreenMessage::HistoryMessageEvent(id, msg));
+                self.sync_edit_draft(idx, config);
+                match changed {
+                    // the new content may link to images
+                    true => Command::batch([cmd, self.fetch_images()]),
+                    false => cmd,
+                }
+            }
+            MainScreenMessage::Editor(EditorMessage::SendInitiated) => {
+                if self
                     .selected_channel(config)
-                    .is_some_and(|c| c.id == channel_id)
+                    .is_some_and(|c| c.confirm_send)
                 {
+                    self.confirming_send = true;
                     return Command::none();
                 }
 
-                new_msgs.reverse();
-                self.messages = new_msgs.into_iter().map(HistoryQMessage::new).collect();
-                Command::none()
+                self.send(config)
             }
-            MainScreenMessage::Gateway(msg) => self.on_gateway_message(msg, config),
-            // TODO: implement more messages
-            _ => Command::none(),
-        }
-    }
-
-    fn on_gateway_event(
-        &mut self,
-        event: GatewayEvent,
-        config: &Config,
-    ) -> Command<MainScreenMessage> {
-        match event {
-            GatewayEvent::MessageCreate { message } => {
-                let is_relevant = self
-                    .selected_channel(config)
-                    .is_some_and(|c| c.id == message.channel)
-                    && self
-                        .gateway_state
-                        .user()
-                        .is_some_and(|u| u.id != message.author.id);
-                if is_relevant {
-                    self.messages.push(HistoryQMessage::new(message));
+            MainScreenMessage::SendConfirmed(confirmed) => {
+                let was_confirming = std::mem::take(&mut self.confirming_send);
+                if !was_confirming || !confirmed {
+                    return Command::none();
                 }
 
+                self.send(config)
+            }
+            MainScreenMessage::ConfirmSendToggled => {
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
+                };
+                let channel = config
+                    .get_account_config_mut(&self.server, user.id)
+                    .channels
+                    .get_mut(self.selected_channel);
+                if let Some(channel) = channel {
+                    channel.confirm_send = !channel.confirm_send;
+                    config.save();
+                }
                 Command::none()
             }
-            GatewayEvent::Error { reason } => {
-                log::warn!("gateway error: {reason:?}");
+            MainScreenMessage::BackupToggled => {
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
+                };
+                let channel = config
+                    .get_account_config_mut(&self.server, user.id)
+                    .channels
+                    .get_mut(self.selected_channel);
+                if let Some(channel) = channel {
+                    channel.backup = !channel.backup;
+                    config.save();
+                }
                 Command::none()
             }
-            _ => Command::none(),
-        }
-    }
-
-    pub fn on_gateway_message(
-        &mut self,
-        message: GatewayMessage,
-        config: &Config,
-    ) -> Command<MainScreenMessage> {
-        match message {
-            GatewayMessage::Connected { user, mut conn, .. } => {
-                self.gateway_state = GatewayState::Connected {
-                    user,
-                    conn: conn.clone(),
+            MainScreenMessage::DenseToggled => {
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
                 };
-                for channel in self.channels(config) {
-                    log::debug!("subscribing to {channel:?}");
-                    conn.send(ClientGatewayMessage::Subscribe {
-                        channel_id: channel.id,
-                    });
+                let channel = config
+                    .get_account_config_mut(&self.server, user.id)
+                    .channels
+                    .get_mut(self.selected_channel);
+                if let Some(channel) = channel {
+                    channel.dense = !channel.dense;
+                    config.save();
                 }
-                self.refresh_messages(config)
+                Command::none()
             }
-            GatewayMessage::DialError(error) => {
-                self.gateway_state = GatewayState::Disconnected { error: Some(error) };
+            MainScreenMessage::SpellcheckLanguageSelected(language) => {
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
+                };
+                let channel = config
+                    .get_account_config_mut(&self.server, user.id)
+                    .channels
+                    .get_mut(self.selected_channel);
+                if let Some(channel) = channel {
+                    channel.spellcheck_language = language;
+                    config.save();
+                }
+                self.load_dictionary(config)
+            }
+            MainScreenMessage::DictionaryLoaded(language, dictionary) => {
+                if dictionary.is_none() {
+                    log::warn!("could not load the {language} dictionary");
+                }
+                self.dictionaries.insert(language, dictionary);
                 Command::none()
             }
-            GatewayMessage::Disconnected => {
-                self.gateway_state = GatewayState::Disconnected { error: None };
+            MainScreenMessage::Plugin(idx, msg) => match self.plugins.get_mut(idx) {
+                Some(plugin) => plugin
+                    .update(msg)
+                    .map(move |msg| MainScreenMessage::Plugin(idx, msg)),
+                None => Command::none(),
+            },
+            MainScreenMessage::BackupDue => self.start_backup(config),
+            MainScreenMessage::ExportRequested => self.export_channel(config),
+            MainScreenMessage::ReconnectRequested => {
+                match &mut self.gateway_state {
+                    GatewayState::Connected { conn, .. } => {
+                        conn.reconnect();
+                    }
+                    GatewayState::Disconnected { .. } | GatewayState::ServerBusy => {
+                        self.retry_now();
+                    }
+                }
                 Command::none()
             }
-            GatewayMessage::ReceiveError(err) => {
-                log::warn!("gateway receive error: {err}", err = ErrorWithCauses(err));
+            MainScreenMessage::RetryRequested => {
+                self.retry_now();
                 Command::none()
             }
-            GatewayMessage::Event(ev) => self.on_gateway_event(ev, config),
-        }
-    }
+            MainScreenMessage::ConnectionLogCopied => {
+                iced::clipboard::write(self.connection_log.to_text())
+            }
+            // only there to redraw the connection banner
+            MainScreenMessage::StatusTick => Command::none(),
+            MainScreenMessage::BackupFinished(results) => {
+                self.backup_running = false;
+                self.last_backup = Some(BackupReport::new(&results));
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
+                };
+                let account = config.get_account_config_mut(&self.server, user.id);
+                for ChannelBackup { channel, result } in results {
+                    match result {
+                        Ok(Some((newest, _))) => {
+                            account.backed_up.insert(channel, newest);
+                        }
+                        Ok(None) => {}
+                        Err(e) => log::warn!("could not back up {channel:?}: {e}"),
+                    }
+                }
+                config.save();
+                Command::none()
+            }
+            MainScreenMessage::QuoteSelected => {
+                let quote = quote_messages({
+                    self.messages
+                        .iter()
+                        .filter(|qmsg| qmsg.is_selected())
+                        .map(HistoryQMessage::msg)
+                });
+                self.editor
+                    .perform(Action::Edit(Edit::Paste(Arc::new(quote))));
+                self.messages.iter_mut().for_each(HistoryQMessage::deselect);
+                self.sync_draft(config);
+                Command::none()
+            }
+            MainScreenMessage::SelectionCleared => {
+                self.messages.iter_mut().for_each(HistoryQMessage::deselect);
+                Command::none()
+            }
+            MainScreenMessage::ChannelIdCopied => match self.selected_channel(config) {
+                Some(channel) => iced::clipboard::write(channel.id.to_string()),
+                None => Command::none(),
+            },
+            MainScreenMessage::RefreshRequested => self.refresh_messages(config),
+            MainScreenMessage::WhatsNewRequested
+            | MainScreenMessage::LogoutRequested
+            | MainScreenMessage::AccountDeleted(_)
+            | MainScreenMessage::RecentSaved
+            | MainScreenMessage::SessionChannelSelected(..)
+            | MainScreenMessage::SessionSectionToggled(_) => Command::none(),
+            MainScreenMessage::RenameRequested => {
+                self.close_account_forms();
+                self.rename = Some(Rename::default());
+                Command::none()
+            }
+            MainScreenMessage::Rename(RenameMessage::Cancelled) => {
+                self.rename = None;
+                Command::none()
+            }
+            MainScreenMessage::Rename(RenameMessage::Succeeded(renamed)) => {
+                self.rename = None;
+                if let GatewayState::Connected { user, .. } = &mut self.gateway_state {
+                    *user = renamed;
+                }
+                Command::none()
+            }
+            MainScreenMessage::Rename(msg) => {
+                let (Some(form), Some(user)) = (&mut self.rename, self.gateway_state.user()) else {
+                    return Command::none();
+                };
+                let Some(name) = form.update(msg, user) else {
+                    return Command::none();
+                };
 
-    fn channel_at<'a>(&self, idx: usize, config: &'a Config) -> Option<&'a Channel> {
+                let http = Arc::clone(&self.http);
+                Command::perform(
+                    async move {
+                        http.run(Priority::Interactive, |http| http.change_username(&name))
+                            .await
+                    },
+                    |res| {
+                        MainScreenMessage::Rename(match res {
+                            Ok(user) => RenameMessage::Succeeded(user),
+                            Err(e) => RenameMessage::Failed(Arc::new(e)),
+                        })
+                    },
+                )
+            }
+            MainScreenMessage::PasswordChangeRequested => {
+                self.close_account_forms();
+                self.password_change = Some(PasswordChange::default());
+                Command::none()
+            }
+            MainScreenMessage::PasswordChange(PasswordChangeMessage::Cancelled) => {
+                self.password_change = None;
+                Command::none()
+            }
+            MainScreenMessage::PasswordChange(msg) => {
+                let Some(form) = &mut self.password_change else {
+                    return Command::none();
+                };
+                let Some((old, new)) = form.update(msg) else {
+                    return Command::none();
+                };
+
+                let http = Arc::clone(&self.http);
+                Command::perform(
+                    async move {
+                        http.run(Priority::Interactive, |http| {
+                            http.change_password(&old, &new)
+                        })
+                        .await
+                    },
+                    |res| {
+                        MainScreenMessage::PasswordChange(match res {
+                            Ok(()) => PasswordChangeMessage::Succeeded,
+                            Err(e) => PasswordChangeMessage::Failed(Arc::new(e)),
+                        })
+                    },
+                )
+            }
+            MainScreenMessage::DeleteAccountRequested => {
+                self.close_account_forms();
+                self.account_deletion = Some(AccountDeletion::default());
+                Command::none()
+            }
+            MainScreenMessage::AccountDeletion(AccountDeletionMessage::Cancelled) => {
+                self.account_deletion = None;
+                Command::none()
+            }
+            MainScreenMessage::AccountDeletion(AccountDeletionMessage::Failed(e)) => {
+                // the form is only shown while connected, but has to stop waiting regardless
+                if let Some(form) = &mut self.account_deletion {
+                    form.fail(e);
+                }
+                Command::none()
+            }
+            MainScreenMessage::AccountDeletion(msg) => {
+                let (Some(form), Some(user)) =
+                    (&mut self.account_deletion, self.gateway_state.user())
+                else {
+                    return Command::none();
+                };
+                let Some(password) = form.update(msg, user) else {
+                    return Command::none();
+                };
+
+                let user = user.id;
+                let http = Arc::clone(&self.http);
+                Command::perform(
+                    async move {
+                        http.run(Priority::Interactive, |http| http.delete_account(&password))
+                            .await
+                    },
+                    move |res| match res {
+                        Ok(()) => MainScreenMessage::AccountDeleted(user),
+                        Err(e) => MainScreenMessage::AccountDeletion(
+                            AccountDeletionMessage::Failed(Arc::new(e)),
+                        ),
+                    },
+                )
+            }
+            MainScreenMessage::SessionChecked(Err(e)) if is_token_expired(&e) => {
+                self.refresh_token()
+            }
+            MainScreenMessage::SessionChecked(res) => {
+                if let Err(e) = res {
+                    log::warn!("could not check the session: {e}", e = ErrorWithCauses(e));
+                }
+                Command::none()
+            }
+            MainScreenMessage::TokenRefreshed(Ok(token)) => {
+                self.refreshing_token = false;
+                let mut http = match scheduler::new_http(self.server.clone(), &self.connect_options)
+                {
+                    Ok(http) => http,
+                    Err(e) => {
+                        log::error!(
+                            "could not recreate the REST client: {e}",
+                            e = ErrorWithCauses(e)
+                        );
+                        return Command::none();
+                    }
+                };
+                if let Some(old) = self.token() {
+                    config.replace_token(&self.server, old, &token);
+                }
+                log::info!("refreshed the session token");
+                // the gateway reconnects with the new token, since it's part of the connection key
+                http.set_token(token);
+                self.http = Arc::new(Scheduler::new(http));
+                Command::batch([self.refresh_messages(config), self.fetch_read_markers()])
+            }
+            MainScreenMessage::TokenRefreshed(Err(e)) => {
+                self.refreshing_token = false;
+                if e.api_error_kind() == Some(ApiErrorKind::Unauthorized) {
+                    log::info!("the session token can't be refreshed: {e}");
+                    return Command::perform(async {}, |()| MainScreenMessage::SessionExpired);
+                }
+                log::warn!(
+                    "could not refresh the session token: {e}",
+                    e = ErrorWithCauses(e)
+                );
+                Command::none()
+            }
+            MainScreenMessage::HistoryRetrievalError(e) if is_token_expired(&e) => {
+                self.refresh_token()
+            }
+            MainScreenMessage::HistoryRetrievalError(e) => {
+                log::warn!("failed to load messages: {e}", e = ErrorWithCauses(e));
+                Command::none()
+            }
+            MainScreenMessage::ServerSectionToggled => {
+                self.server_collapsed = !self.server_collapsed;
+                Command::none()
+            }
+            MainScreenMessage::SubscriptionRetried(channel_id) => {
+                if let GatewayState::Connected { conn, .. } = &mut self.gateway_state {
+                    log::debug!("retrying subscription to {channel_id:?}");
+                    if conn.send(ClientGatewayMessage::Subscribe { channel_id }) {
+                        self.subscriptions
+                            .insert(channel_id, SubscriptionState::Pending(Instant::now()));
+                    }
+                }
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::Action(action)) => {
+                let is_edit = action.is_edit();
+                self.editor.perform(action);
+                if is_edit {
+                    self.expand_shortcode();
+                    self.sync_draft(config);
+                }
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::EmojiPickerToggled) => {
+                self.emoji_picker_open = !self.emoji_picker_open;
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::EmojiPickerDismissed) => {
+                self.emoji_picker_open = false;
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::EmojiPicked(emoji)) => {
+                self.emoji_picker_open = false;
+                self.editor
+                    .perform(Action::Edit(Edit::Paste(Arc::new(emoji.to_string()))));
+                self.sync_draft(config);
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::MentionCompleted(name)) => {
+                if let Some(word) = editor::word_before_cursor(&self.editor, '@') {
+                    let mention = format!("{mention} ", mention = mention::format(&name));
+                    editor::replace_before_cursor(&mut self.editor, word.chars().count(), &mention);
+                    self.sync_draft(config);
+                }
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::EmojiCompleted(emoji)) => {
+                if let Some(word) = editor::word_before_cursor(&self.editor, ':') {
+                    editor::replace_before_cursor(&mut self.editor, word.chars().count(), emoji);
+                    self.sync_draft(config);
+                }
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::EditLastRequested) => {
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
+                };
+                let Some(idx) = self
+                    .messages
+                    .iter()
+                    .rposition(|qmsg| qmsg.msg().author.id == user.id && qmsg.is_editable())
+                else {
+                    return Command::none();
+                };
+
+                Command::batch([
+                    self.update(
+                        MainScreenMessage::HistoryMessageAction(
+                            idx,
+                            HistoryQMsgMessage::EditInitiated,
+                        ),
+                        config,
+                    ),
+                    self.focus_message(idx),
+                ])
+            }
+            MainScreenMessage::Editor(EditorMessage::NextPlaceholder) => {
+                editor::select_next_placeholder(&mut self.editor);
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::TemplatesToggled) => {
+                self.templates_expanded = !self.templates_expanded;
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::TemplatesDismissed) => {
+                self.templates_expanded = false;
+                Command::none()
+            }
+            MainScreenMessage::Editor(EditorMessage::TemplateInserted(idx)) => {
+                self.templates_expanded = false;
+                let Some(template) = self.templates(config).get(idx) else {
+                    return Command::none();
+                };
+                let body = template.body.clone();
+                editor::insert_template(&mut self.editor, &body);
+                self.sync_draft(config);
+                Command::none()
+            }
+            MainScreenMessage::ChannelEditStrip(msg) => {
+                let GatewayState::Connected { user, conn, .. } = &mut self.gateway_state else {
+                    return Command::none();
+                };
+
+                let channels = &mut config
+                    .get_account_config_mut(&self.server, user.id)
+                    .channels;
+                let known = channels.len();
+
+                let cmd = self
+                    .channel_edit_strip
+                    .update(
+                        msg,
+                        channels,
+                        &mut self.selected_channel,
+                        &mut self.messages,
+                        conn,
+                        Arc::clone(&self.http),
+                    )
+                    .map(MainScreenMessage::ChannelEditStrip);
+                // an added channel was subscribed to right away
+                for channel in channels.iter().skip(known) {
+                    self.subscriptions
+                        .insert(channel.id, SubscriptionState::Pending(Instant::now()));
+                }
+                config.save();
+                cmd
+            }
+            MainScreenMessage::ChannelMenu(msg) => {
+                let Some(user_id) = self.gateway_state.user().map(|u| u.id) else {
+                    return Command::none();
+                };
+                let channels = &mut config
+                    .get_account_config_mut(&self.server, user_id)
+                    .channels;
+                match self.channel_menu.update(msg, channels) {
+                    Some(ChannelAction::Rename(idx, name)) => {
+                        if let Some(channel) = channels.get_mut(idx) {
+                            channel.name = name;
+                            config.save();
+                        }
+                        Command::none()
+                    }
+                    Some(ChannelAction::Remove(idx)) => self.remove_channel(idx, config),
+                    None => Command::none(),
+                }
+            }
+            MainScreenMessage::HistoryRetrieved(channel_id, mut new_msgs) => {
+                self.activity
+                    .record(channel_id, new_msgs.iter().map(|m| m.id));
+                self.recent_authors.record(channel_id, &new_msgs);
+                if self
+                    .selected_channel(config)
+                    .is_none_or(|c| c.id != channel_id)
+                {
+                    return Command::none();
+                }
+
+                self.showing_context = false;
+                new_msgs.reverse();
+                let save_recent = match self.gateway_state.user().map(|user| user.id) {
+                    Some(user) => {
+                        let newest = &new_msgs[new_msgs.len().saturating_sub(RECENT_MESSAGES)..];
+                        let server = self.server.clone();
+                        let recent = self.recent(user);
+                        recent.insert(channel_id, newest.to_vec());
+                        Command::perform(recent.save(&server, user), |()| {
+                            MainScreenMessage::RecentSaved
+                        })
+                    }
+                    None => Command::none(),
+                };
+                self.messages = new_msgs.into_iter().map(HistoryQMessage::new).collect();
+                if let Some(user) = self.gateway_state.user() {
+                    let queued = self
+                        .outbox(config)
+                        .iter()
+                        .filter(|outgoing| outgoing.channel == channel_id)
+                        .map(|outgoing| {
+                            HistoryQMessage::queued(
+                                user.clone(),
+                                channel_id,
+                                outgoing.content.clone(),
+                                outgoing.reply_to,
+                            )
+                        });
+                    self.messages.extend(queued);
+                }
+                self.restore_edit_drafts(0..self.messages.len(), config);
+                self.catch_up(config);
+                let scroll = match self.scroll_offsets.get(&channel_id) {
+                    Some(&offset) if self.pending_jump.is_none() => {
+                        scroll_to(scrollable::Id::new(QMESSAGELIST_ID), offset)
+                    }
+                    _ => Command::none(),
+                };
+                Command::batch([
+                    scroll,
+                    save_recent,
+                    self.fetch_images(),
+                    self.fetch_quotes(),
+                    self.continue_jump(config),
+                ])
+            }
+            MainScreenMessage::UnreadCounted(channel_id, msgs) => {
+                self.activity.record(channel_id, msgs.iter().map(|m| m.id));
+                self.recent_authors.record(channel_id, &msgs);
+                let Some(user) = self.gateway_state.user() else {
+                    return Command::none();
+                };
+                let is_selected = self
+                    .selected_channel(config)
+                    .is_some_and(|c| c.id == channel_id);
+                let last_read = config
+                    .get_account_config(&self.server, user.id)
+                    .and_then(|account| account.last_read.get(&channel_id));
+                let (false, Some(&last_read)) = (is_selected, last_read) else {
+                    return Command::none();
+                };
+
+                let unread = msgs
+                    .iter()
+                    .filter(|m| m.id > last_read && m.author.id != user.id);
+                let count = unread.clone().count();
+                let mentioned = unread
+                    .clone()
+                    .any(|m| mention::mentions(&m.content, &user.name));
+                if count > 0 {
+                    self.unread.insert(channel_id, count);
+                } else {
+                    self.unread.remove(&channel_id);
+                }
+                if mentioned {
+                    self.mentioned.insert(channel_id);
+                } else {
+                    self.mentioned.remove(&channel_id);
+                }
+                Command::none()
+            }
+            MainScreenMessage::ReadMarkersFetched(Ok(markers)) => {
+                self.read_sync = true;
+                let server: HashMap<_, _> = markers
+                    .into_iter()
+                    .map(|m| (m.channel_id, m.message_id))
+                    .collect();
+                for (&channel_id, &message_id) in &server {
+                    self.advance_read_marker(channel_id, message_id, config);
+                }
+                // markers that moved while offline or before the server kept them
+                if let Some(account) = self
+                    .gateway_state
+                    .user()
+                    .and_then(|user| config.get_account_config(&self.server, user.id))
+                {
+                    for (&channel_id, &message_id) in &account.last_read {
+                        if server.get(&channel_id).is_none_or(|&id| id < message_id) {
+                            self.unsynced_reads.insert(channel_id, message_id);
+                        }
+                    }
+                }
+                self.count_unread(config)
+            }
+            MainScreenMessage::ReadMarkersFetched(Err(e)) if is_token_expired(&e) => {
+                self.read_sync = false;
+                self.refresh_token()
+            }
+            MainScreenMessage::ReadMarkersFetched(Err(e)) => {
+                self.read_sync = false;
+                if e.api_error_kind() != Some(ApiErrorKind::NotFound) {
+                    log::warn!("could not fetch read markers: {e}", e = ErrorWithCauses(e));
+                }
+                self.count_unread(config)
+            }
+            MainScreenMessage::ReadMarkersDue => self.push_read_markers(),
+            MainScreenMessage::ReadMarkersPushed(failed) => {
+                for marker in failed {
+                    let pending = self.unsynced_reads.entry(marker.channel_id).or_default();
+                    *pending = (*pending).max(marker.message_id);
+                }
+                Command::none()
+            }
+            MainScreenMessage::DraftsDue => {
+                if self
+                    .draft_changed_at
+                    .is_some_and(|at| at.elapsed() >= DRAFT_SAVE_DELAY)
+                {
+                    self.save_drafts(config);
+                }
+                Command::none()
+            }
+            MainScreenMessage::OutboxDue => self.flush_outbox(config),
+            MainScreenMessage::AcksDue => {
+                if self.last_read_at.elapsed() < ACK_DEBOUNCE {
+                    return Command::none();
+                }
+                self.send_acks()
+            }
+            MainScreenMessage::AcksSent(failed) => {
+                for (channel_id, up_to) in failed {
+                    let pending = self.unacked.entry(channel_id).or_default();
+                    *pending = (*pending).max(up_to);
+                }
+                Command::none()
+            }
+            MainScreenMessage::FindOpened => {
+                self.find.get_or_insert_with(FindBar::default);
+                FindBar::focus()
+            }
+            MainScreenMessage::Find(FindMessage::Closed) => {
+                self.find = None;
+                Command::none()
+            }
+            MainScreenMessage::Find(msg) => {
+                let Some(find) = &mut self.find else {
+                    return Command::none();
+                };
+                match find.update(msg, &self.messages) {
+                    Some(idx) => self.focus_message(idx),
+                    None if find.is_searching_history() => self.search_older(config),
+                    None => Command::none(),
+                }
+            }
+            MainScreenMessage::ErrorDetailsToggled => {
+                self.error_details = !self.error_details;
+                Command::none()
+            }
+            MainScreenMessage::WindowFocusChanged(focused) => {
+                self.window_focused = focused;
+                if !focused {
+                    self.save_drafts(config);
+                }
+                self.catch_up(config);
+                Command::none()
+            }
+            MainScreenMessage::HistoryScrolled(viewport) => {
+                self.at_bottom = is_at_bottom(&viewport);
+                self.catch_up(config);
+                let Some(channel) = self.selected_channel(config) else {
+                    return Command::none();
+                };
+                if self.at_bottom {
+                    self.scroll_offsets.remove(&channel.id);
+                    self.missed_below = 0;
+                } else {
+                    self.scroll_offsets
+                        .insert(channel.id, viewport.absolute_offset());
+                }
+
+                self.history_loader.on_scroll(
+                    viewport,
+                    Arc::clone(&self.http),
+                    channel.id,
+                    self.messages.first().map(|qmsg| qmsg.msg().id),
+                    MainScreenMessage::OlderHistoryRetrieved,
+                    MainScreenMessage::OlderHistoryRetrievalError,
+                )
+            }
+            MainScreenMessage::FlashDue => {
+                if self
+                    .flash
+                    .is_some_and(|(_, at)| at.elapsed() >= FLASH_DURATION)
+                {
+                    self.flash = None;
+                }
+                Command::none()
+            }
+            MainScreenMessage::SubscriptionsDue => {
+                for (channel_id, state) in &mut self.subscriptions {
+                    if let SubscriptionState::Pending(since) = state {
+                        if since.elapsed() >= SUBSCRIBE_TIMEOUT {
+                            log::debug!(
+                                "no acknowledgement for {channel_id:?}, assuming it's fine"
+                            );
+                            *state = SubscriptionState::Subscribed;
+                        }
+                    }
+                }
+                Command::none()
+            }
+            MainScreenMessage::JumpedToLatest => {
+                if let Some(channel) = self.selected_channel(config) {
+                    self.scroll_offsets.remove(&channel.id);
+                }
+                self.at_bottom = true;
+                self.missed_below = 0;
+                // the newest messages are caught up on once they're in
+                let refresh = if self.showing_context {
+                    self.showing_context = false;
+                    self.refresh_messages(config)
+                } else {
+                    self.catch_up(config);
+                    Command::none()
+                };
+                Command::batch([
+                    snap_to(scrollable::Id::new(QMESSAGELIST_ID), RelativeOffset::START),
+                    refresh,
+                ])
+            }
+            MainScreenMessage::OlderHistoryRetrieved(channel_id, older_msgs) => {
+                self.activity
+                    .record(channel_id, older_msgs.iter().map(|m| m.id));
+                let is_current = self
+                    .selected_channel(config)
+                    .is_some_and(|c| c.id == channel_id);
+                if !is_current {
+                    return Command::none();
+                }
+                if !self.history_loader.on_loaded(older_msgs.len()) {
+                    // nothing was waiting for this page, e.g. because the history was
+                    // refreshed meanwhile, so neither is a search that's still going
+                    if let Some(find) = &mut self.find {
+                        find.stop_searching_history();
+                    }
+                    return Command::none();
+                }
+
+                let count = older_msgs.len();
+                self.messages
+                    .splice(0..0, older_msgs.into_iter().rev().map(HistoryQMessage::new));
+                self.restore_edit_drafts(0..count, config);
+                let fetch = Command::batch([
+                    self.fetch_images(),
+                    self.fetch_quotes(),
+                    self.continue_jump(config),
+                ]);
+
+                let Some(find) = &mut self.find else {
+                    return fetch;
+                };
+                let exhausted = self.history_loader.is_exhausted();
+                let search = match find.on_older_loaded(count, &self.messages, exhausted) {
+                    Some(idx) => self.focus_message(idx),
+                    None if find.is_searching_history() => self.search_older(config),
+                    None => Command::none(),
+                };
+                Command::batch([fetch, search])
+            }
+            MainScreenMessage::OlderHistoryRetrievalError(err) => {
+                self.history_loader.on_failed();
+                self.pending_jump = None;
+                if let Some(find) = &mut self.find {
+                    find.stop_searching_history();
+                }
+                if is_token_expired(&err) {
+                    return self.refresh_token();
+                }
+                log::warn!(
+                    "failed to load older messages: {err}",
+                    err = ErrorWithCauses(err)
+                );
+                Command::none()
+            }
+            MainScreenMessage::JumpContextRetrieved(channel_id, id, mut msgs) => {
+                self.activity.record(channel_id, msgs.iter().map(|m| m.id));
+                self.recent_authors.record(channel_id, &msgs);
+                let is_current = self
+                    .selected_channel(config)
+                    .is_some_and(|c| c.id == channel_id);
+                if !is_current || self.pending_jump != Some(id) {
+                    return Command::none();
+                }
+
+                msgs.reverse();
+                self.messages = msgs.into_iter().map(HistoryQMessage::new).collect();
+                // a page of older messages on its way would no longer fit
+                self.history_loader.reset();
+                self.showing_context = true;
+                self.missed_below = 0;
+                self.restore_edit_drafts(0..self.messages.len(), config);
+                Command::batch([
+                    self.fetch_images(),
+                    self.fetch_quotes(),
+                    self.continue_jump(config),
+                ])
+            }
+            MainScreenMessage::JumpContextRetrievalError(err) => {
+                self.pending_jump = None;
+                if is_token_expired(&err) {
+                    return self.refresh_token();
+                }
+                log::warn!(
+                    "failed to load the message jumped to: {err}",
+                    err = ErrorWithCauses(err)
+                );
+                Command::none()
+            }
+            MainScreenMessage::OutboxFlushed(results) => {
+                self.flushing_outbox = false;
+                // sent messages have to leave the outbox even if the gateway went down meanwhile
+                let Some(user_id) = self.user().map(|u| u.id) else {
+                    return Command::none();
+                };
+                let mut cmds = Vec::new();
+                for (outgoing, res) in results {
+                    let outbox = &mut config.get_account_config_mut(&self.server, user_id).outbox;
+                    if let Some(pos) = outbox.iter().position(|o| *o == outgoing) {
+                        outbox.remove(pos);
+                    }
+
+                    let queued = self.messages.iter_mut().find(|qmsg| {
+                        qmsg.is_queued()
+                            && qmsg.msg().channel == outgoing.channel
+                            && qmsg.msg().content == outgoing.content
+                    });
+                    let Some(qmsg) = queued else {
+                        match res {
+                            Ok(msg)
+                                if self.selected_channel(config).map(|c| c.id)
+                                    == Some(msg.channel) =>
+                            {
+                                self.messages.push(HistoryQMessage::new(msg))
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::warn!(
+                                "could not send a queued message: {e}",
+                                e = ErrorWithCauses(e)
+                            ),
+                        }
+                        continue;
+                    };
+
+                    // let the message go through the usual sending outcomes
+                    qmsg.resume_sending();
+                    let event = match res {
+                        Ok(msg) => HistoryQMsgMessage::SendingSucceeded(msg),
+                        Err(e) => HistoryQMsgMessage::SendingFailed(e),
+                    };
+                    let id = qmsg.id();
+                    cmds.push(
+                        self.update(MainScreenMessage::HistoryMessageEvent(id, event), config),
+                    );
+                }
+                config.save();
+                Command::batch(cmds)
+            }
+            MainScreenMessage::Gateway(msg) => self.on_gateway_message(msg, config),
+            MainScreenMessage::ReplyRequested(message) => {
+                if !self.channels(config).any(|c| c.id == message.channel) {
+                    return Command::none();
+                }
+                let cmd = self.jump_to(message.channel, message.id, config);
+                // set once the jump is underway, as switching channels drops the reply
+                self.replying_to = Some(message);
+                cmd
+            }
+            MainScreenMessage::MarkReadRequested(channel_id, message_id) => {
+                self.unread.remove(&channel_id);
+                self.mentioned.remove(&channel_id);
+                self.mark_read(channel_id, message_id, config);
+                Command::none()
+            }
+            // TODO: implement more messages
+            _ => Command::none(),
+        }
+    }
+
+    fn on_gateway_event(
+        &mut self,
+        event: GatewayEvent,
+        config: &mut Config,
+        followup: &mut Followup,
+    ) {
+        // events from a channel show that its subscription went through, ack or not
+        let confirmed = match &event {
+            GatewayEvent::MessageCreate { message } | GatewayEvent::MessageEdit { message } => {
+                Some(message.channel)
+            }
+            GatewayEvent::MessageDelete { channel_id, .. }
+            | GatewayEvent::Subscribed { channel_id } => Some(*channel_id),
+            _ => None,
+        };
+        if let Some(state) = confirmed.and_then(|id| self.subscriptions.get_mut(&id)) {
+            *state = SubscriptionState::Subscribed;
+        }
+
+        match event {
+            GatewayEvent::MessageCreate { message } => {
+                self.activity.record(message.channel, [message.id]);
+                self.recent_authors.record(message.channel, [&message]);
+                let is_own = self
+                    .gateway_state
+                    .user()
+                    .is_some_and(|u| u.id == message.author.id);
+                let is_selected = self
+                    .selected_channel(config)
+                    .is_some_and(|c| c.id == message.channel);
+                let mentions_me = !is_own
+                    && self
+                        .gateway_state
+                        .user()
+                        .is_some_and(|me| mention::mentions(&message.content, &me.name));
+
+                if is_selected && (is_own || self.is_reading()) {
+                    followup.read(message.channel, message.id);
+                } else if !is_own {
+                    *self.unread.entry(message.channel).or_default() += 1;
+                    if mentions_me {
+                        self.mentioned.insert(message.channel);
+                        followup.mentions.insert(message.channel, message.clone());
+                    } else if config.settings.notify_messages {
+                        followup.notified.insert(message.channel, message.clone());
+                    }
+                }
+                if is_selected && !is_own && self.showing_context {
+                    // it's loaded along with the rest once the user jumps to the latest
+                    self.missed_below += 1;
+                } else if is_selected && !is_own {
+                    self.messages.push(HistoryQMessage::new(message));
+                    if !self.at_bottom {
+                        self.missed_below += 1;
+                    }
+                    followup.fetch_media = true;
+                }
+            }
+            GatewayEvent::MessageEdit { message } => {
+                let qmsg = self.messages.iter_mut().find(|qmsg| {
+                    qmsg.msg().id == message.id && qmsg.msg().channel == message.channel
+                });
+                if let Some(qmsg) = qmsg {
+                    qmsg.set_msg(message);
+                    followup.fetch_media = true;
+                }
+            }
+            GatewayEvent::MessageDelete {
+                channel_id,
+                message_id,
+            } => {
+                self.messages
+                    .retain(|qmsg| qmsg.msg().id != message_id || qmsg.msg().channel != channel_id);
+            }
+            GatewayEvent::Error { reason } => log::warn!("gateway error: {reason:?}"),
+            GatewayEvent::SubscribeFailed { channel_id, reason } => {
+                log::warn!("could not subscribe to {channel_id:?}: {reason:?}");
+                self.subscriptions
+                    .insert(channel_id, SubscriptionState::Failed(reason));
+            }
+            GatewayEvent::ReadMarkerUpdate {
+                channel_id,
+                message_id,
+            } => {
+                // read on another device
+                if self
+                    .unsynced_reads
+                    .get(&channel_id)
+                    .is_some_and(|&id| id <= message_id)
+                {
+                    self.unsynced_reads.remove(&channel_id);
+                }
+                let is_selected = self
+                    .selected_channel(config)
+                    .is_some_and(|c| c.id == channel_id);
+                if self.advance_read_marker(channel_id, message_id, config) && !is_selected {
+                    followup.recount.insert(channel_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Does what a batch of gateway events left to do.
+    fn finish_events(
+        &mut self,
+        followup: Followup,
+        config: &mut Config,
+    ) -> Command<MainScreenMessage> {
+        for (channel_id, message_id) in followup.read {
+            self.mark_read(channel_id, message_id, config);
+        }
+        let mut cmds: Vec<_> = followup
+            .mentions
+            .values()
+            .map(|message| self.notify_mention(message, config))
+            .collect();
+        cmds.extend(
+            followup
+                .notified
+                .values()
+                .map(|message| self.notify(message, config)),
+        );
+        cmds.extend(
+            followup
+                .recount
+                .into_iter()
+                .map(|id| self.count_unread_in(id)),
+        );
+        if followup.fetch_media {
+            cmds.push(self.fetch_images());
+            cmds.push(self.fetch_quotes());
+        }
+        Command::batch(cmds)
+    }
+
+    pub fn on_gateway_message(
+        &mut self,
+        message: GatewayMessage,
+        config: &mut Config,
+    ) -> Command<MainScreenMessage> {
+        match message {
+            GatewayMessage::Connected { user, mut conn, .. } => {
+                if let Some(token) = self.http.http().token() {
+                    config.remember_session(&self.server, user.id, token);
+                }
+                if self.active {
+                    config.set_active_session(&self.server, user.id);
+                }
+                config.get_account_config_mut(&self.server, user.id).profile = Some(user.clone());
+                config.save();
+                self.cached_user = None;
+                self.connection_log
+                    .push(format!("Connected as {name}", name = user.name));
+                self.gateway_state = GatewayState::Connected {
+                    user,
+                    conn: conn.clone(),
+                    latency: None,
+                };
+                self.disconnected_since = None;
+                self.retry = None;
+                self.subscriptions.clear();
+                for channel in self.channels(config) {
+                    log::debug!("subscribing to {channel:?}");
+                    if conn.send(ClientGatewayMessage::Subscribe {
+                        channel_id: channel.id,
+                    }) {
+                        self.subscriptions
+                            .insert(channel.id, SubscriptionState::Pending(Instant::now()));
+                    }
+                }
+                if self.editor.text().trim().is_empty() {
+                    self.restore_draft(config);
+                }
+                Command::batch([
+                    self.refresh_messages(config),
+                    self.load_dictionary(config),
+                    self.fetch_read_markers(),
+                    self.flush_outbox(config),
+                    self.start_backup(config),
+                    self.open_pending_link(config),
+                ])
+            }
+            GatewayMessage::DialError(error) => {
+                self.connection_log.push(format!(
+                    "Could not connect: {err}",
+                    err = ErrorWithCauses(&error)
+                ));
+                self.gateway_state = GatewayState::Disconnected { error: Some(error) };
+                self.disconnected_since.get_or_insert_with(Instant::now);
+                Command::none()
+            }
+            GatewayMessage::ServerBusy(err) => {
+                log::warn!("failed to identify: {err}", err = ErrorWithCauses(&err));
+                self.connection_log
+                    .push(format!("Server busy: {err}", err = ErrorWithCauses(&err)));
+                self.gateway_state = GatewayState::ServerBusy;
+                self.disconnected_since.get_or_insert_with(Instant::now);
+                Command::none()
+            }
+            GatewayMessage::Disconnected => {
+                self.connection_log.push("Disconnected");
+                self.gateway_state = GatewayState::Disconnected { error: None };
+                self.disconnected_since.get_or_insert_with(Instant::now);
+                // subscriptions don't outlive the connection
+                self.subscriptions.clear();
+                Command::none()
+            }
+            GatewayMessage::RetryScheduled { retry, delay } => {
+                self.retry = Some((retry, Instant::now() + delay));
+                Command::none()
+            }
+            GatewayMessage::ReceiveError(err) => {
+                log::warn!("gateway receive error: {err}", err = ErrorWithCauses(&err));
+                self.connection_log
+                    .push(format!("Receive error: {err}", err = ErrorWithCauses(&err)));
+                Command::none()
+            }
+            GatewayMessage::SendFailed { op, error } => {
+                log::warn!(
+                    "failed to send {op:?} to the gateway: {err}",
+                    err = ErrorWithCauses(&error)
+                );
+                self.connection_log
+                    .push(format!("Send error: {err}", err = ErrorWithCauses(&error)));
+                // shown in the sidebar, where the subscription can be retried
+                if let ClientGatewayMessage::Subscribe { channel_id } = op {
+                    self.subscriptions.insert(
+                        channel_id,
+                        SubscriptionState::Failed(format!("subscribing {error}")),
+                    );
+                }
+                Command::none()
+            }
+            GatewayMessage::LatencyUpdated(rtt) => {
+                if let GatewayState::Connected { latency, .. } = &mut self.gateway_state {
+                    *latency = Some(rtt);
+                }
+                Command::none()
+            }
+            GatewayMessage::Events(evs) => {
+                let mut followup = Followup::default();
+                for ev in evs {
+                    self.on_gateway_event(ev, config, &mut followup);
+                }
+                self.finish_events(followup, config)
+            }
+        }
+    }
+
+    fn retry_now(&mut self) {
+        if let Some((retry, _)) = self.retry.take() {
+            retry.now();
+            self.connection_log.push("Retrying on request");
+            self.gateway_state = GatewayState::Disconnected { error: None };
+        }
+    }
+
+    /// How long the connection has been down and when it's tried again,
+    /// with a button to try right away.
+    fn connection_timers(&self) -> Option<Element<'_, MainScreenMessage>> {
+        let since = self.disconnected_since?;
+        let mut status = format!("Disconnected for {}", format_elapsed(since.elapsed()));
+        // once the wait is over the next attempt is already under way
+        let retry_in = self
+            .retry
+            .as_ref()
+            .map(|(_, at)| at.saturating_duration_since(Instant::now()))
+            .filter(|left| !left.is_zero());
+        if let Some(left) = retry_in {
+            status.push_str(&format!(", retrying in {}", format_elapsed(left)));
+        }
+
+        Some(
+            row![text(status).size(12)]
+                .push_maybe(retry_in.map(|_| {
+                    button(text("Retry now").size(12))
+                        .style(theme::Button::Text)
+                        .on_press(MainScreenMessage::RetryRequested)
+                }))
+                .align_items(iced::Alignment::Center)
+                .spacing(10)
+                .into(),
+        )
+    }
+
+    fn channel_at<'a>(&self, idx: usize, config: &'a Config) -> Option<&'a Channel> {
         config
-            .get_account_config(&self.server, self.gateway_state.user()?.id)?
+            .get_account_config(&self.server, self.user()?.id)?
+            .channels
+            .get(idx)
+    }
+
+    /// The channels in the sidebar, in order.
+    pub fn channels<'a>(&self, config: &'a Config) -> impl Iterator<Item = &'a Channel> {
+        let Some(user) = self.user() else {
+            return None.into_iter().flatten();
+        };
+        config
+            .get_account_config(&self.server, user.id)
+            .map(|account| account.channels.iter())
+            .into_iter()
+            .flatten()
+    }
+
+    /// This account's section in the sidebar of another one in front, so that
+    /// the channels of every server are a click away. It's collapsed along
+    /// with the account's own section, and no channel is shown as selected.
+    pub fn sidebar_section<'a>(
+        &'a self,
+        id: SessionId,
+        name: &str,
+        config: &'a Config,
+    ) -> Element<'a, MainScreenMessage> {
+        column![server_header(
+            name,
+            self.connection_status(),
+            None,
+            self.server_collapsed,
+            MainScreenMessage::SessionSectionToggled(id),
+        )]
+        .push_maybe((!self.server_collapsed).then(|| {
+            ChannelList::new(self.channels(config), usize::MAX)
+                .unread(&self.unread)
+                .mentioned(&self.mentioned)
+                .on_selection(move |idx| MainScreenMessage::SessionChannelSelected(id, idx))
+        }))
+        .spacing(10)
+        .into()
+    }
+
+    fn connection_status(&self) -> ConnectionStatus {
+        match self.gateway_state {
+            GatewayState::Connected { .. } => ConnectionStatus::Connected,
+            GatewayState::Disconnected { error: Some(_) } => ConnectionStatus::Disconnected,
+            GatewayState::Disconnected { error: None } | GatewayState::ServerBusy => {
+                ConnectionStatus::Connecting
+            }
+        }
+    }
+
+    /// Templates defined for the selected channel.
+    fn templates<'a>(&self, config: &'a Config) -> &'a [Template] {
+        let (Some(channel), Some(user)) =
+            (self.selected_channel(config), self.gateway_state.user())
+        else {
+            return &[];
+        };
+        config
+            .get_account_config(&self.server, user.id)
+            .and_then(|account| account.templates.get(&channel.id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn selected_channel<'a>(&self, config: &'a Config) -> Option<&'a Channel> {
+        self.channel_at(self.selected_channel, config)
+    }
+
+    fn switch_channel(
+        &mut self,
+        new_selected: usize,
+        config: &Config,
+    ) -> Command<MainScreenMessage> {
+        if self.selected_channel(config).is_none() {
+            return Command::none();
+        };
+
+        self.selected_channel = new_selected;
+        self.pending_switch = None;
+        self.pending_jump = None;
+        self.showing_context = false;
+        self.permission_notice = None;
+        self.lightbox = None;
+        self.at_bottom = self
+            .selected_channel(config)
+            .is_none_or(|c| !self.scroll_offsets.contains_key(&c.id));
+        self.missed_below = 0;
+        self.confirming_send = false;
+        self.find = None;
+        self.templates_expanded = false;
+        self.long_paste = None;
+        self.attachments = Vec::new();
+        self.upload_error = None;
+        self.replying_to = None;
+        if let Some(channel) = self.selected_channel(config) {
+            self.unread.remove(&channel.id);
+            self.mentioned.remove(&channel.id);
+        }
+        self.messages = Vec::new();
+        self.restore_draft(config);
+        // the list is shared between channels, so start at the bottom;
+        // a saved position is restored once the history is in
+        Command::batch([
+            snap_to(scrollable::Id::new(QMESSAGELIST_ID), RelativeOffset::START),
+            self.refresh_messages(config),
+            self.load_dictionary(config),
+        ])
+    }
+
+    /// The language the composer is spellchecked in for the selected channel, if any.
+    fn spellcheck_language<'a>(&self, config: &'a Config) -> Option<&'a str> {
+        self.selected_channel(config)
+            .and_then(|channel| channel.spellcheck_language.as_deref())
+            .or(config.settings.spellcheck_language.as_deref())
+    }
+
+    /// Starts reading the dictionary the selected channel is spellchecked with,
+    /// unless it was asked for before.
+    fn load_dictionary(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        let Some(language) = self.spellcheck_language(config) else {
+            return Command::none();
+        };
+        if self.dictionaries.contains_key(language) {
+            return Command::none();
+        }
+        let language = language.to_string();
+        self.dictionaries.insert(language.clone(), None);
+        Command::perform(spellcheck::load(language.clone()), move |dictionary| {
+            MainScreenMessage::DictionaryLoaded(language, dictionary)
+        })
+    }
+
+    /// Takes a channel out of the sidebar and stops receiving its events.
+    fn remove_channel(&mut self, idx: usize, config: &mut Config) -> Command<MainScreenMessage> {
+        let Some(user_id) = self.user().map(|u| u.id) else {
+            return Command::none();
+        };
+        let account = config.get_account_config_mut(&self.server, user_id);
+        if idx >= account.channels.len() {
+            return Command::none();
+        }
+
+        let removed = account.channels.remove(idx);
+        let remaining = account.channels.len();
+        // offline, there's no subscription left to end, and reconnecting won't renew it
+        if let GatewayState::Connected { conn, .. } = &mut self.gateway_state {
+            conn.send(ClientGatewayMessage::Unsubscribe {
+                channel_id: removed.id,
+            });
+        }
+        config.save();
+        // written out along with the next history that loads
+        self.recent(user_id).remove(removed.id);
+        self.unread.remove(&removed.id);
+        self.mentioned.remove(&removed.id);
+        self.subscriptions.remove(&removed.id);
+        self.scroll_offsets.remove(&removed.id);
+        // the indices after it moved up
+        self.channel_menu.reset();
+
+        if idx < self.selected_channel {
+            self.selected_channel -= 1;
+            return Command::none();
+        }
+        if idx > self.selected_channel {
+            return Command::none();
+        }
+        // the channel on screen is gone, so show the one that took its place
+        if remaining == 0 {
+            self.selected_channel = 0;
+            self.messages.clear();
+            return Command::none();
+        }
+        self.selected_channel = idx.min(remaining - 1);
+        self.switch_channel(self.selected_channel, config)
+    }
+
+    /// Downloads the avatars of the authors of loaded messages and the images
+    /// they link to, those that haven't been yet.
+    fn fetch_images(&mut self) -> Command<MainScreenMessage> {
+        let urls: Vec<_> = self
+            .messages
+            .iter()
+            .flat_map(|qmsg| {
+                let avatar = qmsg.msg().author.avatar_url.clone();
+                avatar.into_iter().chain(qmsg.images())
+            })
+            .collect();
+        self.images
+            .request(urls, &self.http)
+            .map(MainScreenMessage::Image)
+    }
+
+    /// Loads the page of history before the oldest loaded message.
+    fn load_older(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        let Some(channel) = self.selected_channel(config) else {
+            return Command::none();
+        };
+
+        self.history_loader.load_older(
+            Arc::clone(&self.http),
+            channel.id,
+            self.messages.first().map(|qmsg| qmsg.msg().id),
+            MainScreenMessage::OlderHistoryRetrieved,
+            MainScreenMessage::OlderHistoryRetrievalError,
+        )
+    }
+
+    /// Loads older messages for the find bar to search, or gives up on searching
+    /// them if no page is on its way, e.g. because there are no messages yet.
+    fn search_older(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        let cmd = self.load_older(config);
+        if !self.history_loader.is_loading() {
+            if let Some(find) = &mut self.find {
+                find.stop_searching_history();
+            }
+        }
+        cmd
+    }
+
+    /// Sends the contents of the composer to the selected channel, once its
+    /// attachments are uploaded if it has any.
+    fn send(&mut self, config: &mut Config) -> Command<MainScreenMessage> {
+        if self.uploading.is_some() {
+            return Command::none();
+        }
+        let Some(channel_id) = self.selected_channel(config).map(|c| c.id) else {
+            return Command::none();
+        };
+
+        let Some(user) = self.gateway_state.user().cloned() else {
+            return Command::none();
+        };
+
+        let content = self
+            .plugins
+            .iter()
+            .fold(self.editor.text(), |content, plugin| {
+                plugin.on_send(content)
+            });
+        self.editor = text_editor::Content::new();
+        self.sync_draft(config);
+
+        let reply_to = self.replying_to.take();
+        if self.attachments.is_empty() {
+            return self.send_content(user, channel_id, content, reply_to, config);
+        }
+        let attachments = std::mem::take(&mut self.attachments);
+        self.upload_error = None;
+        self.uploading = Some(Upload {
+            channel_id,
+            content,
+            reply_to,
+            attachments: attachments.clone(),
+        });
+        Command::perform(
+            attachment::upload(Arc::clone(&self.http), channel_id, attachments),
+            |res| MainScreenMessage::AttachmentsUploaded(res.map_err(Arc::new)),
+        )
+    }
+
+    /// Sends `content` to a channel, replying to `reply_to`, and shows it
+    /// among the messages if the channel is still the selected one.
+    fn send_content(
+        &mut self,
+        user: User,
+        channel_id: ChannelId,
+        content: String,
+        reply_to: Option<QMessage>,
+        config: &Config,
+    ) -> Command<MainScreenMessage> {
+        let mut msg =
+            HistoryQMessage::sending(user, channel_id, content, reply_to.as_ref().map(|m| m.id));
+        if let Some(quoted) = reply_to {
+            msg.set_quoted(quoted);
+        }
+        let send_message_cmd = msg
+            .send(Arc::clone(&self.http))
+            .map(|(id, msg)| MainScreenMessage::HistoryMessageEvent(id, msg));
+        if self
+            .selected_channel(config)
+            .is_none_or(|c| c.id != channel_id)
+        {
+            return send_message_cmd;
+        }
+        self.messages.push(msg);
+
+        Command::batch([
+            send_message_cmd,
+            snap_to(scrollable::Id::new(QMESSAGELIST_ID), RelativeOffset::START),
+        ])
+    }
+
+    /// Has the application tell the user that `message` mentions them.
+    fn notify_mention(&self, message: &QMessage, config: &Config) -> Command<MainScreenMessage> {
+        let channel = self
+            .channels(config)
+            .find(|c| c.id == message.channel)
+            .map_or_else(|| message.channel.to_string(), |c| c.name.clone());
+        let message = message.clone();
+        Command::perform(async {}, move |()| MainScreenMessage::Mentioned {
+            channel,
+            message,
+        })
+    }
+
+    /// Whether the user can see new messages arrive in the selected channel.
+    fn is_reading(&self) -> bool {
+        self.active && self.window_focused && self.at_bottom && !self.showing_context
+    }
+
+    /// Marks the selected channel as read up to its newest loaded message,
+    /// if the user is looking at it.
+    fn catch_up(&mut self, config: &mut Config) {
+        if !self.is_reading() {
+            return;
+        }
+        let (Some(channel_id), Some(newest)) = (
+            self.selected_channel(config).map(|c| c.id),
+            self.messages.last().map(|qmsg| qmsg.msg().id),
+        ) else {
+            return;
+        };
+
+        self.unread.remove(&channel_id);
+        self.mentioned.remove(&channel_id);
+        self.mark_read(channel_id, newest, config);
+    }
+
+    /// Moves the read marker of a channel forward to `message_id`,
+    /// and shares it with the user's other devices.
+    fn mark_read(&mut self, channel_id: ChannelId, message_id: MessageId, config: &mut Config) {
+        if self.advance_read_marker(channel_id, message_id, config) {
+            self.unsynced_reads.insert(channel_id, message_id);
+            self.unacked.insert(channel_id, message_id);
+            self.last_read_at = Instant::now();
+        }
+    }
+
+    /// Moves the locally stored read marker of a channel forward to `message_id`.
+    /// Returns whether it moved.
+    fn advance_read_marker(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        config: &mut Config,
+    ) -> bool {
+        let Some(user) = self.gateway_state.user() else {
+            return false;
+        };
+
+        let last_read = &mut config
+            .get_account_config_mut(&self.server, user.id)
+            .last_read;
+        if last_read
+            .get(&channel_id)
+            .is_some_and(|&id| id >= message_id)
+        {
+            return false;
+        }
+
+        last_read.insert(channel_id, message_id);
+        config.save();
+        true
+    }
+
+    /// Trades the expired token for a new one, instead of letting every
+    /// request fail from now on. The session ends if the server won't.
+    fn refresh_token(&mut self) -> Command<MainScreenMessage> {
+        let Some(token) = self.token().map(str::to_string) else {
+            return Command::none();
+        };
+        if self.refreshing_token {
+            return Command::none();
+        }
+
+        self.refreshing_token = true;
+        let server = self.server.clone();
+        let options = self.connect_options.clone();
+        Command::perform(
+            async move {
+                let mut http = scheduler::new_http(server, &options)?;
+                http.set_token(token);
+                http.refresh_token().await?;
+                Ok::<_, http::Error>(http.token().unwrap_or_default().to_string())
+            },
+            |res| MainScreenMessage::TokenRefreshed(res.map_err(Arc::new)),
+        )
+    }
+
+    /// Asks the server where the user left off on their other devices.
+    fn fetch_read_markers(&mut self) -> Command<MainScreenMessage> {
+        self.read_sync = false;
+        let http = Arc::clone(&self.http);
+        Command::perform(
+            async move {
+                http.run(Priority::Background, |http| http.read_markers())
+                    .await
+            },
+            |res| MainScreenMessage::ReadMarkersFetched(res.map_err(Arc::new)),
+        )
+    }
+
+    /// Sends the read markers that moved since the last time.
+    fn push_read_markers(&mut self) -> Command<MainScreenMessage> {
+        if !self.read_sync || self.unsynced_reads.is_empty() {
+            return Command::none();
+        }
+
+        let markers: Vec<_> = self
+            .unsynced_reads
+            .drain()
+            .map(|(channel_id, message_id)| ReadMarker {
+                channel_id,
+                message_id,
+            })
+            .collect();
+        let http = Arc::clone(&self.http);
+        Command::perform(
+            async move {
+                let mut failed = Vec::new();
+                for marker in markers {
+                    let res = http
+                        .run(Priority::Background, |http| {
+                            http.set_read_marker(marker.channel_id, marker.message_id)
+                        })
+                        .await;
+                    if let Err(e) = res {
+                        log::warn!(
+                            "could not sync the read marker of {channel:?}: {e}",
+                            channel = marker.channel_id,
+                            e = ErrorWithCauses(e)
+                        );
+                        failed.push(marker);
+                    }
+                }
+                failed
+            },
+            MainScreenMessage::ReadMarkersPushed,
+        )
+    }
+
+    /// Has the application tell the user about `message`, which arrived in a channel that isn't open.
+    fn notify(&self, message: &QMessage, config: &Config) -> Command<MainScreenMessage> {
+        let channel = self
+            .channels(config)
+            .find(|c| c.id == message.channel)
+            .map_or_else(|| message.channel.to_string(), |c| c.name.clone());
+        let message = message.clone();
+        Command::perform(async {}, move |()| MainScreenMessage::MessageNotified {
+            channel,
+            message,
+        })
+    }
+
+    /// Shows the messages that loaded ones reply to, fetching those that aren't loaded.
+    fn fetch_quotes(&mut self) -> Command<MainScreenMessage> {
+        let missing: Vec<_> = self
+            .messages
+            .iter()
+            .filter_map(|qmsg| Some((qmsg.msg().channel, qmsg.missing_quote()?)))
+            .collect();
+
+        let mut cmds = Vec::new();
+        for (channel_id, id) in missing {
+            let loaded = self.messages.iter().find(|qmsg| qmsg.msg().id == id);
+            if let Some(quoted) = loaded.map(|qmsg| qmsg.msg().clone()) {
+                self.set_quoted(quoted);
+            } else if !self.quotes_failed.contains(&id) && self.quotes_fetching.insert(id) {
+                let http = Arc::clone(&self.http);
+                cmds.push(Command::perform(
+                    async move {
+                        http.run(Priority::Background, |http| {
+                            http.fetch_message(channel_id, id)
+                        })
+                        .await
+                        .map_err(Arc::new)
+                    },
+                    move |res| MainScreenMessage::QuoteFetched(id, res),
+                ));
+            }
+        }
+        Command::batch(cmds)
+    }
+
+    fn set_quoted(&mut self, quoted: QMessage) {
+        for qmsg in &mut self.messages {
+            if qmsg.missing_quote() == Some(quoted.id) {
+                qmsg.set_quoted(quoted.clone());
+            }
+        }
+    }
+
+    /// Selects `channel_id` and scrolls to the message `id` in it, loading
+    /// the messages around it if they aren't.
+    fn jump_to(
+        &mut self,
+        channel_id: ChannelId,
+        id: MessageId,
+        config: &Config,
+    ) -> Command<MainScreenMessage> {
+        let Some(idx) = self.channels(config).position(|c| c.id == channel_id) else {
+            log::warn!("not jumping to {id}, {channel_id} isn't among the channels");
+            return Command::none();
+        };
+        self.saved_open = false;
+        if idx != self.selected_channel {
+            let cmd = self.switch_channel(idx, config);
+            // once the history is in
+            self.pending_jump = Some(id);
+            return cmd;
+        }
+        self.pending_jump = Some(id);
+        self.continue_jump(config)
+    }
+
+    /// Jumps to the linked message, unless the account's channels aren't known yet.
+    fn open_pending_link(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        if self.user().is_none() {
+            return Command::none();
+        }
+        match self.pending_link.take() {
+            Some((channel_id, id)) => self.jump_to(channel_id, id, config),
+            None => Command::none(),
+        }
+    }
+
+    /// Scrolls to the message `pending_jump` waits for if it's loaded, or else
+    /// loads the messages leading up to it in place of those shown.
+    fn continue_jump(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        let Some(id) = self.pending_jump else {
+            return Command::none();
+        };
+        if let Some(idx) = self.messages.iter().position(|qmsg| qmsg.msg().id == id) {
+            self.pending_jump = None;
+            return self.focus_message(idx);
+        }
+        let is_older = self.messages.first().is_some_and(|qmsg| id < qmsg.msg().id);
+        match self.selected_channel(config) {
+            // paging back to it could take any number of requests, so fetch just
+            // the messages up to it, which is also where this ends if it's gone
+            Some(channel) if is_older && !self.history_loader.is_exhausted() => {
+                let channel_id = channel.id;
+                retrieve_history(
+                    Arc::clone(&self.http),
+                    Priority::Interactive,
+                    channel_id,
+                    History::before(MessageId(id.0 + 1)).limit(JUMP_CONTEXT),
+                    move |channel_id, msgs| {
+                        MainScreenMessage::JumpContextRetrieved(channel_id, id, msgs)
+                    },
+                    MainScreenMessage::JumpContextRetrievalError,
+                )
+            }
+            // deleted since it was saved
+            _ => {
+                self.pending_jump = None;
+                Command::none()
+            }
+        }
+    }
+
+    /// Scrolls to the message at `idx` and highlights it for a moment.
+    fn focus_message(&mut self, idx: usize) -> Command<MainScreenMessage> {
+        let Some(qmsg) = self.messages.get(idx) else {
+            return Command::none();
+        };
+        self.flash = Some((qmsg.id(), Instant::now()));
+        scroll_to_message(&self.messages, idx)
+    }
+
+    /// Acknowledges what was read since the last time, one request per channel.
+    fn send_acks(&mut self) -> Command<MainScreenMessage> {
+        // offline, they'd only fail
+        if !matches!(self.gateway_state, GatewayState::Connected { .. }) || self.unacked.is_empty()
+        {
+            return Command::none();
+        }
+
+        let acks: Vec<_> = self.unacked.drain().collect();
+        let http = Arc::clone(&self.http);
+        Command::perform(
+            async move {
+                let mut failed = Vec::new();
+                for (channel_id, up_to) in acks {
+                    let res = http
+                        .run(Priority::Background, |http| {
+                            http.ack_messages(channel_id, up_to)
+                        })
+                        .await;
+                    match res {
+                        Ok(()) => {}
+                        // the message is gone, or the server doesn't take acknowledgements
+                        Err(e) if e.api_error_kind() == Some(ApiErrorKind::NotFound) => {
+                            log::debug!("acknowledgement of {channel_id:?} refused: {e}")
+                        }
+                        Err(e) => {
+                            log::warn!(
+                                "could not acknowledge messages in {channel_id:?}: {e}",
+                                e = ErrorWithCauses(e)
+                            );
+                            failed.push((channel_id, up_to));
+                        }
+                    }
+                }
+                failed
+            },
+            MainScreenMessage::AcksSent,
+        )
+    }
+
+    /// Archives new messages of the channels that opted into backups.
+    fn start_backup(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        let settings = &config.settings.backup;
+        let Some(user) = self.gateway_state.user() else {
+            return Command::none();
+        };
+        let Some(account) = config.get_account_config(&self.server, user.id) else {
+            return Command::none();
+        };
+        if !settings.enabled {
+            return Command::none();
+        }
+
+        let channels: Vec<_> = account
             .channels
-            .get(idx)
+            .iter()
+            .filter(|channel| channel.backup)
+            .map(|channel| (channel.id, account.backed_up.get(&channel.id).copied()))
+            .collect();
+        self.back_up(channels, user.id, config)
     }
 
-    fn channels<'a>(&self, config: &'a Config) -> impl Iterator<Item = &'a Channel> {
+    /// Archives new messages of the selected channel, whether it opted into backups or not.
+    fn export_channel(&mut self, config: &Config) -> Command<MainScreenMessage> {
         let Some(user) = self.gateway_state.user() else {
-            return None.into_iter().flatten();
+            return Command::none();
         };
-        config
-            .get_account_config(&self.server, user.id)
-            .map(|account| account.channels.iter())
-            .into_iter()
-            .flatten()
+        let Some(account) = config.get_account_config(&self.server, user.id) else {
+            return Command::none();
+        };
+        let Some(channel) = account.channels.get(self.selected_channel) else {
+            return Command::none();
+        };
+
+        let channels = vec![(channel.id, account.backed_up.get(&channel.id).copied())];
+        self.back_up(channels, user.id, config)
     }
 
-    fn selected_channel<'a>(&self, config: &'a Config) -> Option<&'a Channel> {
-        self.channel_at(self.selected_channel, config)
+    fn back_up(
+        &mut self,
+        channels: Vec<(ChannelId, Option<MessageId>)>,
+        user: UserId,
+        config: &Config,
+    ) -> Command<MainScreenMessage> {
+        if channels.is_empty() || self.backup_running {
+            return Command::none();
+        }
+
+        self.backup_running = true;
+        Command::perform(
+            backup::run(
+                Arc::clone(&self.http),
+                backup::archive_dir(&config.settings.backup, &self.server, user),
+                channels,
+            ),
+            MainScreenMessage::BackupFinished,
+        )
+    }
+
+    fn bookmarks<'a>(&self, config: &'a Config) -> &'a [QMessage] {
+        self.user()
+            .and_then(|user| config.get_account_config(&self.server, user.id))
+            .map_or(&[], |account| &account.bookmarks)
+    }
+
+    fn outbox<'a>(&self, config: &'a Config) -> &'a [Outgoing] {
+        self.gateway_state
+            .user()
+            .and_then(|user| config.get_account_config(&self.server, user.id))
+            .map_or(&[], |account| &account.outbox)
+    }
+
+    /// Sends the messages that piled up while offline, one after the other
+    /// so that they arrive in order. Stops at the first one that still can't get through.
+    /// Does nothing while the outbox is already being sent, so that reconnecting
+    /// meanwhile doesn't send the same messages twice.
+    fn flush_outbox(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        let outbox = self.outbox(config).to_vec();
+        if outbox.is_empty() || self.flushing_outbox {
+            return Command::none();
+        }
+        self.flushing_outbox = true;
+
+        let http = Arc::clone(&self.http);
+        Command::perform(
+            async move {
+                let mut results = Vec::new();
+                for outgoing in outbox {
+                    let res = http
+                        .run(Priority::Interactive, |http| async {
+                            match outgoing.reply_to {
+                                Some(reply_to) => {
+                                    http.create_reply(outgoing.channel, reply_to, &outgoing.content)
+                                        .await
+                                }
+                                None => {
+                                    http.create_message(outgoing.channel, &outgoing.content)
+                                        .await
+                                }
+                            }
+                        })
+                        .await;
+                    if res.as_ref().is_err_and(http::Error::is_network_error) {
+                        break;
+                    }
+                    results.push((outgoing, res.map_err(Arc::new)));
+                }
+                results
+            },
+            MainScreenMessage::OutboxFlushed,
+        )
+    }
+
+    /// Fetches the latest messages of the other channels in the background
+    /// to find out how many of them are unread.
+    fn count_unread(&self, config: &Config) -> Command<MainScreenMessage> {
+        let selected = self.selected_channel(config).map(|c| c.id);
+        Command::batch(
+            self.channels(config)
+                .filter(|c| Some(c.id) != selected)
+                .map(|c| self.count_unread_in(c.id))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn count_unread_in(&self, channel_id: ChannelId) -> Command<MainScreenMessage> {
+        retrieve_history(
+            Arc::clone(&self.http),
+            Priority::Background,
+            channel_id,
+            History::latest(),
+            MainScreenMessage::UnreadCounted,
+            MainScreenMessage::HistoryRetrievalError,
+        )
+    }
+
+    /// Picks up edits that were left unsubmitted last time for the messages in `range`.
+    fn restore_edit_drafts(&mut self, range: std::ops::Range<usize>, config: &Config) {
+        let Some(edit_drafts) = self
+            .gateway_state
+            .user()
+            .and_then(|user| config.get_account_config(&self.server, user.id))
+            .map(|account| &account.edit_drafts)
+        else {
+            return;
+        };
+
+        for qmsg in &mut self.messages[range] {
+            if let Some(draft) = edit_drafts.get(&qmsg.msg().id) {
+                qmsg.restore_edit(draft);
+            }
+        }
+    }
+
+    /// Replaces the composer contents with the saved draft for the selected channel.
+    fn restore_draft(&mut self, config: &Config) {
+        let draft = self.selected_channel(config).and_then(|channel| {
+            config
+                .get_account_config(&self.server, self.gateway_state.user()?.id)?
+                .drafts
+                .get(&channel.id)
+        });
+        self.editor = match draft {
+            Some(draft) => text_editor::Content::with_text(draft),
+            None => text_editor::Content::new(),
+        };
+    }
+
+    /// Records the composer contents as the draft for the selected channel.
+    /// It's saved once typing pauses.
+    fn sync_draft(&mut self, config: &mut Config) {
+        let (Some(channel_id), Some(user)) = (
+            self.selected_channel(config).map(|c| c.id),
+            self.gateway_state.user(),
+        ) else {
+            return;
+        };
+
+        let text = self.editor.text();
+        let drafts = &mut config.get_account_config_mut(&self.server, user.id).drafts;
+        let changed = if text.trim().is_empty() {
+            drafts.remove(&channel_id).is_some()
+        } else {
+            drafts.insert(channel_id, text.clone()).as_ref() != Some(&text)
+        };
+
+        if changed {
+            self.draft_changed_at = Some(Instant::now());
+        }
+    }
+
+    /// Records the in-place edit buffer of the message at `idx`, or forgets it
+    /// once the message is no longer being edited.
+    fn sync_edit_draft(&mut self, idx: usize, config: &mut Config) {
+        let (Some(qmsg), Some(user)) = (self.messages.get(idx), self.gateway_state.user()) else {
+            return;
+        };
+
+        let message_id = qmsg.msg().id;
+        let edit_drafts = &mut config
+            .get_account_config_mut(&self.server, user.id)
+            .edit_drafts;
+        let changed = match qmsg.edit_draft() {
+            Some(text) => edit_drafts.insert(message_id, text.clone()).as_ref() != Some(&text),
+            None => edit_drafts.remove(&message_id).is_some(),
+        };
+
+        if changed {
+            self.draft_changed_at = Some(Instant::now());
+        }
+    }
+
+    /// Saves drafts that changed since they were last saved.
+    fn save_drafts(&mut self, config: &mut Config) {
+        if self.draft_changed_at.take().is_some() {
+            config.save();
+        }
     }
 
-    fn refresh_messages(&self, config: &Config) -> Command<MainScreenMessage> {
+    fn refresh_messages(&mut self, config: &Config) -> Command<MainScreenMessage> {
+        self.history_loader.reset();
         match self.selected_channel(config) {
             Some(channel) => retrieve_history(
                 Arc::clone(&self.http),
+                Priority::Interactive,
                 channel.id,
-                None,
+                History::latest(),
                 MainScreenMessage::HistoryRetrieved,
                 MainScreenMessage::HistoryRetrievalError,
             ),
@@ -316,20 +3057,779 @@ impl MainScreen {
         }
     }
 
-    pub fn view<'a, 'b>(
+    fn channel_header(&self, config: &Config) -> Option<Element<'_, MainScreenMessage>> {
+        let channel = self.selected_channel(config)?;
+
+        let action = |ic, label, msg| {
+            tooltip(
+                button(icon(ic).size(16))
+                    .style(theme::Button::Text)
+                    .on_press(msg),
+                label,
+                tooltip::Position::FollowCursor,
+            )
+        };
+
+        let header = row![
+            icon(CHANNEL_ICON).size(20),
+            text(&channel.name)
+                .font(DEFAULT_FONT_MEDIUM)
+                .size(18)
+                .width(Length::Fill),
+            action(SAVED, "Saved messages", MainScreenMessage::SavedToggled),
+            action(COPY, "Copy channel ID", MainScreenMessage::ChannelIdCopied),
+            action(REFRESH, "Refresh", MainScreenMessage::RefreshRequested),
+            tooltip(
+                button(icon(DENSE).size(16))
+                    .style(if channel.dense {
+                        pressed_button_style(theme::Button::Text)
+                    } else {
+                        theme::Button::Text
+                    })
+                    .on_press(MainScreenMessage::DenseToggled),
+                if channel.dense {
+                    "Show messages in full"
+                } else {
+                    "Show messages as single lines"
+                },
+                tooltip::Position::FollowCursor,
+            ),
+            tooltip(
+                button(icon(ANNOUNCEMENT).size(16))
+                    .style(if channel.confirm_send {
+                        pressed_button_style(theme::Button::Text)
+                    } else {
+                        theme::Button::Text
+                    })
+                    .on_press(MainScreenMessage::ConfirmSendToggled),
+                if channel.confirm_send {
+                    "Stop asking before sending"
+                } else {
+                    "Ask before sending"
+                },
+                tooltip::Position::FollowCursor,
+            ),
+        ]
+        .push_maybe((!self.spellcheck_languages.is_empty()).then(|| {
+            let choices: Vec<_> = [SpellcheckChoice::Usual]
+                .into_iter()
+                .chain(
+                    self.spellcheck_languages
+                        .iter()
+                        .cloned()
+                        .map(SpellcheckChoice::Language),
+                )
+                .collect();
+            let selected = match &channel.spellcheck_language {
+                Some(language) => SpellcheckChoice::Language(language.clone()),
+                None => SpellcheckChoice::Usual,
+            };
+            pick_list(choices, Some(selected), |choice| {
+                MainScreenMessage::SpellcheckLanguageSelected(match choice {
+                    SpellcheckChoice::Usual => None,
+                    SpellcheckChoice::Language(language) => Some(language),
+                })
+            })
+            .text_size(14)
+        }))
+        .push_maybe(config.settings.backup.enabled.then(|| {
+            tooltip(
+                button(icon(BACKUP).size(16))
+                    .style(if channel.backup {
+                        pressed_button_style(theme::Button::Text)
+                    } else {
+                        theme::Button::Text
+                    })
+                    .on_press(MainScreenMessage::BackupToggled),
+                if channel.backup {
+                    "Stop backing up this channel"
+                } else {
+                    "Back up this channel"
+                },
+                tooltip::Position::FollowCursor,
+            )
+        }))
+        .align_items(iced::Alignment::Center)
+        .spacing(5)
+        .padding([10, 20]);
+
+        Some(header.into())
+    }
+
+    /// What the empty editor says, and whether it can be typed into.
+    fn editor_hint(&self, config: &Config) -> (String, bool) {
+        match (&self.gateway_state, self.selected_channel(config)) {
+            (GatewayState::Connected { .. }, Some(channel)) => {
+                (format!("Message #{name}", name = channel.name), true)
+            }
+            (GatewayState::Connected { .. }, None) => {
+                ("Add a channel to start chatting".to_string(), false)
+            }
+            (GatewayState::Disconnected { error: Some(_) }, _) => {
+                ("You are disconnected".to_string(), false)
+            }
+            (GatewayState::Disconnected { error: None } | GatewayState::ServerBusy, _) => {
+                ("Connecting…".to_string(), false)
+            }
+        }
+    }
+
+    fn permission_banner(&self) -> Option<Element<'_, MainScreenMessage>> {
+        let notice = self.permission_notice?;
+
+        let banner = container(
+            row![
+                icon(WARNING),
+                text(notice).width(Length::Fill),
+                button(icon(CLOSE))
+                    .style(theme::Button::Text)
+                    .on_press(MainScreenMessage::PermissionNoticeDismissed),
+            ]
+            .align_items(iced::Alignment::Center)
+            .spacing(10),
+        )
+        .padding([5, 10])
+        .width(Length::Fill)
+        .style(|t: &Theme| {
+            use container::StyleSheet;
+            let pair = t.extended_palette().danger.weak;
+            container::Appearance {
+                text_color: Some(pair.text),
+                background: Some(Background::Color(pair.color)),
+                ..t.appearance(&theme::Container::Transparent)
+            }
+        });
+
+        Some(container(banner).padding([0, 20]).into())
+    }
+
+    /// The messages the user saved, newest first, in place of the channel's messages.
+    fn saved_view(&self, config: &Config) -> Option<Element<'_, MainScreenMessage>> {
+        if !self.saved_open {
+            return None;
+        }
+
+        let bookmarks = self.bookmarks(config);
+        let list: Element<'_, _> = if bookmarks.is_empty() {
+            text("Nothing saved yet. Save messages with the star that shows when hovering them.")
+                .size(14)
+                .into()
+        } else {
+            widget::scrollable(
+                widget::Column::with_children(bookmarks.iter().rev().map(|msg| {
+                    let channel = self.channels(config).find(|c| c.id == msg.channel);
+                    let sent = timezone::to_display(msg.id.timestamp(), config.settings.timezone);
+                    row![
+                        column![
+                            row![
+                                text(&msg.author.name)
+                                    .font(DEFAULT_FONT_MEDIUM)
+                                    .shaping(text::Shaping::Advanced),
+                                text(format!(
+                                    "#{}",
+                                    channel.map_or_else(
+                                        || msg.channel.to_string(),
+                                        |c| c.name.clone()
+                                    )
+                                ))
+                                .size(12),
+                                text(sent.format("%Y-%m-%d %H:%M")).size(10),
+                            ]
+                            .spacing(5)
+                            .align_items(iced::Alignment::Center),
+                            text(excerpt(&msg.content))
+                                .size(14)
+                                .shaping(text::Shaping::Advanced),
+                        ]
+                        .spacing(3)
+                        .width(Length::Fill),
+                        button(text("Jump").size(14))
+                            .style(theme::Button::Text)
+                            .on_press_maybe(
+                                channel.map(|_| MainScreenMessage::BookmarkJumped(msg.id)),
+                            ),
+                        button(text("Remove").size(14))
+                            .style(theme::Button::Text)
+                            .on_press(MainScreenMessage::BookmarkRemoved(msg.id)),
+                    ]
+                    .spacing(5)
+                    .align_items(iced::Alignment::Center)
+                    .into()
+                }))
+                .spacing(10),
+            )
+            .height(Length::Fill)
+            .into()
+        };
+
+        Some(
+            container(
+                column![
+                    row![
+                        text("Saved messages").font(DEFAULT_FONT_MEDIUM).size(18),
+                        widget::horizontal_space(),
+                        button(text("Close").size(14))
+                            .style(theme::Button::Text)
+                            .on_press(MainScreenMessage::SavedToggled),
+                    ]
+                    .align_items(iced::Alignment::Center),
+                    list,
+                ]
+                .spacing(10),
+            )
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(20)
+            .into(),
+        )
+    }
+
+    /// The image opened from a message, as big as the space for the messages allows.
+    fn lightbox_view(&self) -> Option<Element<'_, MainScreenMessage>> {
+        let url = self.lightbox.as_ref()?;
+        let (image, _) = self.images.view(
+            url,
+            ImageSize::Fill,
+            true,
+            Some(MainScreenMessage::ImageRetried(url.clone())),
+        )?;
+        Some(
+            container(
+                column![
+                    row![
+                        text(url.as_str()).size(12),
+                        widget::horizontal_space(),
+                        button(text("Close").size(14))
+                            .style(theme::Button::Text)
+                            .on_press(MainScreenMessage::LightboxClosed),
+                    ]
+                    .align_items(iced::Alignment::Center),
+                    button(image)
+                        .style(theme::Button::Text)
+                        .padding(0)
+                        .on_press(MainScreenMessage::LightboxClosed),
+                ]
+                .spacing(10),
+            )
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(20)
+            .style(theme::Container::Box)
+            .into(),
+        )
+    }
+
+    /// The message being replied to, above the composer.
+    fn reply_banner(&self) -> Option<Element<'_, MainScreenMessage>> {
+        let quoted = self.replying_to.as_ref()?;
+
+        let banner = container(
+            row![
+                icon(REPLY),
+                text(format!("Replying to {}", quoted.author.name))
+                    .font(DEFAULT_FONT_MEDIUM)
+                    .shaping(text::Shaping::Advanced),
+                text(excerpt(&quoted.content))
+                    .size(14)
+                    .shaping(text::Shaping::Advanced)
+                    .width(Length::Fill),
+                button(icon(CLOSE))
+                    .style(theme::Button::Text)
+                    .on_press(MainScreenMessage::ReplyCancelled),
+            ]
+            .align_items(iced::Alignment::Center)
+            .spacing(10),
+        )
+        .padding([5, 10])
+        .width(Length::Fill)
+        .style(theme::Container::Box);
+
+        Some(container(banner).padding([0, 20]).into())
+    }
+
+    /// Sends the message where Enter starts a new line instead.
+    fn send_button<'a>(&self, editable: bool) -> Element<'a, EditorMessage> {
+        let sendable = editable
+            && self.uploading.is_none()
+            && (!self.editor.text().trim().is_empty() || !self.attachments.is_empty());
+        tooltip(
+            button(icon(SEND).size(18))
+                .style(theme::Button::Primary)
+                .padding([10, 14])
+                .on_press_maybe(sendable.then_some(EditorMessage::SendInitiated)),
+            "Send",
+            tooltip::Position::Top,
+        )
+        .into()
+    }
+
+    /// Turns a `:shortcode:` into its emoji as soon as its closing colon is typed.
+    fn expand_shortcode(&mut self) {
+        let Some(word) = editor::word_before_cursor(&self.editor, ':') else {
+            return;
+        };
+        let Some(emoji) = word
+            .strip_prefix(':')
+            .and_then(|word| word.strip_suffix(':'))
+            .and_then(emoji::lookup)
+        else {
+            return;
+        };
+        editor::replace_before_cursor(&mut self.editor, word.chars().count(), emoji);
+    }
+
+    /// Emoji for the `:shortcode` being typed, once it's long enough to narrow them down.
+    fn emoji_completions(&self) -> Vec<(&'static str, &'static str)> {
+        let Some(word) = editor::word_before_cursor(&self.editor, ':') else {
+            return Vec::new();
+        };
+        let query = &word[1..];
+        if query.chars().count() < 2 || query.contains(':') {
+            return Vec::new();
+        }
+        let mut found = emoji::search(query);
+        found.truncate(MAX_COMPLETIONS);
+        found
+    }
+
+    /// Users for the `@name` being typed, going by who wrote recently: in the
+    /// selected channel first, then anywhere else.
+    fn mention_completions(&self, config: &Config) -> Vec<&User> {
+        let Some(word) = editor::word_before_cursor(&self.editor, '@') else {
+            return Vec::new();
+        };
+        let Some(channel) = self.selected_channel(config) else {
+            return Vec::new();
+        };
+        let mut found = self.recent_authors.candidates(
+            channel.id,
+            &word[1..],
+            self.gateway_state.user().map(|user| user.id),
+        );
+        found.truncate(MAX_COMPLETIONS);
+        found
+    }
+
+    /// Suggestions for the word being typed, each with its label and the message
+    /// that picks it: emoji for a `:shortcode`, users for an `@name`.
+    fn completions(&self, config: &Config) -> Vec<(String, EditorMessage)> {
+        let emoji = self.emoji_completions().into_iter().map(|(name, emoji)| {
+            (
+                format!("{emoji} :{name}:"),
+                EditorMessage::EmojiCompleted(emoji),
+            )
+        });
+        let users = self.mention_completions(config).into_iter().map(|user| {
+            (
+                mention::format(&user.name),
+                EditorMessage::MentionCompleted(user.name.clone()),
+            )
+        });
+        emoji.chain(users).collect()
+    }
+
+    /// The suggestions above the composer. Tab takes the first one.
+    fn completion_strip<'a>(
+        completions: &[(String, EditorMessage)],
+    ) -> Option<Element<'a, EditorMessage>> {
+        if completions.is_empty() {
+            return None;
+        }
+
+        let suggestions = completions.iter().map(|(label, msg)| {
+            button(text(label).size(14).shaping(text::Shaping::Advanced))
+                .style(theme::Button::Text)
+                .padding([2, 6])
+                .on_press(msg.clone())
+                .into()
+        });
+        Some(widget::Row::with_children(suggestions).spacing(5).into())
+    }
+
+    fn emoji_picker(&self) -> Element<'_, EditorMessage> {
+        let toggle = tooltip(
+            button(icon(EMOJI))
+                .style(theme::Button::Secondary)
+                .on_press(EditorMessage::EmojiPickerToggled),
+            "Insert emoji",
+            tooltip::Position::Top,
+        );
+
+        let grid = Wrap::with_elements(
+            emoji::EMOJI
+                .iter()
+                .map(|&(name, emoji)| {
+                    tooltip(
+                        button(text(emoji).size(18))
+                            .style(theme::Button::Text)
+                            .padding(4)
+                            .on_press(EditorMessage::EmojiPicked(emoji)),
+                        text(format!(":{name}:")).size(12),
+                        tooltip::Position::Top,
+                    )
+                    .into()
+                })
+                .collect(),
+        );
+        let menu = container(widget::scrollable(grid).height(200))
+            .style(theme::Container::Box)
+            .padding(5);
+
+        DropDown::new(toggle, menu, self.emoji_picker_open)
+            .alignment(iced_aw::drop_down::Alignment::Top)
+            .on_dismiss(EditorMessage::EmojiPickerDismissed)
+            .width(280)
+            .into()
+    }
+
+    fn template_picker<'a>(&self, config: &'a Config) -> Option<Element<'a, EditorMessage>> {
+        let templates = self.templates(config);
+        if templates.is_empty() {
+            return None;
+        }
+
+        let toggle = tooltip(
+            button(icon(TEMPLATE))
+                .style(theme::Button::Secondary)
+                .on_press(EditorMessage::TemplatesToggled),
+            "Insert template",
+            tooltip::Position::Top,
+        );
+
+        let menu = container(widget::Column::with_children(
+            templates.iter().enumerate().map(|(idx, template)| {
+                button(text(&template.name))
+                    .style(theme::Button::Text)
+                    .width(Length::Fill)
+                    .on_press(EditorMessage::TemplateInserted(idx))
+                    .into()
+            }),
+        ))
+        .style(theme::Container::Box)
+        .padding(5);
+
+        Some(
+            DropDown::new(toggle, menu, self.templates_expanded)
+                .alignment(iced_aw::drop_down::Alignment::Top)
+                .on_dismiss(EditorMessage::TemplatesDismissed)
+                .width(200)
+                .into(),
+        )
+    }
+
+    /// Takes the user back to the newest messages when they've scrolled up.
+    fn jump_to_latest(&self) -> Element<'_, MainScreenMessage> {
+        let label = match self.missed_below {
+            0 => "Jump to latest".to_string(),
+            1 => "1 new message".to_string(),
+            n => format!("{n} new messages"),
+        };
+        container(
+            button(
+                row![icon(JUMP_DOWN).size(14), text(label).size(14)]
+                    .spacing(5)
+                    .align_items(iced::Alignment::Center),
+            )
+            .on_press(MainScreenMessage::JumpedToLatest)
+            .padding([5, 15]),
+        )
+        .padding(30)
+        .into()
+    }
+
+    fn selection_strip(&self) -> Option<Element<'_, MainScreenMessage>> {
+        let selected = self.messages.iter().filter(|m| m.is_selected()).count();
+        if selected == 0 {
+            return None;
+        }
+
+        let strip = row![
+            text(format!("{selected} selected")).width(Length::Fill),
+            button("Quote selected").on_press(MainScreenMessage::QuoteSelected),
+            button("Clear")
+                .style(theme::Button::Secondary)
+                .on_press(MainScreenMessage::SelectionCleared),
+        ]
+        .align_items(iced::Alignment::Center)
+        .spacing(10)
+        .padding([0, 20]);
+
+        Some(strip.into())
+    }
+
+    /// What the plugins have to add to a message in the message list.
+    fn plugin_decorations<'a>(
+        &'a self,
+        msg: &'a QMessage,
+    ) -> Option<Element<'a, MainScreenMessage>> {
+        let decorations: Vec<_> = self
+            .plugins
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, plugin)| {
+                let decoration = plugin.decorate_message(msg)?;
+                Some(decoration.map(move |msg| MainScreenMessage::Plugin(idx, msg)))
+            })
+            .collect();
+
+        (!decorations.is_empty()).then(|| column(decorations).spacing(5).into())
+    }
+
+    fn close_account_forms(&mut self) {
+        self.rename = None;
+        self.password_change = None;
+        self.account_deletion = None;
+    }
+
+    /// The buttons at the bottom of the sidebar, or the form
+    /// for whichever account change was asked for.
+    fn account_controls(&self, theme: &Theme) -> Element<'_, MainScreenMessage> {
+        if let Some(form) = &self.password_change {
+            return form.view(theme).map(MainScreenMessage::PasswordChange);
+        }
+        if let Some(user) = self.gateway_state.user() {
+            if let Some(form) = &self.rename {
+                return form.view(user, theme).map(MainScreenMessage::Rename);
+            }
+            if let Some(form) = &self.account_deletion {
+                return form
+                    .view(user, theme)
+                    .map(MainScreenMessage::AccountDeletion);
+            }
+        }
+
+        let account_action = |label, msg: MainScreenMessage| {
+            button(text(label).size(12))
+                .style(theme::Button::Text)
+                .on_press_maybe(self.gateway_state.user().map(|_| msg))
+        };
+
+        column![
+            row![
+                button(text("What's new").size(14))
+                    .style(theme::Button::Text)
+                    .on_press(MainScreenMessage::WhatsNewRequested),
+                widget::horizontal_space(),
+                button(text("Log out").size(14))
+                    .style(theme::Button::Text)
+                    .on_press(MainScreenMessage::LogoutRequested),
+            ],
+            row![
+                account_action("Rename", MainScreenMessage::RenameRequested),
+                account_action(
+                    "Change password",
+                    MainScreenMessage::PasswordChangeRequested
+                ),
+                account_action("Delete account", MainScreenMessage::DeleteAccountRequested),
+            ],
+        ]
+        .into()
+    }
+
+    /// How the automatic backups are doing, if they're turned on.
+    fn backup_status(&self, config: &Config) -> Option<Element<'_, MainScreenMessage>> {
+        if !config.settings.backup.enabled {
+            return None;
+        }
+
+        let status = match &self.last_backup {
+            _ if self.backup_running => "Backing up…".to_string(),
+            None => "Not backed up yet".to_string(),
+            Some(BackupReport {
+                error: Some(err), ..
+            }) => format!("Backup failed: {err}"),
+            Some(BackupReport {
+                finished, written, ..
+            }) => format!(
+                "Backed up at {time}, {written} new messages",
+                time = finished.format("%H:%M")
+            ),
+        };
+
+        Some(text(status).size(12).into())
+    }
+
+    fn send_prompt(&self, config: &Config) -> Option<Element<'_, MainScreenMessage>> {
+        if !self.confirming_send {
+            return None;
+        }
+        let channel = self.selected_channel(config)?;
+
+        let strip = row![
+            text(format!(
+                "Send this to everyone in {name}?",
+                name = channel.name
+            ))
+            .width(Length::Fill),
+            button("Send").on_press(MainScreenMessage::SendConfirmed(true)),
+            button("Cancel")
+                .style(theme::Button::Secondary)
+                .on_press(MainScreenMessage::SendConfirmed(false)),
+        ]
+        .align_items(iced::Alignment::Center)
+        .spacing(10)
+        .padding([0, 20]);
+
+        Some(strip.into())
+    }
+
+    /// Asks whether a long paste should be attached as a file, showing how it starts.
+    fn paste_prompt(&self, config: &Config) -> Option<Element<'_, MainScreenMessage>> {
+        let pasted = self.long_paste.as_ref()?;
+
+        let preview = pasted
+            .lines()
+            .take(PASTE_PREVIEW_LINES)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let answer = MainScreenMessage::PastePromptAnswered;
+        let prompt = column![
+            row![
+                text(format!(
+                    "This paste is {lines} lines long ({size}). Attach it as paste.{ext} instead?",
+                    lines = pasted.lines().count(),
+                    size = attachment::size_label(pasted.len()),
+                    ext = config.settings.pasted_file_format.extension(),
+                ))
+                .width(Length::Fill),
+                button("Attach as file").on_press(answer(PasteChoice::Attach)),
+                button("Paste as text")
+                    .style(theme::Button::Secondary)
+                    .on_press(answer(PasteChoice::Paste)),
+                button("Cancel")
+                    .style(theme::Button::Secondary)
+                    .on_press(answer(PasteChoice::Cancel)),
+            ]
+            .align_items(iced::Alignment::Center)
+            .spacing(10),
+            container(text(preview).font(iced::Font::MONOSPACE).size(12))
+                .padding([5, 10])
+                .width(Length::Fill)
+                .style(theme::Container::Box),
+        ]
+        .spacing(5)
+        .padding([0, 20]);
+
+        Some(prompt.into())
+    }
+
+    /// The files the next message is sent with, and how their upload is going.
+    fn attachment_strip(&self, theme: &Theme) -> Option<Element<'_, MainScreenMessage>> {
+        if self.attachments.is_empty() && self.uploading.is_none() && self.upload_error.is_none() {
+            return None;
+        }
+
+        let status = match (&self.uploading, &self.upload_error) {
+            (Some(_), _) => Some(text("Uploading…").size(12)),
+            (None, Some(e)) => Some(
+                text(format!(
+                    "Couldn't upload the attachments: {}",
+                    ErrorSummary(e)
+                ))
+                .size(12)
+                .style(theme.palette().danger),
+            ),
+            (None, None) => None,
+        };
+        let chips = self
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(idx, attachment)| attachment.view(MainScreenMessage::AttachmentRemoved(idx)));
+        let strip = row(chips)
+            .push_maybe(status)
+            .align_items(iced::Alignment::Center)
+            .spacing(10)
+            .padding([0, 20]);
+
+        Some(strip.into())
+    }
+
+    fn unsent_prompt(&self) -> Option<Element<'_, MainScreenMessage>> {
+        self.pending_switch?;
+
+        let answer = MainScreenMessage::UnsentPromptAnswered;
+        let strip = row![
+            text("You have an unsent message.").width(Length::Fill),
+            button("Switch anyway")
+                .style(theme::Button::Destructive)
+                .on_press(answer(UnsentChoice::Discard)),
+            button("Save draft").on_press(answer(UnsentChoice::SaveDraft)),
+            button("Cancel")
+                .style(theme::Button::Secondary)
+                .on_press(answer(UnsentChoice::Cancel)),
+        ]
+        .align_items(iced::Alignment::Center)
+        .spacing(10)
+        .padding([0, 20]);
+
+        Some(strip.into())
+    }
+
+    /// Shows the screen, with `others`, the sections of the other logged in
+    /// accounts, listed below this account's channels in the sidebar.
+    pub fn view<'a>(
         &'a self,
-        theme: &'b Theme,
-        config: &'b Config,
+        theme: &Theme,
+        config: &'a Config,
+        others: Vec<Element<'a, MainScreenMessage>>,
     ) -> Element<'a, MainScreenMessage, Theme, Renderer> {
+        let find_matches = self
+            .find
+            .as_ref()
+            .map_or_else(Vec::new, |find| find.matches(&self.messages));
+
+        let (hint, editable) = self.editor_hint(config);
+        let editable = editable && !self.covered;
+
+        // shown in place of the messages
+        let panel = self.lightbox_view().or_else(|| self.saved_view(config));
         let el = row([
             container({
                 column([
                     self.channel_edit_strip
                         .view(theme)
                         .map(MainScreenMessage::ChannelEditStrip),
-                    ChannelList::new(self.channels(config), self.selected_channel)
-                        .on_selection(MainScreenMessage::ChannelSelected)
+                    column([server_header(
+                        self.server.host_str().unwrap_or(self.server.as_str()),
+                        self.connection_status(),
+                        match self.gateway_state {
+                            GatewayState::Connected { latency, .. } => latency,
+                            _ => None,
+                        },
+                        self.server_collapsed,
+                        MainScreenMessage::ServerSectionToggled,
+                    )])
+                    .push_maybe({
+                        (!self.server_collapsed).then(|| {
+                            ChannelList::new(self.channels(config), self.selected_channel)
+                                .unread(&self.unread)
+                                .mentioned(&self.mentioned)
+                                .activity(&self.activity)
+                                .subscriptions(
+                                    &self.subscriptions,
+                                    MainScreenMessage::SubscriptionRetried,
+                                )
+                                .on_selection(MainScreenMessage::ChannelSelected)
+                                .menu(&self.channel_menu, MainScreenMessage::ChannelMenu)
+                        })
+                    })
+                    .extend(others)
+                    .spacing(10)
+                    .into(),
+                    widget::Column::with_children(self.plugins.iter().enumerate().filter_map(
+                        |(idx, plugin)| {
+                            let panel = plugin.sidebar_panel()?;
+                            Some(panel.map(move |msg| MainScreenMessage::Plugin(idx, msg)))
+                        },
+                    ))
+                    .spacing(10)
+                    .into(),
+                    widget::vertical_space().into(),
+                    widget::Column::new()
+                        .push_maybe(self.backup_status(config))
                         .into(),
+                    self.account_controls(theme),
                 ])
                 .width(Length::Fixed(200.0))
                 .height(Length::Fill)
@@ -348,46 +3848,259 @@ impl MainScreen {
                 }
             })
             .into(),
-            column([
-                qmessage_list(theme, &self.messages)
-                    .map(|(idx, a)| MainScreenMessage::HistoryMessageAction(idx, a)),
-                Element::from({
-                    container({
-                        MessageEditor::new(&self.editor)
-                            .on_action(EditorMessage::Action)
-                            .on_enter(EditorMessage::SendInitiated)
-                            .padding(10)
-                    })
-                    .padding(10)
+            widget::Column::new()
+                .push_maybe(self.channel_header(config))
+                .push_maybe(self.find.as_ref().map(|find| {
+                    find.view(&self.messages, self.history_loader.is_exhausted())
+                        .map(MainScreenMessage::Find)
+                }))
+                .push(match panel {
+                    Some(panel) => panel,
+                    None => FloatingElement::new(
+                        FloatingElement::new(
+                            qmessage_list(
+                                theme,
+                                &self.messages,
+                                &self.images,
+                                DisplayOptions {
+                                    timezone: config.settings.timezone,
+                                    me: self.gateway_state.user(),
+                                    collapse_over: config.settings.collapse_messages_over(),
+                                    own_messages: config.settings.own_messages,
+                                    play_animations: config.settings.play_animations,
+                                    bookmarks: self.bookmarks(config),
+                                    dense: self
+                                        .selected_channel(config)
+                                        .is_some_and(|channel| channel.dense),
+                                },
+                                self.history_loader.is_loading(),
+                                |idx| match (&self.find, self.flash) {
+                                    (_, Some((id, _))) if self.messages[idx].id() == id => {
+                                        Highlight::Flash
+                                    }
+                                    (Some(find), _) => find.highlight(idx, &find_matches),
+                                    (None, _) => Highlight::None,
+                                },
+                                |msg| self.plugin_decorations(msg),
+                                MainScreenMessage::HistoryMessageAction,
+                                MainScreenMessage::HistoryScrolled,
+                            ),
+                            self.jump_to_latest(),
+                        )
+                        .anchor(Anchor::South)
+                        .hide(self.at_bottom && !self.showing_context),
+                        self.profile.as_ref().map_or_else(
+                            || Element::from(widget::Space::new(0, 0)),
+                            |card| {
+                                container(card.view(&self.images, MainScreenMessage::ProfileClosed))
+                                    .padding(20)
+                                    .into()
+                            },
+                        ),
+                    )
+                    .anchor(Anchor::NorthEast)
+                    .hide(self.profile.is_none())
+                    .into(),
                 })
-                .map(MainScreenMessage::Editor),
-            ])
-            .into(),
+                .push_maybe(self.selection_strip())
+                .push_maybe(self.unsent_prompt())
+                .push_maybe(self.send_prompt(config))
+                .push_maybe(self.permission_banner())
+                .push_maybe(self.reply_banner())
+                .push_maybe(self.paste_prompt(config))
+                .push_maybe(self.attachment_strip(theme))
+                .push(
+                    Element::from({
+                        container({
+                            let dictionary = self
+                                .spellcheck_language(config)
+                                .and_then(|language| self.dictionaries.get(language))
+                                .cloned()
+                                .flatten();
+                            let completions = self.completions(config);
+                            let composer = row![MessageEditor::new(&self.editor)
+                                .highlight::<spellcheck::Highlighter>(
+                                    dictionary,
+                                    spellcheck::format,
+                                )
+                                .placeholder(hint)
+                                .on_action_if(editable, EditorMessage::Action)
+                                .on_enter_maybe(
+                                    Some(EditorMessage::SendInitiated)
+                                        .filter(|_| config.settings.enter_sends()),
+                                )
+                                .on_up_empty(EditorMessage::EditLastRequested)
+                                .on_long_paste(
+                                    config.settings.paste_to_file_over(),
+                                    EditorMessage::LongPasted,
+                                )
+                                .on_other_paste(EditorMessage::OtherPasted)
+                                .min_rows(config.settings.composer_min_rows())
+                                .passthrough(|key, modifiers| {
+                                    actions::from_key(key, modifiers).is_some()
+                                        || channel_shortcut(key, modifiers).is_some()
+                                })
+                                .on_tab_maybe(
+                                    completions.first().map(|(_, msg)| msg.clone()).or_else(|| {
+                                        Some(EditorMessage::NextPlaceholder).filter(|_| {
+                                            editor::has_placeholder(&self.editor.text())
+                                        })
+                                    }),
+                                )
+                                .padding(10)]
+                            .push(self.emoji_picker())
+                            .push_maybe(self.template_picker(config))
+                            .push_maybe(
+                                (!config.settings.enter_sends())
+                                    .then(|| self.send_button(editable)),
+                            )
+                            .spacing(10);
+                            column![]
+                                .push_maybe(Self::completion_strip(&completions))
+                                .push(composer)
+                                .spacing(5)
+                        })
+                        .padding(10)
+                    })
+                    .map(MainScreenMessage::Editor),
+                )
+                .into(),
         ])
         .width(Length::Fill)
         .height(Length::Fill);
 
-        match &self.gateway_state {
-            GatewayState::Connected { .. } => el.into(),
-            GatewayState::Disconnected { error } => {
-                let row = match error {
-                    Some(err) => connecting_indicator(DISCONNECTED, ErrorWithCauses(err), |t| {
-                        t.extended_palette().danger.base
-                    }),
-                    None => connecting_indicator(CONNECTING, "Connecting...", |t| {
-                        t.extended_palette().background.strong
-                    }),
-                };
-                column![row, el]
-                    .height(Length::Fill)
-                    .width(Length::Fill)
-                    .into()
+        let error = match &self.gateway_state {
+            GatewayState::Connected { .. } => return el.into(),
+            GatewayState::Disconnected { error } => error.as_ref(),
+            GatewayState::ServerBusy => None,
+        };
+        let details = column![details_toggle(
+            self.error_details,
+            MainScreenMessage::ErrorDetailsToggled
+        )]
+        .push_maybe(
+            error
+                .filter(|_| self.error_details)
+                .map(|err| text(ErrorWithCauses(err)).size(12)),
+        )
+        .push_maybe(self.error_details.then(|| {
+            self.connection_log.view(
+                config.settings.timezone,
+                MainScreenMessage::ConnectionLogCopied,
+            )
+        }))
+        .push_maybe(self.connection_timers())
+        .align_items(iced::Alignment::Center)
+        .spacing(5);
+
+        let banner = match (&self.gateway_state, error) {
+            (_, Some(err)) => {
+                connecting_indicator(DISCONNECTED, ErrorSummary(err), Some(details.into()), |t| {
+                    t.extended_palette().danger.base
+                })
             }
-        }
+            (GatewayState::ServerBusy, None) => connecting_indicator(
+                CONNECTING,
+                "Server busy, retrying…",
+                Some(details.into()),
+                |t| t.extended_palette().background.strong,
+            ),
+            (_, None) => {
+                connecting_indicator(CONNECTING, "Connecting...", Some(details.into()), |t| {
+                    t.extended_palette().background.strong
+                })
+            }
+        };
+
+        column![banner, el]
+            .height(Length::Fill)
+            .width(Length::Fill)
+            .into()
     }
 
-    pub fn subscription(&self) -> iced::Subscription<MainScreenMessage> {
-        gateway::connect(self.server.clone(), self.http.token().unwrap().to_string())
-            .map(MainScreenMessage::Gateway)
+    /// The gateway connection this account needs. The application makes one
+    /// per key and hands its messages over as [`MainScreenMessage::Gateway`].
+    pub fn connection(&self) -> (ConnectionKey, ConnectOptions) {
+        let key = ConnectionKey {
+            url: self.gateway_url.clone(),
+            token: self.http.http().token().unwrap().to_string(),
+        };
+        (key, self.connect_options.clone())
+    }
+
+    pub fn subscription(&self, config: &Config) -> iced::Subscription<MainScreenMessage> {
+        let backup = &config.settings.backup;
+        iced::Subscription::batch([
+            if backup.enabled {
+                let interval = Duration::from_secs(backup.interval_minutes.max(1) * 60);
+                iced::time::every(interval).map(|_| MainScreenMessage::BackupDue)
+            } else {
+                iced::Subscription::none()
+            },
+            if self.read_sync && !self.unsynced_reads.is_empty() {
+                iced::time::every(READ_SYNC_INTERVAL).map(|_| MainScreenMessage::ReadMarkersDue)
+            } else {
+                iced::Subscription::none()
+            },
+            if !self.unacked.is_empty() {
+                iced::time::every(ACK_DEBOUNCE).map(|_| MainScreenMessage::AcksDue)
+            } else {
+                iced::Subscription::none()
+            },
+            if self.active && self.images.is_animating(config.settings.play_animations) {
+                iced::time::every(animation::FRAME_TICK)
+                    .map(|now| MainScreenMessage::Image(ImageMessage::Tick(now)))
+            } else {
+                iced::Subscription::none()
+            },
+            if self.draft_changed_at.is_some() {
+                iced::time::every(DRAFT_SAVE_DELAY).map(|_| MainScreenMessage::DraftsDue)
+            } else {
+                iced::Subscription::none()
+            },
+            // the gateway reconnecting flushes the outbox, but it may never have gone down
+            if matches!(self.gateway_state, GatewayState::Connected { .. })
+                && !self.flushing_outbox
+                && !self.outbox(config).is_empty()
+            {
+                iced::time::every(OUTBOX_RETRY).map(|_| MainScreenMessage::OutboxDue)
+            } else {
+                iced::Subscription::none()
+            },
+            if self
+                .subscriptions
+                .values()
+                .any(|state| matches!(state, SubscriptionState::Pending(_)))
+            {
+                iced::time::every(SUBSCRIBE_TIMEOUT).map(|_| MainScreenMessage::SubscriptionsDue)
+            } else {
+                iced::Subscription::none()
+            },
+            if self.flash.is_some() {
+                iced::time::every(FLASH_DURATION).map(|_| MainScreenMessage::FlashDue)
+            } else {
+                iced::Subscription::none()
+            },
+            if self.active && self.disconnected_since.is_some() {
+                iced::time::every(STATUS_TICK).map(|_| MainScreenMessage::StatusTick)
+            } else {
+                iced::Subscription::none()
+            },
+            // accounts in the background aren't on screen to search in
+            if self.active && !self.covered {
+                keyboard::on_key_press(|key, modifiers| channel_shortcut(&key, modifiers))
+            } else {
+                iced::Subscription::none()
+            },
+            iced::event::listen_with(|event, _| match event {
+                iced::Event::Window(_, window::Event::Focused) => {
+                    Some(MainScreenMessage::WindowFocusChanged(true))
+                }
+                iced::Event::Window(_, window::Event::Unfocused) => {
+                    Some(MainScreenMessage::WindowFocusChanged(false))
+                }
+                _ => None,
+            }),
+        ])
     }
 }
@@ -0,0 +1,945 @@
+//! A small markdown tokenizer covering the subset of markdown people
+//! actually use in chat messages.
+//!
+//! Every line of a message is its own block, except for fenced code blocks.
+//! All text borrows from the source, so tokenizing is cheap. Still, messages
+//! are drawn far more often than they change, so their tokens are kept as
+//! [`Tokens`] instead of tokenizing them on every frame. The same goes for the
+//! syntax highlighting of code blocks, which is only done once a block is shown
+//! with a theme it wasn't highlighted for yet.
+
+use std::cell::OnceCell;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use iced::font::{Style as FontStyle, Weight};
+use iced::widget::{button, container, horizontal_space, row, text, Column};
+use iced::{theme, Alignment, Border, Color, Element, Font, Length, Theme};
+use iced_aw::Wrap;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use url::Url;
+
+use crate::messageview::details_tooltip;
+
+/// A link that was clicked. `text_differs` is set if it was shown as something other
+/// than its target, which may have made it look like it leads somewhere else.
+#[derive(Debug, Clone)]
+pub struct ClickedLink {
+    pub url: String,
+    pub text_differs: bool,
+}
+
+/// Inline formatting applied to a [`Span`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+}
+
+/// A run of text sharing the same formatting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub style: Style,
+    /// The link target, if the span is part of a link.
+    pub link: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block<'a> {
+    /// A regular line of text. Empty lines have no spans.
+    Line(Vec<Span<'a>>),
+    Heading(u8, Vec<Span<'a>>),
+    Quote(Vec<Span<'a>>),
+    CodeBlock {
+        lang: Option<&'a str>,
+        code: &'a str,
+    },
+}
+
+const FENCE: &str = "```";
+
+/// Code blocks are split into texts of this many lines, so that a huge block
+/// isn't shaped as one piece. All of them are still laid out and drawn.
+const CODE_CHUNK_LINES: usize = 50;
+
+/// Code blocks longer than this aren't highlighted, as every colored
+/// word is a widget of its own.
+const HIGHLIGHT_MAX_LINES: usize = 200;
+
+/// The colored runs of each line of a code block, as byte ranges of the line.
+type LineColors = Vec<Vec<(Range<usize>, Color)>>;
+
+/// How a code block is highlighted for either kind of theme, once it has been
+/// shown with it. `None` inside if it can't be highlighted.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CodeColors {
+    dark: OnceCell<Option<LineColors>>,
+    light: OnceCell<Option<LineColors>>,
+}
+
+impl CodeColors {
+    /// The highlighting of `code` for a dark or light theme, made on first use.
+    /// `None` if the fence doesn't name a language that syntect knows, or if the
+    /// block is too long.
+    fn get(&self, info: &str, code: &str, dark: bool) -> Option<&LineColors> {
+        let (cell, theme) = match dark {
+            true => (&self.dark, "base16-mocha.dark"),
+            false => (&self.light, "InspiredGitHub"),
+        };
+        cell.get_or_init(|| {
+            if code.lines().count() > HIGHLIGHT_MAX_LINES {
+                return None;
+            }
+            // the info string may go on after the language, as in "rust,ignore" or "py title"
+            let lang = info
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .next()
+                .filter(|lang| !lang.is_empty())?;
+            highlight(code, lang, theme)
+        })
+        .as_ref()
+    }
+}
+
+/// A [`Span`] with its text stored as byte ranges of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SpanRange {
+    text: Range<usize>,
+    style: Style,
+    link: Option<Range<usize>>,
+}
+
+/// A [`Block`] with its text stored as byte ranges of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BlockRange {
+    Line(Vec<SpanRange>),
+    Heading(u8, Vec<SpanRange>),
+    Quote(Vec<SpanRange>),
+    CodeBlock {
+        lang: Option<Range<usize>>,
+        code: Range<usize>,
+    },
+}
+
+/// The tokens of a message, without borrowing from it so that they can be
+/// kept around. They're only valid for the source they were made from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tokens {
+    blocks: Vec<BlockRange>,
+    /// Whether none of the blocks has any formatting.
+    plain: bool,
+    /// The highlighting of each code block, in order.
+    code: Vec<CodeColors>,
+}
+
+/// Where `part`, a slice of `src`, lies in it.
+fn range_in(src: &str, part: &str) -> Range<usize> {
+    // empty slices may come from elsewhere, and all of them look the same anyway
+    if part.is_empty() {
+        return 0..0;
+    }
+    let start = part.as_ptr() as usize - src.as_ptr() as usize;
+    start..start + part.len()
+}
+
+impl Tokens {
+    pub fn new(src: &str) -> Self {
+        let spans = |spans: Vec<Span<'_>>| {
+            spans
+                .into_iter()
+                .map(|span| SpanRange {
+                    text: range_in(src, span.text),
+                    style: span.style,
+                    link: span.link.map(|link| range_in(src, link)),
+                })
+                .collect()
+        };
+
+        let blocks = parse(src);
+        // escapes leave unstyled spans behind, but the backslashes mustn't be shown
+        let plain = !src.contains('\\') && blocks.iter().all(Block::is_plain);
+        let code = blocks
+            .iter()
+            .filter(|block| matches!(block, Block::CodeBlock { .. }))
+            .map(|_| CodeColors::default())
+            .collect();
+        let blocks = blocks
+            .into_iter()
+            .map(|block| match block {
+                Block::Line(s) => BlockRange::Line(spans(s)),
+                Block::Heading(level, s) => BlockRange::Heading(level, spans(s)),
+                Block::Quote(s) => BlockRange::Quote(spans(s)),
+                Block::CodeBlock { lang, code } => BlockRange::CodeBlock {
+                    lang: lang.map(|lang| range_in(src, lang)),
+                    code: range_in(src, code),
+                },
+            })
+            .collect();
+
+        Self {
+            blocks,
+            plain,
+            code,
+        }
+    }
+
+    /// The blocks of `src`, which has to be the source the tokens were made from.
+    pub fn blocks<'a>(&self, src: &'a str) -> Vec<Block<'a>> {
+        let spans = |spans: &[SpanRange]| {
+            spans
+                .iter()
+                .map(|span| Span {
+                    text: &src[span.text.clone()],
+                    style: span.style,
+                    link: span.link.clone().map(|link| &src[link]),
+                })
+                .collect()
+        };
+
+        self.blocks
+            .iter()
+            .map(|block| match block {
+                BlockRange::Line(s) => Block::Line(spans(s)),
+                BlockRange::Heading(level, s) => Block::Heading(*level, spans(s)),
+                BlockRange::Quote(s) => Block::Quote(spans(s)),
+                BlockRange::CodeBlock { lang, code } => Block::CodeBlock {
+                    lang: lang.clone().map(|lang| &src[lang]),
+                    code: &src[code.clone()],
+                },
+            })
+            .collect()
+    }
+
+    /// The targets of the links in `src`, in order, each once.
+    pub fn links<'a>(&self, src: &'a str) -> Vec<&'a str> {
+        let mut links: Vec<&str> = Vec::new();
+        for block in &self.blocks {
+            let spans = match block {
+                BlockRange::Line(s) | BlockRange::Heading(_, s) | BlockRange::Quote(s) => s,
+                BlockRange::CodeBlock { .. } => continue,
+            };
+            for link in spans.iter().filter_map(|span| span.link.clone()) {
+                let link = &src[link];
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+        }
+        links
+    }
+}
+
+/// Tokenizes a message.
+pub fn parse(src: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut lines = src.lines();
+
+    while let Some(line) = lines.next() {
+        if let Some(lang) = line.trim_start().strip_prefix(FENCE) {
+            let lang = Some(lang.trim()).filter(|l| !l.is_empty());
+            let mut code: Option<&str> = None;
+            for line in lines.by_ref() {
+                if line.trim() == FENCE {
+                    break;
+                }
+                // lines are slices of `src`, so they can be joined by extending the slice
+                code = Some(match code {
+                    Some(code) => extend_to(src, code, line),
+                    None => line,
+                });
+            }
+
+            blocks.push(Block::CodeBlock {
+                lang,
+                code: code.unwrap_or_default(),
+            });
+        } else if let Some((level, text)) = heading(line) {
+            blocks.push(Block::Heading(level, parse_inline(text)));
+        } else if let Some(text) = line.strip_prefix('>') {
+            blocks.push(Block::Quote(parse_inline(text.trim_start())));
+        } else {
+            blocks.push(Block::Line(parse_inline(line)));
+        }
+    }
+
+    blocks
+}
+
+/// Returns the slice of `src` spanning from the start of `first` to the end of `last`.
+fn extend_to<'a>(src: &'a str, first: &'a str, last: &'a str) -> &'a str {
+    let start = first.as_ptr() as usize - src.as_ptr() as usize;
+    let end = last.as_ptr() as usize - src.as_ptr() as usize + last.len();
+    &src[start..end]
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6)
+        .contains(&level)
+        .then_some((level as u8, text.trim()))
+}
+
+/// Parses inline formatting.
+pub fn parse_inline(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    parse_inline_into(text, Style::default(), None, &mut spans);
+    spans
+}
+
+fn push_span<'a>(spans: &mut Vec<Span<'a>>, text: &'a str, style: Style, link: Option<&'a str>) {
+    if !text.is_empty() {
+        spans.push(Span { text, style, link });
+    }
+}
+
+/// Splits `[text](url)` off the start of `s`, returning the text, the url
+/// and the length of the whole link.
+fn link(s: &str) -> Option<(&str, &str, usize)> {
+    let rest = s.strip_prefix('[')?;
+    let text_end = rest.find("](")?;
+    let url_start = text_end + 2;
+    let url_len = rest[url_start..].find(')')?;
+    let url = &rest[url_start..url_start + url_len];
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((&rest[..text_end], url, 1 + url_start + url_len + 1))
+}
+
+/// Splits a bare `http(s)://` URL off the start of `s`.
+/// Punctuation at the end is assumed to belong to the surrounding sentence,
+/// except for closing parentheses that the URL opened, as in Wikipedia links.
+fn bare_url(s: &str) -> Option<&str> {
+    if !s.starts_with("https://") && !s.starts_with("http://") {
+        return None;
+    }
+
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    let mut url = &s[..end];
+    loop {
+        url = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"']);
+        match url.strip_suffix(')') {
+            Some(rest) if url.matches('(').count() < url.matches(')').count() => url = rest,
+            _ => break,
+        }
+    }
+    Some(url).filter(|url| !url.ends_with("//"))
+}
+
+fn parse_inline_into<'a>(
+    text: &'a str,
+    mut style: Style,
+    link_target: Option<&'a str>,
+    spans: &mut Vec<Span<'a>>,
+) {
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+
+        if let Some(escaped) = rest
+            .strip_prefix('\\')
+            .and_then(|r| r.chars().next())
+            .filter(char::is_ascii_punctuation)
+        {
+            push_span(spans, &text[start..i], style, link_target);
+            start = i + 1;
+            i += 1 + escaped.len_utf8();
+            continue;
+        }
+
+        if let Some(end) = rest.strip_prefix('`').and_then(|r| r.find('`')) {
+            push_span(spans, &text[start..i], style, link_target);
+            let code = Style {
+                code: true,
+                ..style
+            };
+            push_span(spans, &rest[1..1 + end], code, link_target);
+            i += end + 2;
+            start = i;
+            continue;
+        }
+
+        if link_target.is_none() {
+            if let Some((link_text, url, len)) = link(rest) {
+                push_span(spans, &text[start..i], style, link_target);
+                parse_inline_into(link_text, style, Some(url), spans);
+                i += len;
+                start = i;
+                continue;
+            }
+
+            let prev = text[..i].chars().next_back();
+            if let Some(url) = bare_url(rest).filter(|_| !prev.is_some_and(char::is_alphanumeric)) {
+                push_span(spans, &text[start..i], style, link_target);
+                push_span(spans, url, style, Some(url));
+                i += url.len();
+                start = i;
+                continue;
+            }
+        }
+
+        if let Some((len, toggled)) = toggle_delimiter(text, i, style) {
+            push_span(spans, &text[start..i], style, link_target);
+            style = toggled;
+            i += len;
+            start = i;
+            continue;
+        }
+
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+
+    push_span(spans, &text[start..], style, link_target);
+}
+
+/// Handles an emphasis delimiter at `text[i..]`, returning its length and the
+/// style of the text following it. Delimiters without a partner are left alone.
+fn toggle_delimiter(text: &str, i: usize, mut style: Style) -> Option<(usize, Style)> {
+    let rest = &text[i..];
+    let (delim, active) = if rest.starts_with("**") {
+        ("**", style.bold)
+    } else if rest.starts_with("~~") {
+        ("~~", style.strikethrough)
+    } else if rest.starts_with('*') {
+        ("*", style.italic)
+    } else if rest.starts_with('_') {
+        ("_", style.italic)
+    } else {
+        return None;
+    };
+
+    let after = &rest[delim.len()..];
+    let prev = text[..i].chars().next_back();
+    let next = after.chars().next();
+
+    let valid = if active {
+        !prev.is_some_and(char::is_whitespace)
+    } else {
+        after.contains(delim) && !next.is_some_and(char::is_whitespace)
+    };
+    // underscores inside words (snake_case) aren't emphasis
+    let intraword = delim == "_"
+        && prev.is_some_and(char::is_alphanumeric)
+        && next.is_some_and(char::is_alphanumeric);
+
+    if !valid || intraword {
+        return None;
+    }
+
+    match delim {
+        "**" => style.bold = !style.bold,
+        "~~" => style.strikethrough = !style.strikethrough,
+        _ => style.italic = !style.italic,
+    }
+
+    Some((delim.len(), style))
+}
+
+/// Renders the tokens back as text with all formatting stripped.
+/// Links whose text differs from their target keep the target in parentheses.
+pub fn to_plain_text(blocks: &[Block<'_>]) -> String {
+    fn push_spans(out: &mut String, spans: &[Span<'_>]) {
+        let mut iter = spans.iter().peekable();
+        while let Some(span) = iter.next() {
+            out.push_str(span.text);
+
+            let Some(target) = span.link else {
+                continue;
+            };
+            let link_continues = iter.peek().is_some_and(|next| next.link == Some(target));
+            if !link_continues && span.text != target {
+                out.push_str(&format!(" ({target})"));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+
+        match block {
+            Block::Line(spans) | Block::Heading(_, spans) | Block::Quote(spans) => {
+                push_spans(&mut out, spans)
+            }
+            Block::CodeBlock { code, .. } => out.push_str(code),
+        }
+    }
+
+    out
+}
+
+impl Block<'_> {
+    /// Whether the block renders the same as its source text.
+    fn is_plain(&self) -> bool {
+        match self {
+            Block::Line(spans) => spans
+                .iter()
+                .all(|span| span.style == Style::default() && span.link.is_none()),
+            _ => false,
+        }
+    }
+}
+
+/// Renders a message from its `tokens`. Messages without any formatting are
+/// shown as a single text widget, as that lays out better than a run of spans.
+/// Code blocks get a button that copies them with `on_copy`.
+pub fn view<'a, Message: Clone + 'a>(
+    src: &'a str,
+    tokens: &Tokens,
+    color: Color,
+    theme: &Theme,
+    on_link: fn(ClickedLink) -> Message,
+    on_copy: fn(String) -> Message,
+) -> Element<'a, Message> {
+    if tokens.plain {
+        return plain(src, color);
+    }
+
+    let mut code_colors = tokens.code.iter();
+    Column::with_children(tokens.blocks(src).into_iter().map(|block| {
+        match block {
+            Block::Line(spans) if spans.is_empty() => text("").into(),
+            Block::Line(spans) => inline(&spans, 16, color, theme, on_link),
+            Block::Heading(level, spans) => {
+                let size = match level {
+                    1 => 24,
+                    2 => 20,
+                    3 => 18,
+                    _ => 16,
+                };
+                inline(&spans, size, color, theme, on_link)
+            }
+            Block::Quote(spans) => container(inline(
+                &spans,
+                16,
+                Color { a: 0.7, ..color },
+                theme,
+                on_link,
+            ))
+            .padding([0, 0, 0, 12])
+            .into(),
+            Block::CodeBlock { lang, code } => {
+                let colors = code_colors.next().zip(lang).and_then(|(colors, lang)| {
+                    colors.get(lang, code, theme.extended_palette().is_dark)
+                });
+                let lines = match colors {
+                    Some(colors) => highlighted_code(code, colors, color),
+                    None => {
+                        Column::with_children(line_chunks(code, CODE_CHUNK_LINES).map(|chunk| {
+                            text(chunk)
+                                .font(Font::MONOSPACE)
+                                .size(14)
+                                .style(theme::Text::Color(color))
+                                .shaping(text::Shaping::Advanced)
+                                .into()
+                        }))
+                    }
+                };
+                let header = row![
+                    text(lang.unwrap_or_default())
+                        .size(12)
+                        .style(theme::Text::Color(Color { a: 0.7, ..color })),
+                    horizontal_space(),
+                    button(text("Copy").size(12))
+                        .style(theme::Button::Text)
+                        .padding(0)
+                        .on_press(on_copy(code.to_string())),
+                ]
+                .align_items(Alignment::Center);
+
+                container(Column::new().push(header).push(lines).spacing(4))
+                    .style(code_background)
+                    .padding(8)
+                    .width(Length::Fill)
+                    .into()
+            }
+        }
+    }))
+    .spacing(2)
+    .width(Length::Fill)
+    .into()
+}
+
+/// The syntaxes that come with syntect. Loading them takes a while, so it's done once.
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_nonewlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Colors every line of `code` as `lang` with syntect's theme named `theme`. Whitespace
+/// is merged into the run after it, as that's where it's drawn. `None` if the language is
+/// unknown.
+fn highlight(code: &str, lang: &str, theme: &str) -> Option<LineColors> {
+    let syntax = syntaxes().find_syntax_by_token(lang)?;
+    let mut highlighter = HighlightLines::new(syntax, &themes().themes[theme]);
+    code.lines()
+        .map(|line| {
+            let mut runs: Vec<(Range<usize>, Color)> = Vec::new();
+            let mut start = None;
+            let mut end = 0;
+            for (style, part) in highlighter.highlight_line(line, syntaxes()).ok()? {
+                let range = end..end + part.len();
+                end = range.end;
+                if part.trim().is_empty() {
+                    start.get_or_insert(range.start);
+                    continue;
+                }
+                let start = start.take().unwrap_or(range.start);
+                let fg = style.foreground;
+                let color = Color::from_rgba8(fg.r, fg.g, fg.b, f32::from(fg.a) / 255.0);
+                runs.push((start..range.end, color));
+            }
+            Some(runs)
+        })
+        .collect()
+}
+
+/// The colored runs of each line, laid out word by word so that long lines still wrap.
+/// Whatever the highlighter left uncolored is in `color`.
+fn highlighted_code<'a, Message: 'a>(
+    code: &'a str,
+    colors: &LineColors,
+    color: Color,
+) -> Column<'a, Message> {
+    let words = |elements: &mut Vec<Element<'a, Message>>, run: &'a str, color| {
+        elements.extend(run.split_inclusive(' ').map(|word| {
+            text(word)
+                .font(Font::MONOSPACE)
+                .size(14)
+                .style(theme::Text::Color(color))
+                .shaping(text::Shaping::Advanced)
+                .into()
+        }));
+    };
+
+    Column::with_children(code.lines().zip(colors).map(|(line, runs)| {
+        let mut elements = Vec::new();
+        if line.is_empty() {
+            words(&mut elements, " ", color);
+        }
+        let mut end = 0;
+        for (range, run_color) in runs {
+            if range.start > end {
+                words(&mut elements, &line[end..range.start], color);
+            }
+            words(&mut elements, &line[range.clone()], *run_color);
+            end = range.end;
+        }
+        if end < line.len() {
+            words(&mut elements, &line[end..], color);
+        }
+        Wrap::with_elements(elements).into()
+    }))
+}
+
+/// Splits `code` into runs of at most `lines` lines, leaving out the newlines between them.
+fn line_chunks(code: &str, lines: usize) -> impl Iterator<Item = &str> {
+    let mut rest = code;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .match_indices('\n')
+            .nth(lines - 1)
+            .map_or(rest.len(), |(i, _)| i);
+        let chunk = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or("");
+        Some(chunk)
+    })
+}
+
+fn plain<'a, Message: 'a>(src: &'a str, color: Color) -> Element<'a, Message> {
+    text(src)
+        .style(theme::Text::Color(color))
+        .shaping(text::Shaping::Advanced)
+        .width(Length::Fill)
+        .into()
+}
+
+/// Lays out spans word by word, so that long lines still wrap.
+fn inline<'a, Message: Clone + 'a>(
+    spans: &[Span<'a>],
+    size: u16,
+    color: Color,
+    theme: &Theme,
+    on_link: fn(ClickedLink) -> Message,
+) -> Element<'a, Message> {
+    let words = spans.iter().flat_map(|span| {
+        span.text
+            .split_inclusive(' ')
+            .map(move |word| word_view(word, span, size, color, theme, on_link))
+    });
+
+    Wrap::with_elements(words.collect()).into()
+}
+
+fn word_view<'a, Message: Clone + 'a>(
+    word: &'a str,
+    span: &Span<'a>,
+    size: u16,
+    color: Color,
+    theme: &Theme,
+    on_link: fn(ClickedLink) -> Message,
+) -> Element<'a, Message> {
+    let mut font = crate::DEFAULT_FONT;
+    if span.style.bold {
+        font.weight = Weight::Bold;
+    }
+    if span.style.italic {
+        font.style = FontStyle::Italic;
+    }
+    if span.style.code {
+        font = Font {
+            family: Font::MONOSPACE.family,
+            ..font
+        };
+    }
+
+    let color = match span.link {
+        Some(_) => theme.palette().primary,
+        None => color,
+    };
+    let word = text(word)
+        .font(font)
+        .size(size)
+        .style(theme::Text::Color(color))
+        .shaping(text::Shaping::Advanced);
+
+    let word: Element<'a, Message> = if span.style.code {
+        container(word).style(code_background).into()
+    } else {
+        word.into()
+    };
+
+    match span.link {
+        Some(url) => details_tooltip(
+            button(word)
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(on_link(ClickedLink {
+                    url: url.to_string(),
+                    text_differs: span.text != url,
+                })),
+            link_details(url),
+        ),
+        None => word,
+    }
+}
+
+/// Where a link really leads, for its tooltip. The host gets a line of its own, and
+/// internationalized ones are spelled out in ASCII, so lookalikes are easier to spot.
+fn link_details(target: &str) -> Vec<String> {
+    match Url::parse(target) {
+        Ok(url) => match url.host_str() {
+            Some(host) => vec![format!("Opens {host}"), url.to_string()],
+            None => vec![url.to_string()],
+        },
+        Err(_) => vec![target.to_string()],
+    }
+}
+
+fn code_background(theme: &Theme) -> container::Appearance {
+    container::Appearance {
+        background: Some(theme.extended_palette().background.weak.color.into()),
+        border: Border::with_radius(3),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str) -> Span<'_> {
+        Span {
+            text,
+            style: Style::default(),
+            link: None,
+        }
+    }
+
+    fn styled(text: &str, style: Style) -> Span<'_> {
+        Span {
+            text,
+            style,
+            link: None,
+        }
+    }
+
+    const BOLD: Style = Style {
+        bold: true,
+        italic: false,
+        strikethrough: false,
+        code: false,
+    };
+    const CODE: Style = Style {
+        bold: false,
+        italic: false,
+        strikethrough: false,
+        code: true,
+    };
+
+    #[test]
+    fn test_blocks() {
+        let src = "# Title\n> quoted **bold**\n```rust\nfn main() {}\n\nlet x;\n```\nafter";
+        assert_eq!(
+            parse(src),
+            [
+                Block::Heading(1, vec![span("Title")]),
+                Block::Quote(vec![span("quoted "), styled("bold", BOLD)]),
+                Block::CodeBlock {
+                    lang: Some("rust"),
+                    code: "fn main() {}\n\nlet x;",
+                },
+                Block::Line(vec![span("after")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_code_block() {
+        assert_eq!(
+            parse("```\nstill code"),
+            [Block::CodeBlock {
+                lang: None,
+                code: "still code",
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fence_mid_line() {
+        // only fences at the start of a line open a code block
+        assert_eq!(
+            parse("see ```code``` here\nnext"),
+            [
+                Block::Line(vec![span("see "), styled("code", CODE), span(" here")]),
+                Block::Line(vec![span("next")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(parse_inline("snake_case_name"), [span("snake_case_name")]);
+        assert_eq!(parse_inline("2 * 3 * 4"), [span("2 * 3 * 4")]);
+        assert_eq!(
+            parse_inline(r"\*not italic\*"),
+            [span("*not italic"), span("*")]
+        );
+        assert_eq!(
+            parse_inline("**bold** text"),
+            [styled("bold", BOLD), span(" text")]
+        );
+    }
+
+    #[test]
+    fn test_bare_url() {
+        assert_eq!(
+            bare_url("https://example.com/a, b"),
+            Some("https://example.com/a")
+        );
+        assert_eq!(
+            bare_url("https://example.com/a)."),
+            Some("https://example.com/a")
+        );
+        assert_eq!(
+            bare_url("https://en.wikipedia.org/wiki/Rust_(programming_language)."),
+            Some("https://en.wikipedia.org/wiki/Rust_(programming_language)")
+        );
+        assert_eq!(bare_url("https://"), None);
+        assert_eq!(bare_url("ftp://example.com"), None);
+    }
+
+    #[test]
+    fn test_to_plain_text() {
+        let src = "# **Hi** _there_\n> see [the docs](https://example.com) or https://example.com/a\n```\nlet *x* = 1;\n```";
+        assert_eq!(
+            to_plain_text(&parse(src)),
+            "Hi there\nsee the docs (https://example.com) or https://example.com/a\nlet *x* = 1;"
+        );
+        // a link split into several spans gets its target once, at the end
+        assert_eq!(
+            to_plain_text(&parse("[**a**b](https://example.com)")),
+            "ab (https://example.com)"
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        let src = "https://a.example and [again](https://a.example), xhttps://b.example\n```\nhttps://c.example\n```\n> [**b**](https://d.example)";
+        assert_eq!(
+            Tokens::new(src).links(src),
+            ["https://a.example", "https://d.example"]
+        );
+        assert_eq!(
+            parse_inline("`https://a.example`"),
+            [styled("https://a.example", CODE)]
+        );
+        assert_eq!(parse_inline("[x](not a url)"), [span("[x](not a url)")]);
+    }
+
+    #[test]
+    fn test_tokens_round_trip() {
+        for src in [
+            "",
+            "plain text\nover two lines",
+            "# Title\n> **bold** _it_ ~~gone~~ `code`\n[link](https://example.com) https://example.com\n```rust\nfn main() {}\n```",
+            "```\nunclosed",
+            r"\*escaped\*",
+        ] {
+            assert_eq!(Tokens::new(src).blocks(src), parse(src), "{src:?}");
+        }
+    }
+
+    #[test]
+    fn test_tokens_plain() {
+        assert!(Tokens::new("just some text\nand more").plain);
+        assert!(Tokens::new("2 * 3 * 4").plain);
+        assert!(!Tokens::new("**bold**").plain);
+        assert!(!Tokens::new("> quote").plain);
+        assert!(!Tokens::new("see https://example.com").plain);
+        assert!(!Tokens::new(r"\*not italic\*").plain);
+    }
+
+    #[test]
+    fn test_code_highlighted_when_shown() {
+        let tokens = Tokens::new("```rust\nfn main() {}\n```");
+        let colors = &tokens.code[0];
+        assert!(colors.dark.get().is_none());
+
+        assert!(colors.get("rust", "fn main() {}", true).is_some());
+        assert!(colors.dark.get().is_some());
+        assert!(colors.light.get().is_none());
+
+        let unknown = CodeColors::default();
+        assert!(unknown.get("not-a-language", "x", false).is_none());
+    }
+
+    #[test]
+    fn test_line_chunks() {
+        let chunks = |code, lines| line_chunks(code, lines).collect::<Vec<_>>();
+        assert_eq!(chunks("a\nb\nc\nd\ne", 2), ["a\nb", "c\nd", "e"]);
+        assert_eq!(chunks("a\nb", 2), ["a\nb"]);
+        assert_eq!(chunks("a\nb\n", 2), ["a\nb"]);
+        assert_eq!(chunks("a\n\nb", 1), ["a", "", "b"]);
+        assert!(chunks("", 2).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Mentioning users as `@name`, which is how the composer completes them
+//! and how messages mentioning the logged in user are recognized.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use quaddlecl::model::channel::ChannelId;
+use quaddlecl::model::message::{Message, MessageId};
+use quaddlecl::model::user::{User, UserId};
+
+/// How many authors are remembered per channel.
+const RECENT_AUTHORS: usize = 50;
+
+/// How a mention of `name` is written in a message.
+pub fn format(name: &str) -> String {
+    format!("@{name}")
+}
+
+/// Whether `content` mentions `name`. The mention has to stand on its own,
+/// so that `@meow` doesn't count as a mention of `meo`.
+pub fn mentions(content: &str, name: &str) -> bool {
+    let mention = format(name);
+    content.match_indices(&mention).any(|(idx, _)| {
+        let before = content[..idx].chars().next_back();
+        let after = content[idx + mention.len()..].chars().next();
+        !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+    })
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Users whose name matches `query`, ignoring case: those starting with it first,
+/// then those only containing it. `users` should come most recently active first;
+/// that order is kept, and each user only shows up once.
+pub fn candidates<'a>(
+    users: impl IntoIterator<Item = &'a User>,
+    query: &str,
+    exclude: Option<UserId>,
+) -> Vec<&'a User> {
+    let query = query.to_lowercase();
+    let mut seen = HashSet::new();
+    let (mut prefixed, contained): (Vec<_>, Vec<_>) = users
+        .into_iter()
+        .filter(|user| Some(user.id) != exclude && seen.insert(user.id))
+        .filter(|user| user.name.to_lowercase().contains(&query))
+        .partition(|user| user.name.to_lowercase().starts_with(&query));
+    prefixed.extend(contained);
+    prefixed
+}
+
+/// Who wrote last in each channel, going by the messages that were loaded or
+/// arrived over the gateway, so that the composer can suggest them first.
+#[derive(Debug, Default)]
+pub struct RecentAuthors {
+    /// The latest message of each author and the author as of that message, newest first.
+    channels: HashMap<ChannelId, Vec<(MessageId, User)>>,
+}
+
+impl RecentAuthors {
+    /// Takes note of who wrote `messages` in `channel_id`, in whatever order they come.
+    pub fn record<'a>(
+        &mut self,
+        channel_id: ChannelId,
+        messages: impl IntoIterator<Item = &'a Message>,
+    ) {
+        let authors = self.channels.entry(channel_id).or_default();
+        for msg in messages {
+            match authors
+                .iter_mut()
+                .find(|(_, user)| user.id == msg.author.id)
+            {
+                Some(latest) if latest.0 < msg.id => *latest = (msg.id, msg.author.clone()),
+                Some(_) => {}
+                None => authors.push((msg.id, msg.author.clone())),
+            }
+        }
+        authors.sort_by_key(|(id, _)| Reverse(*id));
+        authors.truncate(RECENT_AUTHORS);
+    }
+
+    /// Like [`candidates`], but those who wrote in `channel_id` come before
+    /// those only seen in other channels.
+    pub fn candidates(
+        &self,
+        channel_id: ChannelId,
+        query: &str,
+        exclude: Option<UserId>,
+    ) -> Vec<&User> {
+        let here = self.channels.get(&channel_id).into_iter().flatten();
+        let mut elsewhere: Vec<_> = self
+            .channels
+            .iter()
+            .filter(|(id, _)| **id != channel_id)
+            .flat_map(|(_, authors)| authors)
+            .collect();
+        elsewhere.sort_by_key(|(id, _)| Reverse(*id));
+
+        let mut found = candidates(here.map(|(_, user)| user), query, exclude);
+        let seen: HashSet<_> = found.iter().map(|user| user.id).collect();
+        let others = candidates(elsewhere.into_iter().map(|(_, user)| user), query, exclude);
+        found.extend(others.into_iter().filter(|user| !seen.contains(&user.id)));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+
+    use super::*;
+
+    #[test]
+    fn test_mentions() {
+        assert!(mentions("hi @meow!", "meow"));
+        assert!(mentions("@meow", "meow"));
+        assert!(!mentions("hi @meowmeow", "meow"));
+        assert!(!mentions("mail me at x@meow", "meow"));
+        assert!(!mentions("hi meow", "meow"));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let me = User::fake("mew");
+        let meow = User::fake("meow");
+        let homeowner = User::fake("homeowner");
+        let users = [&homeowner, &me, &meow, &homeowner];
+        let names: Vec<_> = candidates(users, "MEO", Some(me.id))
+            .into_iter()
+            .map(|user| user.name.as_str())
+            .collect();
+        assert_eq!(names, ["meow", "homeowner"]);
+    }
+
+    #[test]
+    fn test_recent_authors() {
+        let here = ChannelId(1);
+        let elsewhere = ChannelId(2);
+        let [meowth, meower, meow] = ["meowth", "meower", "meow"].map(User::fake);
+        let msg = |author: &User, channel, minutes_ago| {
+            Message::builder()
+                .author(author.clone())
+                .channel(channel)
+                .sent(Utc::now() - TimeDelta::minutes(minutes_ago))
+                .build()
+        };
+        let mut recent = RecentAuthors::default();
+        recent.record(here, &[msg(&meower, here, 1), msg(&meowth, here, 5)]);
+        // meowth wrote here too, so it comes with this channel's authors and only once
+        recent.record(
+            elsewhere,
+            &[msg(&meow, elsewhere, 3), msg(&meowth, elsewhere, 0)],
+        );
+
+        let names: Vec<_> = recent
+            .candidates(here, "meow", None)
+            .into_iter()
+            .map(|user| user.name.as_str())
+            .collect();
+        assert_eq!(names, ["meower", "meowth", "meow"]);
+    }
+}
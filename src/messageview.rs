@@ -1,15 +1,25 @@
-use std::collections::VecDeque;
+use std::cell::{Ref, RefCell};
 use std::error::Error;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 
+use crate::config::OwnMessageStyle;
 use crate::editor::MessageEditor;
-use crate::utils::{icon, ErrorWithCauses, Gaps};
-use chrono::{Local, TimeDelta};
+use crate::image_cache::{self, ImageCache, ImageSize, ImageStatus};
+use crate::markdown::{self, ClickedLink};
+use crate::mention;
+use crate::scheduler::{Priority, Scheduler};
+use crate::timezone;
+use crate::utils::{details_toggle, icon, open_link, ErrorSummary, ErrorWithCauses, Gaps};
+use chrono::{NaiveDate, TimeDelta, Utc};
+use chrono_tz::Tz;
 
-use iced::font::Weight;
-use iced::widget::scrollable::Properties;
-use iced::widget::{button, column, container, mouse_area, row, scrollable, text_editor, Row};
+use iced::widget::scrollable::{Properties, Viewport};
+use iced::widget::{
+    button, column, container, horizontal_rule, mouse_area, row, scrollable, text_editor, tooltip,
+    Row,
+};
 use iced::widget::{text, Column, Space};
 use iced::{theme, Alignment, Color, Command, Element, Font, Length, Theme};
 use iced_aw::floating_element::Anchor;
@@ -17,13 +27,28 @@ use iced_aw::FloatingElement;
 use quaddlecl::model::message::MessageId as QMessageId;
 use quaddlecl::model::user::User;
 use quaddlecl::{
-    client::http::{self, Http},
+    client::http::{self, History},
     model::{channel::ChannelId, message::Message as QMessage, snowflake::Snowflake},
 };
+use url::Url;
 
 const RESEND: &str = "\u{f0453}";
-// const DELETE: &str = "\u{f0a79}"; this will be readded when delete support drops
+const DELETE: &str = "\u{f0a79}";
 const EDIT: &str = "\u{f040}";
+const SELECT: &str = "\u{f0131}";
+const DESELECT: &str = "\u{f0132}";
+const COPY_PLAIN: &str = "\u{f0c5}";
+const COPY_MARKDOWN: &str = "\u{f0354}";
+const COPY_LINK: &str = "\u{f0c1}";
+const REPLY: &str = "\u{f112}";
+const BOOKMARK: &str = "\u{f006}";
+const BOOKMARKED: &str = "\u{f005}";
+const AVATAR_SIZE: u16 = 20;
+/// Images linked in a message are shown inline, scaled down to fit in this.
+const THUMBNAIL_WIDTH: f32 = 320.0;
+const THUMBNAIL_HEIGHT: f32 = 240.0;
+/// The height of a line in the dense view. Whatever wraps past it is cut off.
+const DENSE_LINE_HEIGHT: f32 = 20.0;
 
 #[derive(Debug, Clone)]
 pub enum HistoryQMsgMessage {
@@ -37,12 +62,48 @@ pub enum HistoryQMsgMessage {
     SendingFailed(Arc<http::Error>),
     SendingSucceeded(QMessage),
     ResendInitiated,
+    SelectionToggled,
+    CopyMarkdown,
+    CopyPlainText,
+    /// Handled by the main screen, which knows the server the link has to name.
+    LinkCopied,
+    CodeCopied(String),
+    LinkClicked(ClickedLink),
+    /// Handled by the application, which tells that strict mode copied
+    /// the link instead of opening it.
+    LinkBlocked,
+    LinkConfirmed,
+    LinkCancelled,
+    /// Handled by the main screen, which shows the author's profile.
+    AuthorClicked,
+    /// Handled by the main screen, which has the composer reply to the message.
+    ReplyInitiated,
+    /// Handled by the main screen, which jumps to the message replied to.
+    QuoteClicked,
+    /// Handled by the main screen, which shows the image at full size.
+    ImageOpened(Url),
+    /// Handled by the main screen, which starts playing the animated image.
+    ImagePlayed(Url),
+    /// Handled by the main screen, which downloads the image again.
+    ImageRetried(Url),
+    /// Handled by the main screen, which saves the message or forgets it.
+    BookmarkToggled,
+    DeleteInitiated,
+    DeleteConfirmed,
+    DeleteCancelled,
+    DeleteFailed(Arc<http::Error>),
+    DeleteSucceeded,
+    ErrorDetailsToggled,
+    /// Shows all of a message that was cut short for being long, or cuts it short again.
+    ExpandToggled,
     Editor(text_editor::Action),
 }
 
 #[derive(Debug)]
 pub enum HistoryQMsgState {
     Sending,
+    /// Sending failed for lack of a connection, so the message waits in the outbox.
+    Queued,
     SendingFailed(Arc<http::Error>),
     SubmittingEdit(text_editor::Content),
     Display,
@@ -50,6 +111,10 @@ pub enum HistoryQMsgState {
         editor: text_editor::Content,
         last_error: Option<Arc<http::Error>>,
     },
+    ConfirmingDelete,
+    /// The message is hidden while the deletion is in flight.
+    Deleting,
+    DeleteFailed(Arc<http::Error>),
 }
 
 static HISTORY_QMSG_ID: AtomicU32 = AtomicU32::new(0);
@@ -66,13 +131,95 @@ impl HistoryQMessageId {
     }
 }
 
+impl Default for HistoryQMessageId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The tokens of a message's content, along with the message and content they were made from.
+#[derive(Debug)]
+struct RenderCache {
+    key: (QMessageId, u64),
+    tokens: markdown::Tokens,
+    /// The tokens of the start of the content shown while the message is cut short,
+    /// and where it was cut. Made by the view, as that's where the cut is known.
+    preview: RefCell<Option<(usize, markdown::Tokens)>>,
+}
+
+impl RenderCache {
+    fn key(msg: &QMessage) -> (QMessageId, u64) {
+        let mut hasher = DefaultHasher::new();
+        msg.content.hash(&mut hasher);
+        (msg.id, hasher.finish())
+    }
+
+    fn new(msg: &QMessage) -> Self {
+        Self {
+            key: Self::key(msg),
+            tokens: markdown::Tokens::new(&msg.content),
+            preview: RefCell::new(None),
+        }
+    }
+
+    /// The tokens of `content` up to `cut`, made again only if it was cut elsewhere last time.
+    fn preview(&self, content: &str, cut: usize) -> Ref<'_, markdown::Tokens> {
+        let stale = !matches!(&*self.preview.borrow(), Some((at, _)) if *at == cut);
+        if stale {
+            *self.preview.borrow_mut() = Some((cut, markdown::Tokens::new(&content[..cut])));
+        }
+        Ref::map(self.preview.borrow(), |preview| {
+            &preview.as_ref().expect("the preview was just made").1
+        })
+    }
+
+    /// Tokenizes the message again if it's not the one the tokens were made from,
+    /// or its content changed.
+    fn refresh(&mut self, msg: &QMessage) {
+        if self.key != Self::key(msg) {
+            *self = Self::new(msg);
+        }
+    }
+}
+
+/// How messages are shown, going by the settings and who's logged in.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayOptions<'a> {
+    /// Times and days are shown in this timezone, or the system's if there's none.
+    pub timezone: Option<Tz>,
+    /// Messages by others that mention this user stand out from the rest.
+    pub me: Option<&'a User>,
+    /// Only the start of messages longer than this many bytes is shown until expanded.
+    pub collapse_over: usize,
+    /// How the messages of `me` stand out.
+    pub own_messages: OwnMessageStyle,
+    /// Animated images play right away, instead of once they're clicked.
+    pub play_animations: bool,
+    /// Messages the user saved, which get a filled star.
+    pub bookmarks: &'a [QMessage],
+    /// Every message is a single line, as `[HH:MM] <author> content`, without
+    /// avatars or grouping. Clicking one shows all of it.
+    pub dense: bool,
+}
+
 /// A widget that represents a Quaddle message.
 #[derive(Debug)]
 pub struct HistoryQMessage {
     id: HistoryQMessageId,
     hovered: bool,
+    selected: bool,
+    /// Whether a failure shows every cause of the error, not just its summary.
+    error_details: bool,
+    /// Whether a long message is shown in full, or in the dense view, shown at all
+    /// rather than as a single line.
+    expanded: bool,
     state: HistoryQMsgState,
     msg: QMessage,
+    /// The message this one replies to, once it's known.
+    quoted: Option<QMessage>,
+    render: RenderCache,
+    /// A link that was shown as something else, waiting for the user to confirm opening it.
+    confirming_link: Option<Url>,
 }
 
 impl HistoryQMessage {
@@ -80,23 +227,71 @@ impl HistoryQMessage {
         Self {
             id: HistoryQMessageId::new(),
             hovered: false,
+            selected: false,
+            error_details: false,
+            expanded: false,
             state: HistoryQMsgState::Display,
+            render: RenderCache::new(&msg),
             msg,
+            quoted: None,
+            confirming_link: None,
         }
     }
 
-    pub fn sending(author: User, channel: ChannelId, content: String) -> Self {
+    pub fn sending(
+        author: User,
+        channel: ChannelId,
+        content: String,
+        reply_to: Option<QMessageId>,
+    ) -> Self {
+        let mut msg = QMessage::default();
+        msg.author = author;
+        msg.channel = channel;
+        msg.content = content;
+        msg.reply_to = reply_to;
+
         Self {
             id: HistoryQMessageId::new(),
             hovered: false,
+            selected: false,
+            error_details: false,
+            expanded: false,
             state: HistoryQMsgState::Sending,
-            msg: {
-                let mut m = QMessage::default();
-                m.author = author;
-                m.channel = channel;
-                m.content = content;
-                m
-            },
+            render: RenderCache::new(&msg),
+            msg,
+            quoted: None,
+            confirming_link: None,
+        }
+    }
+
+    /// A message from the outbox, waiting to be sent once the connection is back.
+    pub fn queued(
+        author: User,
+        channel: ChannelId,
+        content: String,
+        reply_to: Option<QMessageId>,
+    ) -> Self {
+        Self {
+            state: HistoryQMsgState::Queued,
+            ..Self::sending(author, channel, content, reply_to)
+        }
+    }
+
+    pub fn is_queued(&self) -> bool {
+        matches!(self.state, HistoryQMsgState::Queued)
+    }
+
+    /// Moves a message that couldn't be sent for lack of a connection to the outbox.
+    pub fn queue(&mut self) {
+        if let HistoryQMsgState::Sending = self.state {
+            self.state = HistoryQMsgState::Queued;
+        }
+    }
+
+    /// Takes a message out of the outbox again, as it's being sent.
+    pub fn resume_sending(&mut self) {
+        if let HistoryQMsgState::Queued = self.state {
+            self.state = HistoryQMsgState::Sending;
         }
     }
 
@@ -104,15 +299,107 @@ impl HistoryQMessage {
         self.id
     }
 
+    /// Returns the underlying Quaddle message.
+    pub fn msg(&self) -> &QMessage {
+        &self.msg
+    }
+
+    /// The message this one replies to, if it isn't known yet.
+    pub fn missing_quote(&self) -> Option<QMessageId> {
+        self.msg.reply_to.filter(|_| self.quoted.is_none())
+    }
+
+    /// Shows `quoted` above the message, if it's the one it replies to.
+    pub fn set_quoted(&mut self, quoted: QMessage) {
+        if self.msg.reply_to == Some(quoted.id) {
+            self.quoted = Some(quoted);
+        }
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.selected
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected = false;
+    }
+
+    /// The links in the message that look like images.
+    pub fn images(&self) -> Vec<Url> {
+        self.render
+            .tokens
+            .links(&self.msg.content)
+            .into_iter()
+            .filter_map(|link| Url::parse(link).ok())
+            .filter(image_cache::is_image_url)
+            .collect()
+    }
+
+    /// Replaces the underlying message with a newer version of it,
+    /// e.g. after it was edited elsewhere.
+    pub fn set_msg(&mut self, msg: QMessage) {
+        self.msg = msg;
+        self.render.refresh(&self.msg);
+    }
+
+    /// Whether the message can be edited in place right now.
+    pub fn is_editable(&self) -> bool {
+        matches!(
+            self.state,
+            HistoryQMsgState::Display | HistoryQMsgState::DeleteFailed(_)
+        )
+    }
+
+    /// Whether the message should be left out of the message list.
+    pub fn is_hidden(&self) -> bool {
+        matches!(self.state, HistoryQMsgState::Deleting)
+    }
+
+    /// Returns the contents of the in-place editor, if the message is being edited.
+    pub fn edit_draft(&self) -> Option<String> {
+        match &self.state {
+            HistoryQMsgState::Editing { editor, .. } | HistoryQMsgState::SubmittingEdit(editor) => {
+                Some(editor.text())
+            }
+            _ => None,
+        }
+    }
+
+    /// Drops the in-place edit, if any, without submitting it.
+    pub fn abandon_edit(&mut self) {
+        if let HistoryQMsgState::Editing { .. } | HistoryQMsgState::SubmittingEdit(_) = self.state {
+            self.state = HistoryQMsgState::Display;
+        }
+    }
+
+    /// Starts editing the message with the given contents, e.g. to restore an unsubmitted edit.
+    pub fn restore_edit(&mut self, content: &str) {
+        if let HistoryQMsgState::Display | HistoryQMsgState::DeleteFailed(_) = self.state {
+            self.state = HistoryQMsgState::Editing {
+                editor: text_editor::Content::with_text(content),
+                last_error: None,
+            };
+        }
+    }
+
     /// Returns a command that sends this message.
-    pub fn send(&self, http: Arc<Http>) -> Command<(HistoryQMessageId, HistoryQMsgMessage)> {
+    pub fn send(&self, http: Arc<Scheduler>) -> Command<(HistoryQMessageId, HistoryQMsgMessage)> {
         use HistoryQMsgMessage as Message;
 
         let id = self.id;
         let cid = self.msg.channel;
         let content = self.msg.content.clone();
+        let reply_to = self.msg.reply_to;
         Command::perform(
-            async move { http.create_message(cid, &content).await },
+            async move {
+                http.run(Priority::Interactive, |http| async move {
+                    match reply_to {
+                        Some(reply_to) => http.create_reply(cid, reply_to, &content).await,
+                        None => http.create_message(cid, &content).await,
+                    }
+                })
+                .await
+            },
             move |res| match res {
                 Ok(msg) => (id, Message::SendingSucceeded(msg)),
                 Err(e) => (id, Message::SendingFailed(Arc::new(e))),
@@ -123,7 +410,7 @@ impl HistoryQMessage {
     pub fn update(
         &mut self,
         msg: HistoryQMsgMessage,
-        http: &Arc<Http>,
+        http: &Arc<Scheduler>,
     ) -> Command<(HistoryQMessageId, HistoryQMsgMessage)> {
         use HistoryQMsgMessage as Message;
         use HistoryQMsgState as State;
@@ -136,7 +423,53 @@ impl HistoryQMessage {
                 self.hovered = false;
                 Command::none()
             }
-            (s @ State::Display, Message::EditInitiated) => {
+            (_, Message::ErrorDetailsToggled) => {
+                self.error_details = !self.error_details;
+                Command::none()
+            }
+            (_, Message::ExpandToggled) => {
+                self.expanded = !self.expanded;
+                Command::none()
+            }
+            (_, Message::CopyMarkdown) => iced::clipboard::write(self.msg.content.clone()),
+            (_, Message::CopyPlainText) => iced::clipboard::write(markdown::to_plain_text(
+                &self.render.tokens.blocks(&self.msg.content),
+            )),
+            (_, Message::CodeCopied(code)) => iced::clipboard::write(code),
+            (_, Message::LinkClicked(link)) => match Url::parse(&link.url) {
+                Ok(url) if link.text_differs && !crate::strict_mode::is_enabled() => {
+                    self.confirming_link = Some(url);
+                    Command::none()
+                }
+                Ok(url) if open_link(&url) => Command::none(),
+                // the user can still paste it wherever they trust it
+                Ok(_) if crate::strict_mode::is_enabled() => {
+                    let id = self.id;
+                    Command::batch([
+                        iced::clipboard::write(link.url),
+                        Command::perform(async {}, move |()| (id, Message::LinkBlocked)),
+                    ])
+                }
+                _ => {
+                    log::warn!("not opening link {url:?}", url = link.url);
+                    Command::none()
+                }
+            },
+            (_, Message::LinkConfirmed) => {
+                if let Some(url) = self.confirming_link.take() {
+                    open_link(&url);
+                }
+                Command::none()
+            }
+            (_, Message::LinkCancelled) => {
+                self.confirming_link = None;
+                Command::none()
+            }
+            (State::Display | State::DeleteFailed(_), Message::SelectionToggled) => {
+                self.selected = !self.selected;
+                Command::none()
+            }
+            (s @ (State::Display | State::DeleteFailed(_)), Message::EditInitiated) => {
                 *s = State::Editing {
                     editor: text_editor::Content::with_text(&self.msg.content),
                     last_error: None,
@@ -154,7 +487,12 @@ impl HistoryQMessage {
                 let hqmid = self.id;
                 let http = Arc::clone(http);
                 Command::perform(
-                    async move { http.edit_message(cid, mid, &content).await },
+                    async move {
+                        http.run(Priority::Interactive, |http| {
+                            http.edit_message(cid, mid, &content)
+                        })
+                        .await
+                    },
                     move |result| match result {
                         Ok(msg) => (hqmid, Message::EditSucceeded(msg)),
                         Err(e) => (hqmid, Message::EditFailed(Arc::new(e))),
@@ -178,6 +516,7 @@ impl HistoryQMessage {
             (s @ State::SubmittingEdit(_), Message::EditSucceeded(msg)) => {
                 *s = State::Display;
                 self.msg = msg;
+                self.render.refresh(&self.msg);
                 Command::none()
             }
             (s @ State::Sending, Message::SendingFailed(err)) => {
@@ -187,12 +526,43 @@ impl HistoryQMessage {
             (s @ State::Sending, Message::SendingSucceeded(msg)) => {
                 *s = State::Display;
                 self.msg = msg;
+                self.render.refresh(&self.msg);
                 Command::none()
             }
             (State::Editing { editor, .. }, Message::Editor(action)) => {
                 editor.perform(action);
                 Command::none()
             }
+            (s @ (State::Display | State::DeleteFailed(_)), Message::DeleteInitiated) => {
+                *s = State::ConfirmingDelete;
+                Command::none()
+            }
+            (s @ State::ConfirmingDelete, Message::DeleteCancelled) => {
+                *s = State::Display;
+                Command::none()
+            }
+            (s @ State::ConfirmingDelete, Message::DeleteConfirmed) => {
+                *s = State::Deleting;
+                self.selected = false;
+                let cid = self.msg.channel;
+                let mid = self.msg.id;
+                let hqmid = self.id;
+                let http = Arc::clone(http);
+                Command::perform(
+                    async move {
+                        http.run(Priority::Interactive, |http| http.delete_message(cid, mid))
+                            .await
+                    },
+                    move |result| match result {
+                        Ok(()) => (hqmid, Message::DeleteSucceeded),
+                        Err(e) => (hqmid, Message::DeleteFailed(Arc::new(e))),
+                    },
+                )
+            }
+            (s @ State::Deleting, Message::DeleteFailed(err)) => {
+                *s = State::DeleteFailed(err);
+                Command::none()
+            }
             _ => Command::none(),
         }
     }
@@ -201,31 +571,68 @@ impl HistoryQMessage {
         button(icon(s)).on_press(message).into()
     }
 
-    fn action_buttons(&self) -> Vec<Element<'_, HistoryQMsgMessage>> {
+    fn action_buttons(&self, bookmarked: bool) -> Vec<Element<'_, HistoryQMsgMessage>> {
         use HistoryQMsgMessage as Message;
         use HistoryQMsgState as State;
         match &self.state {
-            State::Sending => vec![],
-            State::SendingFailed(_) => vec![Self::icon_button(RESEND, Message::ResendInitiated)],
+            State::Sending | State::Queued => vec![],
+            State::SendingFailed(_) => vec![
+                Self::icon_button(RESEND, Message::ResendInitiated),
+                Self::icon_button(COPY_MARKDOWN, Message::CopyMarkdown),
+            ],
             State::SubmittingEdit(_) => vec![],
-            State::Display => vec![Self::icon_button(EDIT, Message::EditInitiated)],
+            State::Display | State::DeleteFailed(_) => vec![
+                Self::icon_button(
+                    if self.selected { DESELECT } else { SELECT },
+                    Message::SelectionToggled,
+                ),
+                Self::icon_button(REPLY, Message::ReplyInitiated),
+                Self::icon_button(
+                    if bookmarked { BOOKMARKED } else { BOOKMARK },
+                    Message::BookmarkToggled,
+                ),
+                Self::icon_button(COPY_MARKDOWN, Message::CopyMarkdown),
+                Self::icon_button(COPY_PLAIN, Message::CopyPlainText),
+                Self::icon_button(COPY_LINK, Message::LinkCopied),
+                Self::icon_button(EDIT, Message::EditInitiated),
+                Self::icon_button(DELETE, Message::DeleteInitiated),
+            ],
             State::Editing { .. } => vec![],
+            State::ConfirmingDelete | State::Deleting => vec![],
         }
     }
 
-    pub fn view(&self, theme: &Theme, extended_info: bool) -> Element<'_, HistoryQMsgMessage> {
+    /// The author's avatar from `images` is shown in the header when `extended_info` is set,
+    /// and the images the message links to below it once they've been downloaded.
+    /// Dense messages ignore `extended_info`.
+    pub fn view(
+        &self,
+        theme: &Theme,
+        extended_info: bool,
+        images: &ImageCache,
+        options: DisplayOptions<'_>,
+    ) -> Element<'_, HistoryQMsgMessage> {
         use HistoryQMsgMessage as Message;
         use HistoryQMsgState as State;
 
-        fn content_plain<'a>(content: &'a str, a: f32, theme: &Theme) -> Element<'a, Message> {
-            text(content)
-                .style(theme::Text::Color(Color {
-                    a,
-                    ..theme.extended_palette().background.weak.text
-                }))
-                .shaping(text::Shaping::Advanced)
-                .width(Length::Fill)
-                .into()
+        fn content_view<'a>(
+            content: &'a str,
+            tokens: &markdown::Tokens,
+            a: f32,
+            theme: &Theme,
+        ) -> Element<'a, Message> {
+            let color = Color {
+                a,
+                ..theme.extended_palette().background.weak.text
+            };
+            markdown::view(
+                content,
+                tokens,
+                color,
+                theme,
+                Message::LinkClicked,
+                Message::CodeCopied,
+            )
         }
 
         fn editor_view<'a>(
@@ -234,7 +641,7 @@ impl HistoryQMessage {
         ) -> Column<'a, Message> {
             column([
                 {
-                    let editor = MessageEditor::new(&content);
+                    let editor = MessageEditor::new(content);
                     if enabled {
                         editor.on_action(Message::Editor).into()
                     } else {
@@ -258,56 +665,242 @@ impl HistoryQMessage {
             .spacing(5)
         }
 
-        fn error_msg<'a, E: 'a + Error>(e: E) -> Element<'a, Message> {
-            row([
-                icon(crate::WARNING).size(14).into(),
-                text(format!("Failed to send: {err}", err = ErrorWithCauses(e)))
-                    .size(14)
-                    .into(),
-            ])
+        fn error_msg<'a, E: 'a + Error>(what: &str, e: E, details: bool) -> Element<'a, Message> {
+            column![row![
+                icon(crate::WARNING).size(14),
+                text(format!("Failed to {what}: {err}", err = ErrorSummary(&e))).size(14),
+                details_toggle(details, Message::ErrorDetailsToggled),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(3)]
+            .push_maybe(details.then(|| text(ErrorWithCauses(e)).size(12)))
             .spacing(3)
             .into()
         }
 
+        // a dense line shows the message in full once it's clicked
+        let one_line = options.dense
+            && !self.expanded
+            && matches!(self.state, State::Display | State::Deleting);
+        let cut = preview_len(&self.msg.content, options.collapse_over);
+        let shown_cut = cut.filter(|_| !self.expanded && !one_line);
+        let preview = shown_cut.map(|cut| self.render.preview(&self.msg.content, cut));
+        let (shown, tokens) = match shown_cut.zip(preview.as_deref()) {
+            Some((cut, tokens)) => (&self.msg.content[..cut], tokens),
+            None => (self.msg.content.as_str(), &self.render.tokens),
+        };
+
         let content = match &self.state {
-            State::Sending => content_plain(&self.msg.content, 0.8, theme),
-            State::SendingFailed(err) => {
-                column([content_plain(&self.msg.content, 1.0, theme), error_msg(err)])
-                    .spacing(5)
-                    .into()
-            }
+            _ if one_line => button(
+                container(
+                    text(dense_line(&self.msg.content))
+                        .line_height(text::LineHeight::Absolute(DENSE_LINE_HEIGHT.into()))
+                        .style(theme::Text::Color(
+                            theme.extended_palette().background.weak.text,
+                        ))
+                        .shaping(text::Shaping::Advanced),
+                )
+                .max_height(DENSE_LINE_HEIGHT)
+                .clip(true),
+            )
+            .style(theme::Button::Text)
+            .padding(0)
+            .on_press(Message::ExpandToggled)
+            .into(),
+            State::Sending => content_view(shown, tokens, 0.8, theme),
+            State::Queued => column([
+                content_view(shown, tokens, 0.8, theme),
+                row([
+                    icon(crate::CONNECTING).size(14).into(),
+                    text("Will be sent once the connection is back")
+                        .size(14)
+                        .into(),
+                ])
+                .spacing(3)
+                .into(),
+            ])
+            .spacing(5)
+            .into(),
+            State::SendingFailed(err) => column([
+                content_view(shown, tokens, 1.0, theme),
+                error_msg("send", err, self.error_details),
+            ])
+            .spacing(5)
+            .into(),
             State::SubmittingEdit(ed) => editor_view(ed, false).into(),
             State::Editing { editor, last_error } => editor_view(editor, true)
-                .push_maybe(last_error.as_ref().map(error_msg))
+                .push_maybe(
+                    last_error
+                        .as_ref()
+                        .map(|err| error_msg("send", err, self.error_details)),
+                )
                 .into(),
-            State::Display => content_plain(&self.msg.content, 1.0, theme),
-        };
-
-        let date_str = self
-            .msg
-            .id
-            .timestamp()
-            .with_timezone(&Local)
-            .format("%Y-%m-%d %H:%M");
-
-        let underlay = if extended_info {
-            column([
-                Space::with_height(10).into(),
+            State::Display | State::Deleting => content_view(shown, tokens, 1.0, theme),
+            State::ConfirmingDelete => column([
+                content_view(shown, tokens, 0.8, theme),
                 row([
-                    text(&self.msg.author.name)
-                        .shaping(text::Shaping::Advanced)
-                        .font(crate::DEFAULT_FONT_MEDIUM)
+                    text("Delete this message?").size(14).into(),
+                    button(text("delete").size(14))
+                        .style(theme::Button::Text)
+                        .on_press(Message::DeleteConfirmed)
                         .into(),
-                    text(date_str)
-                        .size(10)
-                        .style(iced::theme::Text::Color({
-                            theme.extended_palette().background.weak.text
-                        }))
+                    "/".into(),
+                    button(text("cancel").size(14))
+                        .style(theme::Button::Text)
+                        .on_press(Message::DeleteCancelled)
                         .into(),
                 ])
-                .align_items(iced::Alignment::Center)
+                .align_items(Alignment::Center)
+                .spacing(4)
+                .into(),
+            ])
+            .spacing(5)
+            .into(),
+            State::DeleteFailed(err) => column([
+                content_view(shown, tokens, 1.0, theme),
+                error_msg("delete", err, self.error_details),
+            ])
+            .spacing(5)
+            .into(),
+        };
+
+        let collapsible = cut.is_some() || (options.dense && self.expanded);
+        let expand_toggle = (collapsible
+            && !matches!(self.state, State::Editing { .. } | State::SubmittingEdit(_)))
+        .then(|| {
+            let label = if self.expanded {
+                "Show less".to_string()
+            } else {
+                format!(
+                    "Show all of it ({} KB)",
+                    self.msg.content.len().div_ceil(1000)
+                )
+            };
+            button(text(label).size(14))
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(Message::ExpandToggled)
+        });
+        let content: Element<'_, _> = match expand_toggle {
+            Some(toggle) => column![content, toggle].spacing(5).into(),
+            None => content,
+        };
+        let content: Element<'_, _> = match &self.confirming_link {
+            Some(url) => column![
+                content,
+                row![
+                    text(format!("Open {url}?"))
+                        .size(14)
+                        .shaping(text::Shaping::Advanced),
+                    button(text("open").size(14))
+                        .style(theme::Button::Text)
+                        .on_press(Message::LinkConfirmed),
+                    text("/").size(14),
+                    button(text("cancel").size(14))
+                        .style(theme::Button::Text)
+                        .on_press(Message::LinkCancelled),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(4),
+            ]
+            .spacing(5)
+            .into(),
+            None => content,
+        };
+        let thumbnails: Vec<Element<'_, _>> = self
+            .images()
+            .into_iter()
+            .filter(|_| {
+                !one_line && !matches!(self.state, State::Editing { .. } | State::SubmittingEdit(_))
+            })
+            .filter_map(|url| {
+                let (thumbnail, status) = images.view(
+                    &url,
+                    ImageSize::Within(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT),
+                    options.play_animations,
+                    Some(Message::ImageRetried(url.clone())),
+                )?;
+                // the first click plays a paused animation, the next one opens it
+                let on_press = match status {
+                    ImageStatus::Paused => Message::ImagePlayed(url),
+                    ImageStatus::Shown => Message::ImageOpened(url),
+                    ImageStatus::Loading | ImageStatus::Failed => return Some(thumbnail),
+                };
+                Some(
+                    button(thumbnail)
+                        .style(theme::Button::Text)
+                        .padding(0)
+                        .on_press(on_press)
+                        .into(),
+                )
+            })
+            .collect();
+        let content: Element<'_, _> = match thumbnails.is_empty() {
+            true => content,
+            false => column![content, Row::with_children(thumbnails).spacing(5)]
                 .spacing(5)
                 .into(),
+        };
+        let content: Element<'_, _> = match self.msg.reply_to {
+            Some(_) if !one_line => column![quote_view(self.quoted.as_ref(), theme), content]
+                .spacing(3)
+                .into(),
+            Some(_) | None => content,
+        };
+
+        let timestamp = timezone::to_display(self.msg.id.timestamp(), options.timezone);
+        let date_str = timestamp.format("%Y-%m-%d %H:%M");
+        let details = [
+            timestamp.format("%Y-%m-%d %H:%M:%S%.3f %:z").to_string(),
+            format!("ID: {id}", id = self.msg.id),
+        ];
+
+        let underlay = if options.dense {
+            row![
+                details_tooltip(
+                    text(timestamp.format("[%H:%M]"))
+                        .font(Font::MONOSPACE)
+                        .style(iced::theme::Text::Color({
+                            theme.extended_palette().background.weak.text
+                        })),
+                    details,
+                ),
+                button(
+                    text(format!("<{name}>", name = self.msg.author.name))
+                        .shaping(text::Shaping::Advanced)
+                        .font(crate::DEFAULT_FONT_MEDIUM),
+                )
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(Message::AuthorClicked),
+                container(content).width(Length::Fill),
+            ]
+            .spacing(5)
+            .into()
+        } else if extended_info {
+            column([
+                Space::with_height(10).into(),
+                Row::new()
+                    .push(images.avatar(&self.msg.author, AVATAR_SIZE))
+                    .push(
+                        button(
+                            text(&self.msg.author.name)
+                                .shaping(text::Shaping::Advanced)
+                                .font(crate::DEFAULT_FONT_MEDIUM),
+                        )
+                        .style(theme::Button::Text)
+                        .padding(0)
+                        .on_press(Message::AuthorClicked),
+                    )
+                    .push(details_tooltip(
+                        text(date_str).size(10).style(iced::theme::Text::Color({
+                            theme.extended_palette().background.weak.text
+                        })),
+                        details,
+                    ))
+                    .align_items(iced::Alignment::Center)
+                    .spacing(5)
+                    .into(),
                 content,
             ])
             .spacing(3)
@@ -317,7 +910,52 @@ impl HistoryQMessage {
             container(content).width(Length::Fill).into()
         };
 
-        let action_butns = self.action_buttons();
+        let is_own = options.me.is_some_and(|me| me.id == self.msg.author.id);
+        let mentions_me = options
+            .me
+            .is_some_and(|me| !is_own && mention::mentions(&self.msg.content, &me.name));
+        let own_style = match (is_own, options.own_messages) {
+            (false, _) => OwnMessageStyle::Plain,
+            // dense lines all start at the same place, so that they can be skimmed
+            (true, OwnMessageStyle::Right) if options.dense => OwnMessageStyle::Tint,
+            (true, style) => style,
+        };
+
+        let alpha = match (self.selected, mentions_me, own_style) {
+            (true, _, _) => Some(0.3),
+            (_, true, _) => Some(0.12),
+            (_, _, OwnMessageStyle::Tint | OwnMessageStyle::Right) => Some(0.06),
+            (_, _, OwnMessageStyle::Plain) => None,
+        };
+        let underlay = if let Some(alpha) = alpha {
+            container(underlay)
+                .style(move |t: &Theme| {
+                    use iced::widget::container::StyleSheet;
+                    container::Appearance {
+                        background: Some(iced::Background::Color(Color {
+                            a: alpha,
+                            ..t.extended_palette().primary.weak.color
+                        })),
+                        ..t.appearance(&theme::Container::Transparent)
+                    }
+                })
+                .width(Length::Fill)
+                .into()
+        } else {
+            underlay
+        };
+
+        let underlay = match own_style {
+            OwnMessageStyle::Right => row![
+                Space::with_width(Length::FillPortion(1)),
+                container(underlay).width(Length::FillPortion(4)),
+            ]
+            .into(),
+            OwnMessageStyle::Plain | OwnMessageStyle::Tint => underlay,
+        };
+
+        let bookmarked = options.bookmarks.iter().any(|b| b.id == self.msg.id);
+        let action_butns = self.action_buttons(bookmarked);
 
         let el: Element<'_, _> = if !action_butns.is_empty() {
             let overlay = Row::from_vec(action_butns).align_items(Alignment::Center);
@@ -337,27 +975,322 @@ impl HistoryQMessage {
     }
 }
 
+/// A single line leading to the message `quoted`, shown above a reply to it.
+fn quote_view<'a>(quoted: Option<&'a QMessage>, theme: &Theme) -> Element<'a, HistoryQMsgMessage> {
+    let color = theme.extended_palette().background.weak.text;
+    let label: Element<'a, _> = match quoted {
+        Some(quoted) => row![
+            text(&quoted.author.name)
+                .size(12)
+                .font(crate::DEFAULT_FONT_MEDIUM)
+                .shaping(text::Shaping::Advanced),
+            text(excerpt(&quoted.content))
+                .size(12)
+                .style(color)
+                .shaping(text::Shaping::Advanced),
+        ]
+        .spacing(5)
+        .into(),
+        None => text("a message that isn't loaded")
+            .size(12)
+            .style(color)
+            .into(),
+    };
+
+    button(
+        row![icon(REPLY).size(12), label]
+            .spacing(5)
+            .align_items(Alignment::Center),
+    )
+    .style(theme::Button::Text)
+    .padding(0)
+    .on_press(HistoryQMsgMessage::QuoteClicked)
+    .into()
+}
+
+/// The start of `content`, to quote it on a single line.
+pub fn excerpt(content: &str) -> String {
+    const MAX_CHARS: usize = 80;
+
+    let mut lines = content.lines();
+    let line = lines.next().unwrap_or_default();
+    match line.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None if lines.next().is_some() => format!("{line}…"),
+        None => line.to_string(),
+    }
+}
+
+/// The start of `content` for the dense view, with line breaks turned into spaces.
+/// It's cut off where it no longer fits on the line, so this only needs to be long enough.
+fn dense_line(content: &str) -> String {
+    const MAX_CHARS: usize = 300;
+
+    content
+        .chars()
+        .take(MAX_CHARS)
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect()
+}
+
+/// Where to cut `content` short if it's longer than `max` bytes: at the last line break
+/// in the second half of what fits, or else at the last character that fits.
+fn preview_len(content: &str, max: usize) -> Option<usize> {
+    if content.len() <= max {
+        return None;
+    }
+    let mut cut = max;
+    while !content.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    match content[..cut].rfind('\n') {
+        Some(newline) if newline >= max / 2 => Some(newline),
+        _ => Some(cut),
+    }
+}
+
+/// Shows `details`, one per line, when hovering over `content`.
+pub fn details_tooltip<'a, Message: 'a>(
+    content: impl Into<Element<'a, Message>>,
+    details: impl IntoIterator<Item = String>,
+) -> Element<'a, Message> {
+    let details = Column::with_children(details.into_iter().map(|line| text(line).size(12).into()));
+
+    tooltip(content, details, tooltip::Position::FollowCursor)
+        .style(theme::Container::Box)
+        .padding(5)
+        .into()
+}
+
 pub const QMESSAGELIST_ID: &str = "qmessage_list";
 
-pub fn qmessage_list<'a>(
+/// How close to the top of the message list, in pixels, the user has to
+/// scroll for older messages to start loading.
+const LOAD_OLDER_THRESHOLD: f32 = 200.0;
+
+/// How far from the bottom of the message list, in pixels,
+/// the newest messages still count as being in view.
+const AT_BOTTOM_THRESHOLD: f32 = 20.0;
+
+/// Whether the message list is scrolled all the way down.
+pub fn is_at_bottom(viewport: &Viewport) -> bool {
+    // the list is aligned to the end, so offsets count from the bottom
+    viewport.absolute_offset().y <= AT_BOTTOM_THRESHOLD
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum LoaderState {
+    #[default]
+    Idle,
+    Loading,
+    /// The beginning of the channel has been reached.
+    Exhausted,
+}
+
+/// Loads older pages of message history as the user scrolls up.
+///
+/// The message list is aligned to its end, so its scroll offset is measured
+/// from the bottom and stays put when older messages get prepended.
+#[derive(Debug, Default)]
+pub struct HistoryLoader {
+    state: LoaderState,
+}
+
+impl HistoryLoader {
+    /// Forgets about any loaded pages, e.g. after switching channels.
+    pub fn reset(&mut self) {
+        self.state = LoaderState::Idle;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.state == LoaderState::Loading
+    }
+
+    /// Whether the beginning of the channel has been loaded.
+    pub fn is_exhausted(&self) -> bool {
+        self.state == LoaderState::Exhausted
+    }
+
+    /// Starts loading the page before `oldest` if the message list
+    /// has been scrolled close enough to the top.
+    pub fn on_scroll<Message>(
+        &mut self,
+        viewport: Viewport,
+        http: Arc<Scheduler>,
+        channel_id: ChannelId,
+        oldest: Option<QMessageId>,
+        on_success: impl FnOnce(ChannelId, Vec<QMessage>) -> Message + Send + Sync + 'static,
+        on_error: impl FnOnce(Arc<http::Error>) -> Message + Send + Sync + 'static,
+    ) -> Command<Message> {
+        if viewport.absolute_offset_reversed().y > LOAD_OLDER_THRESHOLD {
+            return Command::none();
+        }
+
+        self.load_older(http, channel_id, oldest, on_success, on_error)
+    }
+
+    /// Starts loading the page before `oldest`, unless one is already on its way.
+    pub fn load_older<Message>(
+        &mut self,
+        http: Arc<Scheduler>,
+        channel_id: ChannelId,
+        oldest: Option<QMessageId>,
+        on_success: impl FnOnce(ChannelId, Vec<QMessage>) -> Message + Send + Sync + 'static,
+        on_error: impl FnOnce(Arc<http::Error>) -> Message + Send + Sync + 'static,
+    ) -> Command<Message> {
+        let Some(oldest) = oldest else {
+            return Command::none();
+        };
+
+        if self.state != LoaderState::Idle {
+            return Command::none();
+        }
+
+        self.state = LoaderState::Loading;
+        retrieve_history(
+            http,
+            Priority::Interactive,
+            channel_id,
+            History::before(oldest),
+            on_success,
+            on_error,
+        )
+    }
+
+    /// Records that a page of `count` messages has arrived.
+    /// Returns whether the page was expected.
+    pub fn on_loaded(&mut self, count: usize) -> bool {
+        if self.state != LoaderState::Loading {
+            return false;
+        }
+
+        self.state = if count == 0 {
+            LoaderState::Exhausted
+        } else {
+            LoaderState::Idle
+        };
+        true
+    }
+
+    pub fn on_failed(&mut self) {
+        if self.state == LoaderState::Loading {
+            self.state = LoaderState::Idle;
+        }
+    }
+}
+
+/// How a message stands out in the list, e.g. as a search result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    Match,
+    Current,
+    /// Just jumped to, so that it can be told apart from its neighbours.
+    Flash,
+}
+
+fn highlighted<'a, Message: 'a>(
+    el: Element<'a, Message>,
+    highlight: Highlight,
+) -> Element<'a, Message> {
+    let alpha = match highlight {
+        Highlight::None => return el,
+        Highlight::Match => 0.15,
+        Highlight::Current | Highlight::Flash => 0.45,
+    };
+
+    container(el)
+        .style(move |t: &Theme| {
+            use iced::widget::container::StyleSheet;
+            let palette = t.extended_palette();
+            let color = match highlight {
+                Highlight::Flash => palette.primary.weak.color,
+                _ => palette.secondary.strong.color,
+            };
+            container::Appearance {
+                background: Some(iced::Background::Color(Color { a: alpha, ..color })),
+                ..t.appearance(&theme::Container::Transparent)
+            }
+        })
+        .width(Length::Fill)
+        .into()
+}
+
+/// The line across the message list where a new day starts.
+fn day_separator<'a, Message: 'a>(day: NaiveDate, today: NaiveDate) -> Element<'a, Message> {
+    row![
+        horizontal_rule(1),
+        text(timezone::day_label(day, today))
+            .size(12)
+            .font(crate::DEFAULT_FONT_MEDIUM),
+        horizontal_rule(1),
+    ]
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding([10, 0])
+    .into()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn qmessage_list<'a, Message: 'a>(
     theme: &Theme,
     messages: impl IntoIterator<Item = &'a HistoryQMessage>,
-) -> Element<'a, (usize, HistoryQMsgMessage)> {
+    images: &ImageCache,
+    options: DisplayOptions<'_>,
+    loading_older: bool,
+    highlight: impl Fn(usize) -> Highlight,
+    decorate: impl Fn(&'a QMessage) -> Option<Element<'a, Message>>,
+    on_action: fn(usize, HistoryQMsgMessage) -> Message,
+    on_scroll: fn(Viewport) -> Message,
+) -> Element<'a, Message> {
+    let loading_indicator = loading_older.then(|| {
+        container(text("Loading older messages…").size(14))
+            .width(Length::Fill)
+            .center_x()
+            .padding(10)
+    });
+
+    let timezone = options.timezone;
+    let today = timezone::day_of(Utc::now(), timezone);
     let el = scrollable({
-        Column::with_children({
-            Gaps::new(messages)
-                .enumerate()
-                .filter_map(|(i, (lastmsg, curmsg_opt))| {
-                    let curmsg = curmsg_opt?;
-                    let extended_info = !lastmsg.is_some_and(|lmsg| {
+        Column::new().push_maybe(loading_indicator).extend({
+            Gaps::new({
+                messages
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, qmsg)| !qmsg.is_hidden())
+            })
+            .filter_map(|(last, cur)| {
+                let lastmsg = last.map(|(_, lmsg)| lmsg);
+                let (i, curmsg) = cur?;
+                let sent = curmsg.msg.id.timestamp();
+                let new_day = !lastmsg.is_some_and(|lmsg| {
+                    timezone::same_day(lmsg.msg.id.timestamp(), sent, timezone)
+                });
+                let extended_info = new_day
+                    || !lastmsg.is_some_and(|lmsg| {
                         lmsg.msg.author.id == curmsg.msg.author.id
-                            && (curmsg.msg.id.timestamp() - lmsg.msg.id.timestamp())
-                                < TimeDelta::minutes(5)
+                            && (sent - lmsg.msg.id.timestamp()) < TimeDelta::minutes(5)
                     });
-                    Some(curmsg.view(theme, extended_info).map(move |msg| (i, msg)))
-                })
+                Some(
+                    Column::new()
+                        .push_maybe(
+                            new_day.then(|| day_separator(timezone::day_of(sent, timezone), today)),
+                        )
+                        .push(highlighted(
+                            column![curmsg
+                                .view(theme, extended_info, images, options)
+                                .map(move |msg| on_action(i, msg))]
+                            .push_maybe(decorate(&curmsg.msg))
+                            .into(),
+                            highlight(i),
+                        ))
+                        .into(),
+                )
+            })
         })
     })
+    .on_scroll(on_scroll)
     .direction({
         iced::widget::scrollable::Direction::Vertical({
             Properties::new().alignment(scrollable::Alignment::End)
@@ -368,18 +1301,115 @@ pub fn qmessage_list<'a>(
     container(el).padding(20).height(Length::Fill).into()
 }
 
+/// Scrolls the message list so that the message at `idx` is roughly in view.
+pub fn scroll_to_message<Message: 'static>(
+    messages: &[HistoryQMessage],
+    idx: usize,
+) -> Command<Message> {
+    // hidden messages take no room in the list, so count only the ones shown
+    let shown = |msgs: &[HistoryQMessage]| msgs.iter().filter(|m| !m.is_hidden()).count();
+    let len = shown(messages);
+    let below = shown(messages.get(idx + 1..).unwrap_or_default());
+    // messages have different heights, so this is only an estimate
+    let from_bottom = below as f32 / len.saturating_sub(1).max(1) as f32;
+    scrollable::snap_to(
+        scrollable::Id::new(QMESSAGELIST_ID),
+        scrollable::RelativeOffset {
+            x: 0.0,
+            y: from_bottom,
+        },
+    )
+}
+
+/// Formats messages as a single blockquote, in chronological order.
+/// Consecutive messages by the same author share one attribution line.
+pub fn quote_messages<'a>(messages: impl IntoIterator<Item = &'a QMessage>) -> String {
+    let mut messages: Vec<_> = messages.into_iter().collect();
+    messages.sort_by_key(|m| m.id);
+
+    let mut quote = String::new();
+    let mut last_author = None;
+    for msg in messages {
+        if last_author != Some(msg.author.id) {
+            if last_author.is_some() {
+                quote.push_str(">\n");
+            }
+            quote.push_str(&format!("> {name}:\n", name = msg.author.name));
+            last_author = Some(msg.author.id);
+        }
+        for line in msg.content.trim_end().lines() {
+            quote.push_str("> ");
+            quote.push_str(line);
+            quote.push('\n');
+        }
+    }
+    quote.push('\n');
+
+    quote
+}
+
 pub fn retrieve_history<Message>(
-    http: Arc<Http>,
+    http: Arc<Scheduler>,
+    priority: Priority,
     channel_id: ChannelId,
-    before: Option<QMessageId>,
+    query: History,
     on_success: impl FnOnce(ChannelId, Vec<QMessage>) -> Message + Send + Sync + 'static,
-    on_error: impl FnOnce(http::Error) -> Message + Send + Sync + 'static,
+    on_error: impl FnOnce(Arc<http::Error>) -> Message + Send + Sync + 'static,
 ) -> Command<Message> {
     Command::perform(
-        async move { http.message_history(channel_id, before).await },
+        async move {
+            http.run(priority, |http| http.message_history(channel_id, query))
+                .await
+        },
         move |res| match res {
             Ok(msgs) => on_success(channel_id, msgs),
-            Err(err) => on_error(err),
+            Err(err) => on_error(Arc::new(err)),
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dense_line() {
+        assert_eq!(dense_line("meow\n\tpurr"), "meow  purr");
+        assert_eq!(dense_line(&"a".repeat(1000)).len(), 300);
+    }
+
+    #[test]
+    fn test_preview_len() {
+        assert_eq!(preview_len("meow", 10), None);
+        assert_eq!(preview_len("meowmeow", 8), None);
+        // at the line break, which is in the second half of what fits
+        assert_eq!(preview_len("aaaa\nbbbbbbbb", 8), Some(4));
+        // one in the first half would leave too little
+        assert_eq!(preview_len("a\nbbbbbbbbbb", 8), Some(8));
+        // never in the middle of a character
+        assert_eq!(preview_len("ééééé", 5), Some(4));
+    }
+
+    #[test]
+    fn test_dense_reply() {
+        let reply = QMessage::builder()
+            .content("meow")
+            .reply_to(QMessageId(1))
+            .build();
+        let mut qmsg = HistoryQMessage::new(reply);
+        let images = ImageCache::default();
+        let options = || DisplayOptions {
+            timezone: None,
+            me: None,
+            collapse_over: 1000,
+            own_messages: OwnMessageStyle::default(),
+            play_animations: false,
+            bookmarks: &[],
+            dense: true,
+        };
+        // on one line, and with the quote once expanded
+        drop(qmsg.view(&Theme::Dark, false, &images, options()));
+        qmsg.expanded = true;
+        drop(qmsg.view(&Theme::Dark, false, &images, options()));
+    }
+}
@@ -0,0 +1,99 @@
+//! Compiled-in plugins: self-contained features that hook into the main
+//! screen without it having to know about them.
+//!
+//! A plugin implements [`Plugin`] and is listed in [`builtin`],
+//! usually behind a Cargo feature of its own.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use iced::{Command, Element};
+use quaddlecl::model::message::Message as QMessage;
+
+#[cfg(feature = "plugin-shrug")]
+mod shrug;
+
+/// A message produced by a plugin's widgets. It's handed back
+/// to the same plugin's [`Plugin::update`], which knows its real type.
+#[derive(Clone)]
+pub struct PluginMessage(Arc<dyn Any + Send + Sync>);
+
+impl PluginMessage {
+    pub fn new<T: Any + Send + Sync>(msg: T) -> Self {
+        Self(Arc::new(msg))
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+impl fmt::Debug for PluginMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PluginMessage(..)")
+    }
+}
+
+/// Hooks a plugin can implement. All of them default to doing nothing.
+pub trait Plugin: fmt::Debug {
+    fn name(&self) -> &'static str;
+
+    /// Rewrites a message before it's sent.
+    /// Plugins get to do so in the order they're listed in.
+    fn on_send(&self, content: String) -> String {
+        content
+    }
+
+    /// Shown under a message in the message list.
+    fn decorate_message<'a>(&'a self, _msg: &'a QMessage) -> Option<Element<'a, PluginMessage>> {
+        None
+    }
+
+    /// A panel of the plugin's own in the sidebar.
+    fn sidebar_panel(&self) -> Option<Element<'_, PluginMessage>> {
+        None
+    }
+
+    fn update(&mut self, _msg: PluginMessage) -> Command<PluginMessage> {
+        Command::none()
+    }
+}
+
+/// The plugins compiled into this build.
+pub fn builtin() -> Vec<Box<dyn Plugin>> {
+    vec![
+        #[cfg(feature = "plugin-shrug")]
+        Box::new(shrug::Shrug),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Noop;
+
+    impl Plugin for Noop {
+        fn name(&self) -> &'static str {
+            "noop"
+        }
+    }
+
+    #[test]
+    fn test_default_hooks() {
+        let msg = QMessage::builder().content("meow").build();
+        assert_eq!(Noop.on_send("meow".to_string()), "meow");
+        assert!(Noop.decorate_message(&msg).is_none());
+        assert!(Noop.sidebar_panel().is_none());
+    }
+
+    #[test]
+    fn test_plugin_message() {
+        let msg = PluginMessage::new(42u32);
+        let copy = msg.clone();
+        assert_eq!(copy.downcast_ref::<u32>(), Some(&42));
+        assert_eq!(msg.downcast_ref::<i32>(), None);
+    }
+}
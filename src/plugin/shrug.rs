@@ -0,0 +1,38 @@
+//! Replaces a trailing `/shrug` with a shrug, mostly as an example of an on-send hook.
+
+use super::Plugin;
+
+const COMMAND: &str = "/shrug";
+// escaped, since messages are markdown
+const SHRUG: &str = r"¯\\\_(ツ)\_/¯";
+
+#[derive(Debug)]
+pub struct Shrug;
+
+impl Plugin for Shrug {
+    fn name(&self) -> &'static str {
+        "shrug"
+    }
+
+    fn on_send(&self, content: String) -> String {
+        match content.trim_end().strip_suffix(COMMAND) {
+            Some(rest) => format!("{rest}{SHRUG}"),
+            None => content,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shrug() {
+        assert_eq!(
+            Shrug.on_send("oh well /shrug".to_string()),
+            format!("oh well {SHRUG}")
+        );
+        assert_eq!(Shrug.on_send("/shrug\n".to_string()), SHRUG);
+        assert_eq!(Shrug.on_send("/shrug it off".to_string()), "/shrug it off");
+    }
+}
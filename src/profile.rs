@@ -0,0 +1,117 @@
+//! The card that pops up when clicking on someone's name in the message list.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, Utc};
+use iced::widget::{button, column, container, row, text, Space};
+use iced::{theme, Alignment, Command, Element, Length};
+use quaddlecl::client::http;
+use quaddlecl::model::user::{User, UserId};
+
+use crate::image_cache::ImageCache;
+use crate::scheduler::{Priority, Scheduler};
+use crate::utils::{icon, ErrorWithCauses};
+
+const CLOSE: &str = "\u{f00d}";
+const AVATAR_SIZE: u16 = 56;
+
+#[derive(Debug)]
+enum ProfileState {
+    Loading,
+    Loaded,
+    Failed(Arc<http::Error>),
+}
+
+#[derive(Debug)]
+pub struct ProfileCard {
+    /// What the message knew about the user, replaced once the server answers.
+    user: User,
+    state: ProfileState,
+}
+
+impl ProfileCard {
+    /// Opens the card for the author of a message and starts fetching their profile.
+    pub fn open<Message>(
+        author: User,
+        http: Arc<Scheduler>,
+        on_fetched: impl FnOnce(UserId, Result<User, Arc<http::Error>>) -> Message + Send + 'static,
+    ) -> (Self, Command<Message>) {
+        let user_id = author.id;
+        let cmd = Command::perform(
+            async move {
+                http.run(Priority::Interactive, |http| http.fetch_user(user_id))
+                    .await
+            },
+            move |res| on_fetched(user_id, res.map_err(Arc::new)),
+        );
+
+        (
+            Self {
+                user: author,
+                state: ProfileState::Loading,
+            },
+            cmd,
+        )
+    }
+
+    pub fn on_fetched(&mut self, user_id: UserId, res: Result<User, Arc<http::Error>>) {
+        if user_id != self.user.id {
+            return;
+        }
+        match res {
+            Ok(user) => {
+                self.user = user;
+                self.state = ProfileState::Loaded;
+            }
+            Err(e) => self.state = ProfileState::Failed(e),
+        }
+    }
+
+    pub fn view<'a, Message: Clone + 'a>(
+        &'a self,
+        avatars: &ImageCache,
+        on_close: Message,
+    ) -> Element<'a, Message> {
+        let avatar = avatars.avatar(&self.user, AVATAR_SIZE);
+
+        // snowflakes carry their creation time, so this is when the account was made
+        let joined: DateTime<Utc> = self.user.id.into();
+        let status = match &self.state {
+            ProfileState::Loading => Some("Loading profile…".to_string()),
+            ProfileState::Loaded => None,
+            ProfileState::Failed(e) => Some(format!(
+                "Could not load the profile: {e}",
+                e = ErrorWithCauses(e)
+            )),
+        };
+
+        container(
+            column![
+                row![
+                    avatar,
+                    Space::with_width(Length::Fill),
+                    button(icon(CLOSE).size(14))
+                        .style(theme::Button::Text)
+                        .on_press(on_close),
+                ]
+                .align_items(Alignment::Start),
+                text(&self.user.name)
+                    .shaping(text::Shaping::Advanced)
+                    .font(crate::DEFAULT_FONT_MEDIUM)
+                    .size(20),
+                text(format!("ID: {id}", id = self.user.id)).size(12),
+                text(format!(
+                    "Joined {date}",
+                    date = joined.with_timezone(&Local).format("%Y-%m-%d")
+                ))
+                .size(12),
+            ]
+            .push_maybe(status.map(|status| text(status).size(12)))
+            .spacing(5),
+        )
+        .width(260)
+        .padding(15)
+        .style(theme::Container::Box)
+        .into()
+    }
+}
@@ -0,0 +1,143 @@
+//! The Ctrl+K overlay for jumping to a channel of any logged in account by typing part of its name.
+
+use iced::keyboard::key::Named;
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Column};
+use iced::{keyboard, theme, Alignment, Command, Element, Event, Length, Subscription};
+
+use crate::account_switcher::SessionId;
+use crate::toggle_button::pressed_button_style;
+use crate::utils::fuzzy_score;
+
+const QUICK_SWITCH_INPUT_ID: &str = "quick_switch_input";
+/// Only the best matches are listed, since the rest are a keystroke away anyway.
+const MAX_SHOWN: usize = 8;
+
+#[derive(Debug, Clone)]
+pub enum QuickSwitchMessage {
+    QueryChanged(String),
+    /// Moves the highlight by the given number of matches, wrapping around.
+    Moved(isize),
+    Submitted,
+    Picked(usize),
+    Closed,
+}
+
+/// A channel that can be jumped to.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub session: SessionId,
+    /// Position of the channel in the account's list.
+    pub channel: usize,
+    pub name: String,
+    /// Which account the channel belongs to, e.g. "meow on example.com".
+    pub account: String,
+}
+
+#[derive(Debug, Default)]
+pub struct QuickSwitch {
+    query: String,
+    /// Position of the highlighted match.
+    selected: usize,
+}
+
+impl QuickSwitch {
+    pub fn focus<Message: 'static>() -> Command<Message> {
+        text_input::focus(text_input::Id::new(QUICK_SWITCH_INPUT_ID))
+    }
+
+    /// Indices of the candidates matching the query, best first.
+    pub fn matches(&self, candidates: &[Candidate]) -> Vec<usize> {
+        let mut scored: Vec<_> = candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| Some((fuzzy_score(&self.query, &c.name)?, i)))
+            .collect();
+        // stable, so equally good matches stay in sidebar order
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        scored.into_iter().map(|(_, i)| i).take(MAX_SHOWN).collect()
+    }
+
+    /// Returns the candidate to jump to, if one was picked.
+    pub fn update<'a>(
+        &mut self,
+        message: QuickSwitchMessage,
+        candidates: &'a [Candidate],
+    ) -> Option<&'a Candidate> {
+        let matches = self.matches(candidates);
+        match message {
+            QuickSwitchMessage::QueryChanged(query) => {
+                self.query = query;
+                self.selected = 0;
+                None
+            }
+            QuickSwitchMessage::Moved(step) if !matches.is_empty() => {
+                let len = matches.len() as isize;
+                self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+                None
+            }
+            QuickSwitchMessage::Submitted => matches
+                .get(self.selected)
+                .and_then(|&idx| candidates.get(idx)),
+            QuickSwitchMessage::Picked(idx) => candidates.get(idx),
+            QuickSwitchMessage::Moved(_) | QuickSwitchMessage::Closed => None,
+        }
+    }
+
+    pub fn view(&self, candidates: &[Candidate]) -> Element<'_, QuickSwitchMessage> {
+        let matches = self.matches(candidates);
+        let rows = matches.iter().enumerate().map(|(pos, &idx)| {
+            let candidate = &candidates[idx];
+            button(
+                row![
+                    text(&candidate.name).width(Length::Fill),
+                    text(&candidate.account).size(12),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(10),
+            )
+            .on_press(QuickSwitchMessage::Picked(idx))
+            .style(if pos == self.selected {
+                pressed_button_style(theme::Button::Text)
+            } else {
+                theme::Button::Text
+            })
+            .width(Length::Fill)
+            .into()
+        });
+
+        let list: Element<_> = if matches.is_empty() {
+            text("No matching channels").size(14).into()
+        } else {
+            scrollable(Column::with_children(rows).spacing(2)).into()
+        };
+
+        container(
+            column![
+                text_input("Jump to a channel", &self.query)
+                    .id(text_input::Id::new(QUICK_SWITCH_INPUT_ID))
+                    .on_input(QuickSwitchMessage::QueryChanged)
+                    .on_submit(QuickSwitchMessage::Submitted),
+                list,
+            ]
+            .spacing(10),
+        )
+        .width(400)
+        .padding(10)
+        .style(theme::Container::Box)
+        .into()
+    }
+}
+
+/// Arrow keys move the highlight and Escape closes the switcher.
+pub fn subscription() -> Subscription<QuickSwitchMessage> {
+    // the input captures Escape, so look at key presses whether they were handled or not
+    iced::event::listen_with(|event, _| match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) => match key.as_ref() {
+            keyboard::Key::Named(Named::ArrowUp) => Some(QuickSwitchMessage::Moved(-1)),
+            keyboard::Key::Named(Named::ArrowDown) => Some(QuickSwitchMessage::Moved(1)),
+            keyboard::Key::Named(Named::Escape) => Some(QuickSwitchMessage::Closed),
+            _ => None,
+        },
+        _ => None,
+    })
+}
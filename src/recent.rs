@@ -0,0 +1,217 @@
+//! The newest messages of each channel, kept between runs so that a resumed
+//! session has something to show while its history loads. They're kept apart
+//! from the config, which is written out far more often, and only up to
+//! [`MAX_BYTES`] per account, forgetting the channels opened longest ago first.
+
+use std::future::Future;
+
+use quaddlecl::model::{channel::ChannelId, message::Message, user::UserId};
+use url::Url;
+
+/// How much the messages of one account may take up once written out.
+const MAX_BYTES: usize = 512 * 1024;
+
+#[derive(Debug, Default)]
+pub struct RecentMessages {
+    /// Least recently stored first, with the size each channel's messages take up.
+    channels: Vec<(ChannelId, Vec<Message>, usize)>,
+}
+
+impl RecentMessages {
+    /// Reads what was kept for `user` on `server`, or nothing if it can't be read.
+    pub fn load(server: &Url, user: UserId) -> Self {
+        let Some(json) = storage::read(&key(server, user)) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Vec<(ChannelId, Vec<Message>)>>(&json) {
+            Ok(channels) => {
+                let mut recent = Self::default();
+                for (channel_id, messages) in channels {
+                    recent.insert(channel_id, messages);
+                }
+                recent
+            }
+            Err(e) => {
+                log::warn!("could not read the recent messages of {user} on {server}: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// The messages kept for `channel_id`, oldest first.
+    pub fn get(&self, channel_id: ChannelId) -> Option<&[Message]> {
+        self.channels
+            .iter()
+            .find(|(id, ..)| *id == channel_id)
+            .map(|(_, messages, _)| messages.as_slice())
+    }
+
+    /// Keeps `messages` as the newest of `channel_id`, forgetting other channels
+    /// if there's no room left for them.
+    pub fn insert(&mut self, channel_id: ChannelId, messages: Vec<Message>) {
+        self.remove(channel_id);
+        let size = serde_json::to_vec(&messages).map_or(usize::MAX, |json| json.len());
+        self.channels.push((channel_id, messages, size));
+
+        let mut total: usize = self.channels.iter().map(|(.., size)| size).sum();
+        while total > MAX_BYTES && !self.channels.is_empty() {
+            let (.., size) = self.channels.remove(0);
+            total -= size;
+        }
+    }
+
+    pub fn remove(&mut self, channel_id: ChannelId) {
+        self.channels.retain(|(id, ..)| *id != channel_id);
+    }
+
+    /// Writes the messages out for the next run, once the returned future is run.
+    pub fn save(&self, server: &Url, user: UserId) -> impl Future<Output = ()> {
+        let key = key(server, user);
+        let channels: Vec<_> = self
+            .channels
+            .iter()
+            .map(|(channel_id, messages, _)| (channel_id, messages))
+            .collect();
+        let json = serde_json::to_string(&channels);
+        async move {
+            match json {
+                Ok(json) => storage::write(&key, &json),
+                Err(e) => log::warn!("could not serialize the recent messages: {e}"),
+            }
+        }
+    }
+
+    /// Deletes what was kept for `user` on `server`, e.g. once the account is gone.
+    pub fn forget(server: &Url, user: UserId) {
+        storage::remove(&key(server, user));
+    }
+}
+
+/// A name for the messages of `user` on `server` that's safe to use as a file name.
+fn key(server: &Url, user: UserId) -> String {
+    let server: String = server
+        .as_str()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{server}-{user}")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::path::PathBuf;
+
+    use directories::BaseDirs;
+
+    const CACHE_PATH: &str = "eyeqwst/recent";
+
+    fn path(key: &str) -> Option<PathBuf> {
+        Some(
+            BaseDirs::new()?
+                .cache_dir()
+                .join(CACHE_PATH)
+                .join(format!("{key}.json")),
+        )
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        std::fs::read_to_string(path(key)?).ok()
+    }
+
+    pub fn write(key: &str, json: &str) {
+        let Some(path) = path(key) else {
+            return;
+        };
+        let res = match path.parent() {
+            Some(dir) => std::fs::create_dir_all(dir).and_then(|()| std::fs::write(&path, json)),
+            None => std::fs::write(&path, json),
+        };
+        if let Err(e) = res {
+            log::warn!("could not write {path}: {e}", path = path.display());
+        }
+    }
+
+    pub fn remove(key: &str) {
+        if let Some(path) = path(key) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok().flatten()
+    }
+
+    fn storage_key(key: &str) -> String {
+        format!("recent:{key}")
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        local_storage()?.get_item(&storage_key(key)).ok().flatten()
+    }
+
+    pub fn write(key: &str, json: &str) {
+        let written = local_storage().map(|storage| storage.set_item(&storage_key(key), json));
+        if !matches!(written, Some(Ok(()))) {
+            log::warn!("could not keep the recent messages in local storage");
+        }
+    }
+
+    pub fn remove(key: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(&storage_key(key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(content: &str, count: usize) -> Vec<Message> {
+        (0..count)
+            .map(|_| Message::builder().content(content).build())
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_replaces() {
+        let mut recent = RecentMessages::default();
+        recent.insert(ChannelId(1), messages("meow", 2));
+        recent.insert(ChannelId(1), messages("mrrp", 1));
+
+        let kept = recent.get(ChannelId(1)).unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].content, "mrrp");
+        assert!(recent.get(ChannelId(2)).is_none());
+    }
+
+    #[test]
+    fn test_insert_forgets_oldest_over_cap() {
+        let long = "a".repeat(MAX_BYTES / 3);
+        let mut recent = RecentMessages::default();
+        recent.insert(ChannelId(1), messages(&long, 1));
+        recent.insert(ChannelId(2), messages(&long, 1));
+        recent.insert(ChannelId(3), messages(&long, 1));
+        assert!(recent.get(ChannelId(1)).is_none());
+        assert!(recent.get(ChannelId(2)).is_some());
+
+        // storing a channel again makes it the last to be forgotten
+        recent.insert(ChannelId(2), messages(&long, 1));
+        recent.insert(ChannelId(4), messages(&long, 1));
+        assert!(recent.get(ChannelId(3)).is_none());
+        assert!(recent.get(ChannelId(2)).is_some());
+        assert!(recent.get(ChannelId(4)).is_some());
+    }
+
+    #[test]
+    fn test_key_is_a_file_name() {
+        let server = Url::parse("https://quaddle.example:8080/api/").unwrap();
+        assert_eq!(
+            key(&server, UserId(42)),
+            "https___quaddle_example_8080_api_-42"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use futures::{future, FutureExt};
+use quaddlecl::client::http::middleware::{HookFuture, Method, Middleware, Next, Response};
+use quaddlecl::client::http::retry::{ExponentialBackoff, SleepFuture};
+use quaddlecl::client::http::{self, Http};
+use quaddlecl::client::ConnectOptions;
+use url::Url;
+
+use crate::utils::sleep;
+
+/// The default amount of requests that may be in flight at the same time.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+/// Creates a REST client that retries idempotent requests, such as history
+/// fetches, which fail because of a flaky network.
+pub fn new_http(quaddle_url: Url, options: &ConnectOptions) -> Result<Http, http::Error> {
+    Http::builder(quaddle_url, options.clone())
+        .retry(ExponentialBackoff::default(), retry_sleep)
+        .middleware(RequestLog)
+        .build()
+}
+
+/// Logs how every request went, retries included, at the debug level.
+#[derive(Debug)]
+struct RequestLog;
+
+impl Middleware for RequestLog {
+    fn after_response<'a>(
+        &'a self,
+        method: &'a Method,
+        url: &'a Url,
+        res: &'a mut Result<Response, http::Error>,
+    ) -> HookFuture<'a, Next> {
+        let path = url.path();
+        match res {
+            Ok(resp) => log::debug!("{method} {path}: {status}", status = resp.status()),
+            Err(e) => log::debug!("{method} {path}: {e}"),
+        }
+        Box::pin(future::ready(Next::Continue))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn retry_sleep(d: Duration) -> SleepFuture {
+    sleep(d).boxed()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn retry_sleep(d: Duration) -> SleepFuture {
+    sleep(d).boxed_local()
+}
+
+/// How urgently a request needs to be fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Prefetching and other work the user isn't waiting on.
+    Background,
+    /// Requests the user is actively waiting on, like sending a message
+    /// or loading the history of the current channel.
+    Interactive,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    wake: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    // higher priorities first, then first come first served
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    next_seq: u64,
+    queue: BinaryHeap<Waiter>,
+}
+
+/// Sits in front of [`Http`] and limits the amount of requests in flight,
+/// letting interactive requests skip ahead of background ones.
+#[derive(Debug)]
+pub struct Scheduler {
+    http: Http,
+    max_in_flight: usize,
+    state: Mutex<State>,
+}
+
+/// A slot for one in-flight request. Passed on to the next waiter when dropped.
+struct Permit<'a> {
+    scheduler: &'a Scheduler,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock().unwrap();
+        self.scheduler.release(&mut state);
+    }
+}
+
+/// A place in the queue. If it's given up right after being handed a slot,
+/// before it could turn into a [`Permit`], the slot is passed on as well.
+struct Ticket<'a> {
+    scheduler: &'a Scheduler,
+    receiver: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let Some(mut receiver) = self.receiver.take() else {
+            return;
+        };
+        // holding the lock keeps a slot from being handed over while we leave
+        let mut state = self.scheduler.state.lock().unwrap();
+        receiver.close();
+        if let Ok(Some(())) = receiver.try_recv() {
+            self.scheduler.release(&mut state);
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(http: Http) -> Self {
+        Self::with_max_in_flight(http, DEFAULT_MAX_IN_FLIGHT)
+    }
+
+    pub fn with_max_in_flight(http: Http, max_in_flight: usize) -> Self {
+        Self {
+            http,
+            max_in_flight: max_in_flight.max(1),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the underlying client. Requests made through it bypass the queue.
+    pub fn http(&self) -> &Http {
+        &self.http
+    }
+
+    pub fn http_mut(&mut self) -> &mut Http {
+        &mut self.http
+    }
+
+    /// Hands the slot of a finished request to the next waiter, or frees it.
+    fn release(&self, state: &mut State) {
+        while let Some(waiter) = state.queue.pop() {
+            // the waiter might have been cancelled in the meantime
+            if waiter.wake.send(()).is_ok() {
+                return;
+            }
+        }
+        state.in_flight -= 1;
+    }
+
+    async fn acquire(&self, priority: Priority) -> Permit<'_> {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.in_flight < self.max_in_flight {
+                state.in_flight += 1;
+                return Permit { scheduler: self };
+            }
+
+            let (wake, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.queue.push(Waiter {
+                priority,
+                seq,
+                wake,
+            });
+            receiver
+        };
+
+        let mut ticket = Ticket {
+            scheduler: self,
+            receiver: Some(receiver),
+        };
+        // the sender is only ever dropped after sending, since the scheduler outlives us
+        if let Some(receiver) = &mut ticket.receiver {
+            let _ = receiver.await;
+        }
+        ticket.receiver = None;
+        Permit { scheduler: self }
+    }
+
+    /// Runs a request once a slot frees up for it.
+    pub async fn run<'a, F, Fut, T>(&'a self, priority: Priority, f: F) -> T
+    where
+        F: FnOnce(&'a Http) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let _permit = self.acquire(priority).await;
+        f(&self.http).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(max_in_flight: usize) -> Scheduler {
+        let url = Url::parse("http://localhost:8080").unwrap();
+        let http = Http::new(url, "eyeqwst tests".to_string()).unwrap();
+        Scheduler::with_max_in_flight(http, max_in_flight)
+    }
+
+    #[test]
+    fn test_interactive_skips_ahead() {
+        let scheduler = scheduler(1);
+        let first = scheduler
+            .acquire(Priority::Background)
+            .now_or_never()
+            .unwrap();
+
+        let mut background = Box::pin(scheduler.acquire(Priority::Background));
+        let mut interactive = Box::pin(scheduler.acquire(Priority::Interactive));
+        assert!(background.as_mut().now_or_never().is_none());
+        assert!(interactive.as_mut().now_or_never().is_none());
+
+        drop(first);
+        assert!(background.as_mut().now_or_never().is_none());
+        let second = interactive.as_mut().now_or_never().unwrap();
+
+        drop(second);
+        assert!(background.as_mut().now_or_never().is_some());
+    }
+
+    #[test]
+    fn test_same_priority_in_order() {
+        let scheduler = scheduler(1);
+        let first = scheduler
+            .acquire(Priority::Interactive)
+            .now_or_never()
+            .unwrap();
+
+        let mut a = Box::pin(scheduler.acquire(Priority::Interactive));
+        let mut b = Box::pin(scheduler.acquire(Priority::Interactive));
+        assert!(a.as_mut().now_or_never().is_none());
+        assert!(b.as_mut().now_or_never().is_none());
+
+        drop(first);
+        assert!(b.as_mut().now_or_never().is_none());
+        assert!(a.as_mut().now_or_never().is_some());
+    }
+
+    #[test]
+    fn test_cancelled_waiter_passes_slot_on() {
+        let scheduler = scheduler(1);
+        let first = scheduler
+            .acquire(Priority::Interactive)
+            .now_or_never()
+            .unwrap();
+
+        let mut cancelled = Box::pin(scheduler.acquire(Priority::Interactive));
+        let mut waiting = Box::pin(scheduler.acquire(Priority::Background));
+        assert!(cancelled.as_mut().now_or_never().is_none());
+        assert!(waiting.as_mut().now_or_never().is_none());
+
+        // the slot is handed to `cancelled`, which goes away without ever looking at it
+        drop(first);
+        drop(cancelled);
+        let second = waiting.as_mut().now_or_never().unwrap();
+
+        drop(second);
+        drop(waiting);
+        assert_eq!(scheduler.state.lock().unwrap().in_flight, 0);
+    }
+}
@@ -0,0 +1,268 @@
+//! Spellchecking for the composer, against plain word lists with one word per line.
+//! The system's are used where there are any, such as those in `/usr/share/dict`,
+//! and users can add their own as `<language>.txt` under `eyeqwst/dictionaries`
+//! in their data directory. There are none on the web, so nothing is checked there.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use iced::advanced::text::highlighter;
+use iced::{Font, Theme};
+
+/// The words of one language.
+pub struct Dictionary {
+    language: String,
+    /// Lowercase, with typographic apostrophes made straight.
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// A dictionary of the words in `list`, one per line.
+    pub fn from_words(language: impl Into<String>, list: &str) -> Self {
+        Self {
+            language: language.into(),
+            words: list
+                .lines()
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(normalize)
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(&normalize(word))
+    }
+
+    /// Where in `line` the words this dictionary doesn't know are.
+    pub fn misspelled<'a>(&'a self, line: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        words(line).filter(|range| !self.contains(&line[range.clone()]))
+    }
+}
+
+impl PartialEq for Dictionary {
+    fn eq(&self, other: &Self) -> bool {
+        self.language == other.language
+    }
+}
+
+impl fmt::Debug for Dictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dictionary")
+            .field("language", &self.language)
+            .field("words", &self.words.len())
+            .finish()
+    }
+}
+
+fn normalize(word: &str) -> String {
+    word.replace('’', "'").to_lowercase()
+}
+
+/// Where the words of `line` are. Links, mentions, channels, emoji shortcodes,
+/// code and anything with digits in it aren't words.
+fn words(line: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    line.split_whitespace()
+        .filter(|token| {
+            !token.contains("://")
+                && !token.starts_with(['@', '#', ':', '`'])
+                && !token.chars().any(|c| c.is_ascii_digit())
+        })
+        .flat_map(|token| token.split(|c: char| !c.is_alphabetic() && c != '\'' && c != '’'))
+        .map(|word| word.trim_matches(['\'', '’']))
+        .filter(|word| !word.is_empty())
+        .map(move |word| {
+            let start = word.as_ptr() as usize - line.as_ptr() as usize;
+            start..start + word.len()
+        })
+}
+
+/// A word the dictionary doesn't know.
+#[derive(Debug, Clone, Copy)]
+pub struct Misspelled;
+
+/// Marks the misspelled words of the composer, as many as the dictionary it's given knows.
+/// With no dictionary, it marks nothing.
+pub struct Highlighter {
+    dictionary: Option<Arc<Dictionary>>,
+    current_line: usize,
+}
+
+impl highlighter::Highlighter for Highlighter {
+    type Settings = Option<Arc<Dictionary>>;
+    type Highlight = Misspelled;
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, Misspelled)>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            dictionary: settings.clone(),
+            current_line: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.dictionary = new_settings.clone();
+        self.change_line(0);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        self.current_line += 1;
+        let misspelled: Vec<_> = match &self.dictionary {
+            Some(dictionary) => dictionary
+                .misspelled(line)
+                .map(|range| (range, Misspelled))
+                .collect(),
+            None => Vec::new(),
+        };
+        misspelled.into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+/// How misspelled words look in the composer.
+pub fn format(_: &Misspelled, theme: &Theme) -> highlighter::Format<Font> {
+    highlighter::Format {
+        color: Some(theme.palette().danger),
+        font: None,
+    }
+}
+
+/// The languages there are dictionaries for, sorted.
+pub fn languages() -> Vec<String> {
+    word_lists::languages()
+}
+
+/// Reads the dictionary of `language`, if there is one.
+pub async fn load(language: String) -> Option<Arc<Dictionary>> {
+    word_lists::load(language).await.map(Arc::new)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod word_lists {
+    use std::path::PathBuf;
+
+    use directories::BaseDirs;
+
+    use super::Dictionary;
+
+    const USER_PATH: &str = "eyeqwst/dictionaries";
+    const SYSTEM_PATH: &str = "/usr/share/dict";
+
+    /// The word lists distributions install, by the language they're in.
+    const SYSTEM_LISTS: [(&str, &[&str]); 10] = [
+        ("da", &["danish"]),
+        ("de", &["ngerman", "ogerman"]),
+        ("en", &["american-english", "british-english", "words"]),
+        ("es", &["spanish"]),
+        ("fr", &["french"]),
+        ("it", &["italian"]),
+        ("nl", &["dutch"]),
+        ("pl", &["polish"]),
+        ("pt", &["portuguese", "brazilian"]),
+        ("sv", &["swedish"]),
+    ];
+
+    fn user_dir() -> Option<PathBuf> {
+        Some(BaseDirs::new()?.data_dir().join(USER_PATH))
+    }
+
+    /// Where the word list of `language` may be, the user's own first.
+    fn paths(language: &str) -> Vec<PathBuf> {
+        let system = SYSTEM_LISTS
+            .iter()
+            .filter(|(lang, _)| *lang == language)
+            .flat_map(|(_, names)| names.iter())
+            .map(|name| PathBuf::from(SYSTEM_PATH).join(name));
+        user_dir()
+            .map(|dir| dir.join(format!("{language}.txt")))
+            .into_iter()
+            .chain(system)
+            .collect()
+    }
+
+    pub fn languages() -> Vec<String> {
+        let own = user_dir()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "txt" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_string())
+            });
+        let system = SYSTEM_LISTS
+            .iter()
+            .filter(|(lang, _)| paths(lang).iter().any(|path| path.exists()))
+            .map(|(lang, _)| lang.to_string());
+        let mut languages: Vec<_> = own.chain(system).collect();
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// Reading a word list can take a while, so it's done on tokio's blocking threads.
+    pub async fn load(language: String) -> Option<Dictionary> {
+        tokio::task::spawn_blocking(move || load_now(&language))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    fn load_now(language: &str) -> Option<Dictionary> {
+        let bytes = paths(language)
+            .into_iter()
+            .find_map(|path| std::fs::read(path).ok())?;
+        // older word lists are in Latin-1, whose bytes are each the code point
+        let list = String::from_utf8(bytes)
+            .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect());
+        Some(Dictionary::from_words(language, &list))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod word_lists {
+    use super::Dictionary;
+
+    pub fn languages() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub async fn load(_language: String) -> Option<Dictionary> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_misspelled() {
+        let dictionary = Dictionary::from_words("en", "the\ncat\nsays\nmeow\ndon't\n");
+        let line = "The cat sayz meow, don’t @mreow https://example.com/mrrp 2nd";
+        let misspelled: Vec<_> = dictionary
+            .misspelled(line)
+            .map(|range| &line[range])
+            .collect();
+        assert_eq!(misspelled, ["sayz"]);
+    }
+
+    #[test]
+    fn test_words() {
+        let line = "'quoted' twice-over `code`";
+        let words: Vec<_> = words(line).map(|range| &line[range]).collect();
+        assert_eq!(words, ["quoted", "twice", "over"]);
+    }
+}
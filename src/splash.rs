@@ -0,0 +1,109 @@
+use iced::widget::{column, container, text};
+use iced::{font, Alignment, Command, Element, Length};
+
+use crate::config::{Config, Migration, Profile};
+
+/// Fonts bundled with eyeqwst. The icon font comes first,
+/// since the UI is hardly usable without it.
+const FONTS: &[&[u8]] = &[
+    include_bytes!("../assets/SymbolsNerdFont-Regular.ttf"),
+    include_bytes!("../assets/Roboto-BlackItalic.ttf"),
+    include_bytes!("../assets/Roboto-Black.ttf"),
+    include_bytes!("../assets/Roboto-BoldItalic.ttf"),
+    include_bytes!("../assets/Roboto-Bold.ttf"),
+    include_bytes!("../assets/Roboto-Italic.ttf"),
+    include_bytes!("../assets/Roboto-LightItalic.ttf"),
+    include_bytes!("../assets/Roboto-Light.ttf"),
+    include_bytes!("../assets/Roboto-MediumItalic.ttf"),
+    include_bytes!("../assets/Roboto-Medium.ttf"),
+    include_bytes!("../assets/Roboto-Regular.ttf"),
+    include_bytes!("../assets/Roboto-ThinItalic.ttf"),
+    include_bytes!("../assets/Roboto-Thin.ttf"),
+];
+
+#[derive(Debug)]
+pub enum SplashMessage {
+    FontLoaded(Result<(), font::Error>),
+    ConfigLoaded(Box<(Config, Option<Migration>)>),
+}
+
+/// Shown while the startup work that would otherwise block the first frame runs.
+#[derive(Debug, Default)]
+pub struct Splash {
+    fonts_loaded: usize,
+    config_loaded: bool,
+}
+
+impl Splash {
+    /// Creates the splash screen and kicks off every initialization stage.
+    /// The config is the one of `profile`.
+    pub fn new(profile: Profile) -> (Self, Command<SplashMessage>) {
+        let fonts = FONTS
+            .iter()
+            .map(|&bytes| font::load(bytes).map(SplashMessage::FontLoaded));
+        let config = Command::perform(
+            async { Box::new(Config::load_profile(profile)) },
+            SplashMessage::ConfigLoaded,
+        );
+
+        (
+            Self::default(),
+            Command::batch(fonts.chain(std::iter::once(config))),
+        )
+    }
+
+    /// Returns the loaded config once it's there, and how it was upgraded if it had to be.
+    pub fn update(&mut self, message: SplashMessage) -> Option<(Config, Option<Migration>)> {
+        match message {
+            SplashMessage::FontLoaded(res) => {
+                if let Err(e) = res {
+                    log::warn!("failed to load font: {e:?}");
+                }
+                self.fonts_loaded += 1;
+                None
+            }
+            SplashMessage::ConfigLoaded(config) => {
+                self.config_loaded = true;
+                Some(*config)
+            }
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.fonts_loaded == FONTS.len() && self.config_loaded
+    }
+
+    pub fn view<'a, Message: 'a>(&self) -> Element<'a, Message> {
+        // the bundled fonts may not be there yet, so stick to plain text
+        let stage = |label: String, done: bool| {
+            text(if done {
+                format!("{label}: done")
+            } else {
+                format!("{label}…")
+            })
+            .size(14)
+        };
+
+        container(
+            column![
+                text("eyeqwst").size(30),
+                stage(
+                    format!(
+                        "Loading fonts ({loaded}/{total})",
+                        loaded = self.fonts_loaded,
+                        total = FONTS.len()
+                    ),
+                    self.fonts_loaded == FONTS.len(),
+                ),
+                stage("Loading settings".to_string(), self.config_loaded),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(10),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x()
+        .center_y()
+        .into()
+    }
+}
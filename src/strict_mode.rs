@@ -0,0 +1,51 @@
+//! Strict mode, for privacy-conscious users and locked-down deployments:
+//! nothing is loaded from hosts other than the Quaddle server, and links
+//! from messages are never opened. It's enforced here, in the helpers that
+//! reach out to the network, so that features don't each have to check for it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use url::Url;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Follows the setting. Called whenever it's loaded or changed.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Whether a resource at `url`, such as an image or a link preview, may be
+/// loaded without the user asking for it. In strict mode, only the server's own are.
+pub fn may_load(url: &Url, server: &Url) -> bool {
+    !is_enabled() || url.origin() == server.origin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the only test touching the setting, as it's shared by the whole process
+    #[test]
+    fn test_may_load() {
+        let server = Url::parse("https://quaddle.example/api/").unwrap();
+        let own = Url::parse("https://quaddle.example/files/cat.png").unwrap();
+        let other = Url::parse("https://cdn.example/cat.png").unwrap();
+        let other_port = Url::parse("https://quaddle.example:8443/cat.png").unwrap();
+        let insecure = Url::parse("http://quaddle.example/cat.png").unwrap();
+
+        set_enabled(false);
+        assert!(may_load(&own, &server));
+        assert!(may_load(&other, &server));
+
+        set_enabled(true);
+        assert!(may_load(&own, &server));
+        assert!(!may_load(&other, &server));
+        assert!(!may_load(&other_port, &server));
+        assert!(!may_load(&insecure, &server));
+        set_enabled(false);
+    }
+}
@@ -0,0 +1,171 @@
+//! Which day a message was sent on, as seen in the timezone the user
+//! chose to display times in, and how long ago something happened.
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+/// Converts `time` to the display timezone, or to the system's if there's none.
+/// The offset is looked up for that instant, so times on either side of
+/// a daylight saving transition each get their own.
+pub fn to_display(time: DateTime<Utc>, timezone: Option<Tz>) -> DateTime<FixedOffset> {
+    match timezone {
+        Some(tz) => time.with_timezone(&tz).fixed_offset(),
+        None => time.with_timezone(&Local).fixed_offset(),
+    }
+}
+
+/// The calendar day `time` falls on in the display timezone.
+pub fn day_of(time: DateTime<Utc>, timezone: Option<Tz>) -> NaiveDate {
+    to_display(time, timezone).date_naive()
+}
+
+/// Whether two times fall on the same calendar day in the display timezone.
+pub fn same_day(a: DateTime<Utc>, b: DateTime<Utc>, timezone: Option<Tz>) -> bool {
+    day_of(a, timezone) == day_of(b, timezone)
+}
+
+/// The text of the separator that starts `day` in the message list.
+pub fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        "Today".to_string()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".to_string()
+    } else if day.year() == today.year() {
+        day.format("%A, %B %-d").to_string()
+    } else {
+        day.format("%A, %B %-d, %Y").to_string()
+    }
+}
+
+/// How long before `now` something happened, e.g. "2 min ago".
+/// Further back than a week, the day it happened on is given instead.
+pub fn relative(time: DateTime<Utc>, now: DateTime<Utc>, timezone: Option<Tz>) -> String {
+    let elapsed = now.signed_duration_since(time);
+    match elapsed.num_seconds() {
+        // clocks may disagree a little on when something happened
+        ..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", elapsed.num_minutes()),
+        3600..=86399 => format!("{} h ago", elapsed.num_hours()),
+        _ if elapsed.num_days() < 7 => match elapsed.num_days() {
+            1 => "1 day ago".to_string(),
+            days => format!("{days} days ago"),
+        },
+        _ => day_label(day_of(time, timezone), day_of(now, timezone)),
+    }
+}
+
+/// The full date and time of `time` in the display timezone, e.g. for tooltips.
+pub fn absolute(time: DateTime<Utc>, timezone: Option<Tz>) -> String {
+    to_display(time, timezone)
+        .format("%Y-%m-%d %H:%M:%S %:z")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_day_depends_on_timezone() {
+        // 23:30 in UTC is already the next morning in Tokyo and still the evening in New York
+        let time = utc(2024, 6, 1, 23, 30);
+        assert_eq!(day_of(time, Some(Tz::UTC)), date(2024, 6, 1));
+        assert_eq!(day_of(time, Some(Tz::Asia__Tokyo)), date(2024, 6, 2));
+        assert_eq!(day_of(time, Some(Tz::America__New_York)), date(2024, 6, 1));
+    }
+
+    #[test]
+    fn test_midnight_boundary() {
+        let tz = Some(Tz::Europe__Berlin);
+        // 23:59 and 00:01 in Berlin (UTC+2 in summer)
+        let before = utc(2024, 7, 10, 21, 59);
+        let after = utc(2024, 7, 10, 22, 1);
+        assert!(!same_day(before, after, tz));
+        assert!(same_day(before, utc(2024, 7, 9, 22, 0), tz));
+    }
+
+    #[test]
+    fn test_spring_forward() {
+        // New York skips from 02:00 to 03:00 on 2024-03-10, going from UTC-5 to UTC-4
+        let tz = Some(Tz::America__New_York);
+        let evening_before = utc(2024, 3, 10, 4, 30); // 23:30 EST on the 9th
+        let after_midnight = utc(2024, 3, 10, 5, 30); // 00:30 EST on the 10th
+        let after_switch = utc(2024, 3, 10, 7, 30); // 03:30 EDT
+        let late_evening = utc(2024, 3, 11, 3, 30); // 23:30 EDT on the 10th
+        let next_midnight = utc(2024, 3, 11, 4, 0); // 00:00 EDT on the 11th
+
+        assert!(!same_day(evening_before, after_midnight, tz));
+        assert!(same_day(after_midnight, after_switch, tz));
+        assert!(same_day(after_switch, late_evening, tz));
+        assert_eq!(day_of(late_evening, tz), date(2024, 3, 10));
+        assert_eq!(day_of(next_midnight, tz), date(2024, 3, 11));
+    }
+
+    #[test]
+    fn test_fall_back() {
+        // New York repeats 01:00 to 02:00 on 2024-11-03, going from UTC-4 to UTC-5
+        let tz = Some(Tz::America__New_York);
+        let first_one_am = utc(2024, 11, 3, 5, 30); // 01:30 EDT
+        let second_one_am = utc(2024, 11, 3, 6, 30); // 01:30 EST
+        let late_evening = utc(2024, 11, 4, 4, 30); // 23:30 EST on the 3rd
+        let next_midnight = utc(2024, 11, 4, 5, 0); // 00:00 EST on the 4th
+
+        assert_eq!(
+            to_display(first_one_am, tz).format("%H:%M %:z").to_string(),
+            "01:30 -04:00"
+        );
+        assert_eq!(
+            to_display(second_one_am, tz)
+                .format("%H:%M %:z")
+                .to_string(),
+            "01:30 -05:00"
+        );
+        assert!(same_day(first_one_am, late_evening, tz));
+        assert!(!same_day(late_evening, next_midnight, tz));
+    }
+
+    #[test]
+    fn test_relative() {
+        let now = utc(2024, 3, 10, 12, 0);
+        assert_eq!(relative(utc(2024, 3, 10, 12, 1), now, None), "just now");
+        assert_eq!(relative(now, now, None), "just now");
+        assert_eq!(relative(utc(2024, 3, 10, 11, 58), now, None), "2 min ago");
+        assert_eq!(relative(utc(2024, 3, 10, 9, 30), now, None), "2 h ago");
+        assert_eq!(relative(utc(2024, 3, 9, 11, 0), now, None), "1 day ago");
+        assert_eq!(relative(utc(2024, 3, 5, 12, 0), now, None), "5 days ago");
+        assert_eq!(
+            relative(utc(2024, 2, 29, 12, 0), now, Some(Tz::UTC)),
+            "Thursday, February 29"
+        );
+    }
+
+    #[test]
+    fn test_absolute() {
+        let time = utc(2024, 7, 10, 21, 59);
+        assert_eq!(
+            absolute(time, Some(Tz::Europe__Berlin)),
+            "2024-07-10 23:59:00 +02:00"
+        );
+    }
+
+    #[test]
+    fn test_day_label() {
+        let today = date(2024, 3, 10);
+        assert_eq!(day_label(today, today), "Today");
+        assert_eq!(day_label(date(2024, 3, 9), today), "Yesterday");
+        assert_eq!(day_label(date(2024, 2, 29), today), "Thursday, February 29");
+        assert_eq!(
+            day_label(date(2023, 12, 31), today),
+            "Sunday, December 31, 2023"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use iced::time::{Duration, Instant};
+use iced::widget::{button, column, container, row, text};
+use iced::{theme, Alignment, Background, Element, Length, Subscription, Theme};
+use quaddlecl::model::message::Message as QMessage;
+
+use crate::account_switcher::SessionId;
+use crate::utils::icon;
+use crate::{DEFAULT_FONT_MEDIUM, WARNING};
+
+const INFO: &str = "\u{f05a}";
+const CLOSE: &str = "\u{f00d}";
+
+/// How long a toast stays on screen unless dismissed.
+const TOAST_LIFETIME: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+/// What a button on a toast has the application do.
+#[derive(Debug, Clone)]
+pub enum ToastAction {
+    /// Opens the channel of a message the toast was about
+    /// in the session it came to, to reply to it.
+    Reply(SessionId, QMessage),
+    /// Marks the channel of a message the toast was about as read up to it.
+    MarkRead(SessionId, QMessage),
+}
+
+#[derive(Debug)]
+struct Toast {
+    id: u64,
+    kind: ToastKind,
+    text: String,
+    actions: Vec<(&'static str, ToastAction)>,
+    shown_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub enum ToastMessage {
+    Dismissed(u64),
+    /// Handled by the application, after which the toast goes away.
+    ActionPressed(u64, ToastAction),
+    Tick,
+}
+
+/// Short-lived notifications, shown in a corner over the rest of the UI.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn push(&mut self, kind: ToastKind, text: impl Into<String>) {
+        self.push_with_actions(kind, text, Vec::new());
+    }
+
+    /// Shows a toast with a button for each of `actions`, labeled as given.
+    pub fn push_with_actions(
+        &mut self,
+        kind: ToastKind,
+        text: impl Into<String>,
+        actions: Vec<(&'static str, ToastAction)>,
+    ) {
+        self.toasts.push(Toast {
+            id: self.next_id,
+            kind,
+            text: text.into(),
+            actions,
+            shown_at: Instant::now(),
+        });
+        self.next_id += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+
+    pub fn update(&mut self, message: ToastMessage) {
+        match message {
+            ToastMessage::Dismissed(id) | ToastMessage::ActionPressed(id, _) => {
+                self.toasts.retain(|t| t.id != id)
+            }
+            ToastMessage::Tick => self
+                .toasts
+                .retain(|t| t.shown_at.elapsed() < TOAST_LIFETIME),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, ToastMessage> {
+        column(self.toasts.iter().map(|toast| {
+            let kind = toast.kind;
+            let actions = (!toast.actions.is_empty()).then(|| {
+                row(toast.actions.iter().map(|(label, action)| {
+                    button(text(*label).size(14))
+                        .style(theme::Button::Secondary)
+                        .on_press(ToastMessage::ActionPressed(toast.id, action.clone()))
+                        .into()
+                }))
+                .spacing(5)
+            });
+            container(
+                column![row![
+                    icon(match kind {
+                        ToastKind::Info => INFO,
+                        ToastKind::Error => WARNING,
+                    }),
+                    text(&toast.text)
+                        .font(DEFAULT_FONT_MEDIUM)
+                        .width(Length::Fill),
+                    button(icon(CLOSE))
+                        .style(theme::Button::Text)
+                        .on_press(ToastMessage::Dismissed(toast.id)),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(5)]
+                .push_maybe(actions)
+                .spacing(5),
+            )
+            .padding(10)
+            .width(300)
+            .style(move |t: &Theme| {
+                use container::StyleSheet;
+                let pair = match kind {
+                    ToastKind::Info => t.extended_palette().background.strong,
+                    ToastKind::Error => t.extended_palette().danger.base,
+                };
+                container::Appearance {
+                    text_color: Some(pair.text),
+                    background: Some(Background::Color(pair.color)),
+                    border: iced::Border::with_radius(5),
+                    ..t.appearance(&theme::Container::Box)
+                }
+            })
+            .into()
+        }))
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    pub fn subscription(&self) -> Subscription<ToastMessage> {
+        if self.toasts.is_empty() {
+            return Subscription::none();
+        }
+
+        iced::time::every(Duration::from_secs(1)).map(|_| ToastMessage::Tick)
+    }
+}
@@ -1,11 +1,13 @@
 use iced::time::Duration;
 use std::fmt;
+use std::sync::OnceLock;
 
 use std::error::Error;
 
 use iced::advanced::widget::text::StyleSheet as TextStyleSheet;
-use iced::widget::TextInput;
-use iced::{advanced::widget::Text, widget::text, Font};
+use iced::widget::{button, Button, TextInput};
+use iced::{advanced::widget::Text, theme, widget::text, Font};
+use url::Url;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub async fn sleep(d: Duration) {
@@ -28,6 +30,97 @@ pub async fn sleep(d: Duration) {
     wasm_bindgen_futures::JsFuture::from(fut).await.unwrap();
 }
 
+/// Opens a link from a message, unless strict mode is on.
+/// Anything but web pages is refused, since links come from other users.
+/// Returns whether the link was opened.
+pub fn open_link(url: &Url) -> bool {
+    if crate::strict_mode::is_enabled() || !matches!(url.scheme(), "http" | "https") {
+        log::info!("not opening link {url}");
+        return false;
+    }
+    open_url(url.as_str());
+    true
+}
+
+/// Opens a link in the system browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_url(url: &str) {
+    use std::process::Command;
+
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = Command::new("rundll32");
+        cmd.arg("url.dll,FileProtocolHandler");
+        cmd
+    };
+    #[cfg(target_os = "macos")]
+    let mut cmd = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut cmd = Command::new("xdg-open");
+
+    if let Err(e) = cmd.arg(url).spawn() {
+        log::warn!("failed to open {url}: {e}");
+    }
+}
+
+/// Opens a link in a new tab.
+#[cfg(target_arch = "wasm32")]
+pub fn open_url(url: &str) {
+    let opened = web_sys::window().map(|window| window.open_with_url_and_target(url, "_blank"));
+    if !matches!(opened, Some(Ok(Some(_)))) {
+        log::warn!("failed to open {url}");
+    }
+}
+
+/// Whether the main pointer is a finger, as on phones and tablets.
+/// Only looked up the first time, as views ask for it on every frame.
+pub fn is_touch_device() -> bool {
+    static IS_TOUCH_DEVICE: OnceLock<bool> = OnceLock::new();
+    *IS_TOUCH_DEVICE.get_or_init(detect_touch_device)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn detect_touch_device() -> bool {
+    false
+}
+
+#[cfg(target_arch = "wasm32")]
+fn detect_touch_device() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media("(pointer: coarse)").ok().flatten())
+        .is_some_and(|query| query.matches())
+}
+
+/// How well `query` matches `name`, if all of its characters appear in it in order.
+/// Runs of consecutive characters and matches at the start of words count for more.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<u32> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut prev_matched = false;
+    let mut prev = None::<char>;
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let Some(&q) = query.peek() else {
+            break;
+        };
+        if c == q {
+            query.next();
+            score += 1;
+            if prev_matched {
+                score += 3;
+            }
+            if prev.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 5;
+            }
+            prev_matched = true;
+        } else {
+            prev_matched = false;
+        }
+        prev = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
 pub struct ErrorWithCauses<E>(pub E);
 
 impl<E> fmt::Display for ErrorWithCauses<E>
@@ -45,6 +138,74 @@ where
     }
 }
 
+/// A short description of an error for banners, naming the usual network
+/// failures in plain words instead of the whole chain of causes behind them.
+/// Other errors are described by their outermost message.
+pub struct ErrorSummary<E>(pub E);
+
+impl<E> fmt::Display for ErrorSummary<E>
+where
+    E: Error,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match summarize(&self.0) {
+            Some(summary) => write!(f, "{summary}"),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// What caused `err`, from the closest cause to the furthest.
+fn causes<'e>(err: &'e dyn Error) -> impl Iterator<Item = &'e (dyn Error + 'static)> {
+    std::iter::successors(err.source(), |&e| e.source())
+}
+
+fn summarize(err: &dyn Error) -> Option<&'static str> {
+    for cause in causes(err) {
+        let Some(io) = cause.downcast_ref::<std::io::Error>() else {
+            continue;
+        };
+        match io.kind() {
+            std::io::ErrorKind::ConnectionRefused => {
+                return Some("The server refused the connection")
+            }
+            std::io::ErrorKind::TimedOut => return Some("The server took too long to answer"),
+            std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::UnexpectedEof => return Some("The connection was cut off"),
+            _ => {}
+        }
+    }
+
+    // what doesn't come down to an I/O error is only described in words
+    std::iter::once(err.to_string())
+        .chain(causes(err).map(|e| e.to_string()))
+        .find_map(|msg| {
+            let msg = msg.to_lowercase();
+            if msg.contains("dns error") || msg.contains("failed to lookup address") {
+                Some("Could not find the server, check its address and your connection")
+            } else if msg.contains("certificate") {
+                Some("The server's certificate isn't trusted")
+            } else if msg.contains("timed out") {
+                Some("The server took too long to answer")
+            } else {
+                None
+            }
+        })
+}
+
+/// Shows or hides the full chain of causes under an error summary.
+pub fn details_toggle<'a, Message: Clone + 'a>(
+    expanded: bool,
+    on_toggle: Message,
+) -> Button<'a, Message> {
+    button(text(if expanded { "hide details" } else { "details" }).size(12))
+        .style(theme::Button::Text)
+        .padding(0)
+        .on_press(on_toggle)
+}
+
 pub trait TextInputExt<'a, Message: Clone> {
     fn on_input_if(self, cond: bool, msg: impl Fn(String) -> Message + 'a) -> Self;
 }
@@ -111,3 +272,26 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("gnrl", "general").is_some());
+        assert!(fuzzy_score("GEN", "general").is_some());
+        assert_eq!(fuzzy_score("lareneg", "general"), None);
+        assert_eq!(fuzzy_score("generals", "general"), None);
+        assert_eq!(fuzzy_score("", "general"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_score_ranking() {
+        let score = |name: &str| fuzzy_score("dev", name).unwrap();
+        // runs of letters beat scattered ones
+        assert!(score("devops") > score("dxexv"));
+        // and the start of a word beats the middle of one
+        assert!(score("web-dev") > score("webdev"));
+    }
+}